@@ -0,0 +1,67 @@
+//! Copying output to the system clipboard, using the clipboard tools of the platform or, for text,
+//! the OSC 52 escape sequence understood by many terminals.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Place `data` of the given MIME type on the clipboard. The clipboard tools are tried in turn:
+/// `wl-copy` on Wayland, `xclip` on X11 and `pbcopy` on macOS. If none is available and `data` is
+/// text, it is sent to the terminal using OSC 52.
+pub fn copy(data: &[u8], mime: &str) -> anyhow::Result<()> {
+    let text = std::str::from_utf8(data).ok();
+
+    let mut tools: Vec<Vec<&str>> = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(vec!["wl-copy", "--type", mime]);
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        tools.push(vec!["xclip", "-selection", "clipboard", "-target", mime]);
+    }
+    if cfg!(target_os = "macos") && text.is_some() {
+        tools.push(vec!["pbcopy"]);
+    }
+
+    for tool in tools {
+        let mut child = match Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        // the tools keep running in the background to serve the clipboard once stdin is closed
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(data)?;
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("`{}` failed with {status}", tool[0]);
+        }
+        return Ok(());
+    }
+
+    let Some(text) = text else {
+        anyhow::bail!("copying {mime} requires `wl-copy` or `xclip`");
+    };
+    let mut tty = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|err| {
+            anyhow::anyhow!("no clipboard tool found, and cannot open the terminal: {err}")
+        })?;
+    tty.write_all(osc52(text).as_bytes())?;
+    tty.flush()?;
+    Ok(())
+}
+
+/// The OSC 52 escape sequence setting the clipboard to `text`.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", crate::preview::base64(text.as_bytes()))
+}
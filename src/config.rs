@@ -0,0 +1,131 @@
+//! The configuration file, giving defaults for command-line options. For example:
+//!
+//! ```toml
+//! window-frame = "macos"
+//! padding = 8
+//! font = ["JetBrains Mono"]
+//! color = ["background=#1e1e2e", "foreground=#cdd6f4"]
+//! format = "svg"
+//! ```
+//!
+//! Keys are the long names of the options. The options are defaults, and options given on the
+//! command line take precedence. Options of other subcommands are ignored.
+
+use std::{ffi::OsString, path::PathBuf};
+
+use clap::{Command, CommandFactory, FromArgMatches, Id};
+
+use crate::{Cli, RenderArgs};
+
+/// The default location of the configuration file: `termsnap/config.toml` in `$XDG_CONFIG_HOME`,
+/// or in `~/.config` if that is unset.
+fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("termsnap").join("config.toml"))
+}
+
+/// Get the command-line interface with the options of the configuration file as defaults, for
+/// parsing the command line `argv`. Options given in `argv` take precedence. Keys of options that
+/// only other subcommands define are ignored, such that one configuration file serves all
+/// subcommands.
+pub fn command(argv: &[OsString]) -> anyhow::Result<Command> {
+    let command = Cli::command();
+    // an invalid command line is reported when it is parsed
+    let Ok(matches) = command.clone().try_get_matches_from(argv) else {
+        return Ok(command);
+    };
+    // descend into nested subcommands such as `termsnap theme show`
+    let (mut subcommands, mut target, mut matches) = (Vec::new(), &command, &matches);
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        subcommands.push(name.to_owned());
+        target = target.find_subcommand(name).expect("unreachable");
+        matches = sub_matches;
+    }
+    // subcommands such as `termsnap play` do not render
+    if !target
+        .get_arguments()
+        .any(|arg| arg.get_id() == "no_config")
+    {
+        return Ok(command);
+    }
+
+    let render = RenderArgs::from_arg_matches(matches)?;
+    if render.no_config {
+        return Ok(command);
+    }
+    let path = match render.config {
+        Some(path) => path,
+        None => match default_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(command),
+        },
+    };
+    let config: toml::Table = std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|config| Ok(config.parse()?))
+        .map_err(|err| anyhow::anyhow!("failed to read config file {}: {err}", path.display()))?;
+
+    let mut defaults = Vec::new();
+    for (key, value) in config {
+        let Some(arg) = target
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(&key))
+            .filter(|arg| !matches!(arg.get_id().as_str(), "config" | "no_config"))
+        else {
+            if defines(&command, &key) {
+                continue;
+            }
+            anyhow::bail!("unknown option `{key}` in config file {}", path.display());
+        };
+
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        let mut strings = Vec::new();
+        for value in values {
+            let value = match value {
+                toml::Value::Boolean(false) if !arg.get_action().takes_values() => continue,
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => anyhow::bail!(
+                    "unsupported value for option `{key}` in config file {}",
+                    path.display()
+                ),
+            };
+            strings.push(value);
+        }
+        if !strings.is_empty() {
+            defaults.push((arg.get_id().clone(), strings));
+        }
+    }
+
+    Ok(with_defaults(command, &subcommands, &defaults))
+}
+
+/// Whether the command or any of its subcommands defines the option with the long name `long`.
+fn defines(command: &Command, long: &str) -> bool {
+    command
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some(long))
+        || command
+            .get_subcommands()
+            .any(|command| defines(command, long))
+}
+
+/// Set the default values of the arguments of the subcommand at `path`.
+fn with_defaults(command: Command, path: &[String], defaults: &[(Id, Vec<String>)]) -> Command {
+    match path.split_first() {
+        Some((name, path)) => {
+            command.mut_subcommand(name, |command| with_defaults(command, path, defaults))
+        }
+        None => defaults.iter().fold(command, |command, (id, values)| {
+            command.mut_arg(id, |arg| arg.default_values(values))
+        }),
+    }
+}
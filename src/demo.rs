@@ -0,0 +1,124 @@
+//! Demo scripts, read by `termsnap demo`. A demo script is a TOML file defining the command to
+//! run and the steps to take: text to type, pauses and snapshots. For example:
+//!
+//! ```toml
+//! # the command running the demo, defaulting to `sh`
+//! command = ["bash", "--norc"]
+//! lines = 12
+//! columns = 60
+//! env = { PS1 = "$ " }
+//!
+//! [[step]]
+//! type = "ls --color=always\n"
+//!
+//! [[step]]
+//! snapshot = "ls"
+//!
+//! [[step]]
+//! pause = 1000
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{typing::Step, DemoCommand, EnvVar, RunArgs};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    /// The command running the demo, with its arguments.
+    #[serde(default)]
+    pub command: Vec<String>,
+    pub lines: Option<u16>,
+    pub columns: Option<u16>,
+    /// The typing speed in words per minute.
+    pub wpm: Option<u32>,
+    /// Environment variables to set for the command.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, rename = "step")]
+    steps: Vec<ScriptStep>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ScriptStep {
+    /// Type the text, with newlines typed as the Enter key.
+    Type(String),
+    /// Pause for the given number of milliseconds.
+    Pause(u64),
+    /// Take a named snapshot once the output has settled.
+    Snapshot(String),
+}
+
+impl Script {
+    pub fn parse(script: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(script)?)
+    }
+
+    /// The steps of the script as simulated input.
+    pub fn steps(&self) -> Vec<Step> {
+        let mut steps = Vec::new();
+        for step in &self.steps {
+            match step {
+                ScriptStep::Type(text) => steps.extend(text.chars().map(|c| match c {
+                    // terminals send a carriage return for the Enter key
+                    '\n' => Step::Key('\r'),
+                    c => Step::Key(c),
+                })),
+                ScriptStep::Pause(ms) => steps.push(Step::Pause(Duration::from_millis(*ms))),
+                ScriptStep::Snapshot(name) => steps.push(Step::Snapshot(name.clone())),
+            }
+        }
+        steps
+    }
+}
+
+/// Get the options of `termsnap demo`, running the command of the script, or the command given,
+/// and taking the script's steps.
+pub fn run_args(command: DemoCommand) -> anyhow::Result<RunArgs> {
+    let DemoCommand {
+        script,
+        run: mut args,
+    } = command;
+    if args.interactive
+        || args.simulate_typing.is_some()
+        || args.ttyrec.is_some()
+        || args.timing.is_some()
+        || !args.pane.is_empty()
+    {
+        anyhow::bail!("`termsnap demo` cannot be used with `--interactive`, `--simulate-typing`, `--ttyrec`, `--timing` or `--pane`.");
+    }
+    let script = Script::parse(&std::fs::read_to_string(&script)?)
+        .map_err(|err| anyhow::anyhow!("Invalid demo script {}: {err}", script.display()))?;
+    let steps = script.steps();
+    if steps.iter().any(|step| matches!(step, Step::Snapshot(_)))
+        && !args.render.format.is_recording()
+        && args.render.out.is_none()
+    {
+        anyhow::bail!("The demo script takes snapshots, but no output file is specified in `--out`. See 'termsnap demo --help'.");
+    }
+
+    if args.command.is_none() {
+        let mut command = script.command.into_iter();
+        args.command = Some(command.next().unwrap_or_else(|| "sh".to_owned()));
+        args.args = Some(command.collect());
+    }
+    args.render.lines = args.render.lines.or(script.lines);
+    args.render.columns = args.render.columns.or(script.columns);
+    args.wpm = script.wpm.unwrap_or(args.wpm);
+    // variables set on the command line are set last, overriding the script's
+    let mut env: Vec<EnvVar> = script
+        .env
+        .into_iter()
+        .map(|(name, value)| EnvVar { name, value })
+        .collect();
+    env.append(&mut args.env);
+    Ok(RunArgs {
+        env,
+        steps: Some(steps),
+        ..args
+    })
+}
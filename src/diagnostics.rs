@@ -0,0 +1,57 @@
+//! Collecting the escape sequences the terminal emulator does not handle, to explain why a
+//! rendered screen may look different from how it looks in other terminals.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use termsnap_lib::{PtyWriter, Term};
+
+/// Counts the unhandled escape sequences of the terminals it is attached to.
+#[derive(Clone, Default)]
+pub struct Log {
+    counts: Arc<Mutex<BTreeMap<String, usize>>>,
+    /// Print each unhandled escape sequence to standard error as it is encountered.
+    print_each: bool,
+}
+
+impl Log {
+    pub fn new(print_each: bool) -> Self {
+        Log {
+            counts: Arc::default(),
+            print_each,
+        }
+    }
+
+    /// Collect the unhandled escape sequences of `term`.
+    pub fn attach<W: PtyWriter>(&self, term: &mut Term<W>) {
+        let counts = self.counts.clone();
+        let print_each = self.print_each;
+        term.set_diagnostics_sink(move |diagnostic| {
+            if print_each {
+                eprintln!("Unhandled escape sequence: {diagnostic}");
+            }
+            *counts
+                .lock()
+                .expect("unreachable")
+                .entry(diagnostic.sequence)
+                .or_default() += 1;
+        });
+    }
+
+    /// A summary of the unhandled escape sequences and their counts, or `None` if there were
+    /// none.
+    pub fn summary(&self) -> Option<String> {
+        let counts = self.counts.lock().expect("unreachable");
+        if counts.is_empty() {
+            return None;
+        }
+        let mut summary = String::from("Unhandled escape sequences:");
+        for (sequence, count) in counts.iter() {
+            let _ = write!(summary, "\n  {count:>5}  {sequence}");
+        }
+        Some(summary)
+    }
+}
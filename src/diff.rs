@@ -0,0 +1,167 @@
+//! Comparison of a terminal screen against a golden capture, for `termsnap diff`.
+
+use std::path::Path;
+
+use termsnap_lib::{Highlight, HighlightStyle, Layout, Region, Rgb, Screen, SvgOptions};
+
+use crate::{dimensions, from_read, DiffCommand, Emulator, RunArgs, ScreenArg};
+
+/// Get the runs of cells on each line that differ between `expected` and `actual`, in the
+/// coordinates of `actual`. Cells that exist on only one of the screens differ.
+pub fn changed_regions(expected: &Screen, actual: &Screen) -> Vec<Region> {
+    let lines = expected.lines().max(actual.lines());
+    let columns = expected.columns().max(actual.columns());
+
+    let mut regions = Vec::new();
+    for line in 0..lines {
+        let mut start = None;
+        for column in 0..=columns {
+            let changed =
+                column < columns && expected.get(line, column) != actual.get(line, column);
+            match (changed, start) {
+                (true, None) => start = Some(column),
+                (false, Some(start_column)) => {
+                    regions.push(Region {
+                        start_line: line,
+                        start_column,
+                        end_line: line,
+                        end_column: column,
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    regions
+}
+
+/// Get the text of the lines that differ between `expected` and `actual`, with expected lines
+/// prefixed by `-` and actual lines by `+`. Trailing whitespace is removed.
+pub fn text_diff(expected: &Screen, actual: &Screen) -> String {
+    let mut diff = String::new();
+    for line in 0..expected.lines().max(actual.lines()) {
+        let expected_line = expected.line_text(line);
+        let actual_line = actual.line_text(line);
+        if expected_line == actual_line {
+            continue;
+        }
+
+        diff.push_str(&format!("line {}:\n", line + 1));
+        if let Some(expected_line) = expected_line {
+            diff.push_str(&format!("-{expected_line}\n"));
+        }
+        if let Some(actual_line) = actual_line {
+            diff.push_str(&format!("+{actual_line}\n"));
+        }
+    }
+
+    diff
+}
+
+/// Get the options of `termsnap diff`, capturing the command's screen to compare against the
+/// golden capture or, with `--update`, to store as the new golden capture.
+pub fn run_args(command: DiffCommand) -> anyhow::Result<RunArgs> {
+    let DiffCommand {
+        golden,
+        update,
+        run: args,
+    } = command;
+    if args.raw_out.is_some() {
+        anyhow::bail!("`--raw-out` cannot be used with `termsnap diff`.");
+    }
+    if !args.pane.is_empty()
+        || args.render_before_clear
+        || matches!(args.render.screen, ScreenArg::Both)
+        || args.render.format.is_recording()
+    {
+        anyhow::bail!("`termsnap diff` cannot be used with `--pane`, `--render-before-clear`, `--screen both`, `--format gif` or `--format jsonl`.");
+    }
+    if update {
+        if args.command.is_none() {
+            anyhow::bail!("`--update` requires a command to run. See 'termsnap diff --help'.");
+        }
+        Ok(RunArgs {
+            raw_out: Some(golden),
+            ..args
+        })
+    } else {
+        if !golden.exists() {
+            anyhow::bail!(
+                "The golden capture {} does not exist. Create it by setting `--update`.",
+                golden.display()
+            );
+        }
+        Ok(RunArgs {
+            golden: Some(golden),
+            ..args
+        })
+    }
+}
+
+/// Compare the captured screen against the golden capture, rendered with the same options. If
+/// the screens differ, the differing lines are printed, the captured screen is rendered with the
+/// differing cells highlighted and written to `out` or to standard output, and an error is
+/// returned.
+pub fn compare_golden(
+    screens: Vec<Screen>,
+    golden: &Path,
+    cli: &RunArgs,
+    process: &dyn Fn(Screen) -> Screen,
+    out: Option<&Path>,
+    svg_options: SvgOptions,
+) -> anyhow::Result<()> {
+    /// The color in which differing cells are highlighted.
+    const DIFF_COLOR: Rgb = Rgb {
+        r: 0xe5,
+        g: 0x48,
+        b: 0x4d,
+    };
+
+    let (lines, columns) = dimensions(cli);
+    let expected = from_read(
+        &mut std::fs::File::open(golden)?,
+        lines,
+        columns,
+        cli.render.auto_size.then_some(cli.render.max_lines),
+        cli.render.screen,
+        &Emulator {
+            diagnostics: None,
+            ..Emulator::new(&cli.render)
+        },
+        None,
+    )?;
+    let expected = process(expected.into_iter().next().expect("unreachable"));
+    let actual = process(screens.into_iter().next().expect("unreachable"));
+
+    let changed = changed_regions(&expected, &actual);
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    eprint!("{}", text_diff(&expected, &actual));
+    let highlights = changed
+        .iter()
+        .map(|&region| Highlight {
+            region,
+            color: DIFF_COLOR,
+            style: HighlightStyle::Outline,
+        })
+        .collect::<Vec<_>>();
+    let svg = Layout::screen(actual);
+    let svg = svg.to_svg_with_options(svg_options.highlights(&highlights));
+    match out {
+        Some(out) => std::fs::write(out, svg.to_string())?,
+        None => println!("{svg}"),
+    }
+
+    anyhow::bail!(
+        "The terminal screen differs from the golden capture {} in {} cells.",
+        golden.display(),
+        changed
+            .iter()
+            .map(|region| usize::from(region.end_column - region.start_column))
+            .sum::<usize>()
+    );
+}
@@ -0,0 +1,91 @@
+//! Markdown and HTML snippets embedding a rendered SVG, for pasting into documentation.
+
+/// The markup language of an embedding snippet.
+#[derive(Clone, Copy, Debug)]
+pub enum Markup {
+    Markdown,
+    Html,
+}
+
+/// The maximum length in characters of alt text taken from the screen's contents.
+const MAX_ALT_LEN: usize = 250;
+
+/// An image snippet showing the image at `src` with the text alternative `alt`.
+pub fn image(markup: Markup, alt: &str, src: &str) -> String {
+    let alt = summarize(alt);
+    match markup {
+        Markup::Markdown => {
+            let mut escaped = String::with_capacity(alt.len());
+            for c in alt.chars() {
+                if matches!(c, '\\' | '[' | ']') {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            format!("![{escaped}]({src})")
+        }
+        Markup::Html => {
+            let alt = alt
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;");
+            format!("<img src=\"{src}\" alt=\"{alt}\">")
+        }
+    }
+}
+
+/// A `data:` URI of an SVG.
+pub fn svg_data_uri(svg: &str) -> String {
+    format!("data:image/svg+xml,{}", percent_encode(svg))
+}
+
+/// Percent-encode the characters of a path that are not valid in a URL, or that end the URL of a
+/// Markdown link.
+pub fn path_url(path: &std::path::Path) -> String {
+    percent_encode(&path.to_string_lossy())
+}
+
+/// Collapse runs of whitespace, such as the line breaks and indentation of the screen's text, and
+/// cut long text short.
+fn summarize(text: &str) -> String {
+    let mut summary = String::new();
+    for (idx, word) in text.split_whitespace().enumerate() {
+        if idx > 0 {
+            summary.push(' ');
+        }
+        summary.push_str(word);
+    }
+    if let Some((end, _)) = summary.char_indices().nth(MAX_ALT_LEN) {
+        summary.truncate(end);
+        summary.push('…');
+    }
+    summary
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'/'
+            | b':'
+            | b'='
+            | b';'
+            | b','
+            | b'\''
+            | b'!'
+            | b'*'
+            | b'+'
+            | b'@'
+            | b'$' => encoded.push(char::from(byte)),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
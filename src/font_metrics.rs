@@ -0,0 +1,42 @@
+//! Measuring the metrics of a font file, for `termsnap font-metrics`.
+
+use termsnap_lib::{FontMetrics, OutlineFont};
+
+use crate::{selftest, FontMetricsCommand, RunArgs};
+
+/// Print the metrics of the font file of `termsnap font-metrics`. If `--out` is set, get the
+/// options rendering the test pattern with the font and its metrics.
+pub fn run_args(command: FontMetricsCommand, args: RunArgs) -> anyhow::Result<Option<RunArgs>> {
+    let FontMetricsCommand {
+        font_file,
+        index,
+        mut render,
+    } = command;
+    let outline_font = OutlineFont::from_index(std::fs::read(&font_file)?, index)
+        .map_err(|err| anyhow::anyhow!("{}: {err}", font_file.display()))?;
+    let metrics = outline_font.metrics();
+    print!("{}", fmt_font_metrics(&metrics));
+    if render.out.is_none() {
+        return Ok(None);
+    }
+
+    render.font_metrics = metrics.into();
+    if render.font.is_empty() {
+        render.font.extend(outline_font.family_name());
+    }
+    render.lines = render.lines.or(Some(selftest::LINES));
+    render.columns = render.columns.or(Some(selftest::COLUMNS));
+    Ok(Some(RunArgs {
+        render,
+        output: Some(selftest::pattern()),
+        ..args
+    }))
+}
+
+/// Format font metrics as the options of the configuration file setting them.
+pub fn fmt_font_metrics(metrics: &FontMetrics) -> String {
+    format!(
+        "font-units-per-em = {}\nfont-advance = {}\nfont-line-height = {}\nfont-descent = {}\n",
+        metrics.units_per_em, metrics.advance, metrics.line_height, metrics.descent
+    )
+}
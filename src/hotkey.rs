@@ -0,0 +1,116 @@
+//! Key bindings intercepted from Termsnap's standard input in interactive mode. A binding is an
+//! escape key (a control character, such as Ctrl-]) followed by a command key.
+
+use std::io::{ErrorKind, Read};
+
+/// An action requested through a key binding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Write a snapshot of the terminal screen.
+    Snapshot,
+    /// End the session and render the terminal screen.
+    Detach,
+}
+
+/// The escape key starting a key binding, as the control character it sends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscapeKey(u8);
+
+impl std::str::FromStr for EscapeKey {
+    type Err = String;
+
+    /// Parse a control key, such as "ctrl-]" or "^]".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s
+            .strip_prefix("^")
+            .or_else(|| {
+                s.get(..5)
+                    .filter(|prefix| prefix.eq_ignore_ascii_case("ctrl-"))
+                    .map(|_| &s[5..])
+            })
+            .ok_or_else(|| format!("expected a control key such as \"ctrl-]\", got \"{s}\""))?;
+
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if ('@'..='_').contains(&c.to_ascii_uppercase()) => {
+                Ok(EscapeKey(c.to_ascii_uppercase() as u8 & 0x1f))
+            }
+            _ => Err(format!("\"{key}\" cannot be combined with ctrl")),
+        }
+    }
+}
+
+/// Tracks key bindings typed across reads of standard input.
+pub struct Hotkeys {
+    escape: EscapeKey,
+    /// Whether the escape key was the last key typed.
+    pending: bool,
+    /// Actions requested since they were last taken.
+    actions: Vec<Action>,
+}
+
+impl Hotkeys {
+    pub fn new(escape: EscapeKey) -> Self {
+        Hotkeys {
+            escape,
+            pending: false,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Wrap `read`, removing key bindings from the bytes read. Typing the escape key twice sends
+    /// the escape key itself. The escape key followed by any other key sends only that key.
+    pub fn reader<'h, R: Read>(&'h mut self, read: &'h mut R) -> impl Read + 'h {
+        HotkeyReader {
+            hotkeys: self,
+            read,
+        }
+    }
+
+    /// Take the actions requested since the last call.
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.actions)
+    }
+}
+
+struct HotkeyReader<'h, R> {
+    hotkeys: &'h mut Hotkeys,
+    read: &'h mut R,
+}
+
+impl<R: Read> Read for HotkeyReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.read.read(buf)?;
+        if n == 0 {
+            return Ok(0);
+        }
+
+        let EscapeKey(escape) = self.hotkeys.escape;
+        let mut len = 0;
+        for idx in 0..n {
+            let byte = buf[idx];
+            if std::mem::take(&mut self.hotkeys.pending) {
+                match byte {
+                    b's' | b'S' => self.hotkeys.actions.push(Action::Snapshot),
+                    b'd' | b'D' => self.hotkeys.actions.push(Action::Detach),
+                    _ => {
+                        buf[len] = byte;
+                        len += 1;
+                    }
+                }
+            } else if byte == escape {
+                self.hotkeys.pending = true;
+            } else {
+                buf[len] = byte;
+                len += 1;
+            }
+        }
+
+        if len == 0 {
+            // returning 0 would signal EOF
+            Err(ErrorKind::Interrupted.into())
+        } else {
+            Ok(len)
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! The event log of a recording as JSON Lines, for consumption by other renderers and analysis
+//! tools.
+//!
+//! Each line is a JSON object for one frame, with the frame's `time` in seconds since the start
+//! of the recording. The first frame, and frames where the size of the screen changes, contain
+//! the number of `lines` and `columns` and the full `screen`: the cells of each line. Other frames
+//! contain the `damage`: the runs of cells changed since the previous frame, each with its `line`,
+//! the `column` of its first cell and its `cells`. Cells have the keys and attribute names of
+//! `--format csv`. For example:
+//!
+//! ```json
+//! {"time":0.000,"lines":1,"columns":2,"screen":[[{"char":"$","fg":"#839496","bg":"#002b36"},{"char":" ","fg":"#839496","bg":"#002b36"}]]}
+//! {"time":0.250,"damage":[{"line":0,"column":1,"cells":[{"char":"l","fg":"#839496","bg":"#002b36","flags":["bold"]}]}]}
+//! ```
+
+use std::{fmt::Write as _, io::Write, time::Duration};
+
+use termsnap_lib::{Cell, Screen};
+
+use crate::diff;
+
+/// Write one JSON object per frame to `out`.
+pub fn write(frames: &[(Duration, Screen)], mut out: impl Write) -> std::io::Result<()> {
+    let mut previous: Option<&Screen> = None;
+    for (time, screen) in frames {
+        let mut event = format!("{{\"time\":{:.3}", time.as_secs_f64());
+        match previous {
+            Some(previous)
+                if (previous.lines(), previous.columns()) == (screen.lines(), screen.columns()) =>
+            {
+                event.push_str(",\"damage\":[");
+                for (idx, region) in diff::changed_regions(previous, screen).iter().enumerate() {
+                    if idx > 0 {
+                        event.push(',');
+                    }
+                    let _ = write!(
+                        event,
+                        "{{\"line\":{},\"column\":{},\"cells\":",
+                        region.start_line, region.start_column
+                    );
+                    let line = screen.rows().nth(usize::from(region.start_line));
+                    let cells = line.map_or(&[][..], |line| {
+                        &line[usize::from(region.start_column)..usize::from(region.end_column)]
+                    });
+                    push_cells(&mut event, cells);
+                    event.push('}');
+                }
+            }
+            _ => {
+                let _ = write!(
+                    event,
+                    ",\"lines\":{},\"columns\":{},\"screen\":[",
+                    screen.lines(),
+                    screen.columns()
+                );
+                for (idx, line) in screen.rows().enumerate() {
+                    if idx > 0 {
+                        event.push(',');
+                    }
+                    push_cells(&mut event, line);
+                }
+            }
+        }
+        event.push_str("]}\n");
+        out.write_all(event.as_bytes())?;
+        previous = Some(screen);
+    }
+    out.flush()
+}
+
+/// Append a JSON array of the cells.
+fn push_cells(out: &mut String, cells: &[Cell]) {
+    out.push('[');
+    for (idx, cell) in cells.iter().enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"char\":");
+        push_string(out, &cell.chars().collect::<String>());
+        let _ = write!(out, ",\"fg\":\"{}\",\"bg\":\"{}\"", cell.fg, cell.bg);
+
+        let mut flags = cell.attributes().peekable();
+        if flags.peek().is_some() {
+            out.push_str(",\"flags\":[");
+            for (idx, flag) in flags.enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "\"{flag}\"");
+            }
+            out.push(']');
+        }
+        out.push('}');
+    }
+    out.push(']');
+}
+
+/// Append a JSON string literal.
+fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -1,29 +1,57 @@
 #![forbid(unsafe_code)]
 use std::{
-    cell::RefCell,
-    collections::{HashMap, VecDeque},
     io::{IsTerminal, Read, Write},
-    os::fd::AsFd,
-    path::PathBuf,
-    sync::{atomic::AtomicBool, Arc},
-    time::Instant,
+    ops::{Bound, ControlFlow},
+    os::fd::{AsFd, BorrowedFd},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
-use alacritty_terminal::{
-    event::OnResize,
-    tty::{EventedPty, EventedReadWrite, Pty},
-};
-use clap::{Args, Parser};
+use clap::{Args, FromArgMatches, Parser, ValueEnum};
+use regex::Regex;
 use rustix::{
-    event::{PollFd, PollFlags},
+    process::{self, Pid, Signal},
     termios,
 };
+use signal_hook::consts::signal::{SIGINT, SIGTERM, SIGUSR1, SIGWINCH};
 
-use termsnap_lib::{FontMetrics, Screen, Term, VoidPtyWriter};
+use termsnap_lib::{
+    session, AltText, Annotation, AnnotationKind, AnsiSignal, Badge, Blur, BlurStyle, ColorName,
+    Colors, Corner, ExitStatus, FontMetrics, Highlight, HighlightStyle, Layout, OutlineFont,
+    PtyWriter, Region, Rgb, Screen, SvgOptions, Term, VoidPtyWriter, WindowFrame,
+};
 
+mod clipboard;
+mod config;
+mod demo;
+mod diagnostics;
+mod diff;
+mod embed;
+mod font_metrics;
+mod hotkey;
+mod jsonl;
+mod output;
+mod play;
 mod poll;
-mod ringbuffer;
-use ringbuffer::{IoResult, Ringbuffer};
+mod preview;
+mod raster;
+mod record;
+mod replay;
+mod sanitize;
+mod selftest;
+mod theme;
+mod tmux;
+mod typing;
+mod typst;
+mod watch;
+use hotkey::{Action, EscapeKey, Hotkeys};
+use record::{RecordOptions, Recorder};
+use sanitize::Sanitizer;
+use typing::{Step, Typist};
 
 #[cfg(test)]
 mod tests;
@@ -51,7 +79,7 @@ fn with_raw<F: AsFd, R>(mut fd: F, f: impl FnOnce(&mut F) -> R) -> R {
 
 /// The SVG generated by Termsnap makes assumptions about the metrics of the font used for text
 /// rendering. The user can override these metrics.
-#[derive(Debug, Args)]
+#[derive(Clone, Debug, Args)]
 struct FontMetricsArg {
     /// The number of font units per Em. To scale the font to a specific size, the font metrics are
     /// scaled relative to this unit. For example, the line height in pixels for a font at size
@@ -75,6 +103,348 @@ struct FontMetricsArg {
     font_descent: f32,
 }
 
+impl From<FontMetrics> for FontMetricsArg {
+    fn from(metrics: FontMetrics) -> Self {
+        FontMetricsArg {
+            font_units_per_em: metrics.units_per_em,
+            font_advance: metrics.advance,
+            font_line_height: metrics.line_height,
+            font_descent: metrics.descent,
+        }
+    }
+}
+
+/// A font with known metrics.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FontPresetArg {
+    LiberationMono,
+    Consolas,
+    Menlo,
+    SourceCodePro,
+    /// Iosevka Extended.
+    Iosevka,
+    JetbrainsMono,
+    FiraCode,
+}
+
+impl FontPresetArg {
+    fn metrics(self) -> FontMetrics {
+        match self {
+            FontPresetArg::LiberationMono => FontMetrics::LIBERATION_MONO,
+            FontPresetArg::Consolas => FontMetrics::CONSOLAS,
+            FontPresetArg::Menlo => FontMetrics::MENLO,
+            FontPresetArg::SourceCodePro => FontMetrics::SOURCE_CODE_PRO,
+            FontPresetArg::Iosevka => FontMetrics::IOSEVKA,
+            FontPresetArg::JetbrainsMono => FontMetrics::JETBRAINS_MONO,
+            FontPresetArg::FiraCode => FontMetrics::FIRA_CODE,
+        }
+    }
+
+    fn family(self) -> &'static str {
+        match self {
+            FontPresetArg::LiberationMono => "Liberation Mono",
+            FontPresetArg::Consolas => "Consolas",
+            FontPresetArg::Menlo => "Menlo",
+            FontPresetArg::SourceCodePro => "Source Code Pro",
+            FontPresetArg::Iosevka => "Iosevka Extended",
+            FontPresetArg::JetbrainsMono => "JetBrains Mono",
+            FontPresetArg::FiraCode => "Fira Code",
+        }
+    }
+}
+
+/// A window decoration drawn around the terminal screen.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum WindowFrameArg {
+    /// Do not draw a window decoration.
+    None,
+    /// A window with a title bar with "traffic light" buttons.
+    Macos,
+    /// A window with a title bar with minimize, maximize and close buttons.
+    Generic,
+}
+
+impl From<WindowFrameArg> for WindowFrame {
+    fn from(value: WindowFrameArg) -> Self {
+        match value {
+            WindowFrameArg::None => WindowFrame::None,
+            WindowFrameArg::Macos => WindowFrame::MacOs,
+            WindowFrameArg::Generic => WindowFrame::Generic,
+        }
+    }
+}
+
+/// How a highlighted region is drawn.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum HighlightStyleArg {
+    /// Fill the region with a translucent color.
+    Fill,
+    /// Draw an outline around the region.
+    Outline,
+}
+
+/// A corner of the window.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CornerArg {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl From<CornerArg> for Corner {
+    fn from(value: CornerArg) -> Self {
+        match value {
+            CornerArg::TopLeft => Corner::TopLeft,
+            CornerArg::TopRight => Corner::TopRight,
+            CornerArg::BottomLeft => Corner::BottomLeft,
+            CornerArg::BottomRight => Corner::BottomRight,
+        }
+    }
+}
+
+/// How a blurred region is drawn.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum BlurStyleArg {
+    /// Apply a gaussian blur.
+    Blur,
+    /// Fill each cell with a single color.
+    Pixelate,
+}
+
+impl From<BlurStyleArg> for BlurStyle {
+    fn from(value: BlurStyleArg) -> Self {
+        match value {
+            BlurStyleArg::Blur => BlurStyle::Blur,
+            BlurStyleArg::Pixelate => BlurStyle::Pixelate,
+        }
+    }
+}
+
+impl From<HighlightStyleArg> for HighlightStyle {
+    fn from(value: HighlightStyleArg) -> Self {
+        match value {
+            HighlightStyleArg::Fill => HighlightStyle::Fill,
+            HighlightStyleArg::Outline => HighlightStyle::Outline,
+        }
+    }
+}
+
+/// An environment variable, in the format `NAME=value`.
+#[derive(Clone, Debug, PartialEq)]
+struct EnvVar {
+    name: String,
+    value: String,
+}
+
+impl std::str::FromStr for EnvVar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(EnvVar {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            }),
+            _ => Err(format!("expected `NAME=value`, got `{s}`")),
+        }
+    }
+}
+
+/// Parse a duration such as `2s`, `1.5s` or `500ms`. A number without unit is in seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.strip_suffix("ms") {
+        Some(number) => (number, 0.001),
+        None => (s.strip_suffix('s').unwrap_or(s), 1.),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .and_then(|number| Duration::try_from_secs_f64(number * unit).ok())
+        .ok_or_else(|| format!("expected a duration such as `2s` or `500ms`, got `{s}`"))
+}
+
+/// Parse a scale factor, which must be positive.
+fn parse_scale(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(scale) if scale > 0. && scale.is_finite() => Ok(scale),
+        _ => Err(format!("expected a positive number, got `{s}`")),
+    }
+}
+
+/// Parse a length in pixels, which must not be negative.
+fn parse_length(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(length) if length >= 0. && length.is_finite() => Ok(length),
+        _ => Err(format!("expected a non-negative number, got `{s}`")),
+    }
+}
+
+/// Parse a playback speed, which must be positive.
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed > 0. && speed.is_finite() => Ok(speed),
+        _ => Err(format!("expected a positive number, got `{s}`")),
+    }
+}
+
+/// Parse a contrast ratio, which ranges from 1 to 21.
+fn parse_contrast_ratio(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(ratio) if (1. ..=21.).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("expected a contrast ratio from 1 to 21, got `{s}`")),
+    }
+}
+
+/// An override of a color of the terminal's color table, in the format `NAME=#rrggbb`.
+#[derive(Clone, Debug, PartialEq)]
+struct ColorOverride {
+    name: ColorName,
+    color: Rgb,
+}
+
+impl std::str::FromStr for ColorOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, color) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `NAME=#rrggbb`, got `{s}`"))?;
+        Ok(ColorOverride {
+            name: name.parse().map_err(|err| format!("{err}, got `{name}`"))?,
+            color: color
+                .parse()
+                .map_err(|err| format!("{err}, got `{color}`"))?,
+        })
+    }
+}
+
+/// The output format.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    /// A static SVG of the final terminal screen.
+    Svg,
+    /// An animated GIF of the terminal screen as the command runs.
+    Gif,
+    /// A Typst block with the text of the final terminal screen placed on a monospaced grid, for
+    /// including selectable text in Typst documents. Uses `--font` and the font metrics.
+    Typst,
+    /// A Markdown image of the SVG, for pasting into READMEs. The SVG is written to `--out` and
+    /// referenced by its path, or is inlined as a data URI if `--out` is not set. The Markdown is
+    /// printed to standard output.
+    Md,
+    /// An HTML image of the SVG, like `md`.
+    Html,
+    /// A table of the cells of the final terminal screen, with one row per cell giving its
+    /// position, characters, colors and text attributes, for analysis in external tools.
+    Csv,
+    /// The table of `csv` as tab-separated values.
+    Tsv,
+    /// A log of the terminal screen as the command runs, recorded like `gif`, in JSON Lines. The
+    /// first line holds the full screen, each following line the cells changed in a frame.
+    Jsonl,
+}
+
+impl Format {
+    /// The name of the format on the command line.
+    fn name(self) -> &'static str {
+        match self {
+            Format::Svg => "svg",
+            Format::Gif => "gif",
+            Format::Typst => "typst",
+            Format::Md => "md",
+            Format::Html => "html",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Jsonl => "jsonl",
+        }
+    }
+
+    /// Whether the format records the terminal screen as the command runs.
+    fn is_recording(self) -> bool {
+        matches!(self, Format::Gif | Format::Jsonl)
+    }
+
+    /// Whether the format renders each screen as a document of its own, rather than as an SVG.
+    fn is_document(self) -> bool {
+        matches!(self, Format::Typst | Format::Csv | Format::Tsv)
+    }
+}
+
+/// The format of the output copied to the clipboard.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CopyFormat {
+    /// The SVG, as `image/svg+xml`.
+    Svg,
+    /// The SVG rasterized to a PNG, which can be pasted into most chat applications.
+    Png,
+}
+
+/// The terminal screen buffer to render.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ScreenArg {
+    /// The screen buffer that is active when the command exits.
+    Active,
+    /// The main screen buffer.
+    Main,
+    /// The alternate screen buffer, as used by full-screen programs.
+    Alt,
+    /// Both the main and the alternate screen buffer, written to separate SVGs.
+    Both,
+}
+
+/// The direction in which the screens of multiple panes are placed.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PaneDirection {
+    /// Place the panes side-by-side, from left to right.
+    Horizontal,
+    /// Stack the panes, from top to bottom.
+    Vertical,
+}
+
+/// A rectangular region of the terminal screen, in the format `lines,columns` where both are
+/// half-open ranges `start:end`. Either end of a range can be omitted, e.g., `:1,0:60`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Crop {
+    lines: (Bound<u16>, Bound<u16>),
+    columns: (Bound<u16>, Bound<u16>),
+}
+
+impl std::str::FromStr for Crop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_range(range: &str) -> Result<(Bound<u16>, Bound<u16>), String> {
+            let (start, end) = range
+                .split_once(':')
+                .ok_or_else(|| format!("expected a range `start:end`, got `{range}`"))?;
+            let parse = |bound: &str| -> Result<Option<u16>, String> {
+                let bound = bound.trim();
+                if bound.is_empty() {
+                    Ok(None)
+                } else {
+                    bound
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| format!("invalid range bound `{bound}`"))
+                }
+            };
+            Ok((
+                parse(start)?.map_or(Bound::Unbounded, Bound::Included),
+                parse(end)?.map_or(Bound::Unbounded, Bound::Excluded),
+            ))
+        }
+
+        let (lines, columns) = s
+            .split_once(',')
+            .ok_or_else(|| format!("expected `lines,columns`, got `{s}`"))?;
+        Ok(Crop {
+            lines: parse_range(lines)?,
+            columns: parse_range(columns)?,
+        })
+    }
+}
+
 /// Create an SVG of a command's output by running it in a pseudo-terminal (PTY) and interpreting
 /// the command's output by an in-memory terminal emulator.
 ///
@@ -83,9 +453,25 @@ struct FontMetricsArg {
 /// non-interactively, data on standard input is sent by Termsnap as input to the child PTY (e.g.,
 /// sending 0x03 (^C) causes the PTY driver to send the SIGINT interrupt to the child command). The
 /// child PTY's output is not shown.
-#[derive(Debug, Parser)]
-#[command(version)]
+#[derive(Clone, Debug, Parser)]
+#[command(
+    version,
+    args_conflicts_with_subcommands = true,
+    disable_help_subcommand = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    subcommand: Option<Subcommand>,
+
+    /// Without a subcommand, Termsnap behaves as `termsnap run`.
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+/// Options for running a command, or reading terminal output from standard input, and rendering
+/// the result.
+#[derive(Clone, Debug, Args)]
+struct RunArgs {
     /// Run the command interactively. This prevents the SVG from being output on standard output.
     /// Use `--out` to specify a file for storing the SVG.
     ///
@@ -96,42 +482,197 @@ struct Cli {
     #[arg(short, long)]
     interactive: bool,
 
-    /// A location for storing the resulting SVG.
-    #[arg(short, long)]
-    out: Option<PathBuf>,
-
-    /// The number of lines in the emulated terminal. If unset, this defaults to value of the LINES
-    /// environment variable if set, or 24 otherwise.
-    ///
-    /// This setting is ignored if `--interactive` is set.
-    #[arg(short, long)]
-    lines: Option<u16>,
-
-    /// The number of columns in the emulated terminal. If unset, this defaults to value of the
-    /// COLUMNS enviornment variable if set, or 80 otherwise.
+    /// Save the raw output of the command to the given file, such that the session can be
+    /// rendered again later, e.g., with different colors or fonts, by `termsnap render
+    /// capture.ansi`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "command",
+        conflicts_with = "pane"
+    )]
+    raw_out: Option<PathBuf>,
+
+    /// Echo the raw output of the command while it runs non-interactively. The output is written
+    /// to standard output if `--out` is set, and to standard error otherwise.
+    #[arg(long, requires = "command", conflicts_with_all = ["interactive", "pane"])]
+    tee: bool,
+
+    /// Type the given text into the command key by key, with human-like delays, before passing on
+    /// Termsnap's standard input. This is useful for demos rendered with `--format gif`. The
+    /// escapes `\n` (the Enter key), `\t`, `\e` (the Escape key), `\\` and `\xHH` are
+    /// interpreted. Each line is typed once the command's output has been quiet for a moment. For
+    /// example:
     ///
-    /// This setting is ignored if `--interactive` is set.
-    #[arg(short, long)]
-    columns: Option<u16>,
+    /// $ termsnap --format gif --out demo.gif --simulate-typing 'ls\nexit\n' -- bash
+    #[arg(
+        long,
+        value_name = "TEXT",
+        value_parser = typing::parse_keys,
+        requires = "command",
+        conflicts_with_all = ["interactive", "pane"]
+    )]
+    simulate_typing: Option<String>,
+
+    /// The typing speed of `--simulate-typing` in words per minute.
+    #[arg(long, default_value_t = 300, requires = "simulate_typing")]
+    wpm: u32,
 
     /// The value of the TERM environment variable to pass to the child process. This defaults to
     /// "linux". You can try setting this to "alacritty" or "xterm-256color" to hint to programs to
     /// use more terminal features.
     ///
+    /// If `--interactive` is set, this defaults to the value of Termsnap's TERM environment
+    /// variable instead, and Termsnap's COLORTERM environment variable is passed to the child
+    /// process as well.
+    ///
     /// See also `man terminfo`.
     #[arg(short, long)]
     term: Option<String>,
 
+    /// The working directory of the child process. Defaults to Termsnap's working directory.
+    #[arg(long, value_name = "DIR")]
+    cwd: Option<PathBuf>,
+
+    /// Set an environment variable for the child process, in the format `NAME=value`. This can be
+    /// given multiple times. Variables set here override LINES, COLUMNS and TERM.
+    #[arg(long, value_name = "NAME=value")]
+    env: Vec<EnvVar>,
+
+    /// Start the child process with a clean environment rather than inheriting Termsnap's
+    /// environment. Only LINES, COLUMNS, TERM and the variables set by `--env` are passed. This is
+    /// useful for reproducible snapshots. Note PATH is not set unless it is passed using `--env`.
+    #[arg(long)]
+    no_inherit_env: bool,
+
     /// Render the terminal screen as it was just prior to the child process's last ANSI signal to
     /// clear or swap the terminal screen buffer. This is useful, for example, when a process that
     /// is exiting requests the terminal to clear its screen.
     ///
     /// If the child process does not emit such a signal, this argument has no effect.
-    #[arg(long)]
+    #[arg(long, conflicts_with = "screen")]
     render_before_clear: bool,
 
+    /// Stamp the command's exit status in a corner of the rendered screen: a check mark if the
+    /// command succeeded, or a cross and the exit code if it failed. The exit status is only known
+    /// if the command exited before the screen was captured. With `--pane`, the first failing
+    /// pane's exit status is shown.
+    #[arg(long)]
+    show_exit_status: bool,
+
+    /// The corner the exit status is drawn in.
+    #[arg(long, value_enum, default_value_t = CornerArg::TopRight)]
+    exit_status_corner: CornerArg,
+
+    /// Render the terminal screen once the command has produced no output for the given number
+    /// of milliseconds, rather than waiting for the command to exit. The command is then
+    /// terminated. This is useful for capturing programs that keep running, such as full-screen
+    /// programs waiting for input.
+    ///
+    /// This only has an effect when a command is run.
+    #[arg(long, value_name = "MS")]
+    settle: Option<u64>,
+
+    /// Write a snapshot of the terminal screen whenever Termsnap receives the SIGUSR1 signal, e.g.,
+    /// sent by `kill -USR1 <pid>` from another shell. Snapshots are numbered and stored alongside
+    /// the file given by `--out`, e.g., "out-1.svg", "out-2.svg", etc.
+    #[arg(long, requires = "out", conflicts_with = "pane")]
+    snapshot_on_signal: bool,
+
+    /// Write a snapshot of the terminal screen whenever the command outputs the private escape
+    /// sequence `OSC 7979 ; snapshot ST`, e.g., `printf '\e]7979;snapshot\e\\'`. This lets
+    /// scripts decide exactly when snapshots are taken. Snapshots are stored as with
+    /// `--snapshot-on-signal`. A snapshot can be named with `OSC 7979 ; snapshot ; NAME ST`, in
+    /// which case it is stored as, e.g., "out-NAME.svg".
+    #[arg(long, requires = "out", conflicts_with = "pane")]
+    snapshot_on_sequence: bool,
+
+    /// In interactive mode, intercept key bindings starting with the given control key, e.g.,
+    /// "ctrl-]" or "^]". Type the key followed by `s` to write a snapshot of the terminal screen,
+    /// or followed by `d` to end the session and render the terminal screen. Type the key twice to
+    /// send it to the command.
+    ///
+    /// Snapshots require `--out` and are stored as with `--snapshot-on-signal`.
+    #[arg(long, value_name = "KEY", requires = "interactive")]
+    escape_key: Option<EscapeKey>,
+
+    /// Keep running, and re-run the command and regenerate the output whenever one of the given
+    /// files changes. Directories are watched for changes to the files directly within them. This
+    /// can be used to keep screenshots up to date while editing the program being captured.
+    ///
+    /// Changes made while the command is running are ignored.
+    #[arg(
+        long,
+        value_name = "PATHS",
+        value_delimiter = ',',
+        requires = "out",
+        requires = "command",
+        conflicts_with = "interactive"
+    )]
+    watch: Vec<PathBuf>,
+
+    /// Render the terminal screen as soon as its text matches the given regular expression,
+    /// rather than waiting for the command to exit. The command is then terminated. Lines of the
+    /// screen are separated by newlines, and trailing whitespace of each line and trailing empty
+    /// lines are removed.
+    ///
+    /// This only has an effect when a command is run.
+    #[arg(long, value_name = "REGEX")]
+    snapshot_on_match: Option<Regex>,
+
+    /// Replay a terminal session recorded by ttyrec, rather than running a command or reading
+    /// data from standard input. With `--format gif`, the recording's timing is preserved.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["interactive", "command", "pane", "timing"])]
+    ttyrec: Option<PathBuf>,
+
+    /// Interpret the data on standard input as a typescript recorded by script(1), replayed
+    /// according to this timing file, e.g., as recorded by
+    ///
+    /// $ script --timing=timing.txt typescript
+    ///
+    /// and rendered by
+    ///
+    /// $ termsnap --format gif --timing timing.txt < typescript
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["interactive", "command", "pane"])]
+    timing: Option<PathBuf>,
+
+    /// Run a command in a separate pane. This can be given multiple times to render the output of
+    /// multiple commands in a single SVG, separated by lines. Each command is run by `sh -c`
+    /// non-interactively in a terminal of `--lines` by `--columns`, with an empty standard input.
+    ///
+    /// For example, to compare the output of two commands side-by-side:
+    ///
+    /// $ termsnap --pane "ls --color=always" --pane "ls --color=always -a"
+    #[arg(long, conflicts_with_all = ["interactive", "command", "screen"])]
+    pane: Vec<String>,
+
+    /// The direction in which multiple panes are placed.
+    #[arg(long, value_enum, default_value_t = PaneDirection::Horizontal)]
+    pane_direction: PaneDirection,
+
     #[command(flatten)]
-    font_metrics: FontMetricsArg,
+    render: RenderArgs,
+
+    /// A capture of terminal output to render, set by `termsnap render`.
+    #[arg(skip)]
+    capture: Option<PathBuf>,
+
+    /// A file to record the command's output to as a ttyrec recording, set by `termsnap record`.
+    #[arg(skip)]
+    record: Option<PathBuf>,
+
+    /// A golden capture to compare the terminal screen against, set by `termsnap diff`.
+    #[arg(skip)]
+    golden: Option<PathBuf>,
+
+    /// Terminal output to render, set by `termsnap tmux-pane` to the output reproducing the pane,
+    /// by `termsnap selftest` to the test pattern and by `termsnap theme show` to the swatch.
+    #[arg(skip)]
+    output: Option<Vec<u8>>,
+
+    /// The steps of a demo script to take, set by `termsnap demo`.
+    #[arg(skip)]
+    steps: Option<Vec<Step>>,
 
     /// The command to run. Its output will be turned into an SVG. If this argument is missing and
     /// Termsnap's STDIN is not a TTY, data on STDIN is interpreted by the terminal emulator and
@@ -144,6 +685,8 @@ struct Cli {
     /// alternatively, send captured terminal output into Termsnap through a pipe:
     ///
     /// $ script -O /dev/null -qc "ls --color=always -l" | termsnap
+    ///
+    /// To run a command with the same name as one of Termsnap's subcommands, separate it by `--`.
     command: Option<String>,
 
     /// Arguments provided to the command.
@@ -151,293 +694,1193 @@ struct Cli {
     args: Option<Vec<String>>,
 }
 
-/// Run the command in the pty non-interactively. Data on Termsnap's stdin is proxied to the child
-/// pty. On EOF of Termsnap's stdin, ^D (End of Transmission) is sent to the child pty.
-fn non_interactive<I>(
-    parent_stdin: &mut I,
-    pty: &mut Pty,
-    lines: u16,
-    columns: u16,
-    render_before_clear: bool,
-) -> anyhow::Result<Screen>
-where
-    I: Read + AsFd,
-{
-    /// ASCII End of Transmission byte (TTYs usually send this when ^D is hit)
-    const END_OF_TRANSMISSION: u8 = 0x04;
+/// Options for rendering the terminal screen, shared by running a command and rendering a
+/// capture.
+#[derive(Clone, Debug, Args)]
+struct RenderArgs {
+    /// A location for storing the resulting SVG.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
 
-    let mut last_written_byte: u8 = 0;
+    /// The output format. With `gif`, the terminal screen is recorded while the command runs and
+    /// rasterized into an animated GIF, using the fonts installed on the system. When rendering
+    /// data on standard input, the GIF has a single frame.
+    #[arg(long, value_enum, default_value_t = Format::Svg)]
+    format: Format,
+
+    /// With `--format gif`, record at most this many frames per second. Output arriving faster is
+    /// coalesced, such that animations of programs redrawing the screen often stay small.
+    /// Identical consecutive frames are always merged.
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..))]
+    max_fps: Option<u32>,
+
+    /// With `--format gif`, speed up the animation by this factor, e.g., `2` plays it at double
+    /// speed and `0.5` at half speed.
+    #[arg(long, default_value_t = 1., value_parser = parse_speed)]
+    speed: f64,
+
+    /// With `--format gif`, shorten pauses longer than the given duration to that duration, e.g.,
+    /// `2s` or `500ms`. This is applied before `--speed`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    idle_limit: Option<Duration>,
+
+    /// With `--format gif`, end the animation after the given duration, e.g., `10s`. Frames
+    /// after it are dropped.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    max_duration: Option<Duration>,
+
+    /// With `--format gif`, play the animation once rather than looping it.
+    #[arg(long)]
+    no_loop: bool,
 
-    let pty_write: RefCell<VecDeque<String>> = RefCell::default();
+    /// With `--format gif`, how long to show the final frame before looping or ending the
+    /// animation, e.g., `3s`.
+    #[arg(long, value_name = "DURATION", default_value = "3s", value_parser = parse_duration)]
+    hold: Duration,
 
-    let mut term = Term::new(lines, columns, |text| {
-        let mut pty_write = pty_write.borrow_mut();
-        if pty_write.len() < 128 {
-            pty_write.push_back(text);
-        }
-    });
+    /// The number of lines in the emulated terminal. If unset, this defaults to value of the LINES
+    /// environment variable if set, or 24 otherwise.
+    ///
+    /// This setting is ignored if `--interactive` is set.
+    #[arg(short, long)]
+    lines: Option<u16>,
 
-    let mut stdin_buf = Ringbuffer::<4096>::new();
-    let mut stdout_buf = [0; 4096];
+    /// The number of columns in the emulated terminal. If unset, this defaults to value of the
+    /// COLUMNS enviornment variable if set, or 80 otherwise.
+    ///
+    /// This setting is ignored if `--interactive` is set.
+    #[arg(short, long)]
+    columns: Option<u16>,
 
-    enum EotState {
-        None,
-        SendEot,
-        SentEot(Instant),
-    }
-    let mut eot_state = EotState::None;
+    /// The terminal screen buffer to render. Full-screen programs usually draw on the alternate
+    /// screen buffer, switching back to the main screen buffer when they exit. If the alternate
+    /// screen buffer is not active when the command exits, the alternate screen is rendered as it
+    /// was when it was last active.
+    ///
+    /// With `both`, `--out` must be set. The main screen is written to the file given by `--out`
+    /// and the alternate screen to the same file name with "-alt" appended, e.g., "out-alt.svg".
+    #[arg(long, value_enum, default_value_t = ScreenArg::Active)]
+    screen: ScreenArg,
+
+    /// Size the rendered screen to the lines actually written to, rather than to the number of
+    /// lines of the emulated terminal. Lines scrolled off the top of the emulated terminal are
+    /// included, up to `--max-lines`. Empty lines at the bottom are excluded.
+    ///
+    /// This only has an effect when data on standard input is rendered, i.e., when no command is
+    /// given.
+    #[arg(long)]
+    auto_size: bool,
 
-    // The terminal screen just prior to clearing (if `render_before_clear`).
-    let mut screen_before_clear = None;
-    loop {
-        if let Some(alacritty_terminal::tty::ChildEvent::Exited(_code)) = pty.next_child_event() {
-            break;
-        }
+    /// The maximum number of lines rendered when `--auto-size` is set. If more lines were written,
+    /// the last lines are rendered.
+    #[arg(long, default_value_t = 1000, requires = "auto_size")]
+    max_lines: u16,
 
-        let send_eot = match eot_state {
-            EotState::None => false,
-            EotState::SendEot => true,
-            EotState::SentEot(instant) => Instant::now().duration_since(instant).as_millis() >= 500,
-        };
+    /// Sanitize the data read before interpreting it: invalid UTF-8 is replaced by replacement
+    /// characters, and escape sequences truncated at the end of the data are dropped. This is
+    /// useful for rendering arbitrary captures, e.g., cut off by `head -c`.
+    ///
+    /// This only has an effect when data on standard input or a capture is rendered.
+    #[arg(long)]
+    sanitize: bool,
 
-        // stop reading parent stdin while we have some special transmission queued
-        let read_stdin = !stdin_buf.is_full()
-            && matches!(eot_state, EotState::None)
-            && pty_write.borrow().is_empty();
+    /// Print diagnostics to standard error, such as the problems found by `--sanitize` and a
+    /// summary of the escape sequences the terminal emulator does not handle.
+    #[arg(short, long)]
+    verbose: bool,
 
-        if stdin_buf.is_empty() {
-            if let Some(text) = pty_write.borrow_mut().pop_front() {
-                if text.len() > stdin_buf.capacity() {
-                    panic!("requested to write more than stdin buf size");
-                }
+    /// Print each escape sequence the terminal emulator does not handle to standard error as it
+    /// is encountered, e.g., to find out why a rendered screen looks wrong.
+    #[arg(long)]
+    log_ansi: bool,
 
-                let _ = stdin_buf.read(&mut text.as_bytes());
-            } else if send_eot {
-                let seq: &[u8] = if last_written_byte == b'\r' {
-                    &[END_OF_TRANSMISSION]
-                } else {
-                    &[b'\r', END_OF_TRANSMISSION]
-                };
-                let _ = stdin_buf.read(&mut &*seq);
-                eot_state = EotState::SentEot(Instant::now());
-            }
-        }
+    /// Fail without writing output if the terminal emulator encounters escape sequences it does
+    /// not handle, e.g., to catch screenshots rendered wrongly in CI. Snapshots taken while the
+    /// command runs are still written.
+    #[arg(long)]
+    strict: bool,
 
-        let poll_result = match poll::poll(
-            [
-                read_stdin.then(|| PollFd::from_borrowed_fd(parent_stdin.as_fd(), PollFlags::IN)),
-                Some(PollFd::from_borrowed_fd(pty.file().as_fd(), PollFlags::IN)),
-                (!stdin_buf.is_empty() || send_eot || !pty_write.borrow().is_empty())
-                    .then(|| PollFd::from_borrowed_fd(pty.file().as_fd(), PollFlags::OUT)),
-            ],
-            // stop blocking every so often so we can resend EOT
-            Some(std::time::Duration::from_millis(500)),
-        ) {
-            Ok(r) => r,
-            Err(err) => {
-                if err.kind() == std::io::ErrorKind::Interrupted {
-                    continue;
-                } else {
-                    anyhow::bail!(err);
-                }
-            }
-        };
+    /// Print statistics of each rendered screen to standard error: the number of distinct colors,
+    /// non-blank cells, the width of the widest line and the usage of text attributes. This is
+    /// useful to budget the size of the output.
+    #[arg(long)]
+    stats: bool,
 
-        if poll_result[0] {
-            // read from parent stdin
-            if matches!(
-                stdin_buf.read(parent_stdin),
-                IoResult::EOF(_) | IoResult::Err { .. }
-            ) {
-                eot_state = EotState::SendEot;
-            }
-        }
+    /// After rendering, display the result in the terminal running Termsnap, using the kitty
+    /// graphics protocol or Sixel graphics, to check it without opening a browser. The SVG is
+    /// rasterized using the fonts installed on the system.
+    #[arg(long)]
+    preview: bool,
+
+    /// After rendering, copy the SVG, or with `--copy=png` a PNG rasterized from it, to the system
+    /// clipboard. This
+    /// uses `wl-copy` or `xclip` on Linux and `pbcopy` on macOS. Without these tools, the SVG is
+    /// copied using the OSC 52 escape sequence, if the terminal supports it.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "svg"
+    )]
+    copy: Option<CopyFormat>,
+
+    /// Read defaults for options from this file, rather than from
+    /// `$XDG_CONFIG_HOME/termsnap/config.toml` (or `~/.config/termsnap/config.toml`). The file
+    /// is a TOML table with the long names of options as keys, e.g.:
+    ///
+    /// window-frame = "macos"
+    ///
+    /// padding = 8
+    ///
+    /// color = ["background=#1e1e2e", "foreground=#cdd6f4"]
+    ///
+    /// Flags are set with `true`, and options that can be given multiple times with arrays.
+    /// Options given on the command line take precedence.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-        if poll_result[1] {
-            // read from pty
-            let pty_stdout = pty.reader();
+    /// Do not read the configuration file.
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
 
-            match pty_stdout.read(&mut stdout_buf) {
-                Ok(read) => {
-                    for &byte in &stdout_buf[..read] {
-                        if render_before_clear {
-                            term.process_with_callback(byte, |term, _signal| {
-                                screen_before_clear = Some(term.current_screen());
-                            })
-                        } else {
-                            term.process(byte)
-                        }
-                    }
-                }
-                Err(_err) => {}
-            }
-        }
+    #[command(flatten)]
+    font_metrics: FontMetricsArg,
 
-        if poll_result[2] {
-            // write to pty
-            let pty_stdin = pty.writer();
+    /// Only render a rectangular region of the terminal screen, specified as ranges of lines and
+    /// columns `line_start:line_end,column_start:column_end`. Ranges are half-open and either end
+    /// can be omitted. For example, `--crop 0:1,:` renders only the first line.
+    #[arg(long)]
+    crop: Option<Crop>,
 
-            let res = stdin_buf.write(pty_stdin);
-            if let Some(byte) = res.bytes().last() {
-                last_written_byte = byte;
-            }
-        }
-    }
+    /// Remove empty lines and columns around the terminal screen's content, such that short
+    /// command output does not produce an SVG with many empty lines. This is applied after
+    /// `--crop`.
+    #[arg(long)]
+    trim: bool,
 
-    if let Some(screen) = screen_before_clear {
-        Ok(screen)
-    } else {
-        Ok(term.current_screen())
-    }
-}
+    /// Override a color of the terminal's color table, in the format `NAME=#rrggbb`. NAME is a
+    /// palette index from 0 to 255, an ANSI color name such as `blue` or `bright-blue`, or one of
+    /// `foreground`, `background` and `cursor`. This can be given multiple times, e.g.:
+    ///
+    /// $ termsnap --color blue=#81a1c1 --color background=#1e1e2e -- ls --color=always
+    #[arg(long, value_name = "NAME=#rrggbb")]
+    color: Vec<ColorOverride>,
 
-/// Run the command in the pty interactively by proxying between its and termsnap's stdin and
-/// stdout. If Termsnap has a controlling terminal it is set to raw mode to pass all input through
-/// to the child pty.
-fn interactive<I, O>(
-    parent_stdin: &mut I,
-    parent_stdout: &mut O,
-    pty: &mut Pty,
-    lines: u16,
-    columns: u16,
-    render_before_clear: bool,
-) -> anyhow::Result<Screen>
-where
-    I: Read + AsFd,
-    O: Write + AsFd,
-{
-    // VoidPtyWriter is used here to ignore report responses from the emulated terminal: requests
-    // are proxied through to termsnap's controlling terminal instead.
-    let mut term = Term::new(lines, columns, VoidPtyWriter);
-
-    let window_size_changed = Arc::new(AtomicBool::new(false));
-    signal_hook::flag::register(
-        signal_hook::consts::signal::SIGWINCH,
-        window_size_changed.clone(),
-    )
-    .expect("failed to set signal handler");
-
-    let screen = with_raw(parent_stdout, move |parent_stdout| {
-        // buffers between parent and pty's stdin/stdout pairs
-        let mut stdin_buf = Ringbuffer::<4096>::new();
-        let mut stdout_buf = Ringbuffer::<4096>::new();
-
-        // The terminal screen just prior to clearing (if `render_before_clear`).
-        let mut screen_before_clear = None;
-        loop {
-            if let Some(alacritty_terminal::tty::ChildEvent::Exited(_code)) = pty.next_child_event()
-            {
-                break;
-            }
+    /// Draw bold text in the bright variants of the normal ANSI colors, like xterm-like terminals
+    /// commonly do. Legacy color schemes often expect this.
+    #[arg(long)]
+    bold_is_bright: bool,
 
-            if window_size_changed.load(std::sync::atomic::Ordering::Relaxed) {
-                window_size_changed.store(false, std::sync::atomic::Ordering::Relaxed);
+    /// Draw a window decoration around the terminal screen.
+    #[arg(long, value_enum, default_value_t = WindowFrameArg::None)]
+    window_frame: WindowFrameArg,
 
-                let winsize = termios::tcgetwinsize(std::io::stdout())?;
-                let lines = winsize.ws_row;
-                let columns = winsize.ws_col;
+    /// Draw a drop shadow below the window decoration. This has no effect if `--window-frame` is
+    /// `none`.
+    #[arg(long)]
+    window_shadow: bool,
 
-                pty.on_resize(alacritty_terminal::event::WindowSize {
-                    num_lines: lines,
-                    num_cols: columns,
-                    cell_width: 1,
-                    cell_height: 1,
-                });
-                term.resize(lines, columns);
-            }
+    /// Space in pixels around the terminal screen, filled with the terminal's background color.
+    #[arg(long, default_value_t = 0., value_parser = parse_length)]
+    padding: f32,
 
-            let poll_result = match poll::poll(
-                [
-                    (!stdin_buf.is_full())
-                        .then(|| PollFd::from_borrowed_fd(parent_stdin.as_fd(), PollFlags::IN)),
-                    (!stdout_buf.is_full())
-                        .then(|| PollFd::from_borrowed_fd(pty.file().as_fd(), PollFlags::IN)),
-                    (!stdin_buf.is_empty())
-                        .then(|| PollFd::from_borrowed_fd(pty.file().as_fd(), PollFlags::OUT)),
-                    (!stdout_buf.is_empty())
-                        .then(|| PollFd::from_borrowed_fd(parent_stdout.as_fd(), PollFlags::OUT)),
-                ],
-                None,
-            ) {
-                Ok(r) => r,
-                Err(err) => {
-                    if err.kind() == std::io::ErrorKind::Interrupted {
-                        continue;
-                    } else {
-                        anyhow::bail!(err);
-                    }
-                }
-            };
+    /// Space in pixels around the outside of the terminal screen and its window decoration.
+    #[arg(long, default_value_t = 0., value_parser = parse_length)]
+    margin: f32,
 
-            if poll_result[0] {
-                let _ = stdin_buf.read(parent_stdin);
-            }
+    /// The color to fill the margin with, in the format #rrggbb. Defaults to the terminal's
+    /// background color, or transparent if `--window-frame` is set.
+    #[arg(long)]
+    margin_color: Option<Rgb>,
 
-            if poll_result[1] {
-                let pty_stdout = pty.reader();
-                let res = stdout_buf.read(pty_stdout);
-                for byte in res.bytes() {
-                    if render_before_clear {
-                        term.process_with_callback(byte, |term, _signal| {
-                            screen_before_clear = Some(term.current_screen());
-                        })
-                    } else {
-                        term.process(byte)
-                    }
-                }
-            }
+    /// Render the terminal title, as set by the command, in the window decoration's title bar or
+    /// as a caption above the terminal screen.
+    #[arg(long)]
+    show_title: bool,
+
+    /// A caption drawn below the terminal screen (and its window decoration, if any), e.g.,
+    /// "termsnap v0.5".
+    #[arg(long, value_name = "TEXT")]
+    caption: Option<String>,
+
+    /// An image drawn as a small watermark in a corner of the terminal screen, e.g., a logo. The
+    /// image is fitted in a box of 96 by 24 pixels. SVG, PNG, JPEG, GIF and WebP images are
+    /// supported.
+    #[arg(long, value_name = "IMAGE_FILE", conflicts_with = "badge_text")]
+    badge: Option<PathBuf>,
+
+    /// Text drawn as a small watermark in a corner of the terminal screen.
+    #[arg(long, value_name = "TEXT")]
+    badge_text: Option<String>,
+
+    /// The corner the badge is drawn in.
+    #[arg(long, value_enum, default_value_t = CornerArg::BottomRight)]
+    badge_corner: CornerArg,
+
+    /// Nudge the foreground color of text toward black or white where its contrast ratio with the
+    /// background color is below RATIO, keeping text legible with color themes that have
+    /// low-contrast color combinations. Contrast ratios range from 1 to 21; WCAG recommends at
+    /// least 4.5 for text.
+    #[arg(long, value_name = "RATIO", value_parser = parse_contrast_ratio)]
+    minimum_contrast: Option<f32>,
+
+    /// Render hidden (concealed) text in the background color, keeping it invisible but
+    /// selectable. By default hidden text is not rendered at all.
+    #[arg(long)]
+    hidden_as_background: bool,
 
-            if poll_result[2] {
-                let pty_stdin = pty.writer();
-                let _ = stdin_buf.write(pty_stdin);
-            }
+    /// Draw block element, sextant and Braille characters as shapes, rather than rendering them
+    /// as text using the font. Charts and graphs drawn using these characters then render crisply
+    /// and consistently, regardless of the font.
+    #[arg(long)]
+    glyph_geometry: bool,
 
-            if poll_result[3] {
-                match stdout_buf.write(parent_stdout) {
-                    IoResult::Ok(bytes) | IoResult::EOF(bytes) => {
-                        if bytes.len() > 0 {
-                            parent_stdout.flush().unwrap();
-                        }
-                    }
-                    IoResult::Err { .. } => {}
-                }
-            }
-        }
+    /// Draw spaces as middle dots and tabs as arrows in a dimmed color, like editors do, e.g., to
+    /// document the exact whitespace output of a formatter or diff tool. Whitespace after the last
+    /// non-whitespace character of a line is not drawn.
+    #[arg(long)]
+    show_whitespace: bool,
 
-        if let Some(screen) = screen_before_clear {
-            Ok(screen)
-        } else {
-            Ok(term.current_screen())
-        }
-    })?;
+    /// Position each character at its cell, rather than stretching runs of text to their width.
+    /// Use this if columns of text are misaligned in some SVG renderer, at the cost of larger
+    /// output.
+    #[arg(long)]
+    exact_grid: bool,
 
-    Ok(screen)
-}
+    /// Make the hyperlinks written by programs using the OSC 8 escape sequence clickable in the
+    /// SVG. Links may point to local files, e.g., those written by `ls --hyperlink`, revealing the
+    /// host name and file paths.
+    #[arg(long)]
+    hyperlinks: bool,
 
-/// Interpret `read` as a stream of ANSI-escaped terminal data. Pass the bytes through a terminal
-/// emulator and return the resulting screen.
-fn from_read(read: &mut impl Read, lines: u16, columns: u16) -> anyhow::Result<Screen> {
-    let mut term = Term::new(lines, columns, VoidPtyWriter);
+    /// Attach a tooltip to each run of text listing the position, codepoints, colors and text
+    /// attributes of its cells, e.g., to find out why a rendered screen differs from the screen in
+    /// a terminal. Positions are in the format of `--highlight`.
+    #[arg(long)]
+    debug_cells: bool,
 
-    for byte in read.bytes() {
-        term.process(byte?);
-    }
+    /// Draw faint borders around each cell, e.g., to check the alignment of the layout of a
+    /// terminal user interface.
+    #[arg(long)]
+    grid: bool,
+
+    /// Draw rulers numbering the lines and columns above and to the left of the screen. Lines and
+    /// columns are counted as in `--highlight`. The padding is increased as needed to make room
+    /// for the rulers.
+    #[arg(long)]
+    rulers: bool,
+
+    /// Draw underlines and strikethroughs as lines positioned from the font metrics, rather than
+    /// as CSS text decorations, whose thickness and position vary between renderers.
+    #[arg(long)]
+    decoration_lines: bool,
+
+    /// Give the SVG an explicit width and height in pixels, of its size multiplied by this factor.
+    /// Without this, the SVG is sized by its `viewBox` only, which some renderers, such as some
+    /// Markdown processors, size unpredictably. The SVG is laid out for a font size of 12 pixels,
+    /// so, e.g., `--scale 1.5` sizes it for text at 18 pixels. This also scales `--format gif`.
+    #[arg(long, value_name = "FACTOR", value_parser = parse_scale)]
+    scale: Option<f32>,
+
+    /// Highlight a region of the terminal screen, in the format `line:column-line:column`. The
+    /// region runs from the first position up to, but not including, the second position, wrapping
+    /// around lines like a text selection. Positions start at 0 and are relative to the rendered
+    /// screen, i.e., after `--crop` and `--trim` are applied. This can be given multiple times.
+    ///
+    /// For example, `--highlight 3:0-3:20` highlights the first 20 cells of the fourth line.
+    #[arg(long, value_name = "REGION")]
+    highlight: Vec<Region>,
+
+    /// The color of highlighted regions, in the format #rrggbb.
+    #[arg(long, default_value = "#ffd700")]
+    highlight_color: Rgb,
+
+    /// How highlighted regions are drawn.
+    #[arg(long, value_enum, default_value_t = HighlightStyleArg::Fill)]
+    highlight_style: HighlightStyleArg,
+
+    /// Blur a region of the terminal screen, in the format of `--highlight`, e.g., to obscure
+    /// sensitive content that `--redact` cannot match, such as QR codes or user names in prompts.
+    /// The text of the region is left out of the SVG. This can be given multiple times.
+    #[arg(long, value_name = "REGION")]
+    blur: Vec<Region>,
+
+    /// How blurred regions are drawn.
+    #[arg(long, value_enum, default_value_t = BlurStyleArg::Blur)]
+    blur_style: BlurStyleArg,
+
+    /// Draw an annotation on top of the terminal screen. Positions are in the format
+    /// `line:column`, starting at 0 and relative to the rendered screen. This can be given
+    /// multiple times. The following annotations are supported:
+    ///
+    /// `callout line:column number`: a numbered badge centered on a cell.
+    ///
+    /// `arrow line:column line:column`: an arrow from one cell pointing at another.
+    ///
+    /// `label line:column text`: a line of text on a colored background.
+    #[arg(long, value_name = "ANNOTATION")]
+    annotate: Vec<AnnotationKind>,
+
+    /// Read annotations from a file, one per line in the format of `--annotate`. Empty lines and
+    /// lines starting with `#` are ignored.
+    #[arg(long, value_name = "FILE")]
+    annotations: Option<PathBuf>,
+
+    /// The color of annotations, in the format #rrggbb.
+    #[arg(long, default_value = "#e5484d")]
+    annotation_color: Rgb,
+
+    /// Append the CSS in this file to the SVG's `<style>` element. The terminal screen is in an
+    /// element with class `screen`, and the title in an element with class `title`.
+    #[arg(long, value_name = "FILE")]
+    extra_css: Option<PathBuf>,
+
+    /// The text alternative of the SVG, used by screen readers and search engines. This makes the
+    /// SVG accessible, see `--accessible`.
+    #[arg(long, value_name = "TEXT")]
+    alt_text: Option<String>,
+
+    /// Replace text matching the given regular expression by block characters before rendering,
+    /// e.g., to keep tokens, hostnames or email addresses out of published screenshots. This can
+    /// be given multiple times. The expression is matched against each line of the terminal
+    /// screen separately, and against the terminal title.
+    ///
+    /// Note the text is not redacted from the output of `--raw-out` and `--tee`.
+    #[arg(long, value_name = "REGEX")]
+    redact: Vec<Regex>,
+
+    /// Fonts for the terminal text, as a comma-separated list, e.g., "JetBrains Mono,Menlo". The
+    /// SVG's `font-family` always ends with `monospace`. Defaults to a list of fonts commonly
+    /// installed on Linux, macOS and Windows.
+    #[arg(long, value_name = "FONTS", value_delimiter = ',')]
+    font: Vec<String>,
+
+    /// Render for a font with known metrics, setting the font metrics and, unless `--font` is
+    /// set, the font. For other fonts, the metrics can be measured with `termsnap font-metrics`.
+    #[arg(long, value_enum, value_name = "FONT", conflicts_with_all = [
+        "font_units_per_em",
+        "font_advance",
+        "font_line_height",
+        "font_descent",
+    ])]
+    font_preset: Option<FontPresetArg>,
+
+    /// Fonts for CJK characters, as a comma-separated list, e.g., "Noto Sans Mono CJK SC". Runs of
+    /// CJK characters are rendered with these fonts, falling back to the terminal fonts. This
+    /// keeps the characters aligned to the grid, rather than leaving the choice of font to the
+    /// viewer.
+    #[arg(long, value_name = "FONTS", value_delimiter = ',')]
+    font_cjk: Vec<String>,
+
+    /// Fonts for emoji, as a comma-separated list, e.g., "Noto Color Emoji". See `--font-cjk`.
+    #[arg(long, value_name = "FONTS", value_delimiter = ',')]
+    font_emoji: Vec<String>,
+
+    /// Fonts for symbols, such as arrows, mathematical operators, dingbats and icons in the
+    /// Unicode private use area, as a comma-separated list, e.g., "Symbols Nerd Font Mono". See
+    /// `--font-cjk`.
+    #[arg(long, value_name = "FONTS", value_delimiter = ',')]
+    font_symbols: Vec<String>,
+
+    /// Draw text as path outlines of the glyphs of the font in this file, rather than as SVG text.
+    /// The SVG then renders identically everywhere, regardless of the fonts installed, but the
+    /// output is larger and text can no longer be selected. The font's metrics are used instead of
+    /// the `--font-*` metrics.
+    #[arg(long, value_name = "FONT_FILE")]
+    text_as_paths: Option<PathBuf>,
+
+    /// Make the SVG accessible to screen readers and search engines: give it the `img` role, a
+    /// title, and the plain-text content of the terminal screen as its text alternative and
+    /// description. Set `--alt-text` to give a different text alternative.
+    #[arg(long)]
+    accessible: bool,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum Subcommand {
+    /// Run a command, or read terminal output from standard input, and render the result. This is
+    /// the default when no subcommand is given.
+    Run(RunArgs),
+    /// Render terminal output captured earlier, e.g., by `--raw-out`, rather than running a
+    /// command. This allows capturing a session once and rendering it many times with different
+    /// options.
+    ///
+    /// $ termsnap --raw-out capture.ansi -- ls --color=always
+    ///
+    /// $ termsnap render capture.ansi --columns 100 --window-frame macos --out ls.svg
+    Render(RenderCommand),
+    /// Run a command like `termsnap run`, and record its output with timing information as a
+    /// ttyrec recording.
+    ///
+    /// The recording can be rendered later, e.g., as an animated GIF:
+    ///
+    /// $ termsnap record session.ttyrec --interactive --out session.svg -- bash
+    ///
+    /// $ termsnap --ttyrec session.ttyrec --format gif --out session.gif
+    Record(RecordCommand),
+    /// Play a recording in the terminal with its original timing, e.g., to review a recording
+    /// made by `termsnap record` before rendering it.
+    ///
+    /// $ termsnap play session.ttyrec --speed 2 --idle-limit 1s
+    Play(PlayCommand),
+    /// Run a command like `termsnap run`, and compare the resulting terminal screen against a
+    /// golden capture. If the screens differ, the differing lines are printed, the terminal screen
+    /// is rendered with the differing cells highlighted, and Termsnap exits with a non-zero exit
+    /// code. This can be used for visual regression testing of command-line programs.
+    ///
+    /// The golden capture holds the raw output of the command, and is rendered with the options
+    /// given here before comparing. Create or update it with `--update`:
+    ///
+    /// $ termsnap diff --update golden.ansi -- ls --color=always
+    ///
+    /// $ termsnap diff golden.ansi --out diff.svg -- ls --color=always
+    Diff(DiffCommand),
+    /// Run a demo script, e.g., to reproducibly take the screenshots of a project's documentation.
+    ///
+    /// The script is a TOML file giving the command to run (defaulting to `sh`) and the steps to
+    /// take: text to type, pauses in milliseconds and named snapshots. For example:
+    ///
+    /// command = ["bash", "--norc"]
+    /// lines = 12
+    /// columns = 60
+    /// wpm = 200
+    /// env = { PS1 = "$ " }
+    ///
+    /// [[step]]
+    /// type = "ls --color=always\n"
+    ///
+    /// [[step]]
+    /// snapshot = "ls"
+    ///
+    /// [[step]]
+    /// pause = 1000
+    ///
+    /// Text is typed as with `--simulate-typing`, and snapshots are taken once the output has
+    /// settled. They are stored alongside the file given by `--out` as, e.g., "out-ls.svg". With
+    /// `--format gif`, snapshots are skipped and the session is rendered as one animation
+    /// instead:
+    ///
+    /// $ termsnap demo demo.toml --format gif --out demo.gif
+    ///
+    /// The command receives no input other than the script's, and the end of input once all steps
+    /// have been taken. A command or `--lines` and `--columns` given here take precedence over
+    /// those of the script.
+    #[command(verbatim_doc_comment)]
+    Demo(DemoCommand),
+    /// Render the visible contents of a running tmux pane, including colors and text attributes.
+    /// This allows taking screenshots of existing sessions without restarting their programs
+    /// under Termsnap. The terminal size defaults to the pane's size.
+    ///
+    /// $ termsnap tmux-pane mysession:1.0 --out pane.svg
+    TmuxPane(TmuxPaneCommand),
+    /// Render a test pattern exercising the 256-color palette, truecolor gradients, text
+    /// attributes, box drawing and wide characters. This allows verifying that fonts, font metrics
+    /// and color themes render correctly before capturing real sessions. The terminal size
+    /// defaults to the pattern's size.
+    ///
+    /// $ termsnap selftest --font "JetBrains Mono" --color background=#1e1e2e --out selftest.svg
+    Selftest(SelftestCommand),
+    /// Manage color themes.
+    #[command(subcommand)]
+    Theme(ThemeCommand),
+    /// Measure the metrics of a font file, and print them as options for the configuration file.
+    /// Rendering with the metrics of the font used to display the SVG avoids overlap or gaps
+    /// between box drawing characters.
+    ///
+    /// $ termsnap font-metrics JetBrainsMono-Regular.ttf >> ~/.config/termsnap/config.toml
+    ///
+    /// If `--out` is set, the test pattern of `termsnap selftest` is also rendered with the
+    /// metrics and, unless `--font` is set, the font's family name, for checking the metrics.
+    FontMetrics(FontMetricsCommand),
+}
+
+#[derive(Clone, Debug, Args)]
+struct FontMetricsCommand {
+    /// The font file, in OpenType or TrueType format.
+    font_file: PathBuf,
+
+    /// The index of the font to measure in a font collection file.
+    #[arg(long, default_value_t = 0)]
+    index: u32,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum ThemeCommand {
+    /// Render a labeled swatch of a theme's 16 ANSI colors and its foreground, background and
+    /// cursor colors, e.g., to review a custom theme. The theme is the built-in `solarized-dark`
+    /// or a theme file, listing overrides of the color table in the format of `--color`, one per
+    /// line:
+    ///
+    /// background=#1e1e2e
+    /// foreground=#cdd6f4
+    ///
+    /// Overrides given by `--color` are applied on top of the theme.
+    ///
+    /// $ termsnap theme show mocha.theme --out mocha.svg
+    #[command(verbatim_doc_comment)]
+    Show(ThemeShowCommand),
+}
+
+#[derive(Clone, Debug, Args)]
+struct ThemeShowCommand {
+    /// The name of the built-in theme, or the path of a theme file.
+    theme: String,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clone, Debug, Args)]
+struct DemoCommand {
+    /// The demo script.
+    script: PathBuf,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Clone, Debug, Args)]
+struct DiffCommand {
+    /// The file containing the golden capture.
+    golden: PathBuf,
+
+    /// Store the command's output as the new golden capture, rather than comparing against it.
+    /// The terminal screen is then rendered as with `termsnap run`.
+    #[arg(long)]
+    update: bool,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Clone, Debug, Args)]
+struct RecordCommand {
+    /// The file to store the ttyrec recording in.
+    recording: PathBuf,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Clone, Debug, Args)]
+struct PlayCommand {
+    /// The recording: a ttyrec recording, as made by `termsnap record`, an asciicast recording
+    /// with the extension `.cast`, a typescript recorded by `script` with `--timing`, or raw
+    /// output captured by `--raw-out`, which has no timing and is played at once.
+    recording: PathBuf,
+
+    /// The timing file of a typescript recorded by `script --timing=<FILE>`.
+    #[arg(long, value_name = "FILE")]
+    timing: Option<PathBuf>,
+
+    /// Speed up playback by this factor, e.g., `2` plays at double speed and `0.5` at half speed.
+    #[arg(long, default_value_t = 1., value_parser = parse_speed)]
+    speed: f64,
+
+    /// Shorten pauses longer than the given duration to that duration, e.g., `2s` or `500ms`.
+    /// This is applied before `--speed`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    idle_limit: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Args)]
+struct TmuxPaneCommand {
+    /// The pane to capture, e.g., `mysession:1.0` or `%3`. Defaults to the current pane. See
+    /// the section on target panes in `man tmux`.
+    target: Option<String>,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clone, Debug, Args)]
+struct SelftestCommand {
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clone, Debug, Args)]
+struct RenderCommand {
+    /// The file containing the captured terminal output.
+    capture: PathBuf,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+/// Options for capturing the terminal screen of a command.
+struct CaptureOptions<'r> {
+    /// Capture the screen just prior to the command's last signal to clear or swap the screen.
+    render_before_clear: bool,
+    /// The screen buffer to capture.
+    screen: ScreenArg,
+    /// Records frames while the command runs.
+    recorder: Option<&'r mut Recorder>,
+    /// Stop and capture the screen once the command has produced no output for this long.
+    settle: Option<Duration>,
+    /// Stop and capture the screen once its text matches this pattern.
+    snapshot_on_match: Option<&'r Regex>,
+    /// Creates the emulated terminal.
+    emulator: &'r Emulator,
+    /// Writes a snapshot of the screen when SIGUSR1 is received or the snapshot key binding is
+    /// typed.
+    snapshot: Option<&'r mut SnapshotFn<'r>>,
+    /// Starts key bindings in interactive mode.
+    escape_key: Option<EscapeKey>,
+    /// Write snapshots requested by the command through an escape sequence.
+    snapshot_on_sequence: bool,
+    /// Echoes the raw output of the command in non-interactive mode.
+    tee: Option<&'r mut dyn Write>,
+    /// Stores the raw output of the command.
+    raw_out: Option<&'r mut dyn Write>,
+    /// Types simulated input into the command in non-interactive mode, before Termsnap's stdin.
+    typing: Option<Typist>,
+    /// Stores the exit status of the command, if it exits before the screen is captured.
+    exit_status: Option<&'r mut Option<ExitStatus>>,
+}
+
+/// Writes a snapshot of the terminal screen, optionally named.
+type SnapshotFn<'s> = dyn FnMut(Screen, Option<&str>) -> anyhow::Result<()> + 's;
+
+/// The time to wait for the child to exit after forwarding SIGINT or SIGTERM, before rendering the
+/// screen regardless.
+const SIGNAL_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// The state of sending ^D (End of Transmission) to the command once Termsnap's stdin reached
+/// EOF in non-interactive mode.
+enum EotState {
+    None,
+    SendEot,
+    SentEot(Instant),
+}
+
+/// The time after which ^D is sent again if the command is still running.
+const EOT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Drives a [session::Session] for [run_session]: forwards Termsnap's stdin and simulated typing to
+/// the command, forwards the command's output in interactive mode, and takes snapshots, records
+/// frames and decides when to stop according to the [CaptureOptions].
+struct Driver<'r, 'i, I> {
+    stdin: &'i mut I,
+    /// Whether Termsnap's stdin reached EOF or failed.
+    stdin_eof: bool,
+    /// Termsnap's stdout in interactive mode.
+    stdout: Option<&'i mut dyn Write>,
+    eot_state: EotState,
+    /// The last byte queued to be written to the command.
+    last_written_byte: u8,
+    hotkeys: Option<Hotkeys>,
+    render_before_clear: bool,
+    /// The terminal screen just prior to clearing (if `render_before_clear`).
+    screen_before_clear: Option<Screen>,
+    recorder: Option<&'r mut Recorder>,
+    settle: Option<Duration>,
+    /// The pattern the screen text is matched against, and the snapshot of the screen it is
+    /// matched in, reused between matches.
+    snapshot_on_match: Option<(&'r Regex, Screen)>,
+    snapshot: Option<&'r mut SnapshotFn<'r>>,
+    snapshot_on_sequence: bool,
+    /// The snapshots requested by the command while processing its output.
+    requested: Vec<(Screen, Option<String>)>,
+    tee: Option<&'r mut dyn Write>,
+    raw_out: Option<&'r mut dyn Write>,
+    typing: Option<Typist>,
+    /// The signal received to forward to the command, or 0.
+    received_signal: Arc<AtomicUsize>,
+    signaled_at: Option<Instant>,
+    snapshot_requested: Arc<AtomicBool>,
+    /// Set when Termsnap's window size changed, in interactive mode.
+    window_size_changed: Option<Arc<AtomicBool>>,
+}
+
+impl<I: Read + AsFd> session::Hooks for Driver<'_, '_, I> {
+    type Error = anyhow::Error;
+
+    fn tick(
+        &mut self,
+        session: &mut session::Session,
+    ) -> anyhow::Result<ControlFlow<(), Option<Instant>>> {
+        let last_output = session.last_output();
+        if self
+            .settle
+            .is_some_and(|settle| last_output.elapsed() >= settle)
+        {
+            return Ok(ControlFlow::Break(()));
+        }
+        if self.snapshot_requested.swap(false, Ordering::Relaxed) {
+            if let Some(snapshot) = self.snapshot.as_mut() {
+                snapshot(session.screen(), None)?;
+            }
+        }
+
+        if let Some(signal) =
+            Signal::from_raw(self.received_signal.swap(0, Ordering::Relaxed) as i32)
+        {
+            // the child is the leader of its own process group
+            let child = Pid::from_child(session.pty().child());
+            let _ = process::kill_process_group(child, signal);
+            self.signaled_at = Some(Instant::now());
+        }
+        if self
+            .signaled_at
+            .is_some_and(|at| at.elapsed() >= SIGNAL_GRACE_PERIOD)
+        {
+            return Ok(ControlFlow::Break(()));
+        }
+
+        if self
+            .window_size_changed
+            .as_ref()
+            .is_some_and(|changed| changed.swap(false, Ordering::Relaxed))
+        {
+            let winsize = termios::tcgetwinsize(std::io::stdout())?;
+            // the window may briefly be collapsed to nothing, keep the last size
+            let _ = session.resize(winsize.ws_row, winsize.ws_col);
+        }
+
+        let send_eot = match self.eot_state {
+            EotState::None => false,
+            EotState::SendEot => true,
+            EotState::SentEot(at) => at.elapsed() >= EOT_INTERVAL,
+        };
+
+        // simulated input and ^D wait for earlier input and the terminal's responses to be
+        // written
+        if session.queued_input() == 0 {
+            let step = self
+                .typing
+                .as_mut()
+                .and_then(|typing| typing.next_step(last_output));
+            if let Some(step) = step {
+                match step {
+                    Step::Key(key) => {
+                        let key = key.encode_utf8(&mut [0; 4]).as_bytes().to_vec();
+                        self.write_input(session, &key);
+                    }
+                    Step::Snapshot(name) => {
+                        if let Some(snapshot) = self.snapshot.as_mut() {
+                            snapshot(session.screen(), Some(&name))?;
+                        }
+                    }
+                    Step::Pause(_) => {}
+                }
+            } else if send_eot {
+                /// ASCII End of Transmission byte (TTYs usually send this when ^D is hit)
+                const END_OF_TRANSMISSION: u8 = 0x04;
+
+                if self.last_written_byte == b'\r' {
+                    self.write_input(session, &[END_OF_TRANSMISSION]);
+                } else {
+                    self.write_input(session, &[b'\r', END_OF_TRANSMISSION]);
+                }
+                self.eot_state = EotState::SentEot(Instant::now());
+            }
+        }
+
+        // wake up to check whether the output settled, to type the next key, to resend ^D and to
+        // stop after forwarding a signal
+        let wake = [
+            self.settle.map(|settle| last_output + settle),
+            self.typing
+                .as_ref()
+                .and_then(|typing| typing.next_due(last_output)),
+            match self.eot_state {
+                EotState::SentEot(at) => Some(at + EOT_INTERVAL),
+                _ => None,
+            },
+            self.signaled_at.map(|at| at + SIGNAL_GRACE_PERIOD),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        Ok(ControlFlow::Continue(wake))
+    }
+
+    fn input<'h>(&'h self, session: &session::Session) -> Option<BorrowedFd<'h>> {
+        // stop reading stdin while ^D is queued or simulated input is still being typed
+        let read = !self.stdin_eof
+            && session.queued_input() < 4096
+            && matches!(self.eot_state, EotState::None)
+            && self.typing.as_ref().is_none_or(Typist::is_done);
+        read.then(|| self.stdin.as_fd())
+    }
+
+    fn readable(&mut self, session: &mut session::Session) -> anyhow::Result<ControlFlow<()>> {
+        let mut buf = [0; 4096];
+        let read = match self.hotkeys.as_mut() {
+            Some(hotkeys) => hotkeys.reader(&mut self.stdin).read(&mut buf),
+            None => self.stdin.read(&mut buf),
+        };
+        match read {
+            Ok(0) => self.stdin_closed(),
+            Ok(read) => self.write_input(session, &buf[..read]),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => self.stdin_closed(),
+        }
+
+        let actions = self
+            .hotkeys
+            .as_mut()
+            .map(Hotkeys::take_actions)
+            .unwrap_or_default();
+        for action in actions {
+            match action {
+                Action::Snapshot => {
+                    if let Some(snapshot) = self.snapshot.as_mut() {
+                        snapshot(session.screen(), None)?;
+                    }
+                }
+                Action::Detach => return Ok(ControlFlow::Break(())),
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn signal(&mut self, term: &Term<impl PtyWriter>, signal: AnsiSignal) {
+        match signal {
+            AnsiSignal::Snapshot { name } if self.snapshot_on_sequence => {
+                self.requested.push((term.current_screen(), name));
+            }
+            AnsiSignal::ClearScreen | AnsiSignal::AlternateScreenBuffer { .. }
+                if self.render_before_clear =>
+            {
+                self.screen_before_clear = Some(term.current_screen());
+            }
+            _ => {}
+        }
+    }
+
+    fn output(
+        &mut self,
+        term: &mut Term<impl PtyWriter>,
+        output: &[u8],
+    ) -> anyhow::Result<ControlFlow<()>> {
+        if let Some(raw_out) = self.raw_out.as_mut() {
+            raw_out.write_all(output)?;
+        }
+        if let Some(out) = self.tee.as_mut() {
+            // stop echoing if, e.g., the reading end of a pipe was closed
+            if out.write_all(output).and_then(|()| out.flush()).is_err() {
+                self.tee = None;
+            }
+        }
+        if let Some(stdout) = self.stdout.as_mut() {
+            let _ = stdout.write_all(output).and_then(|()| stdout.flush());
+        }
+
+        for (screen, name) in std::mem::take(&mut self.requested) {
+            if let Some(snapshot) = self.snapshot.as_mut() {
+                snapshot(screen, name.as_deref())?;
+            }
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(term);
+        }
+        if let Some((pattern, screen)) = self.snapshot_on_match.as_mut() {
+            term.snapshot_into(screen);
+            if pattern.is_match(&screen.text()) {
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+impl<I> Driver<'_, '_, I> {
+    /// Queue `input` to be written to the command.
+    fn write_input(&mut self, session: &mut session::Session, input: &[u8]) {
+        if let Some(&byte) = input.last() {
+            self.last_written_byte = byte;
+        }
+        session.write_input(input);
+    }
+
+    /// Stop reading Termsnap's stdin, and send ^D to the command in non-interactive mode.
+    fn stdin_closed(&mut self) {
+        self.stdin_eof = true;
+        if self.stdout.is_none() {
+            self.eot_state = EotState::SendEot;
+        }
+    }
+}
+
+/// Run the command in `session` until it exits or the screen is captured, forwarding Termsnap's
+/// stdin to it.
+///
+/// In interactive mode, `interactive` is Termsnap's stdout, to which the command's output is
+/// forwarded; the caller sets the controlling terminal to raw mode to pass all input through.
+/// Requests of the command are answered by the controlling terminal rather than the emulated
+/// terminal.
+///
+/// In non-interactive mode, ^D (End of Transmission) is sent to the command on EOF of Termsnap's
+/// stdin, and SIGINT and SIGTERM are forwarded to the command.
+fn run_session<'i, I>(
+    mut session: session::Session,
+    stdin: &'i mut I,
+    interactive: Option<&'i mut dyn Write>,
+    capture_options: CaptureOptions<'_>,
+) -> anyhow::Result<Vec<Screen>>
+where
+    I: Read + AsFd,
+{
+    let CaptureOptions {
+        render_before_clear,
+        screen,
+        recorder,
+        settle,
+        snapshot_on_match,
+        emulator,
+        snapshot,
+        escape_key,
+        snapshot_on_sequence,
+        tee,
+        raw_out,
+        typing,
+        exit_status,
+    } = capture_options;
+
+    emulator.configure(session.term_mut());
+    session.set_respond(interactive.is_none());
+
+    let received_signal = Arc::new(AtomicUsize::new(0));
+    let mut signal_ids = Vec::new();
+    let window_size_changed = if interactive.is_some() {
+        let window_size_changed = Arc::new(AtomicBool::new(false));
+        signal_ids.push(signal_hook::flag::register(
+            SIGWINCH,
+            window_size_changed.clone(),
+        )?);
+        Some(window_size_changed)
+    } else {
+        // Forward SIGINT and SIGTERM to the child, such that the screen is still rendered when
+        // Termsnap is interrupted. A second signal terminates Termsnap immediately.
+        let signaled = Arc::new(AtomicBool::new(false));
+        for signal in [SIGINT, SIGTERM] {
+            // the shutdown condition is checked before the flag is set by the first signal
+            signal_ids.push(signal_hook::flag::register_conditional_shutdown(
+                signal,
+                1,
+                signaled.clone(),
+            )?);
+            signal_ids.push(signal_hook::flag::register(signal, signaled.clone())?);
+            signal_ids.push(signal_hook::flag::register_usize(
+                signal,
+                received_signal.clone(),
+                signal as usize,
+            )?);
+        }
+        None
+    };
+    let snapshot_requested = Arc::new(AtomicBool::new(false));
+    if snapshot.is_some() {
+        signal_ids.push(signal_hook::flag::register(
+            SIGUSR1,
+            snapshot_requested.clone(),
+        )?);
+    }
+
+    let mut driver = Driver {
+        stdin,
+        stdin_eof: false,
+        hotkeys: interactive
+            .is_some()
+            .then_some(escape_key)
+            .flatten()
+            .map(Hotkeys::new),
+        stdout: interactive,
+        eot_state: EotState::None,
+        last_written_byte: 0,
+        render_before_clear,
+        screen_before_clear: None,
+        recorder,
+        settle,
+        snapshot_on_match: snapshot_on_match
+            .map(|pattern| (pattern, session.term().current_screen())),
+        snapshot_on_sequence: snapshot_on_sequence && snapshot.is_some(),
+        snapshot,
+        requested: Vec::new(),
+        tee,
+        raw_out,
+        typing,
+        received_signal,
+        signaled_at: None,
+        snapshot_requested,
+        window_size_changed,
+    };
+    let result = session.run(&mut driver);
+
+    for id in signal_ids {
+        signal_hook::low_level::unregister(id);
+    }
+    result?;
+
+    if let Some(exit_status) = exit_status.filter(|_| session.has_exited()) {
+        *exit_status = Some(
+            session
+                .exit_code()
+                .map_or(ExitStatus::Signaled, ExitStatus::Code),
+        );
+    }
+    if let Some(screen) = driver.screen_before_clear {
+        Ok(vec![screen])
+    } else {
+        capture(session.term(), screen)
+    }
+}
+
+/// Get snapshots of the screen buffers of `term` selected by `screen`.
+fn capture<W: PtyWriter>(term: &Term<W>, screen: ScreenArg) -> anyhow::Result<Vec<Screen>> {
+    match screen {
+        ScreenArg::Active => Ok(vec![term.current_screen()]),
+        ScreenArg::Main => Ok(vec![term.main_screen()]),
+        ScreenArg::Alt => match term.alternate_screen() {
+            Some(screen) => Ok(vec![screen]),
+            None => anyhow::bail!("`--screen alt` is set, but the alternate screen was not used"),
+        },
+        ScreenArg::Both => {
+            let mut screens = vec![term.main_screen()];
+            match term.alternate_screen() {
+                Some(screen) => screens.push(screen),
+                None => eprintln!(
+                    "Warning: `--screen both` is set, but the alternate screen was not used; only the main screen is rendered"
+                ),
+            }
+            Ok(screens)
+        }
+    }
+}
 
-    Ok(term.current_screen())
+/// Interpret `read` as a stream of ANSI-escaped terminal data. Pass the bytes through a terminal
+/// emulator and return the resulting screens selected by `screen`.
+///
+/// If `auto_size_max_lines` is set, the returned screen contains all lines written to, including
+/// those scrolled off the top of the terminal, up to the given maximum number of lines.
+///
+/// If `sanitize` is set, the data is sanitized first, and the problems found are stored in it.
+fn from_read(
+    read: &mut impl Read,
+    lines: u16,
+    columns: u16,
+    auto_size_max_lines: Option<u16>,
+    screen: ScreenArg,
+    emulator: &Emulator,
+    sanitize: Option<&mut sanitize::Stats>,
+) -> anyhow::Result<Vec<Screen>> {
+    let mut term = emulator.term(lines, columns, auto_size_max_lines, VoidPtyWriter)?;
+
+    let mut sanitizer = sanitize.is_some().then(Sanitizer::new);
+    let mut sanitized = Vec::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match read.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => match sanitizer.as_mut() {
+                Some(sanitizer) => {
+                    sanitized.clear();
+                    sanitizer.feed(&buf[..n], &mut sanitized);
+                    term.process_bytes(&sanitized);
+                }
+                None => term.process_bytes(&buf[..n]),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    if let (Some(sanitizer), Some(stats)) = (sanitizer, sanitize) {
+        sanitized.clear();
+        *stats = sanitizer.finish(&mut sanitized);
+        term.process_bytes(&sanitized);
+    }
+
+    capture_read(&term, auto_size_max_lines, screen)
+}
+
+/// Replay recorded terminal output through a terminal emulator of size `lines` by `columns`. If
+/// `recorder` is given, a frame is recorded after each event at the time of the event.
+fn from_events(
+    events: Vec<replay::Event>,
+    lines: u16,
+    columns: u16,
+    auto_size_max_lines: Option<u16>,
+    screen: ScreenArg,
+    mut recorder: Option<&mut Recorder>,
+    emulator: &Emulator,
+) -> anyhow::Result<Vec<Screen>> {
+    let mut term = emulator.term(lines, columns, auto_size_max_lines, VoidPtyWriter)?;
+
+    for event in events {
+        term.process_bytes(&event.data);
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record_at(event.time, &mut term);
+        }
+    }
+
+    capture_read(&term, auto_size_max_lines, screen)
+}
+
+/// Get snapshots of the screen buffers of `term` after reading data into it, as selected by
+/// `screen`. With `auto_size_max_lines`, the lines actually written to are captured instead.
+fn capture_read<W: PtyWriter>(
+    term: &Term<W>,
+    auto_size_max_lines: Option<u16>,
+    screen: ScreenArg,
+) -> anyhow::Result<Vec<Screen>> {
+    match auto_size_max_lines {
+        Some(max_lines) => {
+            let screen = term.scrollback_screen();
+            Ok(vec![
+                screen.crop(screen.lines().saturating_sub(max_lines).., ..)
+            ])
+        }
+        None => capture(term, screen),
+    }
 }
 
 fn main() -> anyhow::Result<()> {
-    let mut cli = Cli::parse();
+    let Cli {
+        subcommand,
+        run: args,
+    } = {
+        let argv: Vec<_> = std::env::args_os().collect();
+        let matches = config::command(&argv)?.get_matches_from(argv);
+        Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
+    };
+    let mut cli = match subcommand {
+        None => args,
+        Some(Subcommand::Run(args)) => args,
+        Some(Subcommand::Play(command)) => return play::run(command),
+        // the other options are at their defaults, as they conflict with subcommands
+        Some(Subcommand::Render(RenderCommand { capture, render })) => RunArgs {
+            render,
+            capture: Some(capture),
+            ..args
+        },
+        Some(Subcommand::TmuxPane(command)) => tmux::run_args(command, args)?,
+        Some(Subcommand::Theme(ThemeCommand::Show(command))) => theme::run_args(command, args)?,
+        Some(Subcommand::FontMetrics(command)) => match font_metrics::run_args(command, args)? {
+            Some(args) => args,
+            None => return Ok(()),
+        },
+        Some(Subcommand::Selftest(command)) => selftest::run_args(command, args),
+        Some(Subcommand::Record(command)) => record::run_args(command)?,
+        Some(Subcommand::Diff(command)) => diff::run_args(command)?,
+        Some(Subcommand::Demo(command)) => demo::run_args(command)?,
+    };
 
     let mut parent_stdin = std::io::stdin().lock();
     let mut parent_stdout = std::io::stdout().lock();
 
     if cli.interactive {
-        if cli.out.is_none() {
+        if cli.render.out.is_none() {
             anyhow::bail!("`--interactive` is set but no SVG output file is specified in `--out`. See `termsnap --help`.");
         }
 
-        if cli.lines.is_some() || cli.columns.is_some() {
+        if cli.render.lines.is_some() || cli.render.columns.is_some() {
             eprintln!("Warning: Setting `--lines` and `--columns` has no effect when `--interactive` is set");
         }
 
@@ -450,56 +1893,486 @@ fn main() -> anyhow::Result<()> {
         }
     }
 
-    if cli.command.is_none() && parent_stdin.as_fd().is_terminal() {
+    if let Some(cwd) = &cli.cwd {
+        if !cwd.is_dir() {
+            anyhow::bail!(
+                "`--cwd` is set to {}, which is not a directory",
+                cwd.display()
+            );
+        }
+    }
+
+    if cli.render.format.is_recording() && matches!(cli.render.screen, ScreenArg::Both) {
+        anyhow::bail!(
+            "`--screen both` cannot be used with `--format {}`.",
+            cli.render.format.name()
+        );
+    }
+
+    if cli.render.format.is_recording() && cli.snapshot_on_signal {
+        anyhow::bail!(
+            "`--snapshot-on-signal` cannot be used with `--format {}`.",
+            cli.render.format.name()
+        );
+    }
+
+    if cli.render.format.is_recording() && cli.snapshot_on_sequence {
+        anyhow::bail!(
+            "`--snapshot-on-sequence` cannot be used with `--format {}`.",
+            cli.render.format.name()
+        );
+    }
+
+    if (cli.render.format.is_document() || matches!(cli.render.format, Format::Jsonl))
+        && !cli.pane.is_empty()
+    {
+        anyhow::bail!(
+            "`--pane` cannot be used with `--format {}`.",
+            cli.render.format.name()
+        );
+    }
+
+    if matches!(cli.render.screen, ScreenArg::Both) && cli.render.out.is_none() {
+        anyhow::bail!("`--screen both` is set but no SVG output file is specified in `--out`. See `termsnap --help`.");
+    }
+
+    if cli.command.is_none()
+        && cli.pane.is_empty()
+        && cli.ttyrec.is_none()
+        && cli.capture.is_none()
+        && cli.output.is_none()
+        && parent_stdin.as_fd().is_terminal()
+    {
         anyhow::bail!("No command given to execute. See 'termsnap --help'. To use Termsnap without it executing a command, you can pipe data into Termsnap.");
     }
 
-    let out = cli.out.take();
-    let font_metrics = {
-        let m = &cli.font_metrics;
-        FontMetrics {
-            units_per_em: m.font_units_per_em,
-            advance: m.font_advance,
-            line_height: m.font_line_height,
-            descent: m.font_descent,
+    apply_font_preset(&mut cli.render);
+
+    let out = cli.render.out.clone();
+    let outline_font = match &cli.render.text_as_paths {
+        Some(path) => Some(OutlineFont::new(std::fs::read(path)?).map_err(|err| {
+            anyhow::anyhow!("`--text-as-paths` is set to {}: {err}", path.display())
+        })?),
+        None => None,
+    };
+    let font_metrics = match &outline_font {
+        Some(font) => font.metrics(),
+        None => {
+            let m = &cli.render.font_metrics;
+            FontMetrics {
+                units_per_em: m.font_units_per_em,
+                advance: m.font_advance,
+                line_height: m.font_line_height,
+                descent: m.font_descent,
+            }
         }
     };
 
-    let screen = run(cli, &mut parent_stdin, &mut parent_stdout)?;
-
-    let fonts = &[
-        "ui-monospace",
-        "Liberation Mono",
-        "Consolas",
-        "Menlo",
-        "Source Code Pro",
-    ];
+    let format = cli.render.format;
+    let record_options = RecordOptions {
+        max_fps: cli.render.max_fps,
+        speed: cli.render.speed,
+        idle_limit: cli.render.idle_limit,
+        max_duration: cli.render.max_duration,
+        repeat: !cli.render.no_loop,
+        hold: cli.render.hold,
+    };
+    let crop = cli.render.crop;
+    let trim = cli.render.trim;
+    let window_frame = cli.render.window_frame.into();
+    let window_shadow = cli.render.window_shadow;
+    let padding = cli.render.padding;
+    let margin = cli.render.margin;
+    let margin_color = cli.render.margin_color;
+    let show_title = cli.render.show_title;
+    let hidden_as_background = cli.render.hidden_as_background;
+    let glyph_geometry = cli.render.glyph_geometry;
+    let show_whitespace = cli.render.show_whitespace;
+    let exact_grid = cli.render.exact_grid;
+    let hyperlinks = cli.render.hyperlinks;
+    let debug_cells = cli.render.debug_cells;
+    let grid = cli.render.grid;
+    let rulers = cli.render.rulers;
+    let decoration_lines = cli.render.decoration_lines;
+    let scale = cli.render.scale;
+    let highlights = cli
+        .render
+        .highlight
+        .iter()
+        .map(|&region| Highlight {
+            region,
+            color: cli.render.highlight_color,
+            style: cli.render.highlight_style.into(),
+        })
+        .collect::<Vec<_>>();
+    let blurs = cli
+        .render
+        .blur
+        .iter()
+        .map(|&region| Blur {
+            region,
+            style: cli.render.blur_style.into(),
+        })
+        .collect::<Vec<_>>();
+    let annotations = read_annotations(&cli.render)?;
+    let extra_css = cli
+        .render
+        .extra_css
+        .as_ref()
+        .map(std::fs::read_to_string)
+        .transpose()?;
+    let alt_text = cli.render.alt_text.take();
+    let accessible = cli.render.accessible;
+
+    let pane_direction = (!cli.pane.is_empty()).then_some(cli.pane_direction);
+
+    let redact = cli.render.redact.clone();
+    let minimum_contrast = cli.render.minimum_contrast;
+    let process = |mut screen: Screen| {
+        for pattern in &redact {
+            screen = screen.redact(pattern);
+        }
+        if let Some(ratio) = minimum_contrast {
+            screen = screen.with_minimum_contrast(ratio);
+        }
+        if let Some(crop) = crop {
+            screen = screen.crop(crop.lines, crop.columns);
+        }
+        if trim {
+            screen = screen.trimmed();
+        }
+        screen
+    };
 
-    if let Some(out) = out {
+    // Typst warns about fonts that are not installed, and falls back to its bundled monospace font
+    let fonts: Vec<&str> = if cli.render.font.is_empty() && !matches!(format, Format::Typst) {
+        vec![
+            "ui-monospace",
+            "Liberation Mono",
+            "Consolas",
+            "Menlo",
+            "Source Code Pro",
+        ]
+    } else {
+        cli.render.font.iter().map(String::as_str).collect()
+    };
+    let caption = cli.render.caption.as_deref();
+    let badge_image = match &cli.render.badge {
+        Some(path) => Some((
+            image_mime(path)?,
+            std::fs::read(path)
+                .map_err(|err| anyhow::anyhow!("`--badge` is set to {}: {err}", path.display()))?,
+        )),
+        None => None,
+    };
+    let badge = match (&badge_image, &cli.render.badge_text) {
+        (Some((mime, data)), _) => Some(Badge::Image { mime, data }),
+        (None, Some(text)) => Some(Badge::Text(text)),
+        (None, None) => None,
+    };
+    let badge_corner = cli.render.badge_corner.into();
+    let font_cjk: Vec<&str> = cli.render.font_cjk.iter().map(String::as_str).collect();
+    let font_emoji: Vec<&str> = cli.render.font_emoji.iter().map(String::as_str).collect();
+    let font_symbols: Vec<&str> = cli.render.font_symbols.iter().map(String::as_str).collect();
+    let svg_options = SvgOptions::default()
+        .fonts(&fonts)
+        .cjk_fonts(&font_cjk)
+        .emoji_fonts(&font_emoji)
+        .symbol_fonts(&font_symbols)
+        .font_metrics(font_metrics)
+        .window_frame(window_frame)
+        .window_shadow(window_shadow)
+        .padding(padding)
+        .margin(margin)
+        .margin_color(margin_color)
+        .show_title(show_title)
+        .caption(caption)
+        .badge(badge)
+        .badge_corner(badge_corner)
+        .exit_status_corner(cli.exit_status_corner.into())
+        .hidden_as_background(hidden_as_background)
+        .glyph_geometry(glyph_geometry)
+        .show_whitespace(show_whitespace)
+        .exact_grid(exact_grid)
+        .hyperlinks(hyperlinks)
+        .debug_cells(debug_cells)
+        .grid(grid)
+        .rulers(rulers)
+        .decoration_lines(decoration_lines)
+        .scale(scale)
+        .highlights(&highlights)
+        .annotations(&annotations)
+        .blurs(&blurs)
+        .extra_css(extra_css.as_deref())
+        .alt_text(match &alt_text {
+            Some(alt_text) => AltText::Custom(alt_text),
+            None if accessible => AltText::ScreenText,
+            None => AltText::None,
+        })
+        .text_as_paths(outline_font.as_ref());
+
+    // with `--snapshot-on-signal`, `--snapshot-on-sequence` or `--escape-key`, numbered or named
+    // snapshots are written alongside the output file
+    let mut snapshot_count = 0;
+    let mut write_snapshot = |screen: Screen, name: Option<&str>| -> anyhow::Result<()> {
+        let suffix = match name {
+            // the name is chosen by the command, so keep it to a plain file name
+            Some(name) => name
+                .chars()
+                .map(|c| match c {
+                    'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+                    _ => '_',
+                })
+                .collect(),
+            None => {
+                snapshot_count += 1;
+                snapshot_count.to_string()
+            }
+        };
+        let out = out.as_ref().expect("`--out` is required for snapshots");
         let mut file = std::fs::OpenOptions::new()
             .write(true)
             .truncate(true)
             .create(true)
-            .open(out)?;
-        write!(file, "{}", screen.to_svg(fonts, font_metrics))?;
-    } else {
-        println!("{}", screen.to_svg(fonts, font_metrics))
+            .open(output::suffixed_out_path(out, &format!("-{suffix}")))?;
+        if format.is_document() {
+            file.write_all(
+                output::render_document(format, &process(screen), &svg_options).as_bytes(),
+            )?;
+        } else {
+            Layout::screen(process(screen)).write_svg(file, svg_options)?;
+        }
+        Ok(())
+    };
+    let snapshots = cli.snapshot_on_signal
+        || cli.snapshot_on_sequence
+        || (cli.steps.is_some() && !format.is_recording())
+        || (cli.escape_key.is_some() && out.is_some() && !format.is_recording());
+
+    let mut watcher = (!cli.watch.is_empty())
+        .then(|| watch::Watcher::new(cli.watch.clone()))
+        .transpose()?;
+
+    loop {
+        let mut recorder = format.is_recording().then(|| Recorder::new(record_options));
+
+        let mut exit_status = None;
+        let screens = if pane_direction.is_some() {
+            run_panes(
+                cli.clone(),
+                cli.show_exit_status.then_some(&mut exit_status),
+            )
+        } else {
+            run(
+                cli.clone(),
+                &mut parent_stdin,
+                &mut parent_stdout,
+                recorder.as_mut(),
+                snapshots.then_some(&mut write_snapshot),
+                cli.show_exit_status.then_some(&mut exit_status),
+            )
+        };
+        let svg_options = svg_options.exit_status(exit_status);
+
+        if let Ok(screens) = &screens {
+            if cli.render.stats {
+                for screen in screens {
+                    output::print_stats(&process(screen.clone()));
+                }
+            }
+        }
+
+        // the preview and the copy show the final screen, or the panes, as an SVG
+        let rendered = match &screens {
+            Ok(screens) if cli.render.preview || cli.render.copy.is_some() => {
+                let screens = screens.iter().cloned().map(&process);
+                let layout = match pane_direction {
+                    Some(PaneDirection::Horizontal) => Some(Layout::horizontal(screens)),
+                    Some(PaneDirection::Vertical) => Some(Layout::vertical(screens)),
+                    None => screens.take(1).map(Layout::screen).next(),
+                };
+                layout.map(|layout| layout.to_svg_with_options(svg_options).to_string())
+            }
+            _ => None,
+        };
+
+        let result = screens.and_then(|screens| match &cli.golden {
+            Some(golden) => {
+                diff::compare_golden(screens, golden, &cli, &process, out.as_deref(), svg_options)
+            }
+            None => output::write(
+                screens,
+                recorder,
+                pane_direction,
+                &process,
+                out.as_deref(),
+                format,
+                svg_options,
+            ),
+        });
+
+        if let (Ok(()), Some(svg)) = (&result, rendered) {
+            if cli.render.preview {
+                if let Err(err) = preview::show(&svg) {
+                    eprintln!("Warning: cannot preview the output: {err}");
+                }
+            }
+            let copied = match cli.render.copy {
+                Some(CopyFormat::Svg) => clipboard::copy(svg.as_bytes(), "image/svg+xml"),
+                Some(CopyFormat::Png) => raster::Rasterizer::new()
+                    .render_png(&svg)
+                    .and_then(|png| clipboard::copy(&png, "image/png")),
+                None => Ok(()),
+            };
+            if let Err(err) = copied {
+                eprintln!("Warning: cannot copy the output to the clipboard: {err}");
+            }
+        }
+
+        let Some(watcher) = watcher.as_mut() else {
+            return result;
+        };
+        // in watch mode, errors are reported and the command is re-run on the next change
+        match result {
+            Ok(()) => eprintln!("Wrote output, waiting for changes..."),
+            Err(err) => eprintln!("Error: {err:?}\nWaiting for changes..."),
+        }
+        watcher.wait()?;
     }
+}
 
-    Ok(())
+/// Get the MIME type of an image file from its extension.
+fn image_mime(path: &Path) -> anyhow::Result<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    Ok(match extension.as_deref() {
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => anyhow::bail!(
+            "unsupported image format: {} (expected SVG, PNG, JPEG, GIF or WebP)",
+            path.display()
+        ),
+    })
 }
 
-fn run<I, O>(cli: Cli, parent_stdin: &mut I, parent_stdout: &mut O) -> anyhow::Result<Screen>
-where
-    I: Read + AsFd,
-    O: Write + AsFd,
-{
-    let (lines, columns) = if cli.interactive {
+/// Get the annotations given by `--annotate` and read from the `--annotations` file.
+fn read_annotations(args: &RenderArgs) -> anyhow::Result<Vec<Annotation>> {
+    let mut kinds = args.annotate.clone();
+    if let Some(path) = &args.annotations {
+        for (idx, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            kinds.push(
+                line.parse().map_err(|err| {
+                    anyhow::anyhow!("{}, line {}: {err}", path.display(), idx + 1)
+                })?,
+            );
+        }
+    }
+
+    Ok(kinds
+        .into_iter()
+        .map(|kind| Annotation {
+            kind,
+            color: args.annotation_color,
+        })
+        .collect())
+}
+
+/// Set the font metrics and, unless fonts are given, the font of `--font-preset`.
+fn apply_font_preset(args: &mut RenderArgs) {
+    if let Some(preset) = args.font_preset {
+        args.font_metrics = preset.metrics().into();
+        if args.font.is_empty() {
+            args.font.push(preset.family().to_owned());
+        }
+    }
+}
+
+/// The configuration of the emulated terminals.
+struct Emulator {
+    /// The color table, with the overrides given by `--color` applied.
+    colors: Colors,
+    /// Collects the unhandled escape sequences, with `--verbose`, `--log-ansi` or `--strict`.
+    diagnostics: Option<diagnostics::Log>,
+}
+
+impl Emulator {
+    fn new(args: &RenderArgs) -> Self {
+        let mut colors = Colors::default();
+        for &ColorOverride { name, color } in &args.color {
+            colors.set(name, color);
+        }
+        colors.set_bold_is_bright(args.bold_is_bright);
+        Emulator {
+            colors,
+            diagnostics: (args.verbose || args.log_ansi || args.strict)
+                .then(|| diagnostics::Log::new(args.log_ansi)),
+        }
+    }
+
+    /// Create an emulated terminal of `lines` by `columns`. If `scrollback` is set, the scrollback
+    /// history keeps that many lines rather than the default of 10,000.
+    fn term<W: PtyWriter>(
+        &self,
+        lines: u16,
+        columns: u16,
+        scrollback: Option<u16>,
+        pty_writer: W,
+    ) -> anyhow::Result<Term<W>> {
+        let mut builder = Term::builder().lines(lines).columns(columns);
+        if let Some(scrollback) = scrollback {
+            builder = builder.scrollback(usize::from(scrollback));
+        }
+        let mut term = builder.try_build(pty_writer)?;
+        self.configure(&mut term);
+        Ok(term)
+    }
+
+    /// Apply the configuration to an existing emulated terminal.
+    fn configure<W: PtyWriter>(&self, term: &mut Term<W>) {
+        term.set_colors(self.colors.clone());
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.attach(term);
+        }
+    }
+
+    /// Print the summary of the unhandled escape sequences with `--verbose`, and fail if there
+    /// were any with `--strict`.
+    fn finish(&self, args: &RenderArgs) -> anyhow::Result<()> {
+        let Some(summary) = self
+            .diagnostics
+            .as_ref()
+            .and_then(diagnostics::Log::summary)
+        else {
+            return Ok(());
+        };
+        if args.strict {
+            anyhow::bail!("`--strict` is set, but the terminal emulator does not handle some escape sequences; no output is written\n{summary}");
+        }
+        if args.verbose {
+            eprintln!("{summary}");
+        }
+        Ok(())
+    }
+}
+
+/// Get the number of lines and columns of the emulated terminal.
+fn dimensions(cli: &RunArgs) -> (u16, u16) {
+    if cli.interactive {
         termios::tcgetwinsize(std::io::stdout())
             .map(|winsize| (winsize.ws_row, winsize.ws_col))
             .unwrap_or((DEFAULT_NUM_LINES, DEFAULT_NUM_COLUMNS))
     } else {
         let lines: u16 = cli
+            .render
             .lines
             .or_else(|| {
                 std::env::var("LINES")
@@ -508,6 +2381,7 @@ where
             })
             .unwrap_or(DEFAULT_NUM_LINES);
         let columns: u16 = cli
+            .render
             .columns
             .or_else(|| {
                 std::env::var("COLUMNS")
@@ -516,62 +2390,258 @@ where
             })
             .unwrap_or(DEFAULT_NUM_COLUMNS);
         (lines, columns)
-    };
+    }
+}
 
-    let screen = match cli.command {
-        Some(command) => {
-            let mut pty = alacritty_terminal::tty::new(
-                &alacritty_terminal::tty::Options {
-                    shell: Some(alacritty_terminal::tty::Shell::new(
-                        command,
-                        cli.args.unwrap_or_default(),
-                    )),
-                    working_directory: None,
-                    hold: false,
-                    env: {
-                        let mut env = HashMap::new();
-                        env.insert("LINES".to_owned(), lines.to_string());
-                        env.insert("COLUMNS".to_owned(), columns.to_string());
-                        // TODO: if we're running interactively, perhaps TERM should be defaulted
-                        // to that of the controlling terminal
-                        env.insert(
-                            "TERM".to_owned(),
-                            cli.term.unwrap_or_else(|| "linux".to_owned()),
-                        );
-                        env
-                    },
-                },
-                alacritty_terminal::event::WindowSize {
-                    num_lines: lines,
-                    num_cols: columns,
-                    cell_width: 1,
-                    cell_height: 1,
+/// Run each of the `--pane` commands non-interactively, returning their screens. The exit status
+/// of the first failing command, or of the last command if all succeeded, is stored in
+/// `exit_status`.
+fn run_panes(
+    cli: RunArgs,
+    mut exit_status: Option<&mut Option<ExitStatus>>,
+) -> anyhow::Result<Vec<Screen>> {
+    let (lines, columns) = dimensions(&cli);
+    let emulator = Emulator::new(&cli.render);
+
+    let screens = cli
+        .pane
+        .iter()
+        .map(|command| {
+            let session = spawn(
+                "sh".to_owned(),
+                vec!["-c".to_owned(), command.clone()],
+                lines,
+                columns,
+                &cli,
+            )?;
+            let mut stdin = std::fs::File::open("/dev/null")?;
+            let mut pane_status = None;
+            let screens = run_session(
+                session,
+                &mut stdin,
+                None,
+                CaptureOptions {
+                    render_before_clear: cli.render_before_clear,
+                    screen: cli.render.screen,
+                    recorder: None,
+                    settle: cli.settle.map(Duration::from_millis),
+                    snapshot_on_match: cli.snapshot_on_match.as_ref(),
+                    emulator: &emulator,
+                    snapshot: None,
+                    escape_key: None,
+                    snapshot_on_sequence: false,
+                    tee: None,
+                    raw_out: None,
+                    typing: None,
+                    exit_status: Some(&mut pane_status),
                 },
-                0,
-            )
-            .unwrap();
+            )?;
+            if let Some(exit_status) = exit_status.as_mut() {
+                if pane_status.is_some() && exit_status.is_none_or(ExitStatus::success) {
+                    **exit_status = pane_status;
+                }
+            }
+            Ok(screens)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    emulator.finish(&cli.render)?;
 
-            if cli.interactive {
-                interactive(
-                    parent_stdin,
-                    parent_stdout,
-                    &mut pty,
-                    lines,
-                    columns,
-                    cli.render_before_clear,
-                )?
+    Ok(screens.concat())
+}
+
+/// Spawn `command` with arguments `args` in a new pseudo-terminal of `lines` by `columns`. The
+/// child's environment is set up according to `cli`.
+fn spawn(
+    command: String,
+    args: Vec<String>,
+    lines: u16,
+    columns: u16,
+    cli: &RunArgs,
+) -> anyhow::Result<session::Session> {
+    let mut command = session::Command::new(command);
+    command.args(args).size(lines, columns);
+    // when running interactively, the controlling terminal interprets the child's output, so
+    // default to its terminal type and color support
+    let parent_term = cli
+        .interactive
+        .then(|| std::env::var("TERM").ok())
+        .flatten();
+    if let Some(term) = cli.term.clone().or(parent_term) {
+        command.env("TERM", term);
+    }
+    if cli.interactive {
+        if let Ok(colorterm) = std::env::var("COLORTERM") {
+            command.env("COLORTERM", colorterm);
+        }
+    }
+    for EnvVar { name, value } in &cli.env {
+        command.env(name, value);
+    }
+    if cli.no_inherit_env {
+        command.env_clear();
+    }
+    if let Some(cwd) = &cli.cwd {
+        command.current_dir(cwd);
+    }
+
+    Ok(session::Session::spawn(&command)?)
+}
+
+fn run<I, O>(
+    cli: RunArgs,
+    parent_stdin: &mut I,
+    parent_stdout: &mut O,
+    recorder: Option<&mut Recorder>,
+    snapshot: Option<&mut SnapshotFn<'_>>,
+    exit_status: Option<&mut Option<ExitStatus>>,
+) -> anyhow::Result<Vec<Screen>>
+where
+    I: Read + AsFd,
+    O: Write + AsFd,
+{
+    let (lines, columns) = dimensions(&cli);
+    let emulator = Emulator::new(&cli.render);
+
+    let screens = match &cli.command {
+        Some(command) => {
+            let session = spawn(
+                command.clone(),
+                cli.args.clone().unwrap_or_default(),
+                lines,
+                columns,
+                &cli,
+            )?;
+            let mut raw_out: Option<Box<dyn Write>> = match (&cli.raw_out, &cli.record) {
+                (Some(path), _) => Some(Box::new(std::io::BufWriter::new(std::fs::File::create(
+                    path,
+                )?))),
+                (None, Some(path)) => Some(Box::new(replay::TtyrecWriter::new(
+                    std::io::BufWriter::new(std::fs::File::create(path)?),
+                ))),
+                (None, None) => None,
+            };
+
+            let screens = if cli.interactive {
+                let capture_options = CaptureOptions {
+                    render_before_clear: cli.render_before_clear,
+                    screen: cli.render.screen,
+                    recorder,
+                    settle: cli.settle.map(Duration::from_millis),
+                    snapshot_on_match: cli.snapshot_on_match.as_ref(),
+                    emulator: &emulator,
+                    snapshot: snapshot.map(|snapshot| snapshot as _),
+                    escape_key: cli.escape_key,
+                    snapshot_on_sequence: cli.snapshot_on_sequence,
+                    tee: None,
+                    raw_out: raw_out.as_deref_mut().map(|raw_out| raw_out as _),
+                    typing: None,
+                    exit_status,
+                };
+                // requests of the command are answered by the controlling terminal
+                with_raw(parent_stdout, |stdout| {
+                    run_session(session, parent_stdin, Some(stdout), capture_options)
+                })?
             } else {
-                non_interactive(
-                    parent_stdin,
-                    &mut pty,
+                let mut stderr = std::io::stderr();
+                let tee: Option<&mut dyn Write> = match (cli.tee, &cli.render.out) {
+                    (false, _) => None,
+                    (true, Some(_)) => Some(parent_stdout),
+                    (true, None) => Some(&mut stderr),
+                };
+                let typing = match &cli.steps {
+                    Some(steps) => Some(Typist::with_steps(steps.clone(), cli.wpm)),
+                    None => cli
+                        .simulate_typing
+                        .as_deref()
+                        .map(|keys| Typist::new(keys, cli.wpm)),
+                };
+                let capture_options = CaptureOptions {
+                    render_before_clear: cli.render_before_clear,
+                    screen: cli.render.screen,
+                    recorder,
+                    settle: cli.settle.map(Duration::from_millis),
+                    snapshot_on_match: cli.snapshot_on_match.as_ref(),
+                    emulator: &emulator,
+                    snapshot: snapshot.map(|snapshot| snapshot as _),
+                    escape_key: cli.escape_key,
+                    snapshot_on_sequence: cli.snapshot_on_sequence,
+                    tee,
+                    raw_out: raw_out.as_deref_mut().map(|raw_out| raw_out as _),
+                    typing,
+                    exit_status,
+                };
+                if cli.steps.is_some() {
+                    // the demo script is the command's only input
+                    let mut null = std::fs::File::open("/dev/null")?;
+                    run_session(session, &mut null, None, capture_options)?
+                } else {
+                    run_session(session, parent_stdin, None, capture_options)?
+                }
+            };
+
+            if let Some(mut raw_out) = raw_out {
+                raw_out.flush()?;
+            }
+
+            screens
+        }
+        None => {
+            let events = if let Some(path) = &cli.ttyrec {
+                Some(replay::ttyrec(&std::fs::read(path)?)?)
+            } else if let Some(path) = &cli.timing {
+                let mut typescript = Vec::new();
+                parent_stdin.read_to_end(&mut typescript)?;
+                Some(replay::script(
+                    &typescript,
+                    &std::fs::read_to_string(path)?,
+                )?)
+            } else {
+                None
+            };
+
+            // render the capture given to `termsnap render`, the output set by `termsnap tmux-pane`
+            // or `termsnap selftest`, or the data on standard input
+            let mut capture = cli.capture.as_ref().map(std::fs::File::open).transpose()?;
+            let mut output = cli.output.as_deref();
+            let mut read: &mut dyn Read = match (&mut capture, &mut output) {
+                (Some(capture), _) => capture,
+                (None, Some(output)) => output,
+                (None, None) => parent_stdin,
+            };
+
+            match events {
+                Some(events) => from_events(
+                    events,
                     lines,
                     columns,
-                    cli.render_before_clear,
-                )?
+                    cli.render.auto_size.then_some(cli.render.max_lines),
+                    cli.render.screen,
+                    recorder,
+                    &emulator,
+                )?,
+                None => {
+                    let mut stats = cli.render.sanitize.then(sanitize::Stats::default);
+                    let screens = from_read(
+                        &mut read,
+                        lines,
+                        columns,
+                        cli.render.auto_size.then_some(cli.render.max_lines),
+                        cli.render.screen,
+                        &emulator,
+                        stats.as_mut(),
+                    )?;
+                    if let Some(stats) = stats.filter(|_| cli.render.verbose) {
+                        eprintln!(
+                            "Sanitized input: replaced {} invalid UTF-8 sequence(s), dropped {} truncated escape sequence(s)",
+                            stats.invalid_utf8, stats.truncated_sequences
+                        );
+                    }
+                    screens
+                }
             }
         }
-        None => from_read(parent_stdin, lines, columns)?,
     };
+    emulator.finish(&cli.render)?;
 
-    Ok(screen)
+    Ok(screens)
 }
@@ -0,0 +1,213 @@
+//! Writing the captured screens in the output formats.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use termsnap_lib::{AltText, Layout, Screen, ScreenStats, SvgOptions};
+
+use crate::{
+    embed, jsonl, raster,
+    record::{RecordOptions, Recorder},
+    typst, Format, PaneDirection,
+};
+
+/// Render `screen` in a [document format](Format::is_document).
+pub fn render_document(format: Format, screen: &Screen, svg_options: &SvgOptions) -> String {
+    match format {
+        Format::Csv => screen.to_csv(),
+        Format::Tsv => screen.to_tsv(),
+        _ => typst::render(screen, svg_options),
+    }
+}
+
+/// Render the captured screens and write them to `out`, or to standard output if `out` is not
+/// set.
+pub fn write(
+    screens: Vec<Screen>,
+    recorder: Option<Recorder>,
+    pane_direction: Option<PaneDirection>,
+    process: &dyn Fn(Screen) -> Screen,
+    out: Option<&Path>,
+    format: Format,
+    svg_options: SvgOptions,
+) -> anyhow::Result<()> {
+    let animation = recorder
+        .as_ref()
+        .map_or_else(RecordOptions::default, Recorder::options);
+
+    if format.is_document() {
+        for (idx, screen) in screens.into_iter().enumerate() {
+            let document = render_document(format, &process(screen), &svg_options);
+            match out {
+                Some(out) if idx > 0 => std::fs::write(suffixed_out_path(out, "-alt"), document)?,
+                Some(out) => std::fs::write(out, document)?,
+                None => print!("{document}"),
+            }
+        }
+        return Ok(());
+    }
+
+    if let Format::Jsonl = format {
+        let frames: Vec<(Duration, Screen)> = match recorder {
+            Some(recorder) => {
+                let screen = screens.into_iter().next().expect("unreachable");
+                recorder
+                    .finish(screen)
+                    .into_iter()
+                    .map(|frame| (frame.time, process(frame.screen)))
+                    .collect()
+            }
+            None => screens
+                .into_iter()
+                .take(1)
+                .map(|screen| (Duration::ZERO, process(screen)))
+                .collect(),
+        };
+        match out {
+            Some(out) => jsonl::write(
+                &frames,
+                std::io::BufWriter::new(std::fs::File::create(out)?),
+            )?,
+            None => jsonl::write(&frames, std::io::stdout().lock())?,
+        }
+        return Ok(());
+    }
+
+    // with `--screen both`, each screen is written to its own SVG
+    let layouts: Vec<(Duration, Layout)> = match (pane_direction, recorder) {
+        (None, Some(recorder)) => {
+            let screen = screens.into_iter().next().expect("unreachable");
+            recorder
+                .finish(screen)
+                .into_iter()
+                .map(|frame| (frame.time, Layout::screen(process(frame.screen))))
+                .collect()
+        }
+        (None, None) => screens
+            .into_iter()
+            .map(|screen| (Duration::ZERO, Layout::screen(process(screen))))
+            .collect(),
+        (Some(PaneDirection::Horizontal), _) => vec![(
+            Duration::ZERO,
+            Layout::horizontal(screens.into_iter().map(process)),
+        )],
+        (Some(PaneDirection::Vertical), _) => vec![(
+            Duration::ZERO,
+            Layout::vertical(screens.into_iter().map(process)),
+        )],
+    };
+
+    if let Format::Gif = format {
+        let frames = layouts
+            .iter()
+            .map(|(time, layout)| (*time, layout.to_svg_with_options(svg_options).to_string()))
+            .collect::<Vec<_>>();
+        if let Some(out) = out {
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .create(true)
+                .open(out)?;
+            raster::encode_gif(
+                &frames,
+                animation.repeat,
+                animation.hold,
+                std::io::BufWriter::new(file),
+            )?;
+        } else {
+            raster::encode_gif(
+                &frames,
+                animation.repeat,
+                animation.hold,
+                std::io::stdout().lock(),
+            )?;
+        }
+        return Ok(());
+    }
+
+    for (idx, (_, layout)) in layouts.iter().enumerate() {
+        let out = match out {
+            Some(out) if idx > 0 => Some(suffixed_out_path(out, "-alt")),
+            out => out.map(ToOwned::to_owned),
+        };
+
+        if let Some(out) = &out {
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .create(true)
+                .open(out)?;
+            layout.write_svg(file, svg_options)?;
+        }
+
+        // the alt text of the image is that of the SVG, which leaves out blurred text
+        let markup = match format {
+            Format::Md => embed::Markup::Markdown,
+            Format::Html => embed::Markup::Html,
+            _ => {
+                if out.is_none() {
+                    println!("{}", layout.to_svg_with_options(svg_options));
+                }
+                continue;
+            }
+        };
+        let src = match &out {
+            Some(out) => embed::path_url(out),
+            None => embed::svg_data_uri(&layout.to_svg_with_options(svg_options).to_string()),
+        };
+        // the image in the document gets a text alternative even if the SVG is not accessible
+        let alt_text = match svg_options.alt_text {
+            AltText::None => layout.alt_text(&svg_options.alt_text(AltText::ScreenText)),
+            _ => layout.alt_text(&svg_options),
+        };
+        let alt_text = alt_text.unwrap_or_default();
+        println!("{}", embed::image(markup, &alt_text, &src));
+    }
+
+    Ok(())
+}
+
+/// Print the statistics of `screen` to standard error.
+pub fn print_stats(screen: &Screen) {
+    let ScreenStats {
+        colors,
+        foreground_colors,
+        background_colors,
+        non_blank_cells,
+        widest_line,
+        bold_cells,
+        dim_cells,
+        italic_cells,
+        underlined_cells,
+        strikethrough_cells,
+        hidden_cells,
+    } = screen.stats();
+    eprintln!(
+        "Screen of {} lines by {} columns:
+  colors:          {colors} ({foreground_colors} foreground, {background_colors} background)
+  non-blank cells: {non_blank_cells}
+  widest line:     {widest_line}
+  bold cells:      {bold_cells}
+  dim cells:       {dim_cells}
+  italic cells:    {italic_cells}
+  underlined:      {underlined_cells}
+  struck through:  {strikethrough_cells}
+  hidden cells:    {hidden_cells}",
+        screen.lines(),
+        screen.columns(),
+    );
+}
+
+/// Get a path for storing another SVG alongside `out`, by appending `suffix` to the file stem of
+/// `out`. For example, with `--screen both` the alternate screen is stored in "out-alt.svg".
+pub fn suffixed_out_path(out: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = out.file_stem().unwrap_or_default().to_owned();
+    file_name.push(suffix);
+    if let Some(extension) = out.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    out.with_file_name(file_name)
+}
@@ -0,0 +1,70 @@
+//! Playback of terminal session recordings in the terminal running Termsnap.
+
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    replay::{self, Event},
+    PlayCommand,
+};
+
+/// Reset the text attributes and show the cursor, in case the recording ends without doing so.
+const RESET: &[u8] = b"\x1b[0m\x1b[?25h";
+
+/// Write the output of `events` to `out` with the events' timing. Pauses longer than
+/// `idle_limit` are shortened to it, and playback is sped up by `speed`.
+pub fn play(
+    events: &[Event],
+    speed: f64,
+    idle_limit: Option<Duration>,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    let start = Instant::now();
+    let mut time = Duration::ZERO;
+    let mut previous = Duration::ZERO;
+    for event in events {
+        let mut pause = event.time.saturating_sub(previous);
+        previous = event.time;
+        if let Some(idle_limit) = idle_limit {
+            pause = pause.min(idle_limit);
+        }
+        time += pause.div_f64(speed);
+
+        std::thread::sleep(time.saturating_sub(start.elapsed()));
+        out.write_all(&event.data)?;
+        out.flush()?;
+    }
+
+    out.write_all(RESET)?;
+    out.flush()
+}
+
+/// Play the recording of `termsnap play` on standard output.
+pub fn run(command: PlayCommand) -> anyhow::Result<()> {
+    let PlayCommand {
+        recording,
+        timing,
+        speed,
+        idle_limit,
+    } = command;
+
+    let data = std::fs::read(&recording)?;
+    let events = if let Some(timing) = timing {
+        replay::script(&data, &std::fs::read_to_string(timing)?)?
+    } else if recording.extension().is_some_and(|ext| ext == "cast") {
+        replay::asciicast(std::str::from_utf8(&data)?)?
+    } else {
+        // raw output is rarely a valid ttyrec recording
+        replay::ttyrec(&data).unwrap_or_else(|_| {
+            vec![Event {
+                time: Duration::ZERO,
+                data,
+            }]
+        })
+    };
+
+    play(&events, speed, idle_limit, std::io::stdout().lock())?;
+    Ok(())
+}
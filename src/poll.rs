@@ -11,8 +11,8 @@ pub fn poll<const C: usize>(
     let mut polls = ArrayVec::<_, C>::new();
     let mut poll_nums = ArrayVec::<_, C>::new();
 
-    for idx in 0..C {
-        if let Some(poll_fd) = poll_fds[idx].take() {
+    for (idx, poll_fd) in poll_fds.iter_mut().enumerate() {
+        if let Some(poll_fd) = poll_fd.take() {
             polls.push(poll_fd);
             poll_nums.push(idx);
         }
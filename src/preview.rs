@@ -0,0 +1,232 @@
+//! Inline previews of rendered SVGs in the terminal running Termsnap, using the kitty graphics
+//! protocol or Sixel graphics.
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{Read, Write},
+    os::fd::AsFd,
+    time::{Duration, Instant},
+};
+
+use rustix::{
+    event::{PollFd, PollFlags},
+    termios,
+};
+
+use crate::{poll::poll, raster::Rasterizer};
+
+/// How long to wait for the terminal to answer the query for its features.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The size of the chunks of image data sent using the kitty graphics protocol.
+const KITTY_CHUNK: usize = 4096;
+
+/// A protocol for displaying images in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+/// Rasterize `svg` and display it in the controlling terminal, below the cursor. The SVG is
+/// scaled down to fit the width of the terminal. Standard output is not used, such that the
+/// preview is shown even if the output is redirected.
+pub fn show(svg: &str) -> anyhow::Result<()> {
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|err| anyhow::anyhow!("cannot open the terminal: {err}"))?;
+
+    let Some(protocol) = detect(&mut tty) else {
+        anyhow::bail!("the terminal supports neither the kitty graphics protocol nor Sixel");
+    };
+
+    let max_width = termios::tcgetwinsize(&tty)
+        .ok()
+        .map(|winsize| u32::from(winsize.ws_xpixel))
+        .filter(|&width| width > 0)
+        .unwrap_or(u32::MAX);
+    let (width, height, mut rgba) = Rasterizer::new().render_within(svg, max_width)?;
+
+    let image = match protocol {
+        Protocol::Kitty => encode_kitty(width, height, &rgba),
+        Protocol::Sixel => {
+            let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+                anyhow::bail!("the image is too large to preview ({width}x{height} pixels)");
+            };
+            // Sixel images are paletted; quantize the colors like GIF frames
+            let frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+            encode_sixel(
+                width.into(),
+                height.into(),
+                frame.palette.as_deref().unwrap_or_default(),
+                &frame.buffer,
+            )
+        }
+    };
+
+    tty.write_all(image.as_bytes())?;
+    tty.write_all(b"\n")?;
+    tty.flush()?;
+    Ok(())
+}
+
+/// Find the graphics protocol supported by the terminal. Terminals supporting the kitty graphics
+/// protocol are recognized by their environment variables, as querying support requires sending
+/// an image. Otherwise, the terminal is asked for its primary device attributes, which include
+/// Sixel support.
+fn detect(tty: &mut File) -> Option<Protocol> {
+    let env = |var| std::env::var(var).unwrap_or_default();
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || env("TERM").contains("kitty")
+        || matches!(env("TERM_PROGRAM").as_str(), "WezTerm" | "ghostty")
+    {
+        return Some(Protocol::Kitty);
+    }
+
+    let response = crate::with_raw(tty, |tty| -> std::io::Result<Vec<u8>> {
+        tty.write_all(b"\x1b[c")?;
+        tty.flush()?;
+
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let mut response = Vec::new();
+        while !response.ends_with(b"c") {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let [readable] = poll(
+                [Some(PollFd::from_borrowed_fd(tty.as_fd(), PollFlags::IN))],
+                Some(timeout),
+            )?;
+            if !readable {
+                break;
+            }
+            let mut buf = [0; 64];
+            let read = tty.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..read]);
+        }
+        Ok(response)
+    })
+    .ok()?;
+
+    supports_sixel(&response).then_some(Protocol::Sixel)
+}
+
+/// Whether a response to the primary device attributes query lists Sixel graphics, attribute 4.
+pub fn supports_sixel(response: &[u8]) -> bool {
+    let Some(start) = response.windows(3).position(|w| w == b"\x1b[?") else {
+        return false;
+    };
+    let attributes = &response[start + 3..];
+    let end = attributes
+        .iter()
+        .position(|&b| b == b'c')
+        .unwrap_or(attributes.len());
+    attributes[..end]
+        .split(|&b| b == b';')
+        .skip(1)
+        .any(|attribute| attribute == b"4")
+}
+
+/// Encode RGBA pixels as kitty graphics protocol escape sequences transmitting and displaying the
+/// image. Responses from the terminal are suppressed.
+fn encode_kitty(width: u32, height: u32, rgba: &[u8]) -> String {
+    let data = base64(rgba);
+    let mut out = String::new();
+    let chunks = data.as_bytes().chunks(KITTY_CHUNK);
+    let last = chunks.len().saturating_sub(1);
+    for (idx, chunk) in chunks.enumerate() {
+        let more = u8::from(idx < last);
+        if idx == 0 {
+            let _ = write!(out, "\x1b_Ga=T,q=2,f=32,s={width},v={height},m={more};");
+        } else {
+            let _ = write!(out, "\x1b_Gm={more};");
+        }
+        // base64 is ASCII
+        out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        out.push_str("\x1b\\");
+    }
+    out
+}
+
+/// Encode an image of palette indices as Sixel graphics. The palette consists of RGB triplets.
+pub fn encode_sixel(width: usize, height: usize, palette: &[u8], indices: &[u8]) -> String {
+    let mut out = String::new();
+    // pixels are square, and the background is left unchanged
+    let _ = write!(out, "\x1bP0;1;0q\"1;1;{width};{height}");
+    for (idx, rgb) in palette.chunks_exact(3).enumerate() {
+        let [r, g, b] = [rgb[0], rgb[1], rgb[2]].map(|c| u32::from(c) * 100 / 255);
+        let _ = write!(out, "#{idx};2;{r};{g};{b}");
+    }
+
+    // each band of six rows is drawn in one pass per color, returning to the start of the band
+    // in between
+    let mut sixels = vec![vec![0u8; width]; 256];
+    for band in indices.chunks(width * 6) {
+        for row in &mut sixels {
+            row.fill(0);
+        }
+        let mut used = [false; 256];
+        for (y, row) in band.chunks(width).enumerate() {
+            for (x, &idx) in row.iter().enumerate() {
+                sixels[usize::from(idx)][x] |= 1 << y;
+                used[usize::from(idx)] = true;
+            }
+        }
+
+        let mut first = true;
+        for (idx, row) in sixels.iter().enumerate().filter(|(idx, _)| used[*idx]) {
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            let _ = write!(out, "#{idx}");
+
+            let mut x = 0;
+            while x < row.len() {
+                let run = row[x..].iter().take_while(|&&bits| bits == row[x]).count();
+                let c = char::from(0x3f + row[x]);
+                if x + run == row.len() && row[x] == 0 {
+                    break;
+                } else if run > 3 {
+                    let _ = write!(out, "!{run}{c}");
+                } else {
+                    out.extend(std::iter::repeat_n(c, run));
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Encode data as base64 with padding.
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(char::from(
+                    ALPHABET[(bits >> (18 - 6 * idx) & 0x3f) as usize],
+                ));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,147 @@
+//! Rasterization of SVGs, for exporting to image formats.
+
+use std::{io::Write, sync::Arc, time::Duration};
+
+use resvg::{tiny_skia, usvg};
+
+/// The scale at which SVGs are rasterized. The SVGs are sized assuming a font size of 12px; this
+/// results in more legible images.
+const SCALE: f32 = 2.;
+
+/// Renders SVGs to RGBA pixels.
+pub struct Rasterizer {
+    options: usvg::Options<'static>,
+}
+
+impl Rasterizer {
+    pub fn new() -> Self {
+        let mut options = usvg::Options::default();
+        let fontdb = options.fontdb_mut();
+        fontdb.load_system_fonts();
+
+        // the generic `monospace` family defaults to a font that may not be installed
+        let monospace = fontdb
+            .faces()
+            .find(|face| face.monospaced)
+            .and_then(|face| face.families.first())
+            .map(|(family, _)| family.clone());
+        if let Some(family) = monospace {
+            fontdb.set_monospace_family(family);
+        }
+        options.fontdb = Arc::new(std::mem::take(fontdb));
+
+        Rasterizer { options }
+    }
+
+    /// Render an SVG, returning its width, height and non-premultiplied RGBA pixels.
+    pub fn render(&self, svg: &str) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        self.render_within(svg, u32::MAX)
+    }
+
+    /// Render an SVG like [Rasterizer::render], scaled down as needed to be at most `max_width`
+    /// pixels wide.
+    pub fn render_within(&self, svg: &str, max_width: u32) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+        let pixmap = self.pixmap(svg, max_width)?;
+        let rgba = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        Ok((pixmap.width(), pixmap.height(), rgba))
+    }
+
+    /// Render an SVG like [Rasterizer::render], encoded as PNG.
+    pub fn render_png(&self, svg: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(self.pixmap(svg, u32::MAX)?.encode_png()?)
+    }
+
+    fn pixmap(&self, svg: &str, max_width: u32) -> anyhow::Result<tiny_skia::Pixmap> {
+        let tree = usvg::Tree::from_str(svg, &self.options)?;
+        let scale = SCALE.min(max_width as f32 / tree.size().width());
+        let size = tree
+            .size()
+            .to_int_size()
+            .scale_by(scale)
+            .unwrap_or(tree.size().to_int_size());
+
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+            .ok_or_else(|| anyhow::anyhow!("cannot rasterize an empty SVG"))?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+        Ok(pixmap)
+    }
+}
+
+/// Rasterize SVG frames and encode them as an animated GIF, looping if `repeat` is set. Each frame
+/// is shown until the time of the next frame, and the last frame for `hold`. Consecutive
+/// identical frames are encoded once. Frames smaller than the largest frame are extended with
+/// their top-left pixel's color.
+pub fn encode_gif(
+    frames: &[(Duration, String)],
+    repeat: bool,
+    hold: Duration,
+    out: impl Write,
+) -> anyhow::Result<()> {
+    let end = frames
+        .last()
+        .map_or(Duration::ZERO, |(time, _)| *time + hold);
+    let mut frames = frames.iter().collect::<Vec<_>>();
+    frames.dedup_by(|(_, svg), (_, previous)| svg == previous);
+
+    let rasterizer = Rasterizer::new();
+    let images = frames
+        .iter()
+        .map(|(_, svg)| rasterizer.render(svg))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let width = images.iter().map(|(width, _, _)| *width).max().unwrap_or(0);
+    let height = images
+        .iter()
+        .map(|(_, height, _)| *height)
+        .max()
+        .unwrap_or(0);
+    let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        anyhow::bail!("the image is too large to be encoded as GIF ({width}x{height} pixels)");
+    };
+
+    let mut encoder = gif::Encoder::new(out, gif_width, gif_height, &[])?;
+    // without the looping extension, the animation is played once; a loop count of 0 in the
+    // extension means looping forever
+    if repeat {
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+    }
+
+    for (idx, (frame_width, frame_height, rgba)) in images.into_iter().enumerate() {
+        let mut canvas = if (frame_width, frame_height) == (width, height) {
+            rgba
+        } else {
+            let fill = rgba.get(..4).unwrap_or(&[0; 4]).to_vec();
+            let mut canvas = fill.repeat(width as usize * height as usize);
+            for (y, row) in rgba.chunks(frame_width as usize * 4).enumerate() {
+                let start = y * width as usize * 4;
+                canvas[start..start + row.len()].copy_from_slice(row);
+            }
+            canvas
+        };
+
+        let delay = match frames.get(idx + 1) {
+            Some((next, _)) => next,
+            None => &end,
+        }
+        .saturating_sub(frames[idx].0);
+
+        let mut frame = gif::Frame::from_rgba_speed(gif_width, gif_height, &mut canvas, 10);
+        frame.delay = u16::try_from(delay.as_millis() / 10)
+            .unwrap_or(u16::MAX)
+            .max(1);
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(())
+}
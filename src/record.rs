@@ -0,0 +1,181 @@
+//! Recording of the terminal screen over time, for exporting animations.
+
+use std::time::{Duration, Instant};
+
+use termsnap_lib::{PtyWriter, Screen, Term};
+
+use crate::{RecordCommand, RunArgs};
+
+/// The minimum time between two recorded frames. GIF frame delays have a resolution of 10ms.
+const MIN_FRAME_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A snapshot of the terminal screen at a point in time.
+pub struct Frame {
+    /// The time at which the frame was captured, relative to the start of the recording.
+    pub time: Duration,
+    pub screen: Screen,
+}
+
+/// Options for recording frames and adjusting their timing.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordOptions {
+    /// The maximum number of frames per second of the animation.
+    pub max_fps: Option<u32>,
+    /// The factor by which the animation is sped up.
+    pub speed: f64,
+    /// The maximum time between two frames. Longer pauses are shortened to this, before applying
+    /// `speed`.
+    pub idle_limit: Option<Duration>,
+    /// The maximum length of the animation. Later frames are dropped.
+    pub max_duration: Option<Duration>,
+    /// Whether the animation loops.
+    pub repeat: bool,
+    /// How long the last frame is shown, before looping or ending the animation.
+    pub hold: Duration,
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        RecordOptions {
+            max_fps: None,
+            speed: 1.,
+            idle_limit: None,
+            max_duration: None,
+            repeat: true,
+            hold: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Records frames of the terminal screen as the child process produces output.
+pub struct Recorder {
+    start: Instant,
+    options: RecordOptions,
+    /// The minimum time between two recorded frames, in the time of the recording.
+    min_interval: Duration,
+    frames: Vec<Frame>,
+    /// The terminal's current screen, updated with the terminal's damage as frames are recorded.
+    screen: Option<Screen>,
+}
+
+impl Recorder {
+    pub fn new(options: RecordOptions) -> Self {
+        let min_interval = options
+            .max_fps
+            .filter(|&max_fps| max_fps > 0)
+            .map_or(MIN_FRAME_INTERVAL, |max_fps| {
+                (Duration::from_secs(1) / max_fps).max(MIN_FRAME_INTERVAL)
+            })
+            .mul_f64(options.speed);
+        Recorder {
+            start: Instant::now(),
+            options,
+            min_interval,
+            frames: Vec::new(),
+            screen: None,
+        }
+    }
+
+    pub fn options(&self) -> RecordOptions {
+        self.options
+    }
+
+    /// Record a frame of the terminal's current screen. If the screen has not changed since the
+    /// last frame, no frame is recorded. If the last frame was recorded very recently, the last
+    /// frame is updated instead, such that bursts of output are coalesced into one frame.
+    pub fn record<W: PtyWriter>(&mut self, term: &mut Term<W>) {
+        self.record_at(self.start.elapsed(), term);
+    }
+
+    /// Record a frame of the terminal's current screen at the given time, relative to the start
+    /// of the recording. This is used when replaying recorded output.
+    ///
+    /// Only the cells changed since the previous frame are copied from the terminal, so the
+    /// terminal's damage must not be taken by others during the recording.
+    pub fn record_at<W: PtyWriter>(&mut self, time: Duration, term: &mut Term<W>) {
+        let damage = term.take_damage();
+        let screen = match &mut self.screen {
+            Some(screen) => {
+                screen.apply_damage(term, &damage);
+                screen
+            }
+            None => self.screen.insert(term.current_screen()),
+        };
+        // the screen is only copied when a frame keeps it
+        match self.frames.as_mut_slice() {
+            [.., last] if last.screen == *screen => {}
+            [.., before, last] if time.saturating_sub(last.time) < self.min_interval => {
+                // the burst may have ended where the previous frame left off
+                if before.screen == *screen {
+                    self.frames.pop();
+                } else {
+                    last.screen = screen.clone();
+                }
+            }
+            [last] if time.saturating_sub(last.time) < self.min_interval => {
+                last.screen = screen.clone()
+            }
+            _ => self.frames.push(Frame {
+                time,
+                screen: screen.clone(),
+            }),
+        }
+    }
+
+    /// Finish the recording, appending `screen` as the final frame.
+    pub fn finish(mut self, screen: Screen) -> Vec<Frame> {
+        // when replaying, frames may have been recorded ahead of the wall clock
+        let time = match self.frames.last() {
+            Some(last) => self.start.elapsed().max(last.time),
+            None => self.start.elapsed(),
+        };
+        self.frames.push(Frame { time, screen });
+        retime(self.frames, self.options)
+    }
+}
+
+/// Adjust the times of `frames` according to the speed, idle limit and maximum duration of
+/// `options`.
+fn retime(frames: Vec<Frame>, options: RecordOptions) -> Vec<Frame> {
+    let mut retimed = Vec::with_capacity(frames.len());
+    let mut time = Duration::ZERO;
+    let mut previous = Duration::ZERO;
+    for frame in frames {
+        let mut pause = frame.time.saturating_sub(previous);
+        previous = frame.time;
+        if let Some(idle_limit) = options.idle_limit {
+            pause = pause.min(idle_limit);
+        }
+        time += pause.div_f64(options.speed);
+
+        if options
+            .max_duration
+            .is_some_and(|max_duration| time > max_duration)
+        {
+            break;
+        }
+        retimed.push(Frame {
+            time,
+            screen: frame.screen,
+        });
+    }
+    retimed
+}
+
+/// Get the options of `termsnap record`, recording the command's output.
+pub fn run_args(command: RecordCommand) -> anyhow::Result<RunArgs> {
+    let RecordCommand {
+        recording,
+        run: args,
+    } = command;
+    if args.raw_out.is_some() {
+        anyhow::bail!("`--raw-out` cannot be used with `termsnap record`.");
+    }
+    if args.command.is_none() {
+        anyhow::bail!("No command given to record. See 'termsnap record --help'.");
+    }
+    Ok(RunArgs {
+        record: Some(recording),
+        ..args
+    })
+}
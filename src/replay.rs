@@ -0,0 +1,235 @@
+//! Parsing of terminal session recordings, such as those made by ttyrec, script(1) and asciinema,
+//! into timed chunks of terminal output, and writing of ttyrec recordings.
+
+use std::time::Duration;
+
+/// A chunk of terminal output.
+pub struct Event {
+    /// The time at which the output was produced, relative to the start of the recording.
+    pub time: Duration,
+    pub data: Vec<u8>,
+}
+
+/// Parse a ttyrec recording. Each record consists of a header of three little-endian 32-bit
+/// integers (the timestamp's seconds and microseconds, and the length of the data), followed by
+/// the data.
+pub fn ttyrec(mut recording: &[u8]) -> anyhow::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    let mut start = None;
+
+    while !recording.is_empty() {
+        if recording.len() < 12 {
+            anyhow::bail!("ttyrec recording is truncated: incomplete record header");
+        }
+        let field = |idx: usize| {
+            u32::from_le_bytes(
+                recording[idx * 4..idx * 4 + 4]
+                    .try_into()
+                    .expect("unreachable"),
+            )
+        };
+        let timestamp =
+            Duration::from_secs(field(0).into()) + Duration::from_micros(field(1).into());
+        let len = field(2) as usize;
+        recording = &recording[12..];
+
+        if recording.len() < len {
+            anyhow::bail!("ttyrec recording is truncated: incomplete record data");
+        }
+        let start = *start.get_or_insert(timestamp);
+        events.push(Event {
+            time: timestamp.saturating_sub(start),
+            data: recording[..len].to_vec(),
+        });
+        recording = &recording[len..];
+    }
+
+    Ok(events)
+}
+
+/// Parse a typescript and its timing file recorded by `script --timing=<timing> <typescript>`.
+/// Both the classic timing format (lines of "<delay> <bytes>") and the advanced format (lines of
+/// "<type> <delay> <bytes>") are supported. In the advanced format, only output entries are
+/// replayed.
+pub fn script(typescript: &[u8], timing: &str) -> anyhow::Result<Vec<Event>> {
+    // skip the "Script started on ..." header line
+    let mut typescript = if typescript.starts_with(b"Script started") {
+        match typescript.iter().position(|&b| b == b'\n') {
+            Some(idx) => &typescript[idx + 1..],
+            None => &[],
+        }
+    } else {
+        typescript
+    };
+
+    let mut events = Vec::new();
+    let mut time = Duration::ZERO;
+
+    for (idx, line) in timing.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (output, delay, len) = match fields[..] {
+            [] => continue,
+            [delay, len] => (true, delay, len),
+            [kind, delay, len, ..] => (kind == "O", delay, len),
+            _ => anyhow::bail!("invalid timing data on line {}: `{line}`", idx + 1),
+        };
+
+        let delay: f64 = delay
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid delay on line {}: `{delay}`", idx + 1))?;
+        time += Duration::try_from_secs_f64(delay)
+            .map_err(|_| anyhow::anyhow!("invalid delay on line {}: `{delay}`", idx + 1))?;
+
+        if !output {
+            continue;
+        }
+        let len: usize = len
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid byte count on line {}: `{len}`", idx + 1))?;
+        if typescript.len() < len {
+            anyhow::bail!("the typescript is shorter than its timing data");
+        }
+        events.push(Event {
+            time,
+            data: typescript[..len].to_vec(),
+        });
+        typescript = &typescript[len..];
+    }
+
+    Ok(events)
+}
+
+/// Parse an asciicast recording, as made by asciinema. The first line is a JSON object with the
+/// recording's metadata, and each following line a JSON array of an event's time, type and data.
+/// Only output events are replayed. Times are relative to the start of the recording in version 2
+/// of the format, and relative to the previous event in version 3.
+pub fn asciicast(recording: &str) -> anyhow::Result<Vec<Event>> {
+    let mut lines = recording.lines().enumerate();
+    let header: String = match lines.next() {
+        Some((_, header)) if header.trim_start().starts_with('{') => {
+            header.chars().filter(|c| !c.is_whitespace()).collect()
+        }
+        _ => anyhow::bail!("asciicast recording does not start with a header"),
+    };
+    let relative = header.contains("\"version\":3");
+
+    let mut events = Vec::new();
+    let mut time = Duration::ZERO;
+    for (idx, line) in lines {
+        let line = line.trim();
+        // version 3 allows comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || anyhow::anyhow!("invalid asciicast event on line {}: `{line}`", idx + 1);
+
+        let rest = line.strip_prefix('[').ok_or_else(invalid)?;
+        let (event_time, rest) = rest.split_once(',').ok_or_else(invalid)?;
+        let event_time = event_time
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(invalid)?;
+        let (kind, rest) = json_string(rest.trim_start()).ok_or_else(invalid)?;
+        let rest = rest.trim_start().strip_prefix(',').ok_or_else(invalid)?;
+        let (data, rest) = json_string(rest.trim_start()).ok_or_else(invalid)?;
+        if !rest.trim_start().starts_with(']') {
+            return Err(invalid());
+        }
+
+        time = if relative {
+            time + event_time
+        } else {
+            event_time
+        };
+        if kind == "o" {
+            events.push(Event {
+                time,
+                data: data.into_bytes(),
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+/// Parse a JSON string literal at the start of `s`, returning the string and the rest of `s`.
+fn json_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+    // a high surrogate of a character escaped as a UTF-16 surrogate pair
+    let mut high_surrogate = None;
+    loop {
+        let (idx, c) = chars.next()?;
+        match c {
+            '"' => return Some((string, &s[idx + 2..])),
+            '\\' => {
+                let c = match chars.next()?.1 {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let hex: String = (0..4)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        let unit = u32::from_str_radix(&hex, 16).ok()?;
+                        match (high_surrogate.take(), unit) {
+                            (None, 0xd800..=0xdbff) => {
+                                high_surrogate = Some(unit);
+                                continue;
+                            }
+                            (Some(high), 0xdc00..=0xdfff) => {
+                                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (unit - 0xdc00))?
+                            }
+                            (_, unit) => {
+                                char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER)
+                            }
+                        }
+                    }
+                    c => c,
+                };
+                string.push(c);
+            }
+            c => string.push(c),
+        }
+    }
+}
+
+/// Writes terminal output as a ttyrec recording. Each write becomes a record, timestamped with
+/// the current time.
+pub struct TtyrecWriter<W: std::io::Write> {
+    write: W,
+}
+
+impl<W: std::io::Write> TtyrecWriter<W> {
+    pub fn new(write: W) -> Self {
+        TtyrecWriter { write }
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for TtyrecWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let len = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+
+        // the seconds wrap around in 2106
+        self.write
+            .write_all(&(timestamp.as_secs() as u32).to_le_bytes())?;
+        self.write
+            .write_all(&timestamp.subsec_micros().to_le_bytes())?;
+        self.write.write_all(&len.to_le_bytes())?;
+        self.write.write_all(&buf[..len as usize])?;
+
+        Ok(len as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write.flush()
+    }
+}
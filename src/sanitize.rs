@@ -0,0 +1,177 @@
+//! Sanitizing of captured terminal output, such that invalid UTF-8 and escape sequences truncated
+//! at the end of a capture do not leave junk on the terminal screen.
+
+/// The UTF-8 encoding of U+FFFD REPLACEMENT CHARACTER.
+const REPLACEMENT: &[u8] = "\u{fffd}".as_bytes();
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+/// CAN and SUB abort escape sequences.
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1a;
+
+/// Counts of the problems found while sanitizing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of invalid UTF-8 sequences substituted by replacement characters.
+    pub invalid_utf8: usize,
+    /// The number of incomplete escape sequences dropped at the end of the input.
+    pub truncated_sequences: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Ground,
+    /// In a UTF-8 encoded character of the given length.
+    Utf8(usize),
+    Escape,
+    Csi,
+    /// In a control string, e.g., OSC or DCS, terminated by BEL or ST.
+    String,
+    /// After ESC in a control string, which may start ST.
+    StringEscape,
+}
+
+/// Sanitizes terminal output. Escape sequences are passed through once complete, and invalid
+/// UTF-8 is substituted by replacement characters.
+pub struct Sanitizer {
+    state: State,
+    /// The bytes of the incomplete character or escape sequence.
+    pending: Vec<u8>,
+    stats: Stats,
+}
+
+impl Sanitizer {
+    pub fn new() -> Self {
+        Sanitizer {
+            state: State::Ground,
+            pending: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    /// Sanitize `input`, appending the result to `out`. Incomplete characters and escape
+    /// sequences at the end of `input` are held back until more input is given.
+    pub fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        for &byte in input {
+            self.byte(byte, out);
+        }
+    }
+
+    /// Finish sanitizing, dropping a trailing incomplete escape sequence and substituting a
+    /// trailing incomplete character. Returns the counts of the problems found.
+    pub fn finish(mut self, out: &mut Vec<u8>) -> Stats {
+        match self.state {
+            State::Ground => {}
+            State::Utf8(_) => {
+                self.stats.invalid_utf8 += 1;
+                out.extend_from_slice(REPLACEMENT);
+            }
+            State::Escape | State::Csi | State::String | State::StringEscape => {
+                self.stats.truncated_sequences += 1;
+            }
+        }
+        self.stats
+    }
+
+    /// Pass on the pending bytes and return to the ground state.
+    fn complete(&mut self, out: &mut Vec<u8>) {
+        out.append(&mut self.pending);
+        self.state = State::Ground;
+    }
+
+    fn byte(&mut self, byte: u8, out: &mut Vec<u8>) {
+        match self.state {
+            State::Ground => match byte {
+                ESC => {
+                    self.pending.push(byte);
+                    self.state = State::Escape;
+                }
+                0x00..=0x7f => out.push(byte),
+                0xc2..=0xdf => self.start_utf8(byte, 2),
+                0xe0..=0xef => self.start_utf8(byte, 3),
+                0xf0..=0xf4 => self.start_utf8(byte, 4),
+                _ => {
+                    self.stats.invalid_utf8 += 1;
+                    out.extend_from_slice(REPLACEMENT);
+                }
+            },
+            State::Utf8(len) => {
+                if byte & 0xc0 != 0x80 {
+                    // the character ended early; the byte may start something new
+                    self.pending.clear();
+                    self.stats.invalid_utf8 += 1;
+                    out.extend_from_slice(REPLACEMENT);
+                    self.state = State::Ground;
+                    self.byte(byte, out);
+                    return;
+                }
+                self.pending.push(byte);
+                if self.pending.len() == len {
+                    // rejects overlong encodings and surrogates
+                    if std::str::from_utf8(&self.pending).is_ok() {
+                        out.append(&mut self.pending);
+                    } else {
+                        self.pending.clear();
+                        self.stats.invalid_utf8 += 1;
+                        out.extend_from_slice(REPLACEMENT);
+                    }
+                    self.state = State::Ground;
+                }
+            }
+            State::Escape => {
+                self.pending.push(byte);
+                match byte {
+                    b'[' => self.state = State::Csi,
+                    b']' | b'P' | b'_' | b'^' | b'X' => self.state = State::String,
+                    ESC => self.restart(out),
+                    // intermediates and control characters executed within the sequence
+                    0x00..=0x2f if byte != CAN && byte != SUB => {}
+                    _ => self.complete(out),
+                }
+            }
+            State::Csi => {
+                self.pending.push(byte);
+                match byte {
+                    ESC => self.restart(out),
+                    0x40..=0x7e | CAN | SUB => self.complete(out),
+                    _ => {}
+                }
+            }
+            State::String => {
+                self.pending.push(byte);
+                match byte {
+                    ESC => self.state = State::StringEscape,
+                    BEL | CAN | SUB => self.complete(out),
+                    _ => {}
+                }
+            }
+            State::StringEscape => {
+                if byte == b'\\' {
+                    self.pending.push(byte);
+                    self.complete(out);
+                } else {
+                    // the escape ends the string and starts a new sequence
+                    self.pending.pop();
+                    self.complete(out);
+                    self.pending.push(ESC);
+                    self.state = State::Escape;
+                    self.byte(byte, out);
+                }
+            }
+        }
+    }
+
+    fn start_utf8(&mut self, byte: u8, len: usize) {
+        self.pending.push(byte);
+        self.state = State::Utf8(len);
+    }
+
+    /// Pass on the pending sequence up to the ESC just pushed, which starts a new sequence.
+    fn restart(&mut self, out: &mut Vec<u8>) {
+        self.pending.pop();
+        self.complete(out);
+        self.pending.push(ESC);
+        self.state = State::Escape;
+    }
+}
@@ -0,0 +1,113 @@
+//! The test pattern rendered by `termsnap selftest`, exercising the colors, text attributes and
+//! characters that depend on the fonts, font metrics and colors used for rendering.
+
+use std::fmt::Write;
+
+use crate::{RunArgs, SelftestCommand};
+
+/// The number of lines of the test pattern.
+pub const LINES: u16 = 22;
+
+/// The number of columns of the test pattern.
+pub const COLUMNS: u16 = 80;
+
+/// The number of cells of the truecolor gradients.
+const GRADIENT: u16 = 72;
+
+/// A gradient from 0 to 1, giving RGB channels from 0 to 1.
+type Gradient = fn(f32) -> [f32; 3];
+
+/// The terminal output drawing the test pattern. Each line starts with a label of eight columns.
+pub fn pattern() -> Vec<u8> {
+    let mut lines: Vec<String> = Vec::new();
+    let label = |label: &str| format!("\x1b[0m{label:<8}");
+
+    lines.push("\x1b[1mTermsnap test pattern\x1b[0m".to_owned());
+    lines.push(String::new());
+
+    // the 16 standard colors, as background and as text
+    let mut colors = label("Colors");
+    let mut text = label("Text");
+    for color in 0..16 {
+        let _ = write!(colors, "\x1b[48;5;{color}m    ");
+        let _ = write!(text, "\x1b[38;5;{color}m Aa ");
+    }
+    lines.extend([colors, text]);
+
+    // the 6x6x6 color cube and the grayscale ramp of the 256-color palette
+    for row in 0..6 {
+        let mut line = label(if row == 0 { "Cube" } else { "" });
+        for column in 0..36 {
+            let color = 16 + row * 36 + column;
+            let _ = write!(line, "\x1b[48;5;{color}m  ");
+        }
+        lines.push(line);
+    }
+    let mut gray = label("Gray");
+    for color in 232..256 {
+        let _ = write!(gray, "\x1b[48;5;{color}m   ");
+    }
+    lines.push(gray);
+
+    // truecolor gradients of the primaries and of the hue
+    let gradients: [(&str, Gradient); 4] = [
+        ("Red", |t| [t, 0., 0.]),
+        ("Green", |t| [0., t, 0.]),
+        ("Blue", |t| [0., 0., t]),
+        ("Hue", hue),
+    ];
+    for (name, gradient) in gradients {
+        let mut line = label(name);
+        for cell in 0..GRADIENT {
+            let [r, g, b] = gradient(f32::from(cell) / f32::from(GRADIENT - 1))
+                .map(|channel| (channel * 255.).round() as u8);
+            let _ = write!(line, "\x1b[48;2;{r};{g};{b}m ");
+        }
+        lines.push(line);
+    }
+
+    lines.push(
+        label("Attrs")
+            + "\x1b[1mbold\x1b[0m \x1b[2mdim\x1b[0m \x1b[3mitalic\x1b[0m \x1b[4munderline\x1b[0m \
+               \x1b[4:2mdouble\x1b[0m \x1b[4:3mcurly\x1b[0m \x1b[4:4mdotted\x1b[0m \
+               \x1b[4:5mdashed\x1b[0m \x1b[9mstrike\x1b[0m \x1b[7minverse\x1b[0m",
+    );
+
+    // box drawing and block elements should join up without gaps
+    lines.push(label("Box") + "┌─┬─┐ ┏━┳━┓ ╔═╦═╗ ╭─╮  █▓▒░ ▀▄▌▐ ▁▂▃▄▅▆▇█");
+    lines.push(label("") + "├─┼─┤ ┣━╋━┫ ╠═╬═╣ │ │  ⠁⠃⠇⡇⣇⣧⣷⣿ ◢◣◤◥");
+    lines.push(label("") + "└─┴─┘ ┗━┻━┛ ╚═╩═╝ ╰─╯");
+
+    // wide characters take two columns, combining characters none
+    lines.push(label("Wide") + "漢字かなカナ😀|");
+    lines.push(label("") + "0123456789abcd|");
+    lines.push(label("Combine") + "e\u{301} a\u{308} n\u{303}");
+
+    lines.join("\r\n").into_bytes()
+}
+
+/// A fully saturated color of the hue `t`, from 0 to 1, as RGB channels from 0 to 1.
+fn hue(t: f32) -> [f32; 3] {
+    let h = t * 6.;
+    let x = 1. - (h % 2. - 1.).abs();
+    match h as u8 {
+        0 => [1., x, 0.],
+        1 => [x, 1., 0.],
+        2 => [0., 1., x],
+        3 => [0., x, 1.],
+        4 => [x, 0., 1.],
+        _ => [1., 0., x],
+    }
+}
+
+/// Get the options of `termsnap selftest`, rendering the test pattern.
+pub fn run_args(command: SelftestCommand, args: RunArgs) -> RunArgs {
+    let SelftestCommand { mut render } = command;
+    render.lines = render.lines.or(Some(LINES));
+    render.columns = render.columns.or(Some(COLUMNS));
+    RunArgs {
+        render,
+        output: Some(pattern()),
+        ..args
+    }
+}
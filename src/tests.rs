@@ -19,12 +19,1268 @@ fn echo() {
     // create fake stdin and stdout that do nothing, otherwise the test is impacted by data on
     // stdin that is outside our control
     let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
-    let screen = run(cli, &mut i, &mut o).unwrap();
-    let content: String = screen.cells().map(|c| c.c).collect();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "hello, world");
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn exit_status() {
+    use termsnap_lib::ExitStatus;
+
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--show-exit-status",
+        "--",
+        "sh",
+        "-c",
+        "echo failed; exit 3",
+    ]);
+
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut exit_status = None;
+    let screen = run(cli.run, &mut i, &mut o, None, None, Some(&mut exit_status))
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "failed");
+    assert_eq!(exit_status, Some(ExitStatus::Code(3)));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn strict() {
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--strict",
+        "--",
+        "printf",
+        "\\033[?9999hhi",
+    ]);
+
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let err = run(cli.run, &mut i, &mut o, None, None, None).unwrap_err();
+    assert!(err.to_string().contains("CSI ?9999h"));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn simulate_typing() {
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--simulate-typing",
+        "hi\\n",
+        "--wpm",
+        "6000",
+        "--",
+        "head",
+        "-n1",
+    ]);
+
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "hi\nhi");
 
     assert_eq!(
-        &content[..12],
-        "hello, world",
-        "terminal content was: {content:?}"
+        super::typing::parse_keys(r"a\tb\e\x41\\"),
+        Ok("a\tb\x1bA\\".to_owned())
+    );
+    assert!(super::typing::parse_keys(r"\xff").is_err());
+    assert!(super::typing::parse_keys(r"\q").is_err());
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn env() {
+    let env = |argv: &[&str]| {
+        let cli = Cli::parse_from(
+            ["termsnap", "-l", "100", "-c", "200"]
+                .iter()
+                .chain(argv)
+                .chain(&["--", "env"]),
+        );
+        let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+        let text = run(cli.run, &mut i, &mut o, None, None, None)
+            .unwrap()
+            .remove(0)
+            .text();
+        let mut vars: Vec<String> = text.lines().map(ToOwned::to_owned).collect();
+        vars.sort();
+        vars
+    };
+
+    let vars = env(&["--env", "FOO=a=b", "--term", "xterm-256color"]);
+    assert!(vars.contains(&"FOO=a=b".to_owned()));
+    assert!(vars.contains(&"TERM=xterm-256color".to_owned()));
+    assert!(vars.iter().any(|var| var.starts_with("PATH=")));
+
+    // variables set explicitly are kept when the environment is not inherited
+    assert_eq!(
+        env(&["--env", "FOO=bar", "--no-inherit-env"]),
+        ["COLUMNS=200", "FOO=bar", "LINES=100", "TERM=linux"]
+    );
+
+    assert!(Cli::try_parse_from(["termsnap", "--env", "FOO", "--", "ls"]).is_err());
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn interactive_env() {
+    use std::os::unix::net::UnixStream;
+
+    let env = |argv: &[&str]| {
+        let cli = Cli::parse_from(["termsnap", "--interactive"].iter().chain(argv).chain(&[
+            "--",
+            "sh",
+            "-c",
+            r#"echo "$TERM ${COLORTERM-unset}""#,
+        ]));
+        // the command's output is forwarded to the other end of `o`, which is not read
+        let (mut i, _i) = UnixStream::pair().unwrap();
+        let (mut o, _o) = UnixStream::pair().unwrap();
+        run(cli.run, &mut i, &mut o, None, None, None)
+            .unwrap()
+            .remove(0)
+            .text()
+    };
+
+    // the terminal type and color support of Termsnap's terminal are passed on
+    let term = std::env::var("TERM").unwrap_or_else(|_| "linux".to_owned());
+    let colorterm = std::env::var("COLORTERM").unwrap_or_else(|_| "unset".to_owned());
+    assert_eq!(env(&[]), format!("{term} {colorterm}"));
+    assert_eq!(
+        env(&["--term", "xterm-direct"]),
+        format!("xterm-direct {colorterm}")
+    );
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn cwd() {
+    let dir = std::env::temp_dir().join(format!("termsnap-cwd-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let pwd = |dir: &std::path::Path| {
+        let cli = Cli::parse_from([
+            "termsnap",
+            "-l",
+            "5",
+            "-c",
+            "200",
+            "--cwd",
+            dir.to_str().unwrap(),
+            "--",
+            "pwd",
+        ]);
+        let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+        run(cli.run, &mut i, &mut o, None, None, None).map(|mut screens| screens.remove(0).text())
+    };
+
+    let result = pwd(&dir);
+    let missing = pwd(&dir.join("missing"));
+    std::fs::remove_dir(&dir).unwrap();
+    assert_eq!(result.unwrap(), dir.to_str().unwrap());
+    assert!(missing.is_err());
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn settle() {
+    use std::time::{Duration, Instant};
+
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--settle",
+        "200",
+        "--",
+        "sh",
+        "-c",
+        "echo a; sleep 0.05; echo b; sleep 10; echo c",
+    ]);
+
+    // the screen is captured once the output stops, and the command is terminated
+    let start = Instant::now();
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "a\nb");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn snapshot_on_match() {
+    use std::time::{Duration, Instant};
+
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--snapshot-on-match",
+        "Build (finished|failed)",
+        "--",
+        "sh",
+        "-c",
+        "echo Building; sleep 0.2; echo Build finished; sleep 10; echo done",
+    ]);
+
+    let start = Instant::now();
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "Building\nBuild finished");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn color_overrides() {
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--color",
+        "blue=#000000",
+        "--color",
+        "background=#1e1e2e",
+        "--color",
+        "blue=#81a1c1",
+        "--",
+        "printf",
+        "\\033[34ma\\033[44mb",
+    ]);
+
+    // later overrides take precedence
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen[(0, 0)].fg.to_string(), "#81a1c1");
+    assert_eq!(screen[(0, 0)].bg.to_string(), "#1e1e2e");
+    assert_eq!(screen[(0, 1)].bg.to_string(), "#81a1c1");
+    assert_eq!(screen.background().to_string(), "#1e1e2e");
+
+    assert!(Cli::try_parse_from(["termsnap", "--color", "bleu=#81a1c1"]).is_err());
+    assert!(Cli::try_parse_from(["termsnap", "--color", "blue"]).is_err());
+}
+
+/// Run the test `name` in a separate test process, as signals are received by all tests running
+/// in the process. Returns `true` once it passed, or `false` in the separate process, where the
+/// test itself runs.
+#[cfg(target_family = "unix")]
+fn in_separate_process(name: &str) -> bool {
+    const VAR: &str = "TERMSNAP_TEST_SEPARATE_PROCESS";
+
+    if std::env::var_os(VAR).is_some() {
+        return false;
+    }
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([name, "--exact"])
+        .env(VAR, "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success() && stdout.contains("1 passed"),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    true
+}
+
+/// Send `signal` to the current process after `delay`.
+#[cfg(target_family = "unix")]
+fn raise_after(signal: rustix::process::Signal, delay: std::time::Duration) {
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        rustix::process::kill_process(rustix::process::getpid(), signal).unwrap();
+    });
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn forward_signals() {
+    use std::time::{Duration, Instant};
+
+    use rustix::process::Signal;
+    use termsnap_lib::ExitStatus;
+
+    if in_separate_process("tests::forward_signals") {
+        return;
+    }
+
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--",
+        "sh",
+        "-c",
+        "trap 'echo interrupted; exit 130' INT; echo running; sleep 10; echo done",
+    ]);
+
+    // the interrupt is forwarded to the command, and its screen is still captured
+    let start = Instant::now();
+    raise_after(Signal::Int, Duration::from_millis(300));
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut exit_status = None;
+    let screen = run(cli.run, &mut i, &mut o, None, None, Some(&mut exit_status))
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "running\ninterrupted");
+    assert_eq!(exit_status, Some(ExitStatus::Code(130)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn snapshot_on_signal() {
+    use std::time::Duration;
+
+    use rustix::process::Signal;
+
+    if in_separate_process("tests::snapshot_on_signal") {
+        return;
+    }
+
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--",
+        "sh",
+        "-c",
+        "echo a; sleep 1; echo b",
+    ]);
+
+    // with a snapshot function, as given with `--snapshot-on-signal`, SIGUSR1 snapshots the
+    // screen while the command keeps running
+    let mut snapshots = Vec::new();
+    let mut snapshot = |screen: termsnap_lib::Screen, name: Option<&str>| {
+        snapshots.push((screen.text(), name.map(ToOwned::to_owned)));
+        Ok(())
+    };
+    raise_after(Signal::Usr1, Duration::from_millis(400));
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, Some(&mut snapshot), None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(snapshots, [("a".to_owned(), None)]);
+    assert_eq!(screen.text(), "a\nb");
+}
+
+#[test]
+fn watch() {
+    use std::time::{Duration, Instant};
+
+    use super::watch::Watcher;
+
+    let dir = std::env::temp_dir().join(format!("termsnap-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("a.txt");
+    std::fs::write(&file, "a").unwrap();
+    assert!(Watcher::new(vec![dir.join("missing")]).is_err());
+    let mut watcher = Watcher::new(vec![file.clone(), dir.clone()]).unwrap();
+
+    // changes made before waiting are ignored
+    std::fs::write(&file, "b").unwrap();
+    let delay = Duration::from_millis(300);
+    let write = |path: std::path::PathBuf| {
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            std::fs::write(path, "c").unwrap();
+        })
+    };
+    for path in [file.clone(), dir.join("b.txt")] {
+        let start = Instant::now();
+        let writer = write(path);
+        watcher.wait().unwrap();
+        writer.join().unwrap();
+        assert!(start.elapsed() >= delay);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn tee() {
+    use std::{io::Read, os::unix::net::UnixStream};
+
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--tee",
+        "--out",
+        "out.svg",
+        "--",
+        "printf",
+        "a\\033[1mb",
+    ]);
+
+    // with `--out`, the raw output is echoed to stdout
+    let (mut i, _i) = UnixStream::pair().unwrap();
+    let (mut o, mut echoed) = UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    drop(o);
+    let mut output = Vec::new();
+    echoed.read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"a\x1b[1mb");
+    assert_eq!(screen.text(), "ab");
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn raw_out() {
+    let path = std::env::temp_dir().join(format!("termsnap-raw-{}.ansi", std::process::id()));
+    let cli = Cli::parse_from([
+        "termsnap",
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--raw-out",
+        path.to_str().unwrap(),
+        "--",
+        "printf",
+        "a\\033[1mb\\nc",
+    ]);
+
+    // the capture renders to the same screen
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+    let screen = run(cli.run, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    let capture = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(capture, b"a\x1b[1mb\r\nc");
+    assert_eq!(termsnap_lib::emulate(5, 20, &capture), screen);
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn auto_size() {
+    use std::{io::Write, os::unix::net::UnixStream};
+
+    let auto_size = |max_lines: &str| {
+        let cli = Cli::parse_from([
+            "termsnap",
+            "-l",
+            "24",
+            "-c",
+            "10",
+            "--auto-size",
+            "--max-lines",
+            max_lines,
+        ]);
+        // the data on standard input is more than the default scrollback history of 10,000 lines
+        let (mut i, mut stdin) = UnixStream::pair().unwrap();
+        let writer = std::thread::spawn(move || {
+            let data: Vec<String> = (1..=20_000).map(|line| line.to_string()).collect();
+            stdin.write_all(data.join("\r\n").as_bytes()).unwrap();
+        });
+        let (mut o, _o) = UnixStream::pair().unwrap();
+        let screen = run(cli.run, &mut i, &mut o, None, None, None)
+            .unwrap()
+            .remove(0);
+        writer.join().unwrap();
+        screen
+    };
+
+    let screen = auto_size("30000");
+    assert_eq!(screen.lines(), 20_000);
+    let text = screen.text();
+    assert_eq!(text.lines().next(), Some("1"));
+    assert_eq!(text.lines().last(), Some("20000"));
+
+    // the last lines are rendered
+    let screen = auto_size("100");
+    assert_eq!(screen.lines(), 100);
+    assert_eq!(screen.text().lines().next(), Some("19901"));
+}
+
+#[test]
+fn parse_crop() {
+    use std::ops::Bound;
+
+    use super::Crop;
+
+    assert_eq!(
+        "0:10,5:".parse::<Crop>(),
+        Ok(Crop {
+            lines: (Bound::Included(0), Bound::Excluded(10)),
+            columns: (Bound::Included(5), Bound::Unbounded),
+        })
+    );
+    assert!("0:10".parse::<Crop>().is_err());
+    assert!("a:10,:".parse::<Crop>().is_err());
+}
+
+#[test]
+fn parse_length() {
+    let cli = Cli::parse_from(["termsnap", "--padding", "4.5", "--margin=0", "--", "ls"]);
+    assert_eq!((cli.run.render.padding, cli.run.render.margin), (4.5, 0.));
+    for length in ["-100", "NaN", "inf", "a"] {
+        assert!(
+            Cli::try_parse_from(["termsnap", &format!("--padding={length}"), "--", "ls"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["termsnap", &format!("--margin={length}"), "--", "ls"]).is_err()
+        );
+    }
+}
+
+#[test]
+fn replay_script() {
+    use std::time::Duration;
+
+    let typescript = b"Script started on 2024-06-01\nhello, world\r\n";
+    let timing = "0.5 7\n0.25 7\n";
+    let events = super::replay::script(typescript, timing).unwrap();
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].time, Duration::from_millis(500));
+    assert_eq!(events[0].data, b"hello, ");
+    assert_eq!(events[1].time, Duration::from_millis(750));
+    assert_eq!(events[1].data, b"world\r\n");
+
+    assert!(super::replay::script(typescript, "0.5 100\n").is_err());
+}
+
+#[test]
+fn asciicast() {
+    use std::time::Duration;
+
+    let recording = r#"{"version": 2, "width": 80, "height": 24}
+[0.5, "o", "a\u001b[1m\"\\\ud83d\ude00"]
+[0.6, "i", "x"]
+[1.25, "o", "b\r\n"]
+"#;
+    let events = super::replay::asciicast(recording).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].time, Duration::from_millis(500));
+    assert_eq!(events[0].data, "a\x1b[1m\"\\😀".as_bytes());
+    assert_eq!(events[1].time, Duration::from_millis(1250));
+    assert_eq!(events[1].data, b"b\r\n");
+
+    // times are relative to the previous event in version 3
+    let events =
+        super::replay::asciicast("{\"version\": 3}\n[0.5, \"o\", \"a\"]\n[0.5, \"o\", \"b\"]")
+            .unwrap();
+    assert_eq!(events[1].time, Duration::from_secs(1));
+
+    assert!(super::replay::asciicast("[0.5, \"o\", \"a\"]").is_err());
+    assert!(super::replay::asciicast("{}\n[0.5, \"o\", \"a]").is_err());
+}
+
+#[test]
+fn hotkeys() {
+    use std::io::Read;
+
+    use super::hotkey::{Action, EscapeKey, Hotkeys};
+
+    let escape: EscapeKey = "ctrl-]".parse().unwrap();
+    assert_eq!("^]".parse::<EscapeKey>(), Ok(escape));
+    assert!("ctrl-1".parse::<EscapeKey>().is_err());
+
+    let mut hotkeys = Hotkeys::new(escape);
+    let mut input: &[u8] = b"ab\x1dsc\x1d\x1dd\x1d";
+    let mut output = Vec::new();
+    hotkeys.reader(&mut input).read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"abc\x1dd");
+    assert_eq!(hotkeys.take_actions(), [Action::Snapshot]);
+
+    let mut input: &[u8] = b"d";
+    let _ = hotkeys.reader(&mut input).read_to_end(&mut output);
+    assert_eq!(hotkeys.take_actions(), [Action::Detach]);
+}
+
+#[test]
+fn subcommands() {
+    use std::path::PathBuf;
+
+    use super::{RenderCommand, Subcommand};
+
+    let cli = Cli::parse_from(["termsnap", "render", "capture.ansi", "--columns", "100"]);
+    let Some(Subcommand::Render(RenderCommand { capture, render })) = cli.subcommand else {
+        panic!("expected the render subcommand");
+    };
+    assert_eq!(capture, PathBuf::from("capture.ansi"));
+    assert_eq!(render.columns, Some(100));
+
+    let cli = Cli::parse_from(["termsnap", "--", "render"]);
+    assert!(cli.subcommand.is_none());
+    assert_eq!(cli.run.command.as_deref(), Some("render"));
+
+    let cli = Cli::parse_from(["termsnap", "run", "--columns", "100", "--", "ls"]);
+    let Some(Subcommand::Run(run)) = cli.subcommand else {
+        panic!("expected the run subcommand");
+    };
+    assert_eq!(run.render.columns, Some(100));
+    assert_eq!(run.command.as_deref(), Some("ls"));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn record_and_render() {
+    use super::{RecordCommand, RenderCommand, RunArgs, Subcommand};
+
+    let dir = std::env::temp_dir();
+    let recording = dir.join(format!("termsnap-record-{}.ttyrec", std::process::id()));
+    let capture = dir.join(format!("termsnap-render-{}.ansi", std::process::id()));
+    let (mut i, mut o) = std::os::unix::net::UnixStream::pair().unwrap();
+
+    // `termsnap record` runs the command, and stores its output with timings
+    let cli = Cli::parse_from([
+        "termsnap",
+        "record",
+        recording.to_str().unwrap(),
+        "-l",
+        "5",
+        "-c",
+        "20",
+        "--",
+        "printf",
+        "a\\nb",
+    ]);
+    let Some(Subcommand::Record(RecordCommand {
+        recording,
+        run: args,
+    })) = cli.subcommand
+    else {
+        panic!("expected the record subcommand");
+    };
+    let args = RunArgs {
+        record: Some(recording.clone()),
+        ..args
+    };
+    let screen = run(args, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    assert_eq!(screen.text(), "a\nb");
+    let events = super::replay::ttyrec(&std::fs::read(&recording).unwrap()).unwrap();
+    std::fs::remove_file(&recording).unwrap();
+    let output: Vec<u8> = events.into_iter().flat_map(|event| event.data).collect();
+    assert_eq!(output, b"a\r\nb");
+
+    // `termsnap render` renders a capture
+    std::fs::write(&capture, &output).unwrap();
+    let cli = Cli::parse_from([
+        "termsnap",
+        "render",
+        capture.to_str().unwrap(),
+        "-l",
+        "5",
+        "-c",
+        "20",
+    ]);
+    let Some(Subcommand::Render(RenderCommand { capture, render })) = cli.subcommand else {
+        panic!("expected the render subcommand");
+    };
+    let args = RunArgs {
+        render,
+        capture: Some(capture.clone()),
+        ..cli.run
+    };
+    let rendered = run(args, &mut i, &mut o, None, None, None)
+        .unwrap()
+        .remove(0);
+    std::fs::remove_file(&capture).unwrap();
+    assert_eq!(rendered, screen);
+}
+
+#[test]
+fn config() {
+    use clap::FromArgMatches;
+
+    use super::{config, Subcommand};
+
+    let path = std::env::temp_dir().join(format!("termsnap-config-{}.toml", std::process::id()));
+    let config_arg = format!("--config={}", path.display());
+    let parse = |argv: &[&str]| -> anyhow::Result<Cli> {
+        let argv: Vec<std::ffi::OsString> = argv.iter().map(Into::into).collect();
+        let matches = config::command(&argv)?.try_get_matches_from(argv)?;
+        Ok(Cli::from_arg_matches(&matches)?)
+    };
+
+    // options on the command line take precedence
+    std::fs::write(&path, "columns = 100\nlines = 30\nfont = [\"A\", \"B\"]\n").unwrap();
+    let cli = parse(&["termsnap", "run", &config_arg, "--lines", "10", "--", "ls"]).unwrap();
+    let Some(Subcommand::Run(run)) = cli.subcommand else {
+        panic!("expected the run subcommand");
+    };
+    assert_eq!(run.render.columns, Some(100));
+    assert_eq!(run.render.lines, Some(10));
+    assert_eq!(run.render.font, ["A", "B"]);
+    assert_eq!(run.command.as_deref(), Some("ls"));
+
+    // defaults do not trigger the requirements of options
+    std::fs::write(&path, "wpm = 200\nmax-lines = 50\ninteractive = true\n").unwrap();
+    let cli = parse(&["termsnap", &config_arg, "--", "echo", "hi"]).unwrap();
+    assert_eq!(cli.run.wpm, 200);
+    assert!(cli.run.simulate_typing.is_none());
+    assert_eq!(cli.run.render.max_lines, 50);
+
+    // options of other subcommands are ignored
+    let cli = parse(&["termsnap", "render", &config_arg, "capture.txt"]).unwrap();
+    assert!(matches!(cli.subcommand, Some(Subcommand::Render(_))));
+
+    std::fs::write(&path, "colums = 100\n").unwrap();
+    let result = parse(&["termsnap", &config_arg]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
+
+#[test]
+fn font_metrics() {
+    use clap::FromArgMatches;
+    use resvg::usvg::fontdb;
+    use termsnap_lib::OutlineFont;
+
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    let query = fontdb::Query {
+        families: &[fontdb::Family::Name("DejaVu Sans Mono")],
+        ..fontdb::Query::default()
+    };
+    let Some(fontdb::Source::File(path)) = db
+        .query(&query)
+        .and_then(|id| db.face(id))
+        .map(|face| face.source.clone())
+    else {
+        eprintln!("DejaVu Sans Mono is not installed, skipping");
+        return;
+    };
+
+    let font = OutlineFont::from_index(std::fs::read(path).unwrap(), 0).unwrap();
+    let metrics = font.metrics();
+    assert_eq!(font.family_name().as_deref(), Some("DejaVu Sans Mono"));
+    assert_eq!(
+        (
+            metrics.units_per_em,
+            metrics.advance,
+            metrics.line_height,
+            metrics.descent
+        ),
+        (2048, 1233., 2384., 483.)
+    );
+
+    // the printed options set the same metrics when added to the configuration file
+    let options = super::font_metrics::fmt_font_metrics(&metrics);
+    assert_eq!(
+        options,
+        "font-units-per-em = 2048\nfont-advance = 1233\nfont-line-height = 2384\nfont-descent = 483\n"
+    );
+    let path =
+        std::env::temp_dir().join(format!("termsnap-font-metrics-{}.toml", std::process::id()));
+    std::fs::write(&path, &options).unwrap();
+    let argv: Vec<std::ffi::OsString> = ["termsnap".into(), format!("--config={}", path.display())]
+        .iter()
+        .map(Into::into)
+        .collect();
+    let matches = super::config::command(&argv)
+        .unwrap()
+        .try_get_matches_from(argv);
+    std::fs::remove_file(&path).unwrap();
+    let cli = Cli::from_arg_matches(&matches.unwrap()).unwrap();
+    let parsed = &cli.run.render.font_metrics;
+    assert_eq!(
+        (
+            parsed.font_units_per_em,
+            parsed.font_advance,
+            parsed.font_line_height,
+            parsed.font_descent
+        ),
+        (2048, 1233., 2384., 483.)
+    );
+}
+
+#[test]
+fn font_preset() {
+    use super::apply_font_preset;
+
+    let metrics = |argv: &[&str]| {
+        let mut cli = Cli::parse_from(["termsnap"].iter().chain(argv).chain(&["--", "ls"]));
+        apply_font_preset(&mut cli.run.render);
+        let m = &cli.run.render.font_metrics;
+        (
+            (
+                m.font_units_per_em,
+                m.font_advance,
+                m.font_line_height,
+                m.font_descent,
+            ),
+            cli.run.render.font,
+        )
+    };
+
+    // the preset sets the font metrics and the font
+    assert_eq!(
+        metrics(&["--font-preset", "jetbrains-mono"]),
+        ((1000, 600., 1320., 300.), vec!["JetBrains Mono".to_owned()])
+    );
+    assert_eq!(
+        metrics(&["--font-preset", "liberation-mono"]),
+        (
+            (2048, 1229., 2320., 615.),
+            vec!["Liberation Mono".to_owned()]
+        )
+    );
+
+    // fonts given explicitly are kept
+    assert_eq!(
+        metrics(&["--font-preset", "iosevka", "--font", "Iosevka Term"]),
+        ((1000, 600., 1250., 285.), vec!["Iosevka Term".to_owned()])
+    );
+
+    assert!(Cli::try_parse_from([
+        "termsnap",
+        "--font-preset",
+        "menlo",
+        "--font-advance",
+        "600",
+        "--",
+        "ls"
+    ])
+    .is_err());
+}
+
+#[test]
+fn diff() {
+    use termsnap_lib::{Region, Term, VoidPtyWriter};
+
+    let screen = |bytes: &[u8]| {
+        let mut term = Term::new(2, 10, VoidPtyWriter);
+        term.process_bytes(bytes);
+        term.current_screen()
+    };
+
+    let expected = screen(b"hello\r\nworld");
+    assert!(super::diff::changed_regions(&expected, &expected).is_empty());
+
+    let actual = screen(b"hallo\r\nworld!");
+    assert_eq!(
+        super::diff::changed_regions(&expected, &actual),
+        [
+            Region {
+                start_line: 0,
+                start_column: 1,
+                end_line: 0,
+                end_column: 2,
+            },
+            Region {
+                start_line: 1,
+                start_column: 5,
+                end_line: 1,
+                end_column: 6,
+            },
+        ]
+    );
+    assert_eq!(
+        super::diff::text_diff(&expected, &actual),
+        "line 1:\n-hello\n+hallo\nline 2:\n-world\n+world!\n"
+    );
+}
+
+#[test]
+fn demo_script() {
+    use std::time::Duration;
+
+    use super::{demo::Script, typing::Step};
+
+    let script = Script::parse(
+        r#"
+        # comments are allowed
+        lines = 10
+
+        [[step]]
+        type = "ls\n"
+
+        [[step]]
+        pause = 500
+
+        [[step]]
+        snapshot = "ls"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(script.lines, Some(10));
+    assert!(script.command.is_empty());
+    assert_eq!(
+        script.steps(),
+        [
+            Step::Key('l'),
+            Step::Key('s'),
+            Step::Key('\r'),
+            Step::Pause(Duration::from_millis(500)),
+            Step::Snapshot("ls".to_owned()),
+        ]
+    );
+
+    assert!(Script::parse("[[step]]\nwait = 500").is_err());
+}
+
+#[test]
+fn recorder_coalesces_frames() {
+    use std::time::Duration;
+
+    use termsnap_lib::{Term, VoidPtyWriter};
+
+    use super::record::{RecordOptions, Recorder};
+
+    let mut term = Term::new(2, 10, VoidPtyWriter);
+    let mut recorder = Recorder::new(RecordOptions {
+        max_fps: Some(10),
+        ..RecordOptions::default()
+    });
+    let ms = Duration::from_millis;
+
+    term.process_bytes(b"a");
+    recorder.record_at(ms(0), &mut term);
+    // identical screens are not recorded
+    recorder.record_at(ms(200), &mut term);
+    // a burst of output is coalesced into a single frame
+    term.process_bytes(b"b");
+    recorder.record_at(ms(300), &mut term);
+    term.process_bytes(b"c");
+    recorder.record_at(ms(310), &mut term);
+
+    let frames = recorder.finish(term.current_screen());
+    let frames: Vec<_> = frames
+        .iter()
+        .map(|frame| (frame.time, frame.screen.text()))
+        .collect();
+    // the final frame is kept to preserve the time until the end of the recording
+    assert_eq!(
+        frames[..2],
+        [(ms(0), "a".to_owned()), (ms(300), "abc".to_owned())]
+    );
+    assert_eq!(frames.len(), 3);
+}
+
+#[test]
+fn recorder_timing() {
+    use std::time::Duration;
+
+    use termsnap_lib::{Term, VoidPtyWriter};
+
+    use super::record::{RecordOptions, Recorder};
+
+    let ms = Duration::from_millis;
+    let mut term = Term::new(2, 10, VoidPtyWriter);
+    let mut recorder = Recorder::new(RecordOptions {
+        speed: 2.,
+        idle_limit: Some(ms(1000)),
+        max_duration: Some(ms(1000)),
+        ..RecordOptions::default()
+    });
+
+    for (time, text) in [(0, "a"), (400, "b"), (5000, "c"), (9000, "d")] {
+        term.process_bytes(text.as_bytes());
+        recorder.record_at(ms(time), &mut term);
+    }
+
+    let times: Vec<_> = recorder
+        .finish(term.current_screen())
+        .iter()
+        .map(|frame| frame.time)
+        .collect();
+    assert_eq!(times, [ms(0), ms(200), ms(700)]);
+}
+
+#[test]
+fn tmux_pane() {
+    use std::process::Command;
+
+    let session = format!("termsnap-test-{}", std::process::id());
+    let status = Command::new("tmux")
+        .args(["new-session", "-d", "-s", &session, "-x", "20", "-y", "5"])
+        .args(["printf 'a\\033[31mb\\033[0m\\nc'; sleep 10"])
+        .status();
+    if !status.is_ok_and(|status| status.success()) {
+        eprintln!("tmux is not available, skipping");
+        return;
+    }
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let pane = super::tmux::capture(Some(&session));
+    let _ = Command::new("tmux")
+        .args(["kill-session", "-t", &session])
+        .status();
+    let pane = pane.unwrap();
+    assert!(super::tmux::capture(Some(&format!("{session}-missing"))).is_err());
+
+    // the pane's text, colors and cursor are reproduced
+    assert_eq!((pane.lines, pane.columns), (5, 20));
+    let screen = termsnap_lib::emulate(pane.lines, pane.columns, &pane.output);
+    assert_eq!(screen.text(), "ab\nc");
+    assert_eq!(screen[(0, 1)].fg.to_string(), "#dc322f");
+    assert_eq!(
+        screen.cursor().map(|cursor| (cursor.line, cursor.column)),
+        Some((1, 1))
+    );
+}
+
+#[test]
+fn encode_gif() {
+    use std::time::Duration;
+
+    use termsnap_lib::emulate;
+
+    let svg = |text: &[u8]| {
+        emulate(1, 2, text)
+            .to_svg(&[], termsnap_lib::FontMetrics::DEFAULT)
+            .to_string()
+    };
+    let ms = Duration::from_millis;
+    let frames = [
+        (ms(0), svg(b"a")),
+        (ms(500), svg(b"a")),
+        (ms(1000), svg(b"b")),
+    ];
+    let decode = |repeat: bool, hold: Duration| {
+        let mut gif = Vec::new();
+        super::raster::encode_gif(&frames, repeat, hold, &mut gif).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&gif[..]).unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        (decoder.repeat(), delays)
+    };
+
+    // identical frames are merged, and the last frame is held
+    assert_eq!(
+        decode(true, Duration::from_secs(3)),
+        (gif::Repeat::Infinite, vec![100, 300])
+    );
+    assert_eq!(
+        decode(false, ms(250)),
+        (gif::Repeat::Finite(0), vec![100, 25])
+    );
+}
+
+#[test]
+fn sanitize() {
+    use super::sanitize::{Sanitizer, Stats};
+
+    let mut sanitizer = Sanitizer::new();
+    let mut out = Vec::new();
+    // input is split in the middle of a character and of an escape sequence
+    sanitizer.feed(b"a\xff\xe2\x82", &mut out);
+    sanitizer.feed(b"\xac\x1b[3", &mut out);
+    sanitizer.feed(b"1mb\x1b]0;title", &mut out);
+    let stats = sanitizer.finish(&mut out);
+
+    assert_eq!(out, "a\u{fffd}\u{20ac}\x1b[31mb".as_bytes());
+    assert_eq!(
+        stats,
+        Stats {
+            invalid_utf8: 1,
+            truncated_sequences: 1,
+        }
+    );
+}
+
+#[test]
+fn typst() {
+    use termsnap_lib::{emulate, SvgOptions};
+
+    let screen = emulate(2, 20, b"a \"b\"\\ \x1b[1;44mc  d\x1b[0m");
+    let typst = super::typst::render(&screen, &SvgOptions::default().fonts(&["Mono"]));
+    assert!(typst.contains(r#"set text(font: ("Mono", "DejaVu Sans Mono", )"#));
+    assert!(typst.contains("text(fill: rgb(\"#839496\"), \"a\u{a0}\\\"b\\\"\\\\\")"));
+    assert!(typst.contains("weight: \"bold\", \"c\u{a0}\u{a0}d\")"));
+    assert!(typst.contains(
+        r##"place(dx: 4.2em, dy: 0em, rect(width: 2.4em, height: 1.2em, fill: rgb("#268bd2")))"##
+    ));
+}
+
+#[test]
+fn csv_format() {
+    use termsnap_lib::{emulate, SvgOptions};
+
+    use super::{output::render_document, Format};
+
+    let screen = emulate(1, 3, b"a,\"");
+    for (name, expected) in [("csv", screen.to_csv()), ("tsv", screen.to_tsv())] {
+        let cli = Cli::parse_from(["termsnap", "--format", name, "--", "ls"]);
+        let format = cli.run.render.format;
+        assert!(matches!(format, Format::Csv | Format::Tsv));
+        assert!(format.is_document());
+        assert_eq!(
+            render_document(format, &screen, &SvgOptions::default()),
+            expected
+        );
+    }
+}
+
+#[test]
+fn embed() {
+    use super::embed::{image, path_url, svg_data_uri, Markup};
+
+    assert_eq!(
+        image(Markup::Markdown, "$ ls [dir]\n  a\n\n", "a%20b.svg"),
+        r"![$ ls \[dir\] a](a%20b.svg)"
+    );
+    assert_eq!(
+        image(Markup::Html, "\"a\" & <b>", "c.svg"),
+        r#"<img src="c.svg" alt="&quot;a&quot; &amp; &lt;b>">"#
+    );
+    assert!(
+        image(Markup::Markdown, &"a".repeat(300), "").contains(&format!("{}…]", "a".repeat(250)))
+    );
+    assert_eq!(path_url("docs/a (1).svg".as_ref()), "docs/a%20%281%29.svg");
+    assert_eq!(
+        svg_data_uri(r##"<svg fill="#fff"/>"##),
+        "data:image/svg+xml,%3Csvg%20fill=%22%23fff%22/%3E"
+    );
+}
+
+#[test]
+fn preview() {
+    use super::preview::{base64, encode_sixel, supports_sixel};
+
+    assert_eq!(base64(b"Man"), "TWFu");
+    assert_eq!(base64(b"Ma"), "TWE=");
+    assert_eq!(base64(b"M"), "TQ==");
+
+    assert!(supports_sixel(b"\x1b[?62;4;22c"));
+    assert!(!supports_sixel(b"\x1b[?62;22c"));
+    assert!(!supports_sixel(b""));
+
+    assert_eq!(
+        encode_sixel(2, 2, &[0, 0, 0, 255, 255, 255], &[0, 1, 1, 0]),
+        "\x1bP0;1;0q\"1;1;2;2#0;2;0;0;0#1;2;100;100;100#0@A$#1A@-\x1b\\"
+    );
+    // runs are compressed, and empty runs at the end of a band are left out
+    assert_eq!(
+        encode_sixel(6, 1, &[0, 0, 0, 255, 255, 255], &[1, 1, 1, 1, 1, 0]),
+        "\x1bP0;1;0q\"1;1;6;1#0;2;0;0;0#1;2;100;100;100#0!5?@$#1!5@-\x1b\\"
+    );
+}
+
+#[test]
+fn osc52() {
+    assert_eq!(super::clipboard::osc52("<svg/>"), "\x1b]52;c;PHN2Zy8+\x07");
+}
+
+#[test]
+fn jsonl() {
+    use std::time::Duration;
+    use termsnap_lib::emulate;
+
+    let frames = [
+        (Duration::ZERO, emulate(1, 2, b"\"")),
+        (Duration::from_millis(250), emulate(1, 2, b"\"\x1b[1;4:3mx")),
+        (Duration::from_millis(300), emulate(1, 1, b"")),
+    ];
+    let mut out = Vec::new();
+    super::jsonl::write(&frames, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r##"{"time":0.000,"lines":1,"columns":2,"screen":[[{"char":"\"","fg":"#839496","bg":"#002b36"},{"char":" ","fg":"#839496","bg":"#002b36"}]]}
+{"time":0.250,"damage":[{"line":0,"column":1,"cells":[{"char":"x","fg":"#839496","bg":"#002b36","flags":["bold","curly-underline"]}]}]}
+{"time":0.300,"lines":1,"columns":1,"screen":[[{"char":" ","fg":"#839496","bg":"#002b36"}]]}
+"##
+    );
+}
+
+#[test]
+fn selftest() {
+    use super::selftest::{pattern, COLUMNS, LINES};
+
+    let screen = termsnap_lib::emulate(LINES, COLUMNS, &pattern());
+    let text: Vec<String> = screen
+        .rows()
+        .map(|line| line.iter().flat_map(|cell| cell.chars()).collect())
+        .collect();
+
+    // the pattern fills the screen without wrapping or scrolling
+    assert!(text[0].starts_with("Termsnap test pattern"));
+    assert!(text[usize::from(LINES) - 1].starts_with("Combine e\u{301}"));
+
+    // the wide characters line up with the ruler below them
+    let wide = text
+        .iter()
+        .position(|line| line.starts_with("Wide"))
+        .unwrap();
+    let bar = |line: u16| {
+        (0..COLUMNS)
+            .find(|&column| screen.get(line, column).unwrap().c == '|')
+            .unwrap()
+    };
+    assert_eq!(bar(wide as u16), bar(wide as u16 + 1));
+
+    // all 256 palette colors are drawn
+    let backgrounds: std::collections::HashSet<_> =
+        screen.cells().map(|cell| cell.bg.to_string()).collect();
+    assert!(backgrounds.len() > 256);
+}
+
+#[test]
+fn theme() {
+    use termsnap_lib::{ColorName, Colors, Rgb};
+
+    let overrides =
+        super::theme::parse("# comment\n\nbackground=#1e1e2e\nbright-blue=#89b4fa\n").unwrap();
+    assert_eq!(overrides.len(), 2);
+    assert!(super::theme::parse("blue=#89b4fa\nbleu=#89b4fa")
+        .unwrap_err()
+        .to_string()
+        .starts_with("line 2:"));
+
+    let mut colors = Colors::default();
+    for override_ in overrides {
+        colors.set(override_.name, override_.color);
+    }
+    let swatch = super::theme::swatch("mocha", &colors);
+    let screen = termsnap_lib::emulate(super::theme::LINES, super::theme::COLUMNS, &swatch);
+    let text: String = screen
+        .rows()
+        .map(|line| line.iter().map(|cell| cell.c).collect::<String>() + "\n")
+        .collect();
+    assert!(text.contains("12 bright-blue    #89b4fa"));
+    assert!(text.contains("  background     #1e1e2e"));
+    assert_eq!(
+        colors.get(ColorName::Background),
+        Rgb {
+            r: 0x1e,
+            g: 0x1e,
+            b: 0x2e
+        }
     );
 }
@@ -0,0 +1,123 @@
+//! Color themes and the swatch rendered by `termsnap theme show`.
+//!
+//! A theme file lists overrides of the terminal's color table in the format of `--color`, one per
+//! line. Empty lines and lines starting with `#` are ignored. For example:
+//!
+//! ```text
+//! # Catppuccin Mocha
+//! background=#1e1e2e
+//! foreground=#cdd6f4
+//! blue=#89b4fa
+//! ```
+
+use std::fmt::Write;
+
+use termsnap_lib::{ColorName, Colors};
+
+use crate::{ColorOverride, Emulator, RunArgs, ThemeShowCommand};
+
+/// The name of the built-in theme, Termsnap's default colors.
+pub const BUILTIN: &str = "solarized-dark";
+
+/// The number of lines of the swatch.
+pub const LINES: u16 = 16;
+
+/// The number of columns of the swatch.
+pub const COLUMNS: u16 = 64;
+
+/// The names of the normal ANSI colors.
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Load the color overrides of the built-in theme given by name, or of the theme file at the given
+/// path.
+pub fn load(theme: &str) -> anyhow::Result<Vec<ColorOverride>> {
+    if theme == BUILTIN {
+        return Ok(Vec::new());
+    }
+
+    let contents = std::fs::read_to_string(theme).map_err(|err| {
+        anyhow::anyhow!(
+            "failed to read theme file {theme}: {err}. The built-in theme is `{BUILTIN}`."
+        )
+    })?;
+    parse(&contents).map_err(|err| anyhow::anyhow!("invalid theme file {theme}: {err}"))
+}
+
+/// Parse the color overrides of a theme file.
+pub fn parse(contents: &str) -> anyhow::Result<Vec<ColorOverride>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(idx, line)| {
+            line.parse()
+                .map_err(|err| anyhow::anyhow!("line {}: {err}", idx + 1))
+        })
+        .collect()
+}
+
+/// The terminal output drawing a labeled swatch of the 16 ANSI colors and the foreground,
+/// background and cursor colors of `colors`, titled `title`.
+pub fn swatch(title: &str, colors: &Colors) -> Vec<u8> {
+    let mut lines: Vec<String> = Vec::new();
+    let label = |color: &mut String, name: ColorName, label: &str| {
+        let _ = write!(color, "\x1b[0m {label:<17} {}", colors.get(name));
+    };
+
+    lines.push(format!("\x1b[1m{title}\x1b[0m"));
+    lines.push(String::new());
+
+    // the normal colors next to their bright variants
+    for (idx, name) in (0..).zip(NAMES) {
+        let mut line = String::new();
+        for (idx, name) in [(idx, name.to_owned()), (idx + 8, format!("bright-{name}"))] {
+            let _ = write!(line, "\x1b[48;5;{idx}m    ");
+            label(
+                &mut line,
+                ColorName::Indexed(idx),
+                &format!("{idx:>2} {name}"),
+            );
+            line.push_str("   ");
+        }
+        lines.push(line.trim_end().to_owned());
+    }
+    lines.push(String::new());
+
+    for (name, text) in [
+        (ColorName::Foreground, "foreground"),
+        (ColorName::Background, "background"),
+        (ColorName::Cursor, "cursor"),
+    ] {
+        let color = colors.get(name);
+        let mut line = format!("\x1b[48;2;{};{};{}m    ", color.r, color.g, color.b);
+        label(&mut line, name, &format!("   {text}"));
+        lines.push(line);
+    }
+    lines.push(String::new());
+
+    // the colors as text on the background
+    let mut text = String::new();
+    for idx in 0..16 {
+        let _ = write!(text, "\x1b[38;5;{idx}m Aa ");
+    }
+    lines.push(text);
+
+    lines.join("\r\n").into_bytes()
+}
+
+/// Get the options of `termsnap theme show`, rendering the swatch of the theme.
+pub fn run_args(command: ThemeShowCommand, args: RunArgs) -> anyhow::Result<RunArgs> {
+    let ThemeShowCommand { theme, mut render } = command;
+    render.color.splice(0..0, load(&theme)?);
+    render.lines = render.lines.or(Some(LINES));
+    render.columns = render.columns.or(Some(COLUMNS));
+    let colors = Emulator::new(&render).colors;
+    Ok(RunArgs {
+        render,
+        output: Some(swatch(&theme, &colors)),
+        ..args
+    })
+}
@@ -0,0 +1,99 @@
+//! Capturing the contents of running tmux panes.
+
+use std::process::Command;
+
+use crate::{RunArgs, TmuxPaneCommand};
+
+/// The contents of a tmux pane, as terminal output reproducing the pane's screen.
+pub struct Pane {
+    pub lines: u16,
+    pub columns: u16,
+    pub output: Vec<u8>,
+}
+
+/// Run tmux with the given arguments, returning its standard output.
+fn tmux(args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let output = Command::new("tmux")
+        .args(args)
+        .output()
+        .map_err(|err| anyhow::anyhow!("failed to run tmux: {err}"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "tmux {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Capture the visible contents of the tmux pane `target`, or of the current pane if `None`. See
+/// the section on target panes in `man tmux` for its format.
+pub fn capture(target: Option<&str>) -> anyhow::Result<Pane> {
+    let mut target_args = vec![];
+    if let Some(target) = target {
+        target_args.extend(["-t", target]);
+    }
+
+    let info = tmux(
+        &[
+            &["display-message", "-p"][..],
+            &target_args,
+            &["#{pane_height} #{pane_width} #{cursor_y} #{cursor_x} #{cursor_flag}"],
+        ]
+        .concat(),
+    )?;
+    let info = String::from_utf8_lossy(&info);
+    let numbers = info
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<u16>, _>>()
+        .ok();
+    let Some(&[lines, columns, cursor_line, cursor_column, cursor_visible]) = numbers.as_deref()
+    else {
+        anyhow::bail!("unexpected pane information from tmux: {}", info.trim());
+    };
+
+    // `-e` includes escape sequences for the text and background attributes, and `-p` prints
+    // the pane's lines separated by newlines
+    let content = tmux(&[&["capture-pane", "-p", "-e"][..], &target_args].concat())?;
+
+    let mut output = Vec::with_capacity(content.len() + lines as usize);
+    // the last line's newline would scroll the screen
+    let content = content.strip_suffix(b"\n").unwrap_or(&content);
+    for (idx, line) in content.split(|&b| b == b'\n').enumerate() {
+        if idx > 0 {
+            output.extend_from_slice(b"\r\n");
+        }
+        output.extend_from_slice(line);
+    }
+    output.extend_from_slice(
+        format!(
+            "\x1b[0m\x1b[{};{}H\x1b[?25{}",
+            cursor_line + 1,
+            cursor_column + 1,
+            if cursor_visible == 1 { 'h' } else { 'l' },
+        )
+        .as_bytes(),
+    );
+
+    Ok(Pane {
+        lines,
+        columns,
+        output,
+    })
+}
+
+/// Get the options of `termsnap tmux-pane`, rendering the captured pane at its size unless
+/// `--lines` or `--columns` is set.
+pub fn run_args(command: TmuxPaneCommand, args: RunArgs) -> anyhow::Result<RunArgs> {
+    let TmuxPaneCommand { target, mut render } = command;
+    let pane = capture(target.as_deref())?;
+    render.lines = render.lines.or(Some(pane.lines));
+    render.columns = render.columns.or(Some(pane.columns));
+    Ok(RunArgs {
+        render,
+        output: Some(pane.output),
+        ..args
+    })
+}
@@ -0,0 +1,146 @@
+//! Simulated typing of input, for demos. Keys are typed one at a time with varying delays, and
+//! each line waits for the output of the previous line to settle.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The time the output must be quiet before the next line is typed, such that commands are typed
+/// after the program is ready for them.
+const LINE_SETTLE: Duration = Duration::from_millis(400);
+
+/// Parse the text to type, interpreting the escapes `\n` and `\r` (the Enter key), `\t`, `\e`
+/// (the Escape key), `\\` and `\xHH`.
+pub fn parse_keys(s: &str) -> Result<String, String> {
+    let mut keys = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            keys.push(c);
+            continue;
+        }
+        match chars.next() {
+            // terminals send a carriage return for the Enter key
+            Some('n' | 'r') => keys.push('\r'),
+            Some('t') => keys.push('\t'),
+            Some('e') => keys.push('\x1b'),
+            Some('\\') => keys.push('\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if byte.is_ascii() && hex.len() == 2 => keys.push(char::from(byte)),
+                    _ => return Err(format!("invalid escape \"\\x{hex}\"")),
+                }
+            }
+            Some(c) => return Err(format!("unknown escape \"\\{c}\"")),
+            None => return Err("trailing backslash".to_owned()),
+        }
+    }
+    Ok(keys)
+}
+
+/// A step of simulated input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Type a key.
+    Key(char),
+    /// Wait before the next step.
+    Pause(Duration),
+    /// Take a snapshot of the terminal screen with the given name, once the output has settled.
+    Snapshot(String),
+}
+
+/// Types keys with human-like delays.
+pub struct Typist {
+    steps: VecDeque<Step>,
+    /// The average delay between keys.
+    interval: Duration,
+    /// The time the last step was taken.
+    last_step: Option<Instant>,
+    /// The delay before the next step.
+    delay: Duration,
+    /// Whether the next step starts a new line.
+    line_start: bool,
+    /// The state of the pseudo-random number generator varying the delays. It is seeded
+    /// identically for every run, so recordings are reproducible.
+    rng: u32,
+}
+
+impl Typist {
+    /// Type `keys` at `wpm` words per minute, with a word counted as five keys.
+    pub fn new(keys: &str, wpm: u32) -> Self {
+        Self::with_steps(keys.chars().map(Step::Key).collect(), wpm)
+    }
+
+    /// Take `steps`, typing keys at `wpm` words per minute.
+    pub fn with_steps(steps: Vec<Step>, wpm: u32) -> Self {
+        let keys_per_second = f64::from(wpm.max(1)) * 5. / 60.;
+        let mut typist = Typist {
+            steps: steps.into(),
+            interval: Duration::from_secs_f64(1. / keys_per_second),
+            last_step: None,
+            delay: Duration::ZERO,
+            line_start: true,
+            rng: 0x2545_f491,
+        };
+        typist.delay = typist.next_delay();
+        typist
+    }
+
+    /// Returns `true` if all steps have been taken.
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The time the next step is due, given the time the program last wrote output. Returns
+    /// `None` if all steps have been taken.
+    pub fn next_due(&self, last_output: Instant) -> Option<Instant> {
+        let step = self.steps.front()?;
+        let after_step = self.last_step.map(|last_step| last_step + self.delay);
+        let due = if self.line_start || matches!(step, Step::Snapshot(_)) {
+            let settled = last_output + LINE_SETTLE;
+            after_step.map_or(settled, |after_step| after_step.max(settled))
+        } else {
+            after_step.unwrap_or(last_output)
+        };
+        match step {
+            Step::Pause(pause) => Some(due + *pause),
+            _ => Some(due),
+        }
+    }
+
+    /// Take the next step if it is due.
+    pub fn next_step(&mut self, last_output: Instant) -> Option<Step> {
+        let now = Instant::now();
+        if self.next_due(last_output)? > now {
+            return None;
+        }
+
+        let step = self.steps.pop_front()?;
+        self.last_step = Some(now);
+        self.delay = self.next_delay();
+        match step {
+            Step::Key(key) => {
+                self.line_start = key == '\r';
+                if key == ' ' {
+                    // pause slightly between words
+                    self.delay += self.interval / 2;
+                }
+            }
+            // the delay of the pause has already passed
+            Step::Pause(_) => self.delay = Duration::ZERO,
+            Step::Snapshot(_) => {}
+        }
+        Some(step)
+    }
+    /// Get a delay varying between half and one and a half times the average delay.
+    fn next_delay(&mut self) -> Duration {
+        // xorshift32
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let factor = 0.5 + f64::from(self.rng) / f64::from(u32::MAX);
+        self.interval.mul_f64(factor)
+    }
+}
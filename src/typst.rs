@@ -0,0 +1,134 @@
+//! Typst output: the terminal screen as a Typst block of text and background rectangles placed on
+//! the monospaced grid of cells, for including native, selectable text in Typst documents.
+
+use std::fmt::Write;
+
+use termsnap_lib::{BackgroundRegion, FontMetrics, Rgb, Screen, SvgOptions, TextRun};
+
+/// The fonts listed after the configured fonts. Typst bundles DejaVu Sans Mono.
+const FALLBACK_FONT: &str = "DejaVu Sans Mono";
+
+/// Render `screen` as a Typst block, using the fonts and font metrics of `options`. Distances are
+/// in `em`, such that the block scales with the text size of the document.
+pub fn render(screen: &Screen, options: &SvgOptions) -> String {
+    let FontMetrics {
+        units_per_em,
+        advance,
+        line_height,
+        descent,
+    } = options.font_metrics;
+    let units_per_em = f32::from(units_per_em);
+    let advance = advance / units_per_em;
+    let line_height = line_height / units_per_em;
+    let descent = descent / units_per_em;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated by Termsnap.");
+    let _ = writeln!(
+        out,
+        "#block(width: {}, height: {}, fill: {}, clip: true, {{",
+        em(f32::from(screen.columns()) * advance),
+        em(f32::from(screen.lines()) * line_height),
+        color(screen.background()),
+    );
+
+    out.push_str("  set text(font: (");
+    for font in options.fonts.iter().copied().chain([FALLBACK_FONT]) {
+        string(&mut out, font);
+        out.push_str(", ");
+    }
+    out.push_str("), top-edge: \"baseline\", bottom-edge: \"baseline\", hyphenate: false)\n");
+
+    for BackgroundRegion {
+        line,
+        column,
+        lines,
+        columns,
+        color: fill,
+    } in screen.background_regions()
+    {
+        let _ = writeln!(
+            out,
+            "  place(dx: {}, dy: {}, rect(width: {}, height: {}, fill: {}))",
+            em(f32::from(column) * advance),
+            em(f32::from(line) * line_height),
+            em(f32::from(columns) * advance),
+            em(f32::from(lines) * line_height),
+            color(fill),
+        );
+    }
+
+    for run in screen.text_runs() {
+        let TextRun {
+            line,
+            column,
+            ref text,
+            fg,
+            bold,
+            dim,
+            italic,
+            underline,
+            strikethrough,
+            ..
+        } = run;
+
+        // as in SVGs, dim text is drawn at 60% opacity
+        let mut content = if dim {
+            format!("text(fill: rgb(\"{fg}99\")")
+        } else {
+            format!("text(fill: {}", color(fg))
+        };
+        if bold {
+            content.push_str(", weight: \"bold\"");
+        }
+        if italic {
+            content.push_str(", style: \"italic\"");
+        }
+        content.push_str(", ");
+        // spaces are kept as non-breaking spaces, such that they are neither collapsed nor broken
+        string(&mut content, &text.replace(' ', "\u{a0}"));
+        content.push(')');
+        if underline {
+            content = format!("underline({content})");
+        }
+        if strikethrough {
+            content = format!("strike({content})");
+        }
+
+        let _ = writeln!(
+            out,
+            "  place(dx: {}, dy: {}, {content})",
+            em(f32::from(column) * advance),
+            em(f32::from(line + 1) * line_height - descent),
+        );
+    }
+
+    out.push_str("})\n");
+    out
+}
+
+/// A Typst length in `em`, rounded to keep the output readable.
+fn em(value: f32) -> String {
+    format!("{}em", (value * 1e4).round() / 1e4)
+}
+
+/// A Typst color.
+fn color(color: Rgb) -> String {
+    format!("rgb(\"{color}\")")
+}
+
+/// Write a Typst string literal.
+fn string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,157 @@
+//! Watching files for changes, used to re-run the command in watch mode. On Linux, changes are
+//! observed through inotify. Elsewhere, the modification times of the files are polled.
+
+use std::{path::PathBuf, time::Duration};
+
+#[cfg(not(target_os = "linux"))]
+use std::time::SystemTime;
+
+/// Changes arriving within this time of each other are handled as a single change, as editors
+/// often write a file in multiple steps.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches a set of files and directories for changes. Directories are watched for changes to
+/// the entries directly within them.
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    #[cfg(target_os = "linux")]
+    inotify: rustix::fd::OwnedFd,
+    #[cfg(not(target_os = "linux"))]
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl Watcher {
+    pub fn new(paths: Vec<PathBuf>) -> anyhow::Result<Self> {
+        for path in &paths {
+            if !path.exists() {
+                anyhow::bail!(
+                    "`--watch` is set to {}, which does not exist",
+                    path.display()
+                );
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            use rustix::fs::inotify;
+
+            let watcher = Watcher {
+                paths,
+                inotify: inotify::inotify_init(inotify::CreateFlags::CLOEXEC)?,
+            };
+            watcher.add_watches()?;
+            Ok(watcher)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let modified = paths.iter().map(|path| modified(path)).collect();
+            Ok(Watcher { paths, modified })
+        }
+    }
+
+    /// Watch the paths. Adding a watch to a path that is already watched is a no-op, but editors
+    /// often replace files rather than writing to them, so this is repeated after every change.
+    #[cfg(target_os = "linux")]
+    fn add_watches(&self) -> anyhow::Result<()> {
+        use rustix::{
+            fd::AsFd,
+            fs::inotify::{self, WatchFlags},
+        };
+
+        let flags = WatchFlags::MODIFY
+            | WatchFlags::ATTRIB
+            | WatchFlags::CLOSE_WRITE
+            | WatchFlags::CREATE
+            | WatchFlags::DELETE
+            | WatchFlags::DELETE_SELF
+            | WatchFlags::MOVE_SELF
+            | WatchFlags::MOVED_FROM
+            | WatchFlags::MOVED_TO;
+        for path in &self.paths {
+            // the path may briefly not exist while it is being replaced
+            if path.exists() {
+                inotify::inotify_add_watch(self.inotify.as_fd(), path, flags)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Block until a watched path changes, ignoring changes made since the last call. Returns
+    /// once no further changes arrive for the debounce time.
+    #[cfg(target_os = "linux")]
+    pub fn wait(&mut self) -> anyhow::Result<()> {
+        use rustix::{
+            event::{poll, PollFd, PollFlags},
+            io::Errno,
+        };
+
+        // the events are not inspected: any event is a change
+        let mut events = [0; 4096];
+        // drain the changes made since the last call, e.g., to the output file
+        let mut draining = true;
+        let mut changed = false;
+        loop {
+            let timeout = if draining {
+                0
+            } else if changed {
+                DEBOUNCE.as_millis() as i32
+            } else {
+                -1
+            };
+            let mut fds = [PollFd::new(&self.inotify, PollFlags::IN)];
+            match poll(&mut fds, timeout) {
+                Ok(0) if draining => {
+                    draining = false;
+                    continue;
+                }
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(Errno::INTR) => continue,
+                Err(err) => return Err(err.into()),
+            }
+            match rustix::io::read(&self.inotify, &mut events) {
+                Ok(_) => changed = !draining,
+                Err(Errno::INTR) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        self.add_watches()
+    }
+
+    /// Block until a watched path changes, ignoring changes made since the last call.
+    #[cfg(not(target_os = "linux"))]
+    pub fn wait(&mut self) -> anyhow::Result<()> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+        // ignore the changes made since the last call, e.g., to the output file
+        self.modified = self.paths.iter().map(|path| modified(path)).collect();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current: Vec<_> = self.paths.iter().map(|path| modified(path)).collect();
+            if current != self.modified {
+                std::thread::sleep(DEBOUNCE);
+                self.modified = self.paths.iter().map(|path| modified(path)).collect();
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Get the latest modification time of the file, or of the directory and its entries.
+#[cfg(not(target_os = "linux"))]
+fn modified(path: &std::path::Path) -> Option<SystemTime> {
+    let mut modified = path
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            let entry_modified = entry.metadata().and_then(|metadata| metadata.modified());
+            modified = modified.max(entry_modified.ok());
+        }
+    }
+    modified
+}
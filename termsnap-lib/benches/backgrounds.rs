@@ -0,0 +1,100 @@
+//! Measure the background rectangles of the SVGs of screens with colored backgrounds: the number of
+//! rectangles drawn, the size of the SVG, the time to render it and the time to find the screen's
+//! main background color.
+//!
+//! Run with `cargo bench -p termsnap-lib`.
+
+use std::time::{Duration, Instant};
+
+use termsnap_lib::{emulate, FontMetrics, Screen};
+
+const LINES: u16 = 200;
+const COLUMNS: u16 = 120;
+
+/// Screens with colored backgrounds, as produced by, e.g., tables, diffs, editors and status bars.
+fn screens() -> Vec<(&'static str, Screen)> {
+    let mut screens = Vec::new();
+    let mut add = |name, f: &dyn Fn(u16) -> String| {
+        let mut bytes = String::new();
+        for line in 0..LINES {
+            bytes.push_str(&f(line));
+            if line + 1 < LINES {
+                bytes.push_str("\x1b[0m\r\n");
+            }
+        }
+        screens.push((name, emulate(LINES, COLUMNS, bytes.as_bytes())));
+    };
+
+    let columns = usize::from(COLUMNS);
+    add("alternating rows", &|line| {
+        format!("\x1b[4{}m{:columns$}", 4 + line % 2, "")
+    });
+    add("striped table", &|line| {
+        let bg = if line % 2 == 0 { 44 } else { 46 };
+        format!(
+            "\x1b[{bg}m{:>8} | {:<40} | {:<20}\x1b[K",
+            line, "name", "value"
+        )
+    });
+    add("gutter", &|line| {
+        format!(
+            "\x1b[47m{:>5} \x1b[0m{}",
+            line,
+            "text ".repeat(line as usize % 20)
+        )
+    });
+    add("diff", &|line| {
+        let bg = [41, 42, 49][usize::from(line / 3 % 3)];
+        format!("\x1b[47m{line:>5}\x1b[{bg}m {:<60}\x1b[K", "changed")
+    });
+    add("status bar and window", &|line| {
+        if line % 50 == 0 {
+            format!("\x1b[44m{:columns$}", " status")
+        } else {
+            format!("\x1b[47m \x1b[0m  text \x1b[45m{:40}\x1b[0m  \x1b[47m ", "")
+        }
+    });
+    add("checkerboard", &|line| {
+        (0..COLUMNS)
+            .map(|column| format!("\x1b[4{}m ", 4 + (line + column) % 2))
+            .collect()
+    });
+    add("truecolor gradient", &|line| {
+        (0..COLUMNS)
+            .map(|column| format!("\x1b[48;2;{};{};128m ", line % 256, column * 2))
+            .collect()
+    });
+    screens
+}
+
+fn main() {
+    println!(
+        "{:<24} {:>8} {:>10} {:>12} {:>12}",
+        "screen", "rects", "bytes", "time", "background"
+    );
+    for (name, screen) in screens() {
+        let svg = screen.to_svg(&[], FontMetrics::DEFAULT).to_string();
+        let rects = svg.matches("<rect").count();
+
+        let mut iterations = 0;
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(500) {
+            std::hint::black_box(screen.to_svg(&[], FontMetrics::DEFAULT).to_string());
+            iterations += 1;
+        }
+        let time = start.elapsed() / iterations;
+
+        let mut iterations = 0;
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(500) {
+            std::hint::black_box(screen.background());
+            iterations += 1;
+        }
+        let background = start.elapsed() / iterations;
+
+        println!(
+            "{name:<24} {rects:>8} {:>10} {time:>12.2?} {background:>12.2?}",
+            svg.len()
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Measure the throughput of processing terminal output: plain text, colored text, and text
+//! interleaved with the escape sequences observed alongside alacritty, such as Sixel images.
+//!
+//! Run with `cargo bench -p termsnap-lib --bench process`.
+
+use std::time::{Duration, Instant};
+
+use termsnap_lib::{Term, VoidPtyWriter};
+
+const LINES: u16 = 50;
+const COLUMNS: u16 = 120;
+
+/// Output of about 4 MiB each, as produced by, e.g., build logs, colored listings and image viewers.
+fn outputs() -> Vec<(&'static str, Vec<u8>)> {
+    let repeat = |chunk: &str| chunk.repeat((4 << 20) / chunk.len()).into_bytes();
+    vec![
+        (
+            "plain text",
+            repeat("compiling termsnap-lib v0.4.0 (/src/termsnap-lib)\r\n"),
+        ),
+        (
+            "unicode text",
+            repeat("│ ✓ résumé ├── 日本語テキスト ─┤\r\n"),
+        ),
+        (
+            "colored text",
+            repeat("\x1b[1;32m   Compiling\x1b[0m termsnap-lib \x1b[2mv0.4.0\x1b[0m\r\n"),
+        ),
+        (
+            "line sizes",
+            repeat("\x1b#6double width\r\n\x1b#3double height\r\n"),
+        ),
+        (
+            "sixel",
+            repeat("\x1bPq#0;2;100;0;0#0~~~~$-~~~~\x1b\\text\r\n"),
+        ),
+    ]
+}
+
+fn main() {
+    println!(
+        "{:<16} {:>10} {:>12} {:>14}",
+        "output", "bytes", "time", "throughput"
+    );
+    for (name, output) in outputs() {
+        let mut iterations = 0;
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(500) || iterations == 0 {
+            let mut term = Term::new(LINES, COLUMNS, VoidPtyWriter);
+            term.process_bytes(&output);
+            std::hint::black_box(&term);
+            iterations += 1;
+        }
+        let time = start.elapsed() / iterations;
+        let throughput = output.len() as f64 / time.as_secs_f64() / f64::from(1 << 20);
+
+        println!(
+            "{name:<16} {:>10} {time:>12.2?} {:>9.1} MiB/s",
+            output.len(),
+            throughput
+        );
+    }
+}
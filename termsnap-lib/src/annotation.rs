@@ -0,0 +1,201 @@
+//! Annotations drawn on top of the terminal screen, such as numbered callouts, arrows and labels.
+
+use std::fmt::Display;
+
+use crate::{
+    decoration::contrasting,
+    svg::{fmt_escaped, CalculatedFontMetrics},
+    Rgb,
+};
+
+/// The kind and position of an annotation. Positions are in cell coordinates: lines and columns
+/// start at 0 at the top-left of the screen.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnotationKind {
+    /// A circular badge with a number, centered on a cell.
+    Callout { line: u16, column: u16, number: u32 },
+    /// An arrow from the center of one cell, pointing at the center of another cell.
+    Arrow {
+        from_line: u16,
+        from_column: u16,
+        to_line: u16,
+        to_column: u16,
+    },
+    /// A line of text on a colored background, starting at a cell.
+    Label {
+        line: u16,
+        column: u16,
+        text: String,
+    },
+}
+
+/// An error returned when parsing an [AnnotationKind] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseAnnotationError;
+
+impl Display for ParseAnnotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "expected an annotation in the format `callout line:column number`, \
+            `arrow line:column line:column` or `label line:column text`",
+        )
+    }
+}
+
+impl std::error::Error for ParseAnnotationError {}
+
+impl std::str::FromStr for AnnotationKind {
+    type Err = ParseAnnotationError;
+
+    /// Parse an annotation in one of the formats `callout line:column number`,
+    /// `arrow line:column line:column` (from the first position to the second), or
+    /// `label line:column text`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let position = |s: &str| -> Result<(u16, u16), ParseAnnotationError> {
+            let (line, column) = s.split_once(':').ok_or(ParseAnnotationError)?;
+            Ok((
+                line.parse().map_err(|_| ParseAnnotationError)?,
+                column.parse().map_err(|_| ParseAnnotationError)?,
+            ))
+        };
+
+        let (kind, rest) = s.trim().split_once(' ').ok_or(ParseAnnotationError)?;
+        let (first, rest) = rest
+            .trim_start()
+            .split_once(' ')
+            .ok_or(ParseAnnotationError)?;
+        let (line, column) = position(first)?;
+        let rest = rest.trim();
+
+        match kind {
+            "callout" => Ok(AnnotationKind::Callout {
+                line,
+                column,
+                number: rest.parse().map_err(|_| ParseAnnotationError)?,
+            }),
+            "arrow" => {
+                let (to_line, to_column) = position(rest)?;
+                Ok(AnnotationKind::Arrow {
+                    from_line: line,
+                    from_column: column,
+                    to_line,
+                    to_column,
+                })
+            }
+            "label" if !rest.is_empty() => Ok(AnnotationKind::Label {
+                line,
+                column,
+                text: rest.to_owned(),
+            }),
+            _ => Err(ParseAnnotationError),
+        }
+    }
+}
+
+/// An annotation drawn on top of the terminal screen. See
+/// [SvgOptions::annotations](crate::SvgOptions::annotations).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    pub color: Rgb,
+}
+
+/// Write the annotations over the terminal screen.
+pub(crate) fn fmt_annotations(
+    f: &mut std::fmt::Formatter<'_>,
+    annotations: &[Annotation],
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let center = |line: u16, column: u16| {
+        (
+            (f32::from(column) + 0.5) * font_metrics.advance,
+            (f32::from(line) + 0.5) * font_metrics.line_height,
+        )
+    };
+    let font_size = font_metrics.line_height * 0.7;
+
+    for annotation in annotations {
+        let color = annotation.color;
+        let text_color = contrasting(color);
+
+        match annotation.kind {
+            AnnotationKind::Callout {
+                line,
+                column,
+                number,
+            } => {
+                let (cx, cy) = center(line, column);
+                let radius = font_metrics.line_height * 0.6;
+                writeln!(
+                    f,
+                    r#"<circle cx="{cx}" cy="{cy}" r="{radius}" style="fill: {color};" />"#,
+                )?;
+                writeln!(
+                    f,
+                    r#"<text x="{cx}" y="{cy}" text-anchor="middle" dominant-baseline="central" style="fill: {text_color}; font-size: {font_size}px; font-weight: 600;">{number}</text>"#,
+                )?;
+            }
+            AnnotationKind::Arrow {
+                from_line,
+                from_column,
+                to_line,
+                to_column,
+            } => {
+                let (x0, y0) = center(from_line, from_column);
+                let (x1, y1) = center(to_line, to_column);
+                let length = (x1 - x0).hypot(y1 - y0);
+                if length == 0. {
+                    continue;
+                }
+
+                // the direction of the arrow, and its normal
+                let (dx, dy) = ((x1 - x0) / length, (y1 - y0) / length);
+                let (nx, ny) = (-dy, dx);
+                let head_length = font_metrics.line_height * 0.6;
+                let head_width = head_length * 0.6;
+                let (bx, by) = (x1 - dx * head_length, y1 - dy * head_length);
+                let stroke_width = font_metrics.line_height / 8.;
+
+                writeln!(
+                    f,
+                    r#"<line x1="{x0}" y1="{y0}" x2="{bx}" y2="{by}" style="stroke: {color}; stroke-width: {stroke_width}; stroke-linecap: round;" />"#,
+                )?;
+                writeln!(
+                    f,
+                    r#"<path d="M{x1} {y1}L{} {}L{} {}z" style="fill: {color};" />"#,
+                    bx + nx * head_width,
+                    by + ny * head_width,
+                    bx - nx * head_width,
+                    by - ny * head_width,
+                )?;
+            }
+            AnnotationKind::Label {
+                line,
+                column,
+                ref text,
+            } => {
+                let chars: Vec<char> = text.chars().collect();
+                let padding = font_metrics.advance / 2.;
+                let x = f32::from(column) * font_metrics.advance;
+                let y = f32::from(line) * font_metrics.line_height;
+                let text_length = chars.len() as f32 * font_metrics.advance;
+                writeln!(
+                    f,
+                    r#"<rect x="{}" y="{y}" width="{}" height="{}" rx="{padding}" style="fill: {color};" />"#,
+                    x - padding,
+                    text_length + 2. * padding,
+                    font_metrics.line_height,
+                )?;
+                write!(
+                    f,
+                    r#"<text x="{x}" y="{}" textLength="{text_length}" style="fill: {text_color};">"#,
+                    y + font_metrics.line_height - font_metrics.descent,
+                )?;
+                fmt_escaped(f, &chars)?;
+                f.write_str("</text>\n")?;
+            }
+        }
+    }
+
+    Ok(())
+}
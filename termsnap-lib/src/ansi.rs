@@ -1,12 +1,43 @@
-use alacritty_terminal::vte::ansi::{self, Handler};
+use std::ops::Range;
+
+use alacritty_terminal::{
+    grid::{Dimensions, GridCell},
+    index::Line,
+    term::TermMode,
+    vte::ansi::{self, Handler},
+};
 
 use crate::{PtyWriter, Term};
 
+#[non_exhaustive]
 pub enum AnsiSignal {
     /// Clear the entire terminal screen.
     ClearScreen,
     /// Enable or disable the alternate terminal screen buffer.
     AlternateScreenBuffer { enable: bool },
+    /// The program requested a snapshot of the terminal screen using the private escape sequence
+    /// `OSC 7979 ; snapshot [; name] ST`, optionally naming the snapshot. This signal is sent
+    /// after the sequence is processed.
+    Snapshot { name: Option<String> },
+    /// The bell character (BEL) was received.
+    Bell,
+    /// The terminal title changed, either set using the OSC 0 or OSC 2 escape sequences or
+    /// restored from the title stack. `title` is `None` if the title was reset. This signal is
+    /// sent after the title changed.
+    TitleChanged { title: Option<String> },
+    /// The cursor was shown or hidden. This signal is sent after the change.
+    CursorVisibilityChanged { visible: bool },
+    /// The screen content scrolled up by `lines` lines, either explicitly or by a line feed at the
+    /// bottom of the scrolling region. On the main screen buffer, lines scrolled off the top of
+    /// the screen move into the scrollback history. This signal is sent after scrolling.
+    ScrollUp { lines: usize },
+    /// The screen content scrolled down by `lines` lines, either explicitly or by a reverse line
+    /// feed at the top of the scrolling region. This signal is sent after scrolling.
+    ScrollDown { lines: usize },
+    /// The program requested the terminal to be resized to `lines` by `columns` cells using the
+    /// `CSI 8 ; lines ; columns t` escape sequence. The terminal is not resized; see
+    /// [Term::resize](crate::Term::resize).
+    ResizeRequest { lines: u16, columns: u16 },
 }
 
 pub(crate) struct HandlerWrapper<'t, W: PtyWriter> {
@@ -14,18 +45,90 @@ pub(crate) struct HandlerWrapper<'t, W: PtyWriter> {
     pub cb: &'t mut dyn FnMut(&Term<W>, AnsiSignal),
 }
 
+impl<'t, W: PtyWriter> HandlerWrapper<'t, W> {
+    /// Apply `f` to the terminal, signalling if the title changed.
+    fn signal_title(&mut self, f: impl FnOnce(&mut Self)) {
+        let title = self.term.title();
+        f(self);
+        let new_title = self.term.title();
+        if new_title != title {
+            (self.cb)(self.term, AnsiSignal::TitleChanged { title: new_title });
+        }
+    }
+
+    /// Apply `f` to the terminal, signalling if the cursor was shown or hidden.
+    fn signal_cursor_visibility(&mut self, f: impl FnOnce(&mut Self)) {
+        let visible = self.term.cursor().visible;
+        f(self);
+        let now_visible = self.term.cursor().visible;
+        if now_visible != visible {
+            (self.cb)(
+                self.term,
+                AnsiSignal::CursorVisibilityChanged {
+                    visible: now_visible,
+                },
+            );
+        }
+    }
+
+    /// Apply `f` to the terminal, signalling scrolling by a single line if the cursor is at the
+    /// edge of the scrolling region. This assumes `f` moves the cursor down (`down` is `true`) or
+    /// up one line, scrolling the region if it cannot.
+    fn signal_line_scroll(&mut self, down: bool, f: impl FnOnce(&mut Self)) {
+        let line = self.cursor_line();
+        let region = self.term.scroll_region.clone();
+        if down && line + 1 == region.end {
+            self.scroll(region, 1, f);
+            (self.cb)(self.term, AnsiSignal::ScrollUp { lines: 1 });
+        } else if !down && line == region.start {
+            self.scroll(region, -1, f);
+            (self.cb)(self.term, AnsiSignal::ScrollDown { lines: 1 });
+        } else {
+            f(self);
+        }
+    }
+
+    /// Apply `f` to the terminal, which scrolls the screen lines in `region` up by `lines` lines,
+    /// or down if `lines` is negative, moving line sizes and images along.
+    fn scroll(&mut self, region: Range<usize>, lines: isize, f: impl FnOnce(&mut Self)) {
+        let history_size = self.term.term.grid().history_size();
+        f(self);
+        self.term.lines_scrolled(region, lines, history_size);
+    }
+
+    /// The screen line of the cursor.
+    fn cursor_line(&self) -> usize {
+        self.term.term.grid().cursor.point.line.0.max(0) as usize
+    }
+}
+
 impl<'t, W: PtyWriter> Handler for HandlerWrapper<'t, W> {
     fn set_title(&mut self, p: Option<String>) {
-        self.term.term.set_title(p)
+        self.signal_title(|this| this.term.term.set_title(p))
     }
     fn set_cursor_style(&mut self, p: Option<ansi::CursorStyle>) {
-        self.term.term.set_cursor_style(p)
+        self.signal_cursor_visibility(|this| this.term.term.set_cursor_style(p))
     }
     fn set_cursor_shape(&mut self, p: ansi::CursorShape) {
-        self.term.term.set_cursor_shape(p)
+        self.signal_cursor_visibility(|this| this.term.term.set_cursor_shape(p))
     }
     fn input(&mut self, p: char) {
-        self.term.term.input(p)
+        let column = self.term.term.grid().cursor.point.column;
+        let region = self.term.scroll_region.clone();
+        // wrapping from the last column of the bottom line of the scrolling region scrolls it up,
+        // which shows as the cursor moving back to the start of the same line
+        if column + 1 == self.term.term.columns()
+            && self.cursor_line() + 1 == region.end
+            && self.term.term.mode().contains(TermMode::LINE_WRAP)
+        {
+            let history_size = self.term.term.grid().history_size();
+            self.term.term.input(p);
+            if self.term.term.grid().cursor.point.column < column {
+                self.term.lines_scrolled(region, 1, history_size);
+            }
+        } else {
+            self.term.term.input(p)
+        }
     }
     fn goto(&mut self, p1: i32, p2: usize) {
         self.term.term.goto(p1, p2)
@@ -73,31 +176,47 @@ impl<'t, W: PtyWriter> Handler for HandlerWrapper<'t, W> {
         self.term.term.carriage_return()
     }
     fn linefeed(&mut self) {
-        self.term.term.linefeed()
+        self.signal_line_scroll(true, |this| this.term.term.linefeed())
     }
     fn bell(&mut self) {
+        (self.cb)(self.term, AnsiSignal::Bell);
+
         self.term.term.bell()
     }
     fn substitute(&mut self) {
         self.term.term.substitute()
     }
     fn newline(&mut self) {
-        self.term.term.newline()
+        self.signal_line_scroll(true, |this| this.term.term.newline())
     }
     fn set_horizontal_tabstop(&mut self) {
         self.term.term.set_horizontal_tabstop()
     }
     fn scroll_up(&mut self, p: usize) {
-        self.term.term.scroll_up(p)
+        let region = self.term.scroll_region.clone();
+        self.scroll(region, p as isize, |this| this.term.term.scroll_up(p));
+
+        (self.cb)(self.term, AnsiSignal::ScrollUp { lines: p });
     }
     fn scroll_down(&mut self, p: usize) {
-        self.term.term.scroll_down(p)
+        let region = self.term.scroll_region.clone();
+        self.scroll(region, -(p as isize), |this| this.term.term.scroll_down(p));
+
+        (self.cb)(self.term, AnsiSignal::ScrollDown { lines: p });
     }
     fn insert_blank_lines(&mut self, p: usize) {
-        self.term.term.insert_blank_lines(p)
+        let origin = self.cursor_line();
+        let end = self.term.scroll_region.end;
+        self.scroll(origin..end, -(p as isize), |this| {
+            this.term.term.insert_blank_lines(p)
+        });
     }
     fn delete_lines(&mut self, p: usize) {
-        self.term.term.delete_lines(p)
+        let origin = self.cursor_line();
+        let end = self.term.scroll_region.end;
+        self.scroll(origin..end, p as isize, |this| {
+            this.term.term.delete_lines(p)
+        });
     }
     fn erase_chars(&mut self, p: usize) {
         self.term.term.erase_chars(p)
@@ -118,21 +237,58 @@ impl<'t, W: PtyWriter> Handler for HandlerWrapper<'t, W> {
         self.term.term.restore_cursor_position()
     }
     fn clear_line(&mut self, p: ansi::LineClearMode) {
-        self.term.term.clear_line(p)
+        let all = matches!(p, ansi::LineClearMode::All);
+        self.term.term.clear_line(p);
+
+        if all {
+            let line = self.cursor_line();
+            self.term.lines_erased(line..line + 1);
+        }
     }
     fn clear_screen(&mut self, p: ansi::ClearMode) {
-        (self.cb)(&self.term, AnsiSignal::ClearScreen);
+        (self.cb)(self.term, AnsiSignal::ClearScreen);
 
-        self.term.term.clear_screen(p)
+        let line = self.cursor_line();
+        let lines = self.term.term.screen_lines();
+        match p {
+            ansi::ClearMode::Above => {
+                self.term.term.clear_screen(p);
+                self.term.lines_erased(0..line);
+            }
+            ansi::ClearMode::Below => {
+                self.term.term.clear_screen(p);
+                self.term.lines_erased(line + 1..lines);
+            }
+            ansi::ClearMode::All => {
+                // on the main screen, alacritty scrolls the lines up to the last non-empty line
+                // into the history
+                let grid = self.term.term.grid();
+                let used = (0..lines)
+                    .rev()
+                    .find(|&line| {
+                        grid[Line(line as i32)][..]
+                            .iter()
+                            .any(|cell| !cell.is_empty())
+                    })
+                    .map_or(0, |line| line + 1);
+                self.scroll(0..lines, used as isize, |this| {
+                    this.term.term.clear_screen(p)
+                });
+                self.term.lines_erased(0..lines);
+            }
+            _ => self.term.term.clear_screen(p),
+        }
     }
     fn clear_tabs(&mut self, p: ansi::TabulationClearMode) {
         self.term.term.clear_tabs(p)
     }
     fn reset_state(&mut self) {
-        self.term.term.reset_state()
+        self.term.inactive_screen = None;
+        self.term.scroll_region = 0..usize::from(self.term.lines);
+        self.signal_cursor_visibility(|this| this.term.term.reset_state())
     }
     fn reverse_index(&mut self) {
-        self.term.term.reverse_index()
+        self.signal_line_scroll(false, |this| this.term.term.reverse_index())
     }
     fn terminal_attribute(&mut self, p: ansi::Attr) {
         self.term.term.terminal_attribute(p)
@@ -152,12 +308,15 @@ impl<'t, W: PtyWriter> Handler for HandlerWrapper<'t, W> {
             ansi::PrivateMode::Named(ansi::NamedPrivateMode::SwapScreenAndSetRestoreCursor)
         ) {
             (self.cb)(
-                &self.term,
+                self.term,
                 AnsiSignal::AlternateScreenBuffer { enable: true },
             );
+            if !self.term.is_alternate_screen() {
+                self.term.inactive_screen = Some(self.term.current_screen());
+            }
         }
 
-        self.term.term.set_private_mode(p)
+        self.signal_cursor_visibility(|this| this.term.term.set_private_mode(p))
     }
     fn unset_private_mode(&mut self, p: ansi::PrivateMode) {
         if matches!(
@@ -165,18 +324,28 @@ impl<'t, W: PtyWriter> Handler for HandlerWrapper<'t, W> {
             ansi::PrivateMode::Named(ansi::NamedPrivateMode::SwapScreenAndSetRestoreCursor)
         ) {
             (self.cb)(
-                &self.term,
+                self.term,
                 AnsiSignal::AlternateScreenBuffer { enable: false },
             );
+            if self.term.is_alternate_screen() {
+                self.term.inactive_screen = Some(self.term.current_screen());
+            }
         }
 
-        self.term.term.unset_private_mode(p)
+        self.signal_cursor_visibility(|this| this.term.term.unset_private_mode(p))
     }
     fn report_private_mode(&mut self, p: ansi::PrivateMode) {
         self.term.term.report_private_mode(p)
     }
     fn set_scrolling_region(&mut self, p1: usize, p2: Option<usize>) {
-        self.term.term.set_scrolling_region(p1, p2)
+        self.term.term.set_scrolling_region(p1, p2);
+
+        // as alacritty, ignoring invalid regions
+        let lines = usize::from(self.term.lines);
+        let bottom = p2.unwrap_or(lines);
+        if p1 < bottom {
+            self.term.scroll_region = p1.saturating_sub(1).min(lines)..bottom.min(lines);
+        }
     }
     fn set_keypad_application_mode(&mut self) {
         self.term.term.set_keypad_application_mode()
@@ -212,7 +381,7 @@ impl<'t, W: PtyWriter> Handler for HandlerWrapper<'t, W> {
         self.term.term.push_title()
     }
     fn pop_title(&mut self) {
-        self.term.term.pop_title()
+        self.signal_title(|this| this.term.term.pop_title())
     }
     fn text_area_size_pixels(&mut self) {
         self.term.term.text_area_size_pixels()
@@ -0,0 +1,162 @@
+//! Blurring and pixelation of regions of the terminal screen, to obscure sensitive content that
+//! cannot be matched by a regular expression for `Screen::redact`.
+
+use crate::{decoration, svg, Cell, Region, Screen, SvgOptions};
+
+/// The id of the SVG filter used to blur regions.
+pub(crate) const FILTER_ID: &str = "termsnap-blur";
+
+/// How a blurred region is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlurStyle {
+    /// Apply a gaussian blur.
+    #[default]
+    Blur,
+    /// Fill each cell with a single color, mixing the cell's background and foreground colors
+    /// according to whether the cell has text.
+    Pixelate,
+}
+
+/// A region of the terminal screen drawn blurred or pixelated. See
+/// [SvgOptions::blurs](crate::SvgOptions::blurs).
+///
+/// The text of the cells in the region is not included in the SVG: blurred text is replaced by
+/// placeholder characters before blurring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Blur {
+    pub region: Region,
+    pub style: BlurStyle,
+}
+
+/// Get the line segments of `region` on a screen of `lines` by `columns`, as tuples of `(line,
+/// start column, end column)` with an exclusive end.
+fn segments(region: Region, lines: u16, columns: u16) -> impl Iterator<Item = (u16, u16, u16)> {
+    (region.start_line..=region.end_line.min(lines.saturating_sub(1))).filter_map(move |line| {
+        let x0 = if line == region.start_line {
+            region.start_column
+        } else {
+            0
+        };
+        let x1 = if line == region.end_line {
+            region.end_column
+        } else {
+            columns
+        }
+        .min(columns);
+        (x0 < x1).then_some((line, x0, x1))
+    })
+}
+
+/// Get a copy of `screen` with the text of the cells in `blurs` cleared. The cells' backgrounds are
+/// kept.
+pub(crate) fn clear(screen: &Screen, blurs: &[Blur]) -> Screen {
+    let mut screen = screen.clone();
+    for blur in blurs {
+        for (line, x0, x1) in segments(blur.region, screen.lines, screen.columns) {
+            for column in x0..x1 {
+                let idx = screen.idx(line, column);
+                screen.cells[idx] = Cell {
+                    c: ' ',
+                    zerowidth: Box::default(),
+                    hyperlink: None,
+                    ..screen.cells[idx].clone()
+                };
+            }
+        }
+    }
+    screen
+}
+
+/// Get the plain-text content of `screen`, leaving out the text of the cells in `blurs` and of
+/// hidden cells.
+pub(crate) fn text(screen: &Screen, blurs: &[Blur]) -> String {
+    if blurs.is_empty() && !screen.cells.iter().any(|cell| cell.hidden) {
+        return screen.text();
+    }
+
+    let mut screen = clear(screen, blurs);
+    for cell in &mut screen.cells {
+        if cell.hidden {
+            cell.c = ' ';
+            cell.zerowidth = Box::default();
+        }
+    }
+    screen.text()
+}
+
+/// Write the blurs of `options` over `screen`, which must not have been [cleared](clear).
+pub(crate) fn fmt_blurs(
+    f: &mut std::fmt::Formatter<'_>,
+    screen: &Screen,
+    options: &SvgOptions,
+    main_bg: crate::Rgb,
+    font_metrics: &svg::CalculatedFontMetrics,
+) -> std::fmt::Result {
+    for blur in options.blurs {
+        for (line, x0, x1) in segments(blur.region, screen.lines, screen.columns) {
+            match blur.style {
+                BlurStyle::Blur => {
+                    // the text is replaced, such that it cannot be recovered from the SVG
+                    let mut segment = screen.crop(line..=line, x0..x1);
+                    for cell in &mut segment.cells {
+                        if !cell.c.is_whitespace() {
+                            cell.c = 'x';
+                            cell.zerowidth = Box::default();
+                        }
+                    }
+                    writeln!(
+                        f,
+                        r#"<g transform="translate({} {})" filter="url(#{FILTER_ID})">"#,
+                        f32::from(x0) * font_metrics.advance,
+                        f32::from(line) * font_metrics.line_height,
+                    )?;
+                    svg::fmt_screen(
+                        f,
+                        &segment,
+                        &SvgOptions {
+                            highlights: &[],
+                            annotations: &[],
+                            blurs: &[],
+                            ..*options
+                        },
+                        main_bg,
+                        font_metrics,
+                    )?;
+                    f.write_str("</g>\n")?;
+                }
+                BlurStyle::Pixelate => {
+                    for column in x0..x1 {
+                        let cell = &screen.cells[screen.idx(line, column)];
+                        let ink = if cell.c.is_whitespace() || cell.hidden {
+                            0.
+                        } else {
+                            0.4
+                        };
+                        writeln!(
+                            f,
+                            r#"<rect x="{}" y="{}" width="{}" height="{}" style="fill: {};" />"#,
+                            f32::from(column) * font_metrics.advance,
+                            f32::from(line) * font_metrics.line_height,
+                            font_metrics.advance,
+                            font_metrics.line_height,
+                            decoration::mix(cell.bg, cell.fg, ink),
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the definition of the filter used by blurs.
+pub(crate) fn fmt_filter(
+    f: &mut std::fmt::Formatter<'_>,
+    font_metrics: &svg::CalculatedFontMetrics,
+) -> std::fmt::Result {
+    writeln!(
+        f,
+        r#"<defs><filter id="{FILTER_ID}" x="-10%" y="-30%" width="120%" height="160%"><feGaussianBlur stdDeviation="{}" /></filter></defs>"#,
+        font_metrics.advance / 2.,
+    )
+}
@@ -1,80 +1,264 @@
 use alacritty_terminal::{
-    term::color::Colors as AlacrittyColors,
+    term::color::{Colors as AlacrittyColors, COUNT},
     vte::ansi::{Color, NamedColor, Rgb as AlacrittyRgb},
 };
 
 use std::collections::HashMap;
 
-use crate::{Rgb, Screen};
+use crate::{decoration, Rgb, Screen};
 
-pub(crate) struct Colors {
-    colors: AlacrittyColors,
+/// A color of the terminal's color table that can be overridden. See [Colors::set].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorName {
+    /// A color of the 256-color palette. Colors 0 to 7 are the normal ANSI colors black, red,
+    /// green, yellow, blue, magenta, cyan and white, and colors 8 to 15 their bright variants.
+    Indexed(u8),
+    /// The default foreground color.
+    Foreground,
+    /// The default background color.
+    Background,
+    /// The color of the cursor.
+    Cursor,
+}
+
+/// An error returned when parsing a [ColorName] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorNameError;
+
+impl std::fmt::Display for ParseColorNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "expected a palette index from 0 to 255, an ANSI color name such as `blue` or \
+            `bright-blue`, or one of `foreground`, `background` and `cursor`",
+        )
+    }
+}
+
+impl std::error::Error for ParseColorNameError {}
+
+impl std::str::FromStr for ColorName {
+    type Err = ParseColorNameError;
+
+    /// Parse a palette index from 0 to 255, an ANSI color name (e.g., `blue`), a bright ANSI color
+    /// name (e.g., `bright-blue`), or one of `foreground`, `background` and `cursor`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const NAMES: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+
+        if let Ok(idx) = s.parse() {
+            return Ok(ColorName::Indexed(idx));
+        }
+
+        let s = s.to_ascii_lowercase();
+        match s.as_str() {
+            "foreground" | "fg" => return Ok(ColorName::Foreground),
+            "background" | "bg" => return Ok(ColorName::Background),
+            "cursor" => return Ok(ColorName::Cursor),
+            _ => {}
+        }
+
+        let (name, offset) = match s.strip_prefix("bright") {
+            Some(name) => (name.trim_start_matches(['-', '_']), 8),
+            None => (s.as_str(), 0),
+        };
+        NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map(|idx| ColorName::Indexed(idx as u8 + offset))
+            .ok_or(ParseColorNameError)
+    }
+}
+
+/// The color table used to resolve the colors of the terminal's cells. This defaults to the
+/// Solarized Dark theme for the named colors, and the standard xterm colors for the remainder of
+/// the 256-color palette.
+#[derive(Clone)]
+pub struct Colors {
+    /// The resolved colors, indexed like alacritty's color table.
+    lut: [Rgb; COUNT],
+    bold_is_bright: bool,
+}
+
+impl std::fmt::Debug for Colors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Colors")
+            .field("foreground", &self.get(ColorName::Foreground))
+            .field("background", &self.get(ColorName::Background))
+            .field("bold_is_bright", &self.bold_is_bright)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Colors {
-    pub fn to_rgb(&self, color: Color) -> Rgb {
-        let AlacrittyRgb { r, g, b } = match color {
-            Color::Named(named_color) => {
-                self.colors[named_color as usize].expect("all colors should be defined")
-            }
-            Color::Indexed(idx) => {
-                self.colors[usize::from(idx)].expect("all colors should be defined")
-            }
-            Color::Spec(rgb) => rgb,
+    /// Override a color of the color table.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{ColorName, Colors, Term, VoidPtyWriter};
+    ///
+    /// let mut colors = Colors::default();
+    /// colors.set(ColorName::Background, "#1e1e2e".parse().unwrap());
+    ///
+    /// let mut term = Term::new(24, 80, VoidPtyWriter);
+    /// term.set_colors(colors);
+    /// ```
+    pub fn set(&mut self, name: ColorName, color: Rgb) {
+        // dim variants are not distinguished from the normal colors
+        let indices: &[usize] = match name {
+            ColorName::Indexed(idx @ 0..=7) => &[
+                usize::from(idx),
+                NamedColor::DimBlack as usize + usize::from(idx),
+            ],
+            ColorName::Indexed(idx) => &[usize::from(idx)],
+            ColorName::Foreground => &[
+                NamedColor::Foreground as usize,
+                NamedColor::DimForeground as usize,
+                NamedColor::BrightForeground as usize,
+            ],
+            ColorName::Background => &[NamedColor::Background as usize],
+            ColorName::Cursor => &[NamedColor::Cursor as usize],
         };
+        for &idx in indices {
+            self.lut[idx] = color;
+        }
+    }
 
-        Rgb { r, g, b }
+    /// Draw bold text in the bright variants of the normal ANSI colors and the default foreground
+    /// color, as many xterm-like terminals do. Legacy color schemes often expect this. Disabled by
+    /// default.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{ColorName, Colors, Term, VoidPtyWriter};
+    ///
+    /// let mut colors = Colors::default();
+    /// colors.set_bold_is_bright(true);
+    ///
+    /// let mut term = Term::new(1, 10, VoidPtyWriter);
+    /// term.set_colors(colors.clone());
+    /// term.process_bytes(b"\x1b[1;31mbold");
+    /// let screen = term.current_screen();
+    /// assert_eq!(screen[(0, 0)].fg, colors.get(ColorName::Indexed(9)));
+    /// ```
+    pub fn set_bold_is_bright(&mut self, bold_is_bright: bool) {
+        self.bold_is_bright = bold_is_bright;
+    }
+
+    /// Get a color of the color table.
+    pub fn get(&self, name: ColorName) -> Rgb {
+        self.to_rgb(match name {
+            ColorName::Indexed(idx) => Color::Indexed(idx),
+            ColorName::Foreground => Color::Named(NamedColor::Foreground),
+            ColorName::Background => Color::Named(NamedColor::Background),
+            ColorName::Cursor => Color::Named(NamedColor::Cursor),
+        })
+    }
+
+    /// Get a copy of this color table with the colors set in `overrides` replaced, e.g., colors
+    /// changed by the program running in the terminal.
+    pub(crate) fn with_overrides(&self, overrides: &AlacrittyColors) -> Colors {
+        let mut colors = self.clone();
+        for idx in 0..COUNT {
+            if let Some(color) = overrides[idx] {
+                colors.lut[idx] = from_alacritty_rgb(color);
+            }
+        }
+        colors
+    }
+
+    pub(crate) fn to_rgb(&self, color: Color) -> Rgb {
+        match color {
+            Color::Named(named_color) => self.lut[named_color as usize],
+            Color::Indexed(idx) => self.lut[usize::from(idx)],
+            Color::Spec(rgb) => from_alacritty_rgb(rgb),
+        }
+    }
+
+    /// Get the color of text, taking into account whether bold text is drawn in bright colors.
+    pub(crate) fn text_color(&self, color: Color, bold: bool) -> Color {
+        if !(bold && self.bold_is_bright) {
+            return color;
+        }
+        match color {
+            Color::Named(NamedColor::Foreground) => Color::Named(NamedColor::BrightForeground),
+            Color::Named(named) if (named as usize) < 8 => Color::Indexed(named as u8 + 8),
+            Color::Indexed(idx @ 0..=7) => Color::Indexed(idx + 8),
+            color => color,
+        }
     }
 }
 
 impl Default for Colors {
     /// Generate a terminal color table
     fn default() -> Colors {
-        let mut colors = AlacrittyColors::default();
+        let mut lut = [Rgb { r: 0, g: 0, b: 0 }; COUNT];
 
-        fill_named(&mut colors);
-        fill_cube(&mut colors);
-        fill_gray_ramp(&mut colors);
+        fill_named(&mut lut);
+        fill_cube(&mut lut);
+        fill_gray_ramp(&mut lut);
 
-        Colors { colors }
+        Colors {
+            lut,
+            bold_is_bright: false,
+        }
+    }
+}
+
+fn from_alacritty_rgb(AlacrittyRgb { r, g, b }: AlacrittyRgb) -> Rgb {
+    Rgb { r, g, b }
+}
+
+/// Get the index of `color` in the color table, or `None` for colors given directly as RGB.
+pub(crate) fn palette_index(color: Color) -> Option<u16> {
+    match color {
+        Color::Named(named_color) => Some(named_color as u16),
+        Color::Indexed(idx) => Some(u16::from(idx)),
+        Color::Spec(_) => None,
+    }
+}
+
+/// A color from its hexadecimal `0xrrggbb` notation.
+const fn hex(rgb: u32) -> Rgb {
+    Rgb {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
     }
 }
 
 /// Fill named terminal colors with the solarized dark theme
-fn fill_named(colors: &mut AlacrittyColors) {
-    colors[NamedColor::Black as usize] = Some("#073642".parse().unwrap());
-    colors[NamedColor::Black] = Some("#073642".parse().unwrap());
-    colors[NamedColor::Red] = Some("#dc322f".parse().unwrap());
-    colors[NamedColor::Green] = Some("#859900".parse().unwrap());
-    colors[NamedColor::Yellow] = Some("#b58900".parse().unwrap());
-    colors[NamedColor::Blue] = Some("#268bd2".parse().unwrap());
-    colors[NamedColor::Magenta] = Some("#d33682".parse().unwrap());
-    colors[NamedColor::Cyan] = Some("#2aa198".parse().unwrap());
-    colors[NamedColor::White] = Some("#eee8d5".parse().unwrap());
-    colors[NamedColor::BrightBlack] = Some("#002b36".parse().unwrap());
-    colors[NamedColor::BrightRed] = Some("#cb4b16".parse().unwrap());
-    colors[NamedColor::BrightGreen] = Some("#586e75".parse().unwrap());
-    colors[NamedColor::BrightYellow] = Some("#657b83".parse().unwrap());
-    colors[NamedColor::BrightBlue] = Some("#839496".parse().unwrap());
-    colors[NamedColor::BrightMagenta] = Some("#6c71c4".parse().unwrap());
-    colors[NamedColor::BrightCyan] = Some("#93a1a1".parse().unwrap());
-    colors[NamedColor::BrightWhite] = Some("#fdf6e3".parse().unwrap());
-    colors[NamedColor::Foreground] = Some("#839496".parse().unwrap());
-    colors[NamedColor::Background] = Some("#002b36".parse().unwrap());
-    colors[NamedColor::Cursor] = Some("#839496".parse().unwrap());
-    colors[NamedColor::DimBlack] = Some("#073642".parse().unwrap());
-    colors[NamedColor::DimRed] = Some("#dc322f".parse().unwrap());
-    colors[NamedColor::DimGreen] = Some("#859900".parse().unwrap());
-    colors[NamedColor::DimYellow] = Some("#b58900".parse().unwrap());
-    colors[NamedColor::DimBlue] = Some("#268bd2".parse().unwrap());
-    colors[NamedColor::DimMagenta] = Some("#d33682".parse().unwrap());
-    colors[NamedColor::DimCyan] = Some("#2aa198".parse().unwrap());
-    colors[NamedColor::DimWhite] = Some("#eee8d5".parse().unwrap());
-    colors[NamedColor::DimForeground] = Some("#839496".parse().unwrap());
-    colors[NamedColor::BrightForeground] = Some("#839496".parse().unwrap());
-}
-
-fn fill_cube(colors: &mut AlacrittyColors) {
+fn fill_named(lut: &mut [Rgb; COUNT]) {
+    lut[NamedColor::Black as usize] = hex(0x073642);
+    lut[NamedColor::Red as usize] = hex(0xdc322f);
+    lut[NamedColor::Green as usize] = hex(0x859900);
+    lut[NamedColor::Yellow as usize] = hex(0xb58900);
+    lut[NamedColor::Blue as usize] = hex(0x268bd2);
+    lut[NamedColor::Magenta as usize] = hex(0xd33682);
+    lut[NamedColor::Cyan as usize] = hex(0x2aa198);
+    lut[NamedColor::White as usize] = hex(0xeee8d5);
+    lut[NamedColor::BrightBlack as usize] = hex(0x002b36);
+    lut[NamedColor::BrightRed as usize] = hex(0xcb4b16);
+    lut[NamedColor::BrightGreen as usize] = hex(0x586e75);
+    lut[NamedColor::BrightYellow as usize] = hex(0x657b83);
+    lut[NamedColor::BrightBlue as usize] = hex(0x839496);
+    lut[NamedColor::BrightMagenta as usize] = hex(0x6c71c4);
+    lut[NamedColor::BrightCyan as usize] = hex(0x93a1a1);
+    lut[NamedColor::BrightWhite as usize] = hex(0xfdf6e3);
+    lut[NamedColor::Foreground as usize] = hex(0x839496);
+    lut[NamedColor::Background as usize] = hex(0x002b36);
+    lut[NamedColor::Cursor as usize] = hex(0x839496);
+    lut[NamedColor::DimBlack as usize] = hex(0x073642);
+    lut[NamedColor::DimRed as usize] = hex(0xdc322f);
+    lut[NamedColor::DimGreen as usize] = hex(0x859900);
+    lut[NamedColor::DimYellow as usize] = hex(0xb58900);
+    lut[NamedColor::DimBlue as usize] = hex(0x268bd2);
+    lut[NamedColor::DimMagenta as usize] = hex(0xd33682);
+    lut[NamedColor::DimCyan as usize] = hex(0x2aa198);
+    lut[NamedColor::DimWhite as usize] = hex(0xeee8d5);
+    lut[NamedColor::DimForeground as usize] = hex(0x839496);
+    lut[NamedColor::BrightForeground as usize] = hex(0x839496);
+}
+
+fn fill_cube(lut: &mut [Rgb; COUNT]) {
     // adapted from: https://github.com/alacritty/alacritty/blob/da554e41f3a91ed6cc5db66b23bf65c58529db83/alacritty/src/display/color.rs#L91-L115
     let mut index = 16usize;
 
@@ -83,11 +267,11 @@ fn fill_cube(colors: &mut AlacrittyColors) {
         for g in 0..6 {
             for b in 0..6 {
                 // Override colors 16..232 with the config (if present).
-                colors[index] = Some(AlacrittyRgb {
+                lut[index] = Rgb {
                     r: if r == 0 { 0 } else { r * 40 + 55 },
                     g: if g == 0 { 0 } else { g * 40 + 55 },
                     b: if b == 0 { 0 } else { b * 40 + 55 },
-                });
+                };
                 index += 1;
             }
         }
@@ -96,18 +280,18 @@ fn fill_cube(colors: &mut AlacrittyColors) {
     debug_assert!(index == 232);
 }
 
-fn fill_gray_ramp(colors: &mut AlacrittyColors) {
+fn fill_gray_ramp(lut: &mut [Rgb; COUNT]) {
     // adapted from: https://github.com/alacritty/alacritty/blob/da554e41f3a91ed6cc5db66b23bf65c58529db83/alacritty/src/display/color.rs#L118-L139
     let mut index: usize = 232;
 
     // Build colors.
     for i in 0..24 {
         let value = i * 10 + 8;
-        colors[index] = Some(AlacrittyRgb {
+        lut[index] = Rgb {
             r: value,
             g: value,
             b: value,
-        });
+        };
         index += 1;
     }
 
@@ -115,25 +299,16 @@ fn fill_gray_ramp(colors: &mut AlacrittyColors) {
 }
 
 pub(crate) fn most_common_color(screen: &Screen) -> Rgb {
-    use std::hash::{Hash, Hasher};
-
-    #[derive(PartialEq, Eq, Copy, Clone)]
-    struct Rgb_(Rgb);
-
-    impl Hash for Rgb_ {
-        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-            state.write_u32(
-                (u32::from(self.0.r) << 16) + (u32::from(self.0.g) << 8) + u32::from(self.0.b),
-            );
-        }
-    }
+    use std::hash::Hasher;
 
+    /// Spreads the bits of the colors over the hash by Fibonacci hashing, as the hash table uses
+    /// the top bits of the hash to probe.
     #[derive(Default)]
-    struct NoHashHasher(u64);
+    struct FibonacciHasher(u64);
 
-    impl Hasher for NoHashHasher {
+    impl Hasher for FibonacciHasher {
         fn finish(&self) -> u64 {
-            self.0
+            self.0.wrapping_mul(0x9e37_79b9_7f4a_7c15)
         }
 
         fn write(&mut self, bytes: &[u8]) {
@@ -144,22 +319,107 @@ pub(crate) fn most_common_color(screen: &Screen) -> Rgb {
         }
     }
 
-    let mut counts = HashMap::<Rgb_, u32, _>::with_capacity_and_hasher(
+    // backgrounds mostly come in runs of cells, so the runs are counted rather than the cells;
+    // backgrounds from the color table are counted by their index, and their colors are looked up
+    // once counted
+    let mut palette_counts = [0u32; COUNT];
+    let mut palette_colors = [Rgb { r: 0, g: 0, b: 0 }; COUNT];
+    let mut counts = HashMap::<Rgb, u32, _>::with_capacity_and_hasher(
         16,
-        std::hash::BuildHasherDefault::<NoHashHasher>::default(),
+        std::hash::BuildHasherDefault::<FibonacciHasher>::default(),
     );
-
-    for idx in 0..screen.lines() * screen.columns() {
-        let cell = &screen.cells[usize::from(idx)];
-        let bg = &cell.bg;
-
-        *counts.entry(Rgb_(*bg)).or_insert(0) += 1;
+    let mut count_run = |(bg, bg_index): (Rgb, Option<u16>), len: u32| match bg_index {
+        Some(idx) => {
+            palette_counts[usize::from(idx)] += len;
+            palette_colors[usize::from(idx)] = bg;
+        }
+        None => *counts.entry(bg).or_insert(0) += len,
+    };
+    let mut run: Option<((Rgb, Option<u16>), u32)> = None;
+    for (cell, &bg_index) in screen.cells.iter().zip(&screen.bg_indices) {
+        match &mut run {
+            Some(((bg, first_index), len))
+                if *first_index == bg_index && (bg_index.is_some() || *bg == cell.bg) =>
+            {
+                *len += 1
+            }
+            _ => {
+                if let Some((first, len)) = run.replace(((cell.bg, bg_index), 1)) {
+                    count_run(first, len);
+                }
+            }
+        }
+    }
+    if let Some((first, len)) = run {
+        count_run(first, len);
+    }
+    // distinct indices may have the same color
+    for (&count, &bg) in palette_counts.iter().zip(&palette_colors) {
+        if count > 0 {
+            *counts.entry(bg).or_insert(0) += count;
+        }
     }
 
     counts
         .iter()
         .max_by_key(|(_, count)| *count)
-        .map(|(k, _)| k.0)
+        .map(|(&color, _)| color)
         // counts can be empty for 0x0 screens
         .unwrap_or(Rgb { r: 0, g: 0, b: 0 })
 }
+
+/// Get the relative luminance of `color`, as defined by WCAG 2.
+fn relative_luminance(color: Rgb) -> f32 {
+    let channel = |value: u8| {
+        let value = f32::from(value) / 255.;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(color.r) + 0.7152 * channel(color.g) + 0.0722 * channel(color.b)
+}
+
+/// Get the contrast ratio of two colors, as defined by WCAG 2. This ranges from 1 for equal colors
+/// to 21 for black and white.
+pub(crate) fn contrast_ratio(a: Rgb, b: Rgb) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Get `fg`, nudged toward black or white as little as possible such that its contrast ratio with
+/// `bg` is at least `minimum`. If that is not possible, this is whichever of black and white
+/// contrasts most with `bg`.
+pub(crate) fn with_minimum_contrast(fg: Rgb, bg: Rgb, minimum: f32) -> Rgb {
+    if contrast_ratio(fg, bg) >= minimum {
+        return fg;
+    }
+
+    let black = Rgb { r: 0, g: 0, b: 0 };
+    let white = Rgb {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+    let target = if contrast_ratio(black, bg) > contrast_ratio(white, bg) {
+        black
+    } else {
+        white
+    };
+    if contrast_ratio(target, bg) < minimum {
+        return target;
+    }
+
+    // the mixes reaching the minimum contrast form a single range ending at the target
+    let (mut low, mut high) = (0., 1.);
+    for _ in 0..12 {
+        let amount = (low + high) / 2.;
+        if contrast_ratio(decoration::mix(fg, target, amount), bg) >= minimum {
+            high = amount;
+        } else {
+            low = amount;
+        }
+    }
+    decoration::mix(fg, target, high)
+}
@@ -0,0 +1,243 @@
+//! Composition of multiple screens into a single SVG, e.g., to compare the output of two commands
+//! side-by-side.
+
+use std::fmt::Display;
+
+use crate::{
+    blur::{self, Blur},
+    colors,
+    decoration::{contrasting, mix},
+    svg::{self, AltText, CalculatedFontMetrics, Content, Svg, SvgOptions},
+    Rgb, Screen,
+};
+
+/// Space in pixels between two screens in a layout. A separator line is drawn in the middle of
+/// this space.
+const GAP: f32 = 9.;
+
+/// A composition of one or more [screens](Screen), placed side-by-side or stacked. Layouts can be
+/// nested.
+///
+/// ```rust
+/// use termsnap_lib::{emulate, FontMetrics, Layout, SvgOptions};
+///
+/// let before = emulate(4, 20, b"before");
+/// let after = emulate(4, 20, b"after");
+/// let layout = Layout::horizontal([before, after]);
+///
+/// println!(
+///     "{}",
+///     layout.to_svg_with_options(SvgOptions::default().font_metrics(FontMetrics::DEFAULT))
+/// );
+/// ```
+pub struct Layout {
+    kind: Kind,
+}
+
+enum Kind {
+    Screen(Box<Screen>),
+    Horizontal(Vec<Layout>),
+    Vertical(Vec<Layout>),
+}
+
+impl From<Screen> for Layout {
+    fn from(screen: Screen) -> Self {
+        Layout::screen(screen)
+    }
+}
+
+impl Layout {
+    /// A layout consisting of a single screen.
+    pub fn screen(screen: Screen) -> Self {
+        Layout {
+            kind: Kind::Screen(Box::new(screen)),
+        }
+    }
+
+    /// Place screens or layouts side-by-side, from left to right.
+    pub fn horizontal(items: impl IntoIterator<Item = impl Into<Layout>>) -> Self {
+        Layout {
+            kind: Kind::Horizontal(items.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// Stack screens or layouts, from top to bottom.
+    pub fn vertical(items: impl IntoIterator<Item = impl Into<Layout>>) -> Self {
+        Layout {
+            kind: Kind::Vertical(items.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// Get a [std::fmt::Display] that prints an SVG of the layout when formatted. Separators are
+    /// drawn between the screens. A title is only drawn if the layout consists of a single screen.
+    ///
+    /// See also [`Screen::to_svg_with_options`].
+    pub fn to_svg_with_options<'s>(&'s self, options: SvgOptions<'s>) -> impl Display + 's {
+        Svg {
+            content: Content::Layout(self),
+            options,
+        }
+    }
+
+    /// Write an SVG of the layout, rendered with the given [options](SvgOptions), to `writer`.
+    ///
+    /// See also [`Screen::write_svg`].
+    pub fn write_svg(
+        &self,
+        mut writer: impl std::io::Write,
+        options: SvgOptions<'_>,
+    ) -> std::io::Result<()> {
+        write!(writer, "{}", self.to_svg_with_options(options))
+    }
+
+    /// The text alternative of an SVG of the layout rendered with the given
+    /// [options](SvgOptions), as set by [`SvgOptions::alt_text`]: the custom text, or the
+    /// plain-text content of the screens with the text of blurred regions left out. Returns `None`
+    /// if the SVG is not made accessible.
+    ///
+    /// This is for referencing the SVG from documents, such as in the alt text of an HTML image.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{emulate, AltText, Layout, SvgOptions};
+    ///
+    /// let layout = Layout::screen(emulate(1, 10, b"$ ls"));
+    ///
+    /// let options = SvgOptions::default().alt_text(AltText::ScreenText);
+    /// let alt_text = layout.alt_text(&options);
+    /// assert_eq!(alt_text.as_deref().map(str::trim_end), Some("$ ls"));
+    /// let options = SvgOptions::default().alt_text(AltText::Custom("A listing"));
+    /// assert_eq!(layout.alt_text(&options).as_deref(), Some("A listing"));
+    /// assert_eq!(layout.alt_text(&SvgOptions::default()), None);
+    /// ```
+    pub fn alt_text(&self, options: &SvgOptions<'_>) -> Option<String> {
+        match options.alt_text {
+            AltText::None => None,
+            AltText::ScreenText => Some(self.text(options.blurs)),
+            AltText::Custom(alt_text) => Some(alt_text.to_owned()),
+        }
+    }
+
+    /// The first screen of the layout in reading order.
+    fn first_screen(&self) -> Option<&Screen> {
+        match &self.kind {
+            Kind::Screen(screen) => Some(screen),
+            Kind::Horizontal(items) | Kind::Vertical(items) => {
+                items.iter().find_map(Layout::first_screen)
+            }
+        }
+    }
+
+    pub(crate) fn title(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::Screen(screen) => screen.title(),
+            _ => None,
+        }
+    }
+
+    /// The plain-text content of the screens in the layout in reading order, separated by empty
+    /// lines. The text of blurred regions is left out.
+    pub(crate) fn text(&self, blurs: &[Blur]) -> String {
+        match &self.kind {
+            Kind::Screen(screen) => blur::text(screen, blurs),
+            Kind::Horizontal(items) | Kind::Vertical(items) => items
+                .iter()
+                .map(|item| item.text(blurs))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        }
+    }
+
+    /// The background color of the layout, taken from its first screen.
+    pub(crate) fn background(&self) -> Rgb {
+        self.first_screen()
+            .map(colors::most_common_color)
+            .unwrap_or(Rgb { r: 0, g: 0, b: 0 })
+    }
+
+    /// The width and height of the layout in pixels.
+    pub(crate) fn size(&self, font_metrics: &CalculatedFontMetrics) -> (f32, f32) {
+        match &self.kind {
+            Kind::Screen(screen) => (
+                f32::from(screen.columns()) * font_metrics.advance,
+                f32::from(screen.lines()) * font_metrics.line_height,
+            ),
+            Kind::Horizontal(items) => {
+                let gaps = items.len().saturating_sub(1) as f32 * GAP;
+                items
+                    .iter()
+                    .map(|item| item.size(font_metrics))
+                    .fold((gaps, 0f32), |(width, height), (w, h)| {
+                        (width + w, height.max(h))
+                    })
+            }
+            Kind::Vertical(items) => {
+                let gaps = items.len().saturating_sub(1) as f32 * GAP;
+                items
+                    .iter()
+                    .map(|item| item.size(font_metrics))
+                    .fold((0f32, gaps), |(width, height), (w, h)| {
+                        (width.max(w), height + h)
+                    })
+            }
+        }
+    }
+
+    /// Write the layout with its top-left at (0, 0).
+    pub(crate) fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: &SvgOptions,
+        font_metrics: &CalculatedFontMetrics,
+    ) -> std::fmt::Result {
+        let (items, horizontal) = match &self.kind {
+            Kind::Screen(screen) => {
+                let background = colors::most_common_color(screen);
+                return svg::fmt_screen(f, screen, options, background, font_metrics);
+            }
+            Kind::Horizontal(items) => (items, true),
+            Kind::Vertical(items) => (items, false),
+        };
+
+        let background = self.background();
+        let separator = mix(background, contrasting(background), 0.3);
+        let (width, height) = self.size(font_metrics);
+
+        // fill the space around screens smaller than the layout
+        writeln!(
+            f,
+            r#"<rect x="0" y="0" width="{width}" height="{height}" style="fill: {background};" />"#,
+        )?;
+
+        let mut offset = 0.;
+        for (idx, item) in items.iter().enumerate() {
+            if idx > 0 {
+                let center = offset - GAP / 2.;
+                if horizontal {
+                    writeln!(
+                        f,
+                        r#"<line x1="{center}" y1="0" x2="{center}" y2="{height}" style="stroke: {separator};" />"#,
+                    )?;
+                } else {
+                    writeln!(
+                        f,
+                        r#"<line x1="0" y1="{center}" x2="{width}" y2="{center}" style="stroke: {separator};" />"#,
+                    )?;
+                }
+            }
+
+            let (x, y) = if horizontal {
+                (offset, 0.)
+            } else {
+                (0., offset)
+            };
+            writeln!(f, r#"<g transform="translate({x} {y})">"#)?;
+            item.fmt(f, options, font_metrics)?;
+            f.write_str("</g>\n")?;
+
+            let (item_width, item_height) = item.size(font_metrics);
+            offset += if horizontal { item_width } else { item_height } + GAP;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+//! Export of the cells of a terminal screen as delimiter-separated values, for analyzing and
+//! diffing screens in external tools.
+
+use std::borrow::Cow;
+
+use crate::Screen;
+
+/// The header row of the exported table.
+const HEADER: [&str; 6] = ["line", "column", "char", "fg", "bg", "flags"];
+
+/// Quote `field` as in RFC 4180 if it contains `delimiter`, quotes or line breaks.
+pub(crate) fn quote(field: &str, delimiter: char) -> Cow<'_, str> {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Write one row per cell of `screen`, in reading order, preceded by a header row. Fields are
+/// separated by `delimiter`, and [quoted](quote) as needed.
+pub(crate) fn to_delimited(screen: &Screen, delimiter: char) -> String {
+    let mut out = String::new();
+    let mut row = |fields: &[&str]| {
+        for (idx, field) in fields.iter().enumerate() {
+            if idx > 0 {
+                out.push(delimiter);
+            }
+            out.push_str(&quote(field, delimiter));
+        }
+        out.push('\n');
+    };
+
+    row(&HEADER);
+    for (line, cells) in screen.rows().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            let flags: Vec<&str> = cell.attributes().collect();
+
+            row(&[
+                &line.to_string(),
+                &column.to_string(),
+                &cell.chars().collect::<String>(),
+                &cell.fg.to_string(),
+                &cell.bg.to_string(),
+                &flags.join(" "),
+            ]);
+        }
+    }
+    out
+}
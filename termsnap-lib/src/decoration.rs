@@ -0,0 +1,462 @@
+//! Decorations drawn around the rendered terminal screen, such as window frames, captions and
+//! badges.
+
+use crate::{
+    grid,
+    images::base64_encode,
+    svg::{self, fmt_attribute_escaped, fmt_escaped, SvgOptions},
+    Rgb,
+};
+
+/// Height of the window title bar in pixels.
+const TITLE_BAR_HEIGHT: f32 = 28.;
+/// Space between the window edges and the terminal screen in pixels.
+const WINDOW_PADDING: f32 = 8.;
+/// Radius of the window corners in pixels.
+const CORNER_RADIUS: f32 = 8.;
+/// Space around the window reserved for the drop shadow in pixels.
+const SHADOW_MARGIN: f32 = 24.;
+/// Height of the caption below the window in pixels.
+const CAPTION_HEIGHT: f32 = 28.;
+/// The size of the box an image badge is fitted in, in pixels.
+const BADGE_WIDTH: f32 = 96.;
+const BADGE_HEIGHT: f32 = 24.;
+/// Font size of text badges in pixels.
+const BADGE_FONT_SIZE: f32 = 10.;
+/// Space between a badge and the window edges in pixels.
+const BADGE_INSET: f32 = 4.;
+/// Height of the exit status badge in pixels.
+const EXIT_STATUS_HEIGHT: f32 = 18.;
+
+/// A window decoration drawn around the terminal screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowFrame {
+    /// Do not draw a window decoration.
+    #[default]
+    None,
+    /// A window with rounded corners and a title bar with "traffic light" buttons on the left.
+    MacOs,
+    /// A window with rounded corners and a title bar with minimize, maximize and close buttons on
+    /// the right.
+    Generic,
+}
+
+/// A small watermark drawn in a corner of the window. See [SvgOptions::badge].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Badge<'a> {
+    /// Text, drawn in a small sans-serif font.
+    Text(&'a str),
+    /// An encoded image, fitted in a box of 96 by 24 pixels while keeping its aspect ratio.
+    Image {
+        /// The MIME type of the image data, e.g., `image/svg+xml`.
+        mime: &'a str,
+        data: &'a [u8],
+    },
+}
+
+/// The exit status of the captured command. See [SvgOptions::exit_status].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The command exited with the given code.
+    Code(i32),
+    /// The command was terminated by a signal.
+    Signaled,
+}
+
+impl ExitStatus {
+    /// Whether the command exited with code 0.
+    pub fn success(self) -> bool {
+        self == ExitStatus::Code(0)
+    }
+}
+
+/// A corner of the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// The position of the terminal screen and the window within the SVG, in pixels.
+pub(crate) struct FrameLayout {
+    /// Total width of the SVG.
+    pub width: f32,
+    /// Total height of the SVG.
+    pub height: f32,
+    /// The x-coordinate of the top-left of the terminal screen.
+    pub screen_x: f32,
+    /// The y-coordinate of the top-left of the terminal screen.
+    pub screen_y: f32,
+    window_x: f32,
+    window_y: f32,
+    window_width: f32,
+    window_height: f32,
+    title_bar_height: f32,
+    /// The y-coordinate of the center of the caption, if any.
+    caption_y: Option<f32>,
+}
+
+impl FrameLayout {
+    pub fn new(
+        options: &SvgOptions,
+        title: Option<&str>,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Self {
+        let framed = options.window_frame != WindowFrame::None;
+
+        let padding = svg::non_negative(options.padding);
+        let padding = if options.rulers {
+            padding.max(grid::RULER_SIZE)
+        } else {
+            padding
+        };
+        let padding = if framed {
+            padding + WINDOW_PADDING
+        } else {
+            padding
+        };
+        // without a window decoration, a title is drawn as a caption above the screen
+        let title_bar_height = if framed || title.is_some() {
+            TITLE_BAR_HEIGHT
+        } else {
+            0.
+        };
+        let margin = svg::non_negative(options.margin);
+        let margin = if framed && options.window_shadow {
+            margin.max(SHADOW_MARGIN)
+        } else {
+            margin
+        };
+
+        let window_width = screen_width + 2. * padding;
+        let window_height = screen_height + title_bar_height + 2. * padding;
+        // the caption is drawn between the window and the bottom margin
+        let caption_height = if options.caption.is_some() {
+            CAPTION_HEIGHT
+        } else {
+            0.
+        };
+
+        FrameLayout {
+            width: window_width + 2. * margin,
+            height: window_height + caption_height + 2. * margin,
+            screen_x: margin + padding,
+            screen_y: margin + title_bar_height + padding,
+            window_x: margin,
+            window_y: margin,
+            window_width,
+            window_height,
+            title_bar_height,
+            caption_y: options
+                .caption
+                .map(|_| margin + window_height + CAPTION_HEIGHT / 2.),
+        }
+    }
+}
+
+/// The color of the margin, or `None` if it is left transparent.
+fn margin_color(options: &SvgOptions, background: Rgb) -> Option<Rgb> {
+    match options.margin_color {
+        Some(color) => Some(color),
+        None if options.window_frame == WindowFrame::None => Some(background),
+        None => None,
+    }
+}
+
+/// Mix two colors. An `amount` of 0 results in `a`, an `amount` of 1 results in `b`.
+pub(crate) fn mix(a: Rgb, b: Rgb, amount: f32) -> Rgb {
+    let mix = |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * amount).round() as u8;
+    Rgb {
+        r: mix(a.r, b.r),
+        g: mix(a.g, b.g),
+        b: mix(a.b, b.b),
+    }
+}
+
+/// Get white for dark colors and black for light colors.
+pub(crate) fn contrasting(color: Rgb) -> Rgb {
+    let luma = 0.299 * f32::from(color.r) + 0.587 * f32::from(color.g) + 0.114 * f32::from(color.b);
+    if luma < 128. {
+        Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        }
+    } else {
+        Rgb { r: 0, g: 0, b: 0 }
+    }
+}
+
+/// Write the margin, padding and window decoration drawn below the terminal screen. The padding
+/// and window are filled with `background`.
+pub(crate) fn fmt_frame_start(
+    f: &mut std::fmt::Formatter<'_>,
+    options: &SvgOptions,
+    layout: &FrameLayout,
+    title: Option<&str>,
+    background: Rgb,
+) -> std::fmt::Result {
+    let framed = options.window_frame != WindowFrame::None;
+
+    // the caption is drawn on the margin's color
+    if svg::non_negative(options.margin) > 0. || options.caption.is_some() {
+        if let Some(color) = margin_color(options, background) {
+            writeln!(
+                f,
+                r#"<rect x="0" y="0" width="{}" height="{}" style="fill: {color};" />"#,
+                layout.width, layout.height,
+            )?;
+        }
+    }
+
+    let FrameLayout {
+        window_x: x,
+        window_y: y,
+        window_width: width,
+        window_height: height,
+        ..
+    } = *layout;
+
+    let center_x = x + width / 2.;
+    let center_y = y + TITLE_BAR_HEIGHT / 2.;
+
+    if !framed {
+        if svg::non_negative(options.padding) > 0. || layout.title_bar_height > 0. {
+            writeln!(
+                f,
+                r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" style="fill: {background};" />"#,
+            )?;
+        }
+        if let Some(title) = title {
+            let color = mix(background, contrasting(background), 0.6);
+            fmt_title(f, title, center_x, center_y, color)?;
+        }
+        return Ok(());
+    }
+
+    f.write_str("<defs>\n")?;
+    writeln!(
+        f,
+        r#"<clipPath id="termsnap-window"><rect x="{x}" y="{y}" width="{width}" height="{height}" rx="{CORNER_RADIUS}" /></clipPath>"#,
+    )?;
+    if options.window_shadow {
+        f.write_str(
+            r#"<filter id="termsnap-shadow" x="-50%" y="-50%" width="200%" height="200%"><feDropShadow dx="0" dy="4" stdDeviation="8" flood-opacity="0.4" /></filter>
+"#,
+        )?;
+    }
+    f.write_str("</defs>\n")?;
+
+    if options.window_shadow {
+        writeln!(
+            f,
+            r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" rx="{CORNER_RADIUS}" style="fill: {background};" filter="url(#termsnap-shadow)" />"#,
+        )?;
+    }
+
+    let title_bar = mix(background, contrasting(background), 0.12);
+    let buttons = mix(background, contrasting(background), 0.5);
+
+    f.write_str("<g clip-path=\"url(#termsnap-window)\">\n")?;
+    writeln!(
+        f,
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" style="fill: {background};" />"#,
+    )?;
+    writeln!(
+        f,
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{TITLE_BAR_HEIGHT}" style="fill: {title_bar};" />"#,
+    )?;
+
+    if let Some(title) = title {
+        fmt_title(f, title, center_x, center_y, buttons)?;
+    }
+
+    match options.window_frame {
+        WindowFrame::None => {}
+        WindowFrame::MacOs => {
+            for (idx, color) in ["#ff5f56", "#ffbd2e", "#27c93f"].into_iter().enumerate() {
+                writeln!(
+                    f,
+                    r#"<circle cx="{cx}" cy="{center_y}" r="6" style="fill: {color};" />"#,
+                    cx = x + 16. + idx as f32 * 20.,
+                )?;
+            }
+        }
+        WindowFrame::Generic => {
+            let close_x = x + width - 20.;
+            let maximize_x = close_x - 24.;
+            let minimize_x = maximize_x - 24.;
+            writeln!(
+                f,
+                r#"<g style="fill: none; stroke: {buttons}; stroke-width: 1.5;">
+<line x1="{x0}" y1="{y1}" x2="{x1}" y2="{y1}" />
+<rect x="{mx}" y="{my}" width="10" height="10" />
+<path d="M{cx0} {cy0} L{cx1} {cy1} M{cx0} {cy1} L{cx1} {cy0}" />
+</g>"#,
+                x0 = minimize_x - 5.,
+                x1 = minimize_x + 5.,
+                y1 = center_y + 4.,
+                mx = maximize_x - 5.,
+                my = center_y - 5.,
+                cx0 = close_x - 5.,
+                cx1 = close_x + 5.,
+                cy0 = center_y - 5.,
+                cy1 = center_y + 5.,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the terminal title centered on the given coordinates.
+fn fmt_title(
+    f: &mut std::fmt::Formatter<'_>,
+    title: &str,
+    center_x: f32,
+    center_y: f32,
+    color: Rgb,
+) -> std::fmt::Result {
+    write!(
+        f,
+        r#"<text class="title" x="{center_x}" y="{center_y}" text-anchor="middle" dominant-baseline="central" style="fill: {color};">"#,
+    )?;
+    fmt_escaped(f, &title.chars().collect::<Vec<_>>())?;
+    f.write_str("</text>\n")
+}
+
+/// Write the badge in its corner of the window.
+fn fmt_badge(
+    f: &mut std::fmt::Formatter<'_>,
+    badge: Badge,
+    corner: Corner,
+    layout: &FrameLayout,
+    background: Rgb,
+) -> std::fmt::Result {
+    let left = layout.window_x + BADGE_INSET;
+    let right = layout.window_x + layout.window_width - BADGE_INSET;
+    let top = layout.window_y + layout.title_bar_height + BADGE_INSET;
+    let bottom = layout.window_y + layout.window_height - BADGE_INSET;
+    let (at_left, at_top) = match corner {
+        Corner::TopLeft => (true, true),
+        Corner::TopRight => (false, true),
+        Corner::BottomLeft => (true, false),
+        Corner::BottomRight => (false, false),
+    };
+
+    match badge {
+        Badge::Text(text) => {
+            let color = mix(background, contrasting(background), 0.5);
+            write!(
+                f,
+                r#"<text x="{x}" y="{y}" text-anchor="{anchor}" dominant-baseline="{baseline}" style="fill: {color}; font-family: sans-serif; font-size: {BADGE_FONT_SIZE}px;">"#,
+                x = if at_left { left } else { right },
+                y = if at_top { top } else { bottom },
+                anchor = if at_left { "start" } else { "end" },
+                baseline = if at_top {
+                    "text-before-edge"
+                } else {
+                    "text-after-edge"
+                },
+            )?;
+            fmt_escaped(f, &text.chars().collect::<Vec<_>>())?;
+            f.write_str("</text>\n")
+        }
+        Badge::Image { mime, data } => {
+            // the image is aligned to the corner within its box
+            write!(
+                f,
+                r#"<image x="{x}" y="{y}" width="{BADGE_WIDTH}" height="{BADGE_HEIGHT}" preserveAspectRatio="x{align_x}Y{align_y} meet" opacity="0.8" href="data:"#,
+                x = if at_left { left } else { right - BADGE_WIDTH },
+                y = if at_top { top } else { bottom - BADGE_HEIGHT },
+                align_x = if at_left { "Min" } else { "Max" },
+                align_y = if at_top { "Min" } else { "Max" },
+            )?;
+            fmt_attribute_escaped(f, mime)?;
+            let mut encoded = String::from(";base64,");
+            base64_encode(data, &mut encoded);
+            f.write_str(&encoded)?;
+            f.write_str("\" />\n")
+        }
+    }
+}
+
+/// Write the exit status as a colored pill in its corner of the window.
+fn fmt_exit_status(
+    f: &mut std::fmt::Formatter<'_>,
+    status: ExitStatus,
+    corner: Corner,
+    layout: &FrameLayout,
+) -> std::fmt::Result {
+    let (text, color) = match status {
+        ExitStatus::Code(0) => ("✓ 0".to_owned(), "#2da44e"),
+        ExitStatus::Code(code) => (format!("✗ {code}"), "#cf222e"),
+        ExitStatus::Signaled => ("✗ signal".to_owned(), "#cf222e"),
+    };
+    // an estimate, as the width of the text is not known
+    let width = text.chars().count() as f32 * BADGE_FONT_SIZE * 0.65 + EXIT_STATUS_HEIGHT;
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => layout.window_x + BADGE_INSET,
+        Corner::TopRight | Corner::BottomRight => {
+            layout.window_x + layout.window_width - BADGE_INSET - width
+        }
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => {
+            layout.window_y + layout.title_bar_height + BADGE_INSET
+        }
+        Corner::BottomLeft | Corner::BottomRight => {
+            layout.window_y + layout.window_height - BADGE_INSET - EXIT_STATUS_HEIGHT
+        }
+    };
+
+    writeln!(
+        f,
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{EXIT_STATUS_HEIGHT}" rx="{radius}" style="fill: {color};" />"#,
+        radius = EXIT_STATUS_HEIGHT / 2.,
+    )?;
+    writeln!(
+        f,
+        r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central" style="fill: #ffffff; font-family: sans-serif; font-size: {BADGE_FONT_SIZE}px; font-weight: 600;">{text}</text>"#,
+        x + width / 2.,
+        y + EXIT_STATUS_HEIGHT / 2.,
+    )
+}
+
+/// Write the end of the window decoration, drawn after the terminal screen, followed by the
+/// badges and the caption.
+pub(crate) fn fmt_frame_end(
+    f: &mut std::fmt::Formatter<'_>,
+    options: &SvgOptions,
+    layout: &FrameLayout,
+    background: Rgb,
+) -> std::fmt::Result {
+    if let Some(badge) = options.badge {
+        fmt_badge(f, badge, options.badge_corner, layout, background)?;
+    }
+    if let Some(status) = options.exit_status {
+        fmt_exit_status(f, status, options.exit_status_corner, layout)?;
+    }
+
+    if options.window_frame != WindowFrame::None {
+        f.write_str("</g>\n")?;
+    }
+
+    if let (Some(caption), Some(caption_y)) = (options.caption, layout.caption_y) {
+        let caption_bg = margin_color(options, background).unwrap_or(background);
+        let color = mix(caption_bg, contrasting(caption_bg), 0.6);
+        fmt_title(
+            f,
+            caption,
+            layout.window_x + layout.window_width / 2.,
+            caption_y,
+            color,
+        )?;
+    }
+
+    Ok(())
+}
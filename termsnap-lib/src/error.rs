@@ -0,0 +1,108 @@
+//! An error type covering all errors of the library.
+
+use std::fmt::Display;
+
+use crate::{
+    ParseAnnotationError, ParseColorNameError, ParseRegionError, ParseRgbError, TermSizeError,
+};
+
+/// Any error returned by the library. The fallible functions of the library return their own,
+/// more specific, error types; these convert into this error, for applications embedding the
+/// library to handle the errors of different calls uniformly.
+///
+/// ```rust
+/// use termsnap_lib::{ColorName, Colors, Error, Term, VoidPtyWriter};
+///
+/// fn configure(lines: u16, columns: u16, background: &str) -> Result<(), Error> {
+///     let mut colors = Colors::default();
+///     colors.set(ColorName::Background, background.parse()?);
+///     let mut term = Term::try_new(lines, columns, VoidPtyWriter)?;
+///     term.set_colors(colors);
+///     Ok(())
+/// }
+///
+/// assert!(matches!(configure(0, 80, "#000000"), Err(Error::TermSize(_))));
+/// assert!(matches!(configure(24, 80, "black"), Err(Error::ParseRgb(_))));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A terminal was created or resized with an invalid size.
+    TermSize(TermSizeError),
+    /// An [Rgb](crate::Rgb) color could not be parsed.
+    ParseRgb(ParseRgbError),
+    /// A [ColorName](crate::ColorName) could not be parsed.
+    ParseColorName(ParseColorNameError),
+    /// A [Region](crate::Region) could not be parsed.
+    ParseRegion(ParseRegionError),
+    /// An [AnnotationKind](crate::AnnotationKind) could not be parsed.
+    ParseAnnotation(ParseAnnotationError),
+    /// The data of an [OutlineFont](crate::OutlineFont) is not a valid font.
+    #[cfg(feature = "text-as-paths")]
+    InvalidFont(crate::InvalidFontError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::TermSize(err) => err.fmt(f),
+            Error::ParseRgb(err) => err.fmt(f),
+            Error::ParseColorName(err) => err.fmt(f),
+            Error::ParseRegion(err) => err.fmt(f),
+            Error::ParseAnnotation(err) => err.fmt(f),
+            #[cfg(feature = "text-as-paths")]
+            Error::InvalidFont(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::TermSize(err) => Some(err),
+            Error::ParseRgb(err) => Some(err),
+            Error::ParseColorName(err) => Some(err),
+            Error::ParseRegion(err) => Some(err),
+            Error::ParseAnnotation(err) => Some(err),
+            #[cfg(feature = "text-as-paths")]
+            Error::InvalidFont(err) => Some(err),
+        }
+    }
+}
+
+impl From<TermSizeError> for Error {
+    fn from(err: TermSizeError) -> Self {
+        Error::TermSize(err)
+    }
+}
+
+impl From<ParseRgbError> for Error {
+    fn from(err: ParseRgbError) -> Self {
+        Error::ParseRgb(err)
+    }
+}
+
+impl From<ParseColorNameError> for Error {
+    fn from(err: ParseColorNameError) -> Self {
+        Error::ParseColorName(err)
+    }
+}
+
+impl From<ParseRegionError> for Error {
+    fn from(err: ParseRegionError) -> Self {
+        Error::ParseRegion(err)
+    }
+}
+
+impl From<ParseAnnotationError> for Error {
+    fn from(err: ParseAnnotationError) -> Self {
+        Error::ParseAnnotation(err)
+    }
+}
+
+#[cfg(feature = "text-as-paths")]
+impl From<crate::InvalidFontError> for Error {
+    fn from(err: crate::InvalidFontError) -> Self {
+        Error::InvalidFont(err)
+    }
+}
@@ -0,0 +1,67 @@
+//! Classification of characters that are commonly missing from monospace fonts, such that they
+//! can be rendered with separate fallback font stacks.
+
+use crate::SvgOptions;
+
+/// A class of characters rendered with its own font stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FontClass {
+    Cjk,
+    Emoji,
+    Symbol,
+}
+
+impl FontClass {
+    /// The CSS class of text runs of this class.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            FontClass::Cjk => "cjk",
+            FontClass::Emoji => "emoji",
+            FontClass::Symbol => "symbol",
+        }
+    }
+
+    /// The font stack of this class in `options`.
+    pub fn fonts<'a>(self, options: &SvgOptions<'a>) -> &'a [&'a str] {
+        match self {
+            FontClass::Cjk => options.cjk_fonts,
+            FontClass::Emoji => options.emoji_fonts,
+            FontClass::Symbol => options.symbol_fonts,
+        }
+    }
+
+    pub const ALL: [FontClass; 3] = [FontClass::Cjk, FontClass::Emoji, FontClass::Symbol];
+}
+
+/// Get the class of the character `c`, followed by the zero-width characters `zerowidth`. Only
+/// classes with a font stack set in `options` are returned.
+pub(crate) fn classify(options: &SvgOptions, c: char, zerowidth: &[char]) -> Option<FontClass> {
+    let class = match u32::from(c) {
+        0x1100..=0x115f
+        | 0x2e80..=0x303f
+        | 0x3040..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xa960..=0xa97f
+        | 0xac00..=0xd7ff
+        | 0xf900..=0xfaff
+        | 0xfe10..=0xfe1f
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xffef
+        | 0x20000..=0x3ffff => FontClass::Cjk,
+        0x1f1e6..=0x1f1ff | 0x1f300..=0x1f6ff | 0x1f900..=0x1faff => FontClass::Emoji,
+        // symbols followed by the emoji presentation selector are drawn as emoji
+        0x2190..=0x2bff | 0x1f100..=0x1f1e5 if zerowidth.contains(&'\u{fe0f}') => FontClass::Emoji,
+        // arrows, mathematical operators, technical symbols, enclosed alphanumerics, geometric
+        // shapes, miscellaneous symbols, dingbats and the private use area commonly used for
+        // icons; box drawing and block elements are left to the main font
+        0x2190..=0x23ff
+        | 0x2460..=0x24ff
+        | 0x25a0..=0x2bff
+        | 0xe000..=0xf8ff
+        | 0x1f100..=0x1f1e5 => FontClass::Symbol,
+        _ => return None,
+    };
+    (!class.fonts(options).is_empty()).then_some(class)
+}
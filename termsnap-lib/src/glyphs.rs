@@ -0,0 +1,217 @@
+//! Rendering of block element, sextant and Braille characters as geometry. These characters are
+//! used to draw charts and images in the terminal. Their appearance varies wildly between fonts,
+//! and they often don't quite fill their cells, leaving gaps. Drawing them as shapes makes them
+//! crisp and consistent.
+
+use crate::{svg::CalculatedFontMetrics, Rgb};
+
+/// A rectangle within a cell, in fractions of the cell's width and height.
+type Rect = (f32, f32, f32, f32);
+
+const QUADRANT_UPPER_LEFT: Rect = (0., 0., 0.5, 0.5);
+const QUADRANT_UPPER_RIGHT: Rect = (0.5, 0., 1., 0.5);
+const QUADRANT_LOWER_LEFT: Rect = (0., 0.5, 0.5, 1.);
+const QUADRANT_LOWER_RIGHT: Rect = (0.5, 0.5, 1., 1.);
+
+/// Returns `true` if the character is drawn as geometry.
+pub(crate) fn is_geometric(c: char) -> bool {
+    matches!(c, '\u{2580}'..='\u{259f}' | '\u{2800}'..='\u{28ff}' | '\u{1fb00}'..='\u{1fb3b}')
+}
+
+/// Get the rectangles making up a block element character (U+2580 to U+259F), and the opacity
+/// to fill them with.
+fn block_element(c: char) -> Option<(&'static [Rect], f32)> {
+    const FULL: Rect = (0., 0., 1., 1.);
+    const LOWER_EIGHTHS: [Rect; 7] = [
+        (0., 7. / 8., 1., 1.),
+        (0., 6. / 8., 1., 1.),
+        (0., 5. / 8., 1., 1.),
+        (0., 4. / 8., 1., 1.),
+        (0., 3. / 8., 1., 1.),
+        (0., 2. / 8., 1., 1.),
+        (0., 1. / 8., 1., 1.),
+    ];
+    const LEFT_EIGHTHS: [Rect; 7] = [
+        (0., 0., 7. / 8., 1.),
+        (0., 0., 6. / 8., 1.),
+        (0., 0., 5. / 8., 1.),
+        (0., 0., 4. / 8., 1.),
+        (0., 0., 3. / 8., 1.),
+        (0., 0., 2. / 8., 1.),
+        (0., 0., 1. / 8., 1.),
+    ];
+
+    let rects: (&'static [Rect], f32) = match c {
+        '▀' => (&[(0., 0., 1., 0.5)], 1.),
+        '▁'..='▇' => {
+            let idx = c as usize - '▁' as usize;
+            (std::slice::from_ref(&LOWER_EIGHTHS[idx]), 1.)
+        }
+        '█' => (&[FULL], 1.),
+        '▉'..='▏' => {
+            let idx = c as usize - '▉' as usize;
+            (std::slice::from_ref(&LEFT_EIGHTHS[idx]), 1.)
+        }
+        '▐' => (&[(0.5, 0., 1., 1.)], 1.),
+        '░' => (&[FULL], 0.25),
+        '▒' => (&[FULL], 0.5),
+        '▓' => (&[FULL], 0.75),
+        '▔' => (&[(0., 0., 1., 1. / 8.)], 1.),
+        '▕' => (&[(7. / 8., 0., 1., 1.)], 1.),
+        '▖' => (&[QUADRANT_LOWER_LEFT], 1.),
+        '▗' => (&[QUADRANT_LOWER_RIGHT], 1.),
+        '▘' => (&[QUADRANT_UPPER_LEFT], 1.),
+        '▙' => (
+            &[
+                QUADRANT_UPPER_LEFT,
+                QUADRANT_LOWER_LEFT,
+                QUADRANT_LOWER_RIGHT,
+            ],
+            1.,
+        ),
+        '▚' => (&[QUADRANT_UPPER_LEFT, QUADRANT_LOWER_RIGHT], 1.),
+        '▛' => (
+            &[
+                QUADRANT_UPPER_LEFT,
+                QUADRANT_UPPER_RIGHT,
+                QUADRANT_LOWER_LEFT,
+            ],
+            1.,
+        ),
+        '▜' => (
+            &[
+                QUADRANT_UPPER_LEFT,
+                QUADRANT_UPPER_RIGHT,
+                QUADRANT_LOWER_RIGHT,
+            ],
+            1.,
+        ),
+        '▝' => (&[QUADRANT_UPPER_RIGHT], 1.),
+        '▞' => (&[QUADRANT_UPPER_RIGHT, QUADRANT_LOWER_LEFT], 1.),
+        '▟' => (
+            &[
+                QUADRANT_UPPER_RIGHT,
+                QUADRANT_LOWER_LEFT,
+                QUADRANT_LOWER_RIGHT,
+            ],
+            1.,
+        ),
+        _ => return None,
+    };
+
+    Some(rects)
+}
+
+/// Get the filled sixths of a sextant character (U+1FB00 to U+1FB3B) as a bitmask. Bit 0 is the
+/// upper-left sixth, bit 1 the upper-right sixth, bit 2 the middle-left sixth, etc.
+fn sextant(c: char) -> Option<u8> {
+    if !('\u{1fb00}'..='\u{1fb3b}').contains(&c) {
+        return None;
+    }
+
+    // The sextants are encoded in order of their bit patterns, but skip the empty and full
+    // patterns, and the left and right half patterns, as those are already encoded as block
+    // elements.
+    let mut pattern = (c as u32 - 0x1fb00 + 1) as u8;
+    if pattern >= 0b010101 {
+        pattern += 1;
+    }
+    if pattern >= 0b101010 {
+        pattern += 1;
+    }
+
+    Some(pattern)
+}
+
+/// Write the geometry of a character at cell (`x`, `y`). Returns `false` if the character is not
+/// drawn as geometry, in which case nothing is written.
+pub(crate) fn fmt_glyph(
+    f: &mut std::fmt::Formatter<'_>,
+    c: char,
+    x: u16,
+    y: u16,
+    color: Rgb,
+    opacity: f32,
+    font_metrics: &CalculatedFontMetrics,
+) -> Result<bool, std::fmt::Error> {
+    let cell_x = f32::from(x) * font_metrics.advance;
+    let cell_y = f32::from(y) * font_metrics.line_height;
+    let width = font_metrics.advance;
+    let height = font_metrics.line_height;
+
+    let fmt_rects =
+        |f: &mut std::fmt::Formatter<'_>, rects: &mut dyn Iterator<Item = Rect>, opacity: f32| {
+            f.write_str("<path d=\"")?;
+            for (x0, y0, x1, y1) in rects {
+                write!(
+                    f,
+                    "M{} {}h{}v{}h{}z",
+                    cell_x + x0 * width,
+                    cell_y + y0 * height,
+                    (x1 - x0) * width,
+                    (y1 - y0) * height,
+                    (x0 - x1) * width,
+                )?;
+            }
+            write!(f, "\" style=\"fill: {color};")?;
+            if opacity < 1. {
+                write!(f, " fill-opacity: {opacity};")?;
+            }
+            f.write_str("\" />\n")
+        };
+
+    if let Some((rects, block_opacity)) = block_element(c) {
+        fmt_rects(f, &mut rects.iter().copied(), opacity * block_opacity)?;
+    } else if let Some(pattern) = sextant(c) {
+        let mut rects = (0..6u8).filter(|bit| pattern & (1 << bit) != 0).map(|bit| {
+            let column = f32::from(bit % 2);
+            let row = f32::from(bit / 2);
+            (column / 2., row / 3., (column + 1.) / 2., (row + 1.) / 3.)
+        });
+        fmt_rects(f, &mut rects, opacity)?;
+    } else if ('\u{2800}'..='\u{28ff}').contains(&c) {
+        // Braille patterns: bits 0 to 2 are the dots in the left column, bits 3 to 5 the dots in
+        // the right column, and bits 6 and 7 the bottom left and bottom right dots respectively
+        const DOTS: [(f32, f32); 8] = [
+            (0., 0.),
+            (0., 1.),
+            (0., 2.),
+            (1., 0.),
+            (1., 1.),
+            (1., 2.),
+            (0., 3.),
+            (1., 3.),
+        ];
+
+        let pattern = c as u32 - 0x2800;
+        if pattern == 0 {
+            return Ok(true);
+        }
+
+        let radius = (width / 2.).min(height / 4.) * 0.3;
+        f.write_str("<path d=\"")?;
+        for (bit, (column, row)) in DOTS.into_iter().enumerate() {
+            if pattern & (1 << bit) == 0 {
+                continue;
+            }
+            let center_x = cell_x + (column + 0.5) * width / 2.;
+            let center_y = cell_y + (row + 0.5) * height / 4.;
+            write!(
+                f,
+                "M{} {center_y}a{radius} {radius} 0 1 0 {diameter} 0a{radius} {radius} 0 1 0 {neg_diameter} 0",
+                center_x - radius,
+                diameter = 2. * radius,
+                neg_diameter = -2. * radius,
+            )?;
+        }
+        write!(f, "\" style=\"fill: {color};")?;
+        if opacity < 1. {
+            write!(f, " fill-opacity: {opacity};")?;
+        }
+        f.write_str("\" />\n")?;
+    } else {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
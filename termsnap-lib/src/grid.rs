@@ -0,0 +1,98 @@
+//! Cell grids and rulers drawn over terminal screens, for checking the alignment of layouts.
+
+use crate::{
+    decoration::{contrasting, mix},
+    svg::CalculatedFontMetrics,
+    Rgb,
+};
+
+/// The space in pixels reserved around the screen for rulers.
+pub(crate) const RULER_SIZE: f32 = 24.;
+
+/// The font size in pixels of ruler labels.
+const LABEL_SIZE_PX: f32 = 8.;
+
+/// Write faint borders around each of the cells of a screen of `lines` by `columns` cells.
+pub(crate) fn fmt_grid(
+    f: &mut std::fmt::Formatter<'_>,
+    lines: u16,
+    columns: u16,
+    background: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let width = f32::from(columns) * font_metrics.advance;
+    let height = f32::from(lines) * font_metrics.line_height;
+    let color = mix(background, contrasting(background), 0.2);
+
+    f.write_str("<path d=\"")?;
+    for column in 0..=columns {
+        write!(
+            f,
+            "M{} 0v{height}",
+            f32::from(column) * font_metrics.advance
+        )?;
+    }
+    for line in 0..=lines {
+        write!(
+            f,
+            "M0 {}h{width}",
+            f32::from(line) * font_metrics.line_height
+        )?;
+    }
+    writeln!(
+        f,
+        r#"" style="fill: none; stroke: {color}; stroke-width: 0.5;" />"#
+    )
+}
+
+/// Write rulers above and to the left of a screen of `lines` by `columns` cells, in the
+/// [RULER_SIZE] pixels of space around the screen. Columns are ticked and labeled every ten
+/// columns, lines are labeled individually. Lines and columns are counted from 0, as in
+/// [Region](crate::Region).
+pub(crate) fn fmt_rulers(
+    f: &mut std::fmt::Formatter<'_>,
+    lines: u16,
+    columns: u16,
+    background: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let color = mix(background, contrasting(background), 0.6);
+
+    f.write_str("<path d=\"")?;
+    for column in 0..=columns {
+        let tick = match column {
+            _ if column % 10 == 0 => 6.,
+            _ if column % 5 == 0 => 4.,
+            _ => 2.,
+        };
+        write!(
+            f,
+            "M{} -{tick}v{tick}",
+            f32::from(column) * font_metrics.advance
+        )?;
+    }
+    writeln!(
+        f,
+        r#"" style="fill: none; stroke: {color}; stroke-width: 0.5;" />"#
+    )?;
+
+    writeln!(
+        f,
+        r#"<g style="fill: {color}; font-size: {LABEL_SIZE_PX}px;">"#
+    )?;
+    for column in (0..columns).step_by(10) {
+        writeln!(
+            f,
+            r#"<text x="{}" y="-8">{column}</text>"#,
+            f32::from(column) * font_metrics.advance + 1.
+        )?;
+    }
+    for line in 0..lines {
+        writeln!(
+            f,
+            r#"<text x="-3" y="{}" text-anchor="end">{line}</text>"#,
+            f32::from(line + 1) * font_metrics.line_height - font_metrics.descent
+        )?;
+    }
+    f.write_str("</g>\n")
+}
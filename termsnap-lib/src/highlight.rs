@@ -0,0 +1,123 @@
+//! Highlights drawn over regions of the terminal screen, to call attention to part of a screen.
+
+use std::fmt::Display;
+
+use crate::{svg::CalculatedFontMetrics, Rgb};
+
+/// A region of cells on the terminal screen, selected in reading order like a text selection: it
+/// starts at the cell at `start_line` and `start_column`, and runs through the ends of lines up to,
+/// but not including, the cell at `end_line` and `end_column`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub start_line: u16,
+    pub start_column: u16,
+    pub end_line: u16,
+    pub end_column: u16,
+}
+
+/// An error returned when parsing a [Region] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRegionError;
+
+impl Display for ParseRegionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("expected a region in the format line:column-line:column")
+    }
+}
+
+impl std::error::Error for ParseRegionError {}
+
+impl std::str::FromStr for Region {
+    type Err = ParseRegionError;
+
+    /// Parse a region in the format `line:column-line:column`, e.g., `3:0-3:20` for the first
+    /// twenty cells of the fourth line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let position = |s: &str| -> Result<(u16, u16), ParseRegionError> {
+            let (line, column) = s.split_once(':').ok_or(ParseRegionError)?;
+            Ok((
+                line.trim().parse().map_err(|_| ParseRegionError)?,
+                column.trim().parse().map_err(|_| ParseRegionError)?,
+            ))
+        };
+
+        let (start, end) = s.split_once('-').ok_or(ParseRegionError)?;
+        let (start_line, start_column) = position(start)?;
+        let (end_line, end_column) = position(end)?;
+        Ok(Region {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        })
+    }
+}
+
+/// How a highlight is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HighlightStyle {
+    /// Fill the region with a translucent color.
+    #[default]
+    Fill,
+    /// Draw an outline around the region.
+    Outline,
+}
+
+/// A region of the terminal screen drawn highlighted. See
+/// [SvgOptions::highlights](crate::SvgOptions::highlights).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Highlight {
+    pub region: Region,
+    pub color: Rgb,
+    pub style: HighlightStyle,
+}
+
+/// Write the highlights over a screen of `lines` by `columns`. Each line of a region is drawn as
+/// a separate rectangle.
+pub(crate) fn fmt_highlights(
+    f: &mut std::fmt::Formatter<'_>,
+    highlights: &[Highlight],
+    lines: u16,
+    columns: u16,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    for highlight in highlights {
+        let Region {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        } = highlight.region;
+
+        for line in start_line..=end_line.min(lines.saturating_sub(1)) {
+            let x0 = if line == start_line { start_column } else { 0 };
+            let x1 = if line == end_line {
+                end_column
+            } else {
+                columns
+            };
+            let x1 = x1.min(columns);
+            if x0 >= x1 {
+                continue;
+            }
+
+            let x = f32::from(x0) * font_metrics.advance;
+            let y = f32::from(line) * font_metrics.line_height;
+            let width = f32::from(x1 - x0) * font_metrics.advance;
+            let height = font_metrics.line_height;
+            let color = highlight.color;
+            match highlight.style {
+                HighlightStyle::Fill => writeln!(
+                    f,
+                    r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" style="fill: {color}; fill-opacity: 0.35;" />"#,
+                )?,
+                HighlightStyle::Outline => writeln!(
+                    f,
+                    r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" rx="2" style="fill: none; stroke: {color}; stroke-width: 1.5;" />"#,
+                )?,
+            }
+        }
+    }
+
+    Ok(())
+}
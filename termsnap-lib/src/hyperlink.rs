@@ -0,0 +1,55 @@
+//! Hyperlinks set using the OSC 8 escape sequence, rendered as links around the text and
+//! backgrounds of their cells.
+
+use alacritty_terminal::term::cell::Hyperlink as AlacrittyHyperlink;
+
+use crate::svg;
+
+/// The URI schemes of links made clickable. Links with other schemes, such as `javascript:`, are
+/// rendered as plain text.
+const SAFE_SCHEMES: [&str; 4] = ["http", "https", "file", "mailto"];
+
+/// A hyperlink set using the OSC 8 escape sequence. See [Cell::hyperlink](crate::Cell::hyperlink).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hyperlink(pub(crate) AlacrittyHyperlink);
+
+impl Hyperlink {
+    /// The id of the hyperlink. Cells with the same id belong to the same link, even if they are
+    /// not adjacent, e.g., a link wrapped over multiple lines by a program. Hyperlinks set without
+    /// an id get a unique generated id.
+    pub fn id(&self) -> &str {
+        self.0.id()
+    }
+
+    /// The URI the hyperlink points to.
+    pub fn uri(&self) -> &str {
+        self.0.uri()
+    }
+
+    /// Whether the URI's scheme is one of [SAFE_SCHEMES].
+    fn is_safe(&self) -> bool {
+        self.uri().split_once(':').is_some_and(|(scheme, _)| {
+            SAFE_SCHEMES
+                .iter()
+                .any(|safe| scheme.eq_ignore_ascii_case(safe))
+        })
+    }
+}
+
+/// Write the output of `fmt` wrapped in an `<a>` element linking to `link`, making it clickable.
+/// The output is written as is without a link, or if the link's URI scheme is not one of
+/// [SAFE_SCHEMES].
+pub(crate) fn fmt_linked(
+    f: &mut std::fmt::Formatter<'_>,
+    link: Option<&Hyperlink>,
+    fmt: impl FnOnce(&mut std::fmt::Formatter<'_>) -> std::fmt::Result,
+) -> std::fmt::Result {
+    let Some(link) = link.filter(|link| link.is_safe()) else {
+        return fmt(f);
+    };
+    f.write_str(r#"<a href=""#)?;
+    svg::fmt_attribute_escaped(f, link.uri())?;
+    f.write_str("\">")?;
+    fmt(f)?;
+    f.write_str("</a>\n")
+}
@@ -0,0 +1,213 @@
+//! Images displayed in the terminal, such as Sixel graphics.
+
+use std::sync::Arc;
+
+/// The width in pixels of a terminal cell, as assumed for displaying images in the terminal.
+pub(crate) const CELL_WIDTH_PX: u32 = 8;
+/// The height in pixels of a terminal cell, as assumed for displaying images in the terminal.
+pub(crate) const CELL_HEIGHT_PX: u32 = 16;
+
+/// An image displayed on the terminal screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    /// The screen line of the top of the image. This is negative if the image is partially
+    /// scrolled off the top of the screen.
+    pub line: i32,
+    /// The screen column of the left of the image. This is negative if the image is partially
+    /// cropped off the left of the screen.
+    pub column: i32,
+    /// The width of the image in cells. This can be fractional.
+    pub columns: f32,
+    /// The height of the image in cells. This can be fractional.
+    pub lines: f32,
+    /// The MIME type of the image data, e.g., `image/png`.
+    pub mime: &'static str,
+    /// The encoded image data.
+    pub data: Arc<[u8]>,
+}
+
+impl Image {
+    /// Get the image as a `data:` URI with base64-encoded data.
+    pub fn data_uri(&self) -> String {
+        let mut uri = format!("data:{};base64,", self.mime);
+        base64_encode(&self.data, &mut uri);
+        uri
+    }
+}
+
+/// An image placed in the terminal grid. The line is absolute, counted from the top of the
+/// scrollback history including the lines dropped from it, such that the image moves along when
+/// the terminal scrolls.
+#[derive(Clone, Debug)]
+pub(crate) struct PlacedImage {
+    pub absolute_line: usize,
+    pub image: Image,
+}
+
+/// Append the base64 encoding of `data` to `out`.
+pub(crate) fn base64_encode(data: &[u8], out: &mut String) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    out.reserve(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * idx)) as usize & 0x3f]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// Decode base64-encoded `data`, ignoring whitespace. Returns `None` if the data is not valid
+/// base64.
+pub(crate) fn base64_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let mut n = 0u32;
+    let mut bits = 0;
+
+    for &byte in data {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b if b.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        n = (n << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+/// Get the MIME type of encoded image data by its magic bytes. Returns `None` if the image format
+/// is not recognized.
+pub(crate) fn sniff_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// Get the width and height in pixels of encoded image data. Returns `None` if the image format is
+/// not supported, or the data is malformed.
+pub(crate) fn sniff_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be_u16 = |idx: usize| Some(u16::from_be_bytes(data.get(idx..idx + 2)?.try_into().ok()?));
+    let le_u16 = |idx: usize| Some(u16::from_le_bytes(data.get(idx..idx + 2)?.try_into().ok()?));
+    let be_u32 = |idx: usize| Some(u32::from_be_bytes(data.get(idx..idx + 4)?.try_into().ok()?));
+    let le_i32 = |idx: usize| Some(i32::from_le_bytes(data.get(idx..idx + 4)?.try_into().ok()?));
+
+    match sniff_mime(data)? {
+        "image/png" => Some((be_u32(16)?, be_u32(20)?)),
+        "image/gif" => Some((le_u16(6)?.into(), le_u16(8)?.into())),
+        "image/bmp" => Some((le_i32(18)?.unsigned_abs(), le_i32(22)?.unsigned_abs())),
+        "image/jpeg" => {
+            // walk the segments until a start-of-frame segment is found
+            let mut idx = 2;
+            loop {
+                if *data.get(idx)? != 0xff {
+                    return None;
+                }
+                let marker = *data.get(idx + 1)?;
+                if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                    return Some((be_u16(idx + 7)?.into(), be_u16(idx + 5)?.into()));
+                }
+                idx += 2 + usize::from(be_u16(idx + 2)?);
+            }
+        }
+        _ => None,
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Encode RGBA pixels as a PNG image. The image data is stored uncompressed.
+pub(crate) fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    // each scanline is prefixed by its filter type (0: none)
+    let stride = width as usize * 4;
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in rgba.chunks(stride) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib stream consisting of uncompressed deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(u8::from(last));
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, color type RGBA, default compression, filtering and no interlacing
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &ihdr);
+    chunk(&mut png, b"IDAT", &zlib);
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
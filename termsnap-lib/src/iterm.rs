@@ -0,0 +1,147 @@
+//! Parsing of inline images sent using the iTerm2 inline image protocol (OSC 1337 `File=`).
+
+use crate::images::{self, CELL_HEIGHT_PX, CELL_WIDTH_PX};
+
+/// A requested image dimension.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Size {
+    /// The image's natural dimension.
+    Auto,
+    /// A number of cells.
+    Cells(f32),
+    /// A number of pixels.
+    Pixels(f32),
+    /// A percentage of the terminal screen's dimension.
+    Percent(f32),
+}
+
+impl Size {
+    fn parse(value: &str) -> Option<Self> {
+        if value == "auto" {
+            Some(Size::Auto)
+        } else if let Some(pixels) = value.strip_suffix("px") {
+            pixels.parse().ok().map(Size::Pixels)
+        } else if let Some(percent) = value.strip_suffix('%') {
+            percent.parse().ok().map(Size::Percent)
+        } else {
+            value.parse().ok().map(Size::Cells)
+        }
+    }
+
+    /// Get the size in cells, where `cell_px` is the size of a cell in pixels and `screen` the
+    /// size of the screen in cells.
+    fn to_cells(self, cell_px: u32, screen: u16) -> Option<f32> {
+        match self {
+            Size::Auto => None,
+            Size::Cells(cells) => Some(cells),
+            Size::Pixels(pixels) => Some(pixels / cell_px as f32),
+            Size::Percent(percent) => Some(f32::from(screen) * percent / 100.),
+        }
+    }
+}
+
+/// An image sent using the iTerm2 inline image protocol.
+#[derive(Debug)]
+pub(crate) struct InlineImage {
+    pub mime: &'static str,
+    pub data: Vec<u8>,
+    width: Size,
+    height: Size,
+    preserve_aspect_ratio: bool,
+}
+
+impl InlineImage {
+    /// Parse the parameters of an OSC 1337 `File=` sequence. Returns `None` if the sequence is
+    /// not an inline image, e.g., because it is a file download, or if the image format is not
+    /// recognized.
+    pub fn parse(params: &[&[u8]]) -> Option<Self> {
+        let [b"1337", rest @ ..] = params else {
+            return None;
+        };
+
+        // the payload may contain semicolons in file names, so split on them manually
+        let rest = rest.join(&b';');
+        let rest = rest.strip_prefix(b"File=")?;
+        let separator = rest.iter().position(|&b| b == b':')?;
+        let (args, payload) = (&rest[..separator], &rest[separator + 1..]);
+
+        let mut inline = false;
+        let mut width = Size::Auto;
+        let mut height = Size::Auto;
+        let mut preserve_aspect_ratio = true;
+
+        for arg in std::str::from_utf8(args).ok()?.split(';') {
+            let Some((key, value)) = arg.split_once('=') else {
+                continue;
+            };
+            match key {
+                "inline" => inline = value == "1",
+                "width" => width = Size::parse(value)?,
+                "height" => height = Size::parse(value)?,
+                "preserveAspectRatio" => preserve_aspect_ratio = value != "0",
+                _ => {}
+            }
+        }
+
+        if !inline {
+            return None;
+        }
+
+        let data = images::base64_decode(payload)?;
+        let mime = images::sniff_mime(&data)?;
+
+        Some(InlineImage {
+            mime,
+            data,
+            width,
+            height,
+            preserve_aspect_ratio,
+        })
+    }
+
+    /// Get the size of the image in cells, as displayed on a screen of `columns` by `lines`.
+    /// Returns `None` if the size cannot be determined.
+    pub fn size(&self, columns: u16, lines: u16) -> Option<(f32, f32)> {
+        let width = self.width.to_cells(CELL_WIDTH_PX, columns);
+        let height = self.height.to_cells(CELL_HEIGHT_PX, lines);
+        let natural = images::sniff_dimensions(&self.data).map(|(width, height)| {
+            (
+                width as f32 / CELL_WIDTH_PX as f32,
+                height as f32 / CELL_HEIGHT_PX as f32,
+            )
+        });
+
+        let size = match (width, height, natural) {
+            (Some(width), Some(height), Some((natural_width, natural_height)))
+                if self.preserve_aspect_ratio =>
+            {
+                // fit the image within the requested box
+                let scale = (width / natural_width).min(height / natural_height);
+                (natural_width * scale, natural_height * scale)
+            }
+            (Some(width), Some(height), _) => (width, height),
+            (Some(width), None, Some((natural_width, natural_height))) => {
+                if self.preserve_aspect_ratio {
+                    (width, natural_height * width / natural_width)
+                } else {
+                    (width, natural_height)
+                }
+            }
+            (None, Some(height), Some((natural_width, natural_height))) => {
+                if self.preserve_aspect_ratio {
+                    (natural_width * height / natural_height, height)
+                } else {
+                    (natural_width, height)
+                }
+            }
+            (None, None, Some((natural_width, natural_height))) => {
+                // images are scaled down to fit the screen's width
+                let scale = (f32::from(columns) / natural_width).min(1.);
+                (natural_width * scale, natural_height * scale)
+            }
+            _ => return None,
+        };
+
+        (size.0 > 0. && size.1 > 0.).then_some(size)
+    }
+}
@@ -13,8 +13,7 @@
 //! // Create a snapshot of the terminal screen grid.
 //! let screen = term.current_screen();
 //!
-//! let text: String = screen.cells().map(|c| c.c).collect();
-//! assert_eq!(text.trim(), "a line of colored terminal data");
+//! assert_eq!(screen.text(), "a line of colored terminal data");
 //!
 //! assert_eq!(&format!("{}", screen.get(0, 0).unwrap().fg), "#839496");
 //! assert_eq!(&format!("{}", screen.get(0, 10).unwrap().fg), "#859900");
@@ -24,25 +23,69 @@
 //! ```
 
 #![forbid(unsafe_code)]
-use std::fmt::{Display, Write};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    ops::{Bound, Range, RangeBounds},
+    sync::{Arc, Mutex, PoisonError},
+};
 
 use alacritty_terminal::{
+    grid::{Dimensions, GridCell},
+    index::{Column, Line},
     term::{
         cell::{Cell as AlacrittyCell, Flags},
         test::TermSize,
-        Config, Term as AlacrittyTerm,
+        Config, Term as AlacrittyTerm, TermMode,
     },
     vte::{self, ansi::Processor},
 };
 
+mod annotation;
 mod ansi;
+mod blur;
 mod colors;
-
+mod compose;
+mod csv;
+mod decoration;
+mod error;
+mod fallback;
+mod glyphs;
+mod grid;
+mod highlight;
+mod hyperlink;
+mod images;
+mod iterm;
+#[cfg(feature = "text-as-paths")]
+mod outline;
+mod reemit;
+mod runs;
+mod search;
+mod sequences;
+#[cfg(feature = "session")]
+pub mod session;
+mod sixel;
+mod snapshot;
+mod stats;
+mod svg;
+
+pub use annotation::{Annotation, AnnotationKind, ParseAnnotationError};
 pub use ansi::AnsiSignal;
-use colors::Colors;
-
-/// A sensible default font size, in case some renderers don't automatically scale up the SVG.
-const FONT_SIZE_PX: f32 = 12.;
+pub use blur::{Blur, BlurStyle};
+pub use colors::{ColorName, Colors, ParseColorNameError};
+pub use compose::Layout;
+pub use decoration::{Badge, Corner, ExitStatus, WindowFrame};
+pub use error::Error;
+pub use highlight::{Highlight, HighlightStyle, ParseRegionError, Region};
+pub use hyperlink::Hyperlink;
+pub use images::Image;
+#[cfg(feature = "text-as-paths")]
+pub use outline::{InvalidFontError, OutlineFont};
+pub use runs::{BackgroundRegion, TextRun};
+pub use sequences::{Diagnostic, SequenceKind};
+pub use snapshot::{assert_snapshot, UPDATE_SNAPSHOTS_VAR};
+pub use stats::ScreenStats;
+pub use svg::{AltText, SvgOptions};
 
 /// Metrics for rendering a monospaced font.
 #[derive(Clone, Copy, Debug)]
@@ -65,7 +108,8 @@ pub struct FontMetrics {
 impl FontMetrics {
     /// Font metrics that should work for fonts that are similar to, e.g., Liberation mono, Consolas
     /// or Menlo. If this is not accurate, it will be noticeable as overlap or gaps between box
-    /// drawing characters.
+    /// drawing characters. The metrics of some fonts are available as constants, such as
+    /// [FontMetrics::JETBRAINS_MONO].
     ///
     /// ```norun
     /// FontMetrics {
@@ -80,37 +124,63 @@ impl FontMetrics {
         advance: 600.,
         line_height: 1200.,
         descent: 300.,
+    };
+
+    /// Metrics of Liberation Mono.
+    pub const LIBERATION_MONO: FontMetrics = FontMetrics {
+        units_per_em: 2048,
+        advance: 1229.,
+        line_height: 2320.,
+        descent: 615.,
+    };
+
+    /// Metrics of Consolas.
+    pub const CONSOLAS: FontMetrics = FontMetrics {
+        units_per_em: 2048,
+        advance: 1226.,
+        line_height: 2398.,
+        descent: 514.,
+    };
+
+    /// Metrics of Menlo.
+    pub const MENLO: FontMetrics = FontMetrics {
+        units_per_em: 2048,
+        advance: 1233.,
+        line_height: 2384.,
+        descent: 483.,
+    };
+
+    /// Metrics of Source Code Pro.
+    pub const SOURCE_CODE_PRO: FontMetrics = FontMetrics {
+        units_per_em: 1000,
+        advance: 600.,
+        line_height: 1257.,
+        descent: 273.,
+    };
+
+    /// Metrics of Iosevka Extended. The normal width of Iosevka is narrower, with an advance of
+    /// 500.
+    pub const IOSEVKA: FontMetrics = FontMetrics {
+        units_per_em: 1000,
+        advance: 600.,
+        line_height: 1250.,
+        descent: 285.,
+    };
+
+    /// Metrics of JetBrains Mono.
+    pub const JETBRAINS_MONO: FontMetrics = FontMetrics {
+        units_per_em: 1000,
+        advance: 600.,
+        line_height: 1320.,
+        descent: 300.,
+    };
 
-        // Metrics of some fonts:
-        // - Liberation mono:
-        //     units_per_em: 2048,  1.000
-        //     advance: 1229.,      0.600
-        //     line_height: 2320.,  1.133
-        //     descent: 615.,       0.300
-        //
-        // - Consolas:
-        //     units_per_em: 2048,  1.000
-        //     advance: 1226,       0.599
-        //     line_height: 2398,   1.171
-        //     descent: 514,        0.251
-        //
-        // - Menlo:
-        //     units_per_em: 2048,  1.000
-        //     advance: 1233,       0.602
-        //     line_height: 2384,   1.164
-        //     descent: 483,        0.236
-        //
-        // - Source Code Pro
-        //     units_per_em: 1000,  1.000
-        //     advance: 600.,       0.600
-        //     line_height: 1257.,  1.257
-        //     descent: 273.,       0.273
-
-        // - Iosevka extended
-        //     units_per_em: 1000,  1.000
-        //     advance: 600.,       0.600
-        //     line_height: 1250.,  1.250
-        //     descent: 285.,       0.285
+    /// Metrics of Fira Code.
+    pub const FIRA_CODE: FontMetrics = FontMetrics {
+        units_per_em: 1950,
+        advance: 1200.,
+        line_height: 2400.,
+        descent: 600.,
     };
 }
 
@@ -120,33 +190,8 @@ impl Default for FontMetrics {
     }
 }
 
-/// Metrics for a font at a specific font size. Calculated from [FontMetrics].
-#[derive(Clone, Copy)]
-struct CalculatedFontMetrics {
-    /// The amount of horizontal advance between characters.
-    advance: f32,
-    /// Height of a line of text. Lines of text directly touch each other, i.e., it is assumed
-    /// the text "leading" is 0.
-    line_height: f32,
-    /// Distance below the text baseline. This is the distance between the text baseline of a line
-    /// and the top of the next line.It is assumed there is no
-    descent: f32,
-}
-
-impl FontMetrics {
-    /// Get the font metrics at a specific font size.
-    fn at_font_size(self, font_size: f32) -> CalculatedFontMetrics {
-        let scale_factor = font_size / f32::from(self.units_per_em);
-        CalculatedFontMetrics {
-            advance: self.advance * scale_factor,
-            line_height: self.line_height * scale_factor,
-            descent: self.descent * scale_factor,
-        }
-    }
-}
-
 /// A color in the sRGB color space.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Rgb {
     pub r: u8,
     pub g: u8,
@@ -159,192 +204,260 @@ impl Display for Rgb {
     }
 }
 
+/// An error returned when parsing an [Rgb] color fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRgbError;
+
+impl Display for ParseRgbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("expected a color in the format #rrggbb")
+    }
+}
+
+impl std::error::Error for ParseRgbError {}
+
+impl std::str::FromStr for Rgb {
+    type Err = ParseRgbError;
+
+    /// Parse a color in the hexadecimal format `#rrggbb`. The leading `#` is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(ParseRgbError);
+        }
+
+        let component =
+            |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| ParseRgbError);
+        Ok(Rgb {
+            r: component(0)?,
+            g: component(2)?,
+            b: component(4)?,
+        })
+    }
+}
+
+/// The style of a cell's underline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Underline {
+    /// The cell is not underlined.
+    #[default]
+    None,
+    /// A single straight line.
+    Single,
+    /// Two straight lines.
+    Double,
+    /// A wavy line, commonly used to mark errors.
+    Curly,
+    /// A dotted line.
+    Dotted,
+    /// A dashed line.
+    Dashed,
+}
+
+impl Underline {
+    fn from_flags(flags: Flags) -> Self {
+        if flags.intersects(Flags::UNDERLINE) {
+            Underline::Single
+        } else if flags.intersects(Flags::DOUBLE_UNDERLINE) {
+            Underline::Double
+        } else if flags.intersects(Flags::UNDERCURL) {
+            Underline::Curly
+        } else if flags.intersects(Flags::DOTTED_UNDERLINE) {
+            Underline::Dotted
+        } else if flags.intersects(Flags::DASHED_UNDERLINE) {
+            Underline::Dashed
+        } else {
+            Underline::None
+        }
+    }
+}
+
 /// The unicode character and style of a single cell in the terminal grid.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///
+/// The colors are as displayed: for cells with the reverse video (inverse) attribute, the
+/// foreground and background colors are swapped.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Cell {
     pub c: char,
+    /// Zero-width characters following `c` in the cell's grapheme cluster, such as combining
+    /// accents and variation selectors. Empty for most cells. See also [Cell::chars].
+    pub zerowidth: Box<[char]>,
     pub fg: Rgb,
     pub bg: Rgb,
     pub bold: bool,
+    /// Whether the cell is rendered with faint (decreased) intensity.
+    pub dim: bool,
     pub italic: bool,
-    pub underline: bool,
+    /// The style of the cell's underline.
+    pub underline: Underline,
+    /// The color of the cell's underline. If unset, the underline is drawn in the foreground
+    /// color.
+    pub underline_color: Option<Rgb>,
     pub strikethrough: bool,
+    /// Whether the cell's text is concealed, e.g., for password input.
+    pub hidden: bool,
+    /// The hyperlink the cell is part of, set using the OSC 8 escape sequence.
+    pub hyperlink: Option<Hyperlink>,
 }
 
 impl Cell {
-    fn from_alacritty_cell(colors: &Colors, cell: &AlacrittyCell) -> Self {
-        Cell {
-            c: cell.c,
-            fg: colors.to_rgb(cell.fg),
-            bg: colors.to_rgb(cell.bg),
-            bold: cell.flags.intersects(Flags::BOLD),
-            italic: cell.flags.intersects(Flags::ITALIC),
-            underline: cell.flags.intersects(Flags::ALL_UNDERLINES),
-            strikethrough: cell.flags.intersects(Flags::STRIKEOUT),
-        }
+    /// The characters of the cell's grapheme cluster: `c` followed by the zero-width characters.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        std::iter::once(self.c).chain(self.zerowidth.iter().copied())
     }
-}
 
-#[derive(PartialEq)]
-struct TextStyle {
-    fg: Rgb,
-    bold: bool,
-    italic: bool,
-    underline: bool,
-    strikethrough: bool,
-}
-
-impl TextStyle {
-    /// private conversion from alacritty Cell to Style
-    fn from_cell(cell: &Cell) -> Self {
-        let Cell {
-            fg,
-            bold,
-            italic,
-            underline,
-            strikethrough,
-            ..
-        } = *cell;
-
-        TextStyle {
-            fg,
-            bold,
-            italic,
+    /// The names of the cell's text attributes, as listed by [Screen::to_csv]: `bold`, `dim`,
+    /// `italic`, the style of the underline (`underline`, `double-underline`, `curly-underline`,
+    /// `dotted-underline` or `dashed-underline`), `strikethrough`, `hidden` and `hyperlink`.
+    pub fn attributes(&self) -> impl Iterator<Item = &'static str> {
+        let underline = match self.underline {
+            Underline::None => None,
+            Underline::Single => Some("underline"),
+            Underline::Double => Some("double-underline"),
+            Underline::Curly => Some("curly-underline"),
+            Underline::Dotted => Some("dotted-underline"),
+            Underline::Dashed => Some("dashed-underline"),
+        };
+        [
+            self.bold.then_some("bold"),
+            self.dim.then_some("dim"),
+            self.italic.then_some("italic"),
             underline,
-            strikethrough,
-        }
+            self.strikethrough.then_some("strikethrough"),
+            self.hidden.then_some("hidden"),
+            self.hyperlink.is_some().then_some("hyperlink"),
+        ]
+        .into_iter()
+        .flatten()
     }
-}
-
-struct TextLine {
-    text: Vec<char>,
-}
 
-impl TextLine {
-    fn with_capacity(capacity: usize) -> Self {
-        TextLine {
-            text: Vec::with_capacity(capacity),
+    /// Convert an alacritty cell, also returning the index of the cell's background in the color
+    /// table. See [Screen::bg_indices].
+    fn from_alacritty_cell(
+        colors: &Colors,
+        reverse_video: bool,
+        cell: &AlacrittyCell,
+    ) -> (Self, Option<u16>) {
+        let bold = cell.flags.intersects(Flags::BOLD);
+        let dim = cell.flags.intersects(Flags::DIM);
+        let mut fg = colors.text_color(cell.fg, bold && !dim);
+        let mut bg = cell.bg;
+
+        // reverse video swaps the foreground and background colors, and screen-wide reverse video
+        // swaps them back for cells in reverse video
+        if cell.flags.intersects(Flags::INVERSE) != reverse_video {
+            std::mem::swap(&mut fg, &mut bg);
         }
-    }
 
-    fn push_cell(&mut self, char: char) {
-        self.text.push(char);
+        let cell = Cell {
+            c: cell.c,
+            zerowidth: cell.zerowidth().map(Box::from).unwrap_or_default(),
+            fg: colors.to_rgb(fg),
+            bg: colors.to_rgb(bg),
+            bold,
+            dim,
+            italic: cell.flags.intersects(Flags::ITALIC),
+            underline: Underline::from_flags(cell.flags),
+            underline_color: cell.underline_color().map(|color| colors.to_rgb(color)),
+            strikethrough: cell.flags.intersects(Flags::STRIKEOUT),
+            hidden: cell.flags.intersects(Flags::HIDDEN),
+            hyperlink: cell.hyperlink().map(Hyperlink),
+        };
+        (cell, colors::palette_index(bg))
     }
+}
 
-    fn clear(&mut self) {
-        self.text.clear();
-    }
+/// The shape of the terminal cursor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    /// A filled block covering the cell.
+    #[default]
+    Block,
+    /// A line below the cell.
+    Underline,
+    /// A vertical bar to the left of the cell.
+    Beam,
+    /// An outline of the cell.
+    HollowBlock,
+}
 
-    fn len(&self) -> usize {
-        self.text.len()
-    }
+/// The position and appearance of the terminal cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub line: u16,
+    pub column: u16,
+    pub shape: CursorShape,
+    /// Whether the cursor is shown. Programs commonly hide the cursor while drawing, or when
+    /// drawing a full-screen interface.
+    pub visible: bool,
+}
 
-    fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
+/// A terminal screen buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Buffer {
+    /// The main screen buffer, which has a scrollback history.
+    Main,
+    /// The alternate screen buffer, commonly used by full-screen programs.
+    Alternate,
+}
 
-    /// Get the character cells of this text line, discarding trailing whitespace.
-    fn chars(&self) -> &[char] {
-        let trailing_whitespace_chars = self
-            .text
-            .iter()
-            .rev()
-            .position(|c| !c.is_whitespace())
-            .unwrap_or(self.text.len());
-        let end = self.text.len() - trailing_whitespace_chars;
-        &self.text[..end]
-    }
-}
-
-fn fmt_rect(
-    f: &mut std::fmt::Formatter<'_>,
-    x0: u16,
-    y0: u16,
-    x1: u16,
-    y1: u16,
-    color: Rgb,
-    font_metrics: &CalculatedFontMetrics,
-) -> std::fmt::Result {
-    writeln!(
-        f,
-        r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" style="fill: {color};" />"#,
-        x = f32::from(x0) * font_metrics.advance,
-        y = f32::from(y0) * font_metrics.line_height,
-        width = f32::from(x1 - x0 + 1) * font_metrics.advance,
-        height = f32::from(y1 - y0 + 1) * font_metrics.line_height,
-        color = color,
-    )
-}
-
-fn fmt_text(
-    f: &mut std::fmt::Formatter<'_>,
-    x: u16,
-    y: u16,
-    text: &TextLine,
-    style: &TextStyle,
-    font_metrics: &CalculatedFontMetrics,
-) -> std::fmt::Result {
-    let chars = text.chars();
-    let text_length = chars.len() as f32 * font_metrics.advance;
-    write!(
-        f,
-        r#"<text x="{x}" y="{y}" textLength="{text_length}" style="fill: {color};"#,
-        x = f32::from(x) * font_metrics.advance,
-        y = f32::from(y + 1) * font_metrics.line_height - font_metrics.descent,
-        color = style.fg,
-    )?;
-
-    if style.bold {
-        f.write_str(" font-weight: 600;")?;
-    }
-    if style.italic {
-        f.write_str(" font-style: italic;")?;
-    }
-    if style.underline || style.strikethrough {
-        f.write_char(' ')?;
-        if style.underline {
-            f.write_str(" underline")?;
-        }
-        if style.strikethrough {
-            f.write_str(" line-through")?;
-        }
-    }
-
-    f.write_str(r#"">"#)?;
-    let mut prev_char_was_space = false;
-    for char in chars {
-        match *char {
-            ' ' => {
-                if prev_char_was_space {
-                    // non-breaking space
-                    f.write_str("&#160;")?
-                } else {
-                    f.write_char(' ')?
-                }
-            }
-            // escape tag opening
-            '<' => f.write_str("&lt;")?,
-            '&' => f.write_str("&amp;")?,
-            c => f.write_char(c)?,
-        }
+/// The size of the characters of a line, set using the DEC line attribute escape sequences
+/// `ESC # 3` to `ESC # 6`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineSize {
+    /// Characters of the normal size (DECSWL).
+    #[default]
+    Single,
+    /// Characters of double width (DECDWL). Only the first half of the line's cells fit on the
+    /// screen.
+    DoubleWidth,
+    /// The top half of characters of double width and double height (DECDHL). This is usually
+    /// followed by a line with the same text as [LineSize::DoubleHeightBottom].
+    DoubleHeightTop,
+    /// The bottom half of characters of double width and double height (DECDHL).
+    DoubleHeightBottom,
+}
 
-        prev_char_was_space = *char == ' ';
-    }
-    f.write_str("</text>\n")?;
+/// The cells of the terminal screen that changed since the damage was last taken. See
+/// [Term::take_damage].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Damage {
+    /// Any cell may have changed, e.g., because the screen scrolled or its colors changed.
+    Full,
+    /// Only the cells in these ranges changed. Each line occurs at most once.
+    Lines(Vec<LineDamage>),
+}
 
-    Ok(())
+/// A range of changed cells on a line of the terminal screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineDamage {
+    pub line: u16,
+    pub start_column: u16,
+    /// The end of the range, exclusive.
+    pub end_column: u16,
 }
 
 /// A static snapshot of a terminal screen.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Screen {
     lines: u16,
     columns: u16,
+    title: Option<String>,
     cells: Vec<Cell>,
+    /// The index of each cell's background color in the color table of the terminal, or `None`
+    /// for colors given directly as RGB. The backgrounds are counted by these indices, see
+    /// [Screen::background].
+    bg_indices: Vec<Option<u16>>,
+    images: Vec<Image>,
+    cursor: Option<Cursor>,
+    /// The size of the characters of each line, or empty if all lines are [LineSize::Single].
+    line_sizes: Vec<LineSize>,
 }
 
 impl Screen {
     /// Get a [std::fmt::Display] that prints an SVG when formatted. Set `fonts` to specify fonts
     /// to be included in the SVG's `font-family` style. `font-family` always includes `monospace`.
+    /// See [Screen::to_svg_with_options] to set other options.
     ///
     /// The SVG is generated once [std::fmt::Display::fmt] is called; cache the call's output if
     /// you want to use it multiple times.
@@ -356,174 +469,41 @@ impl Screen {
     where
         'f: 's,
     {
-        struct Svg<'s> {
-            screen: &'s Screen,
-            fonts: &'s [&'s str],
-            font_metrics: CalculatedFontMetrics,
-        }
-
-        impl<'s> Display for Svg<'s> {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                let font_metrics = self.font_metrics;
-
-                let Screen {
-                    lines,
-                    columns,
-                    ref cells,
-                } = self.screen;
-
-                write!(
-                    f,
-                    r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg">"#,
-                    f32::from(*columns) * font_metrics.advance,
-                    f32::from(*lines) * font_metrics.line_height,
-                )?;
-
-                f.write_str(
-                    "
-<style>
-  .screen {
-    font-family: ",
-                )?;
-
-                for font in self.fonts {
-                    f.write_char('"')?;
-                    f.write_str(font)?;
-                    f.write_str("\", ")?;
-                }
-
-                write!(
-                    f,
-                    r#"monospace;
-    font-size: {FONT_SIZE_PX}px;
-  }}
-</style>
-<g class="screen">
-"#,
-                )?;
-
-                let main_bg = colors::most_common_color(self.screen);
-                fmt_rect(
-                    f,
-                    0,
-                    0,
-                    self.screen.columns().saturating_sub(1),
-                    self.screen.lines().saturating_sub(1),
-                    main_bg,
-                    &font_metrics,
-                )?;
-
-                // find background rectangles to draw by greedily flooding lines then flooding down columns
-                let mut drawn = vec![false; usize::from(*lines) * usize::from(*columns)];
-                for y0 in 0..*lines {
-                    for x0 in 0..*columns {
-                        let idx = self.screen.idx(y0, x0);
-
-                        if drawn[idx] {
-                            continue;
-                        }
-
-                        let cell = &cells[idx];
-                        let bg = cell.bg;
-
-                        if bg == main_bg {
-                            continue;
-                        }
-
-                        let mut end_x = x0;
-                        let mut end_y = y0;
-
-                        for x1 in x0 + 1..*columns {
-                            let idx = self.screen.idx(y0, x1);
-                            let cell = &cells[idx];
-                            if cell.bg == bg {
-                                end_x = x1;
-                            } else {
-                                break;
-                            }
-                        }
-
-                        for y1 in y0 + 1..*lines {
-                            let mut all = true;
-                            for x1 in x0 + 1..*columns {
-                                let idx = self.screen.idx(y1, x1);
-                                let cell = &cells[idx];
-                                if cell.bg != bg {
-                                    all = false;
-                                    break;
-                                }
-                            }
-                            if !all {
-                                break;
-                            }
-                            end_y = y1;
-                        }
-
-                        {
-                            for y in y0..=end_y {
-                                for x in x0..=end_x {
-                                    let idx = self.screen.idx(y, x);
-                                    drawn[idx] = true;
-                                }
-                            }
-                        }
-
-                        fmt_rect(f, x0, y0, end_x, end_y, bg, &font_metrics)?;
-                    }
-                }
-
-                // write text
-                let mut text_line =
-                    TextLine::with_capacity(usize::from(*columns).next_power_of_two());
-                for y in 0..*lines {
-                    let idx = self.screen.idx(y, 0);
-                    let cell = &cells[idx];
-                    let mut style = TextStyle::from_cell(cell);
-                    let mut start_x = 0;
-
-                    for x in 0..*columns {
-                        let idx = self.screen.idx(y, x);
-                        let cell = &cells[idx];
-                        let style_ = TextStyle::from_cell(cell);
-
-                        if style_ != style {
-                            if !text_line.is_empty() {
-                                fmt_text(f, start_x, y, &text_line, &style, &font_metrics)?;
-                            }
-                            text_line.clear();
-                            style = style_;
-                        }
-
-                        if text_line.is_empty() {
-                            start_x = x;
-                            if cell.c == ' ' {
-                                continue;
-                            }
-                        }
-
-                        text_line.push_cell(cell.c);
-                    }
-
-                    if !text_line.is_empty() {
-                        fmt_text(f, start_x, y, &text_line, &style, &font_metrics)?;
-                        text_line.clear();
-                    }
-                }
-
-                f.write_str(
-                    "</g>
-</svg>",
-                )?;
+        self.to_svg_with_options(SvgOptions {
+            fonts,
+            font_metrics,
+            ..SvgOptions::default()
+        })
+    }
 
-                Ok(())
-            }
+    /// Get a [std::fmt::Display] that prints an SVG when formatted, rendered with the given
+    /// [options](SvgOptions).
+    ///
+    /// The SVG is generated once [std::fmt::Display::fmt] is called; cache the call's output if
+    /// you want to use it multiple times.
+    pub fn to_svg_with_options<'s>(&'s self, options: SvgOptions<'s>) -> impl Display + 's {
+        svg::Svg {
+            content: svg::Content::Screen(self),
+            options,
         }
+    }
 
-        Svg {
-            screen: self,
-            fonts,
-            font_metrics: font_metrics.at_font_size(FONT_SIZE_PX),
-        }
+    /// Write an SVG of the screen, rendered with the given [options](SvgOptions), to `writer`.
+    pub fn write_svg(
+        &self,
+        mut writer: impl std::io::Write,
+        options: SvgOptions<'_>,
+    ) -> std::io::Result<()> {
+        write!(writer, "{}", self.to_svg_with_options(options))
+    }
+
+    /// Get a [std::fmt::Display] that prints the screen's text with ANSI escape sequences setting
+    /// its colors and attributes, such that printing it to a terminal (e.g., using `cat`)
+    /// reproduces the screen. Each screen line is ended by a newline.
+    ///
+    /// Colors are written as 24-bit RGB colors, as displayed on the screen.
+    pub fn to_ansi(&self) -> impl Display + '_ {
+        reemit::Ansi { screen: self }
     }
 
     #[inline(always)]
@@ -541,16 +521,515 @@ impl Screen {
         self.columns
     }
 
+    /// The terminal title at the time of the snapshot, as set by the program running in the
+    /// terminal.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
     /// An iterator over all cells in the terminal grid. This iterates over all columns in the
     /// first line from left to right, then the second line, etc.
     pub fn cells(&self) -> impl Iterator<Item = &Cell> {
         self.cells.iter()
     }
 
+    /// An iterator over the lines of the terminal grid, from top to bottom, yielding the cells of
+    /// each line from left to right.
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        // `chunks` panics on a chunk size of zero, but a screen without columns has no cells
+        self.cells.chunks(usize::from(self.columns).max(1))
+    }
+
+    /// Get the plain-text content of the screen, with lines separated by newlines. Trailing
+    /// whitespace of lines and trailing empty lines are removed.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(24, 80, b"$ echo hello\r\nhello");
+    /// assert_eq!(screen.text(), "$ echo hello\nhello");
+    /// ```
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows() {
+            let start = text.len();
+            text.extend(row.iter().flat_map(Cell::chars));
+            text.truncate(start + text[start..].trim_end().len());
+            text.push('\n');
+        }
+        text.truncate(text.trim_end().len());
+        text
+    }
+
+    /// Get the plain-text content of the screen line `line`, with trailing whitespace removed.
+    /// Returns `None` if the line is outside of the screen.
+    pub fn line_text(&self, line: u16) -> Option<String> {
+        let row = self.rows().nth(usize::from(line))?;
+        let text: String = row.iter().flat_map(Cell::chars).collect();
+        Some(text.trim_end().to_owned())
+    }
+
+    /// An iterator over all cells in the terminal grid with their positions, in the same order
+    /// as [Screen::cells]. Yields tuples of `(line, column, cell)`.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(2, 10, b"plain \x1b[1mbold");
+    /// let bold: Vec<_> = screen
+    ///     .cells_indexed()
+    ///     .filter(|(_, _, cell)| cell.bold)
+    ///     .map(|(line, column, _)| (line, column))
+    ///     .collect();
+    /// assert_eq!(bold.first(), Some(&(0, 6)));
+    /// ```
+    pub fn cells_indexed(&self) -> impl Iterator<Item = (u16, u16, &Cell)> {
+        self.rows().zip(0..).flat_map(|(row, line)| {
+            row.iter()
+                .zip(0..)
+                .map(move |(cell, column)| (line, column, cell))
+        })
+    }
+
     /// Get the cell at the terminal grid position specified by `line` and `column`.
     pub fn get(&self, line: u16, column: u16) -> Option<&Cell> {
+        if column >= self.columns {
+            return None;
+        }
         self.cells.get(self.idx(line, column))
     }
+
+    /// Get a new screen containing only the rectangular region of `lines` by `columns` of this
+    /// screen. The ranges are clamped to the screen's dimensions.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(24, 80, b"a prompt $ ");
+    /// let prompt = screen.crop(0..1, ..8);
+    /// assert_eq!((prompt.lines(), prompt.columns()), (1, 8));
+    /// ```
+    pub fn crop(&self, lines: impl RangeBounds<u16>, columns: impl RangeBounds<u16>) -> Screen {
+        fn clamp(range: impl RangeBounds<u16>, len: u16) -> (u16, u16) {
+            let start = match range.start_bound() {
+                Bound::Included(&start) => start,
+                Bound::Excluded(&start) => start.saturating_add(1),
+                Bound::Unbounded => 0,
+            };
+            let end = match range.end_bound() {
+                Bound::Included(&end) => end.saturating_add(1),
+                Bound::Excluded(&end) => end,
+                Bound::Unbounded => len,
+            };
+            let end = end.min(len);
+            (start.min(end), end)
+        }
+
+        let (line_start, line_end) = clamp(lines, self.lines);
+        let (column_start, column_end) = clamp(columns, self.columns);
+
+        let len = usize::from(line_end - line_start) * usize::from(column_end - column_start);
+        let mut cells = Vec::with_capacity(len);
+        let mut bg_indices = Vec::with_capacity(len);
+        for line in line_start..line_end {
+            let start = self.idx(line, column_start);
+            let end = self.idx(line, column_end);
+            cells.extend_from_slice(&self.cells[start..end]);
+            bg_indices.extend_from_slice(&self.bg_indices[start..end]);
+        }
+
+        let images = self
+            .images
+            .iter()
+            .filter_map(|image| {
+                let line = image.line - i32::from(line_start);
+                let column = image.column - i32::from(column_start);
+                let visible = line < i32::from(line_end - line_start)
+                    && line as f32 + image.lines > 0.
+                    && column < i32::from(column_end - column_start)
+                    && column as f32 + image.columns > 0.;
+                visible.then(|| Image {
+                    line,
+                    column,
+                    ..image.clone()
+                })
+            })
+            .collect();
+
+        let cursor = self.cursor.and_then(|cursor| {
+            let visible = (line_start..line_end).contains(&cursor.line)
+                && (column_start..column_end).contains(&cursor.column);
+            visible.then(|| Cursor {
+                line: cursor.line - line_start,
+                column: cursor.column - column_start,
+                ..cursor
+            })
+        });
+
+        let line_sizes = if self.line_sizes.is_empty() {
+            Vec::new()
+        } else {
+            self.line_sizes[usize::from(line_start)..usize::from(line_end)].to_vec()
+        };
+
+        Screen {
+            lines: line_end - line_start,
+            columns: column_end - column_start,
+            title: self.title.clone(),
+            cells,
+            bg_indices,
+            images,
+            cursor,
+            line_sizes,
+        }
+    }
+
+    /// Get a new screen with empty lines and columns around the screen's content removed. A cell
+    /// is empty if it is a blank space without underline or strikethrough, drawn in the screen's
+    /// most common background color. Images count as content.
+    pub fn trimmed(&self) -> Screen {
+        let background = colors::most_common_color(self);
+
+        let mut line_start = self.lines;
+        let mut line_end = 0;
+        let mut column_start = self.columns;
+        let mut column_end = 0;
+
+        for line in 0..self.lines {
+            for column in 0..self.columns {
+                let cell = &self.cells[self.idx(line, column)];
+                let empty = cell.bg == background
+                    && (cell.c == ' ' || cell.hidden)
+                    && cell.underline == Underline::None
+                    && !cell.strikethrough;
+                if !empty {
+                    line_start = line_start.min(line);
+                    line_end = line_end.max(line + 1);
+                    column_start = column_start.min(column);
+                    column_end = column_end.max(column + 1);
+                }
+            }
+        }
+
+        for image in &self.images {
+            let clamp = |value: f32, max: u16| value.clamp(0., f32::from(max)) as u16;
+            line_start = line_start.min(clamp(image.line as f32, self.lines));
+            line_end = line_end.max(clamp((image.line as f32 + image.lines).ceil(), self.lines));
+            column_start = column_start.min(clamp(image.column as f32, self.columns));
+            column_end = column_end.max(clamp(
+                (image.column as f32 + image.columns).ceil(),
+                self.columns,
+            ));
+        }
+
+        self.crop(line_start..line_end, column_start..column_end)
+    }
+
+    /// Find the occurrences of `needle` in the text of the screen. Each line is searched
+    /// separately. The occurrences are returned as the regions of the cells they are in, in
+    /// reading order. Occurrences do not span multiple lines, and do not overlap.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{emulate, Region};
+    ///
+    /// let screen = emulate(2, 20, b"[ OK ] started\r\n[FAIL] stopped");
+    /// assert_eq!(
+    ///     screen.find("FAIL"),
+    ///     [Region {
+    ///         start_line: 1,
+    ///         start_column: 1,
+    ///         end_line: 1,
+    ///         end_column: 5,
+    ///     }],
+    /// );
+    /// ```
+    pub fn find(&self, needle: &str) -> Vec<Region> {
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        search::find_in_lines(self, |text| {
+            text.match_indices(needle)
+                .map(|(start, m)| start..start + m.len())
+                .collect()
+        })
+    }
+
+    /// Find the matches of `pattern` in the text of the screen. Like [Screen::find], each line is
+    /// searched separately, and the matches are returned as the regions of the cells they are in.
+    /// Empty matches are ignored.
+    ///
+    /// ```rust
+    /// use regex::Regex;
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(2, 20, b"test a ... ok\r\ntest b ... FAILED");
+    /// let failed = screen.find_regex(&Regex::new(r"test (\w+) \.+ FAILED").unwrap());
+    /// assert_eq!(failed.len(), 1);
+    /// assert_eq!(failed[0].start_line, 1);
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn find_regex(&self, pattern: &regex::Regex) -> Vec<Region> {
+        search::find_in_lines(self, |text| {
+            pattern.find_iter(text).map(|m| m.range()).collect()
+        })
+    }
+
+    /// Get a new screen with the text matching `pattern` replaced by full block characters (█),
+    /// such that, e.g., tokens, hostnames or email addresses do not end up in published
+    /// screenshots. The pattern is matched against the text of each line separately, and against
+    /// the title.
+    ///
+    /// ```rust
+    /// use regex::Regex;
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(2, 30, b"token: abc123\r\nuser@example.com");
+    /// let redacted = screen.redact(&Regex::new(r"token: (\w+)|\S+@\S+").unwrap());
+    /// assert_eq!(redacted.text(), "█████████████\n████████████████");
+    /// ```
+    #[cfg(feature = "redact")]
+    pub fn redact(&self, pattern: &regex::Regex) -> Screen {
+        use unicode_width::UnicodeWidthChar;
+
+        const BLOCK: char = '\u{2588}';
+
+        let mut screen = self.clone();
+        for region in self.find_regex(pattern) {
+            for column in region.start_column..region.end_column {
+                let idx = self.idx(region.start_line, column);
+                // the spacers of wide characters are kept
+                if column > region.start_column && self.cells[idx - 1].c.width() == Some(2) {
+                    continue;
+                }
+                screen.cells[idx].c = BLOCK;
+                screen.cells[idx].zerowidth = Box::default();
+            }
+        }
+
+        if let Some(title) = &mut screen.title {
+            *title = pattern
+                .replace_all(title, |captures: &regex::Captures| {
+                    BLOCK.to_string().repeat(captures[0].chars().count())
+                })
+                .into_owned();
+        }
+
+        screen
+    }
+
+    /// Get the size of the characters of `line`, as set using the DEC double-width and
+    /// double-height line escape sequences.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{emulate, LineSize};
+    ///
+    /// let screen = emulate(3, 20, b"\x1b#6wide\r\n\x1b#3tall\r\n\x1b#4tall");
+    /// assert_eq!(screen.line_size(0), LineSize::DoubleWidth);
+    /// assert_eq!(screen.line_size(1), LineSize::DoubleHeightTop);
+    /// assert_eq!(screen.line_size(2), LineSize::DoubleHeightBottom);
+    /// ```
+    pub fn line_size(&self, line: u16) -> LineSize {
+        self.line_sizes
+            .get(usize::from(line))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Get the images displayed on the screen, such as Sixel graphics, in the order they were
+    /// drawn.
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// The terminal cursor at the time of the snapshot. Returns `None` if the cursor is outside
+    /// of this screen, e.g., because the screen was cropped.
+    pub fn cursor(&self) -> Option<Cursor> {
+        self.cursor
+    }
+
+    /// Serialize this screen to a stable, human-readable text format for snapshot testing. The
+    /// text lists the screen's lines, followed by the most common cell style and the runs of
+    /// cells with a different style. See also [assert_snapshot].
+    pub fn to_snapshot(&self) -> String {
+        snapshot::serialize(self)
+    }
+
+    /// Get statistics of the screen's content, such as the number of distinct colors and the
+    /// usage of text attributes. This is useful to budget the size of rendered SVGs, or to assert
+    /// in tests that a screen uses no more than a given number of colors.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(2, 20, b"plain \x1b[1;31mbold red");
+    /// let stats = screen.stats();
+    /// assert_eq!(stats.foreground_colors, 2);
+    /// assert_eq!(stats.bold_cells, 8);
+    /// assert_eq!(stats.widest_line, 14);
+    /// ```
+    pub fn stats(&self) -> ScreenStats {
+        stats::compute(self)
+    }
+
+    /// Get the cells of the screen as CSV, with one row per cell in reading order: its line and
+    /// column, its characters, its foreground and background colors, and its text attributes
+    /// separated by spaces. The first row is a header. This is useful for analyzing or diffing
+    /// screens in spreadsheets and other external tools.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(1, 2, b"\x1b[1m,");
+    /// assert_eq!(
+    ///     screen.to_csv(),
+    ///     "line,column,char,fg,bg,flags\n\
+    ///      0,0,\",\",#839496,#002b36,bold\n\
+    ///      0,1, ,#839496,#002b36,\n"
+    /// );
+    /// ```
+    pub fn to_csv(&self) -> String {
+        csv::to_delimited(self, ',')
+    }
+
+    /// Get the cells of the screen as tab-separated values, like [Screen::to_csv].
+    pub fn to_tsv(&self) -> String {
+        csv::to_delimited(self, '\t')
+    }
+
+    /// Get the background color of the screen: the most common background color of its cells. In
+    /// SVGs, the screen is filled with this color, and the cells with other background colors are
+    /// drawn over it.
+    pub fn background(&self) -> Rgb {
+        colors::most_common_color(self)
+    }
+
+    /// Get the rectangles of cells with a background color other than [Screen::background], as
+    /// drawn in SVGs. Runs of cells of a line with the same background color are merged, and runs
+    /// spanning the same columns are merged over consecutive lines. The rectangles do not overlap.
+    ///
+    /// Together with [Screen::text_runs], this is for rendering screens in formats other than SVG.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{emulate, BackgroundRegion};
+    ///
+    /// let screen = emulate(3, 10, b"\x1b[44m1 \x1b[0m one\r\n\x1b[44m2 \x1b[0m two");
+    /// let regions = screen.background_regions();
+    /// assert_eq!(regions.len(), 1);
+    /// assert_eq!((regions[0].lines, regions[0].columns), (2, 2));
+    /// ```
+    pub fn background_regions(&self) -> Vec<BackgroundRegion> {
+        runs::background_regions(self)
+    }
+
+    /// Get the runs of consecutive cells of each line with the same text style, as drawn in SVGs,
+    /// in reading order. Blank cells outside of runs are left out, as is the trailing whitespace
+    /// of runs. Hidden text is left out.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(2, 20, b"plain \x1b[1mbold\x1b[0m   plain");
+    /// let runs = screen.text_runs();
+    /// let runs: Vec<_> = runs.iter().map(|run| (run.column, &run.text[..], run.bold)).collect();
+    /// assert_eq!(runs, [(0, "plain", false), (6, "bold", true), (13, "plain", false)]);
+    /// ```
+    pub fn text_runs(&self) -> Vec<TextRun> {
+        runs::text_runs(self)
+    }
+
+    /// Get a copy of the screen where the foreground color of each cell is nudged toward black or
+    /// white, as needed for its contrast ratio with the cell's background color to be at least
+    /// `ratio`. Contrast ratios are as defined by WCAG 2, ranging from 1 to 21. This keeps text
+    /// legible with color themes that have low-contrast color combinations.
+    ///
+    /// Hidden cells are left as they are.
+    ///
+    /// ```rust
+    /// use termsnap_lib::emulate;
+    ///
+    /// let screen = emulate(1, 10, b"\x1b[38;2;90;90;90;48;2;60;60;60mdim");
+    /// let screen = screen.with_minimum_contrast(4.5);
+    /// assert!(screen[(0, 0)].fg.r > 90);
+    /// ```
+    pub fn with_minimum_contrast(&self, ratio: f32) -> Screen {
+        let mut screen = self.clone();
+        for cell in &mut screen.cells {
+            if !cell.hidden {
+                cell.fg = colors::with_minimum_contrast(cell.fg, cell.bg, ratio);
+            }
+        }
+        screen
+    }
+}
+
+impl Screen {
+    /// Update this snapshot of the current screen of `term` with `damage`, the damage taken from
+    /// `term` since the snapshot was taken or last updated. Only the changed cells are copied. If
+    /// the damage is [Damage::Full], or the terminal was resized, the snapshot is replaced.
+    ///
+    /// ```rust
+    /// use termsnap_lib::{Term, VoidPtyWriter};
+    ///
+    /// let mut term = Term::new(24, 80, VoidPtyWriter);
+    /// term.take_damage();
+    /// let mut screen = term.current_screen();
+    ///
+    /// term.process_bytes(b"hello");
+    /// let damage = term.take_damage();
+    /// screen.apply_damage(&term, &damage);
+    /// assert_eq!(screen, term.current_screen());
+    /// ```
+    pub fn apply_damage<W: PtyWriter>(&mut self, term: &Term<W>, damage: &Damage) {
+        let lines = match damage {
+            Damage::Lines(lines) if (self.lines, self.columns) == (term.lines, term.columns) => {
+                lines
+            }
+            _ => {
+                term.snapshot_into(self);
+                return;
+            }
+        };
+
+        let colors = &term.colors();
+        let grid = term.term.grid();
+        for damage in lines {
+            let row = &grid[Line(i32::from(damage.line))];
+            for column in damage.start_column..damage.end_column.min(self.columns) {
+                let idx = self.idx(damage.line, column);
+                (self.cells[idx], self.bg_indices[idx]) = Cell::from_alacritty_cell(
+                    colors,
+                    term.reverse_video,
+                    &row[Column(usize::from(column))],
+                );
+            }
+        }
+
+        let top = term.top();
+        self.title = term.title();
+        self.images = term.images_in(top, self.lines);
+        self.cursor = Some(term.cursor());
+        self.line_sizes = term.line_sizes_in(top, self.lines);
+    }
+}
+
+impl std::ops::Index<(u16, u16)> for Screen {
+    type Output = Cell;
+
+    /// Get the cell at the terminal grid position `(line, column)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position is outside of the screen. See [Screen::get] for a non-panicking
+    /// alternative.
+    fn index(&self, (line, column): (u16, u16)) -> &Cell {
+        assert!(
+            line < self.lines && column < self.columns,
+            "position {line}:{column} is outside of the screen of {} lines and {} columns",
+            self.lines,
+            self.columns,
+        );
+        &self.cells[self.idx(line, column)]
+    }
 }
 
 /// A sink for responses sent by the [terminal emulator](Term). The terminal emulator sends
@@ -576,6 +1055,8 @@ impl PtyWriter for VoidPtyWriter {
 
 struct EventProxy<Ev> {
     handler: std::cell::RefCell<Ev>,
+    /// The terminal title as most recently set by the OSC 0 or OSC 2 escape sequences.
+    title: Arc<Mutex<Option<String>>>,
 }
 
 impl<W: PtyWriter> alacritty_terminal::event::EventListener for EventProxy<W> {
@@ -583,17 +1064,177 @@ impl<W: PtyWriter> alacritty_terminal::event::EventListener for EventProxy<W> {
         use alacritty_terminal::event::Event as AEvent;
         match event {
             AEvent::PtyWrite(text) => self.handler.borrow_mut().write(text),
+            AEvent::Title(title) => {
+                *self.title.lock().unwrap_or_else(PoisonError::into_inner) = Some(title)
+            }
+            AEvent::ResetTitle => *self.title.lock().unwrap_or_else(PoisonError::into_inner) = None,
             _ev => {}
         }
     }
 }
 
-/// An in-memory terminal emulator.
-pub struct Term<W: PtyWriter> {
-    lines: u16,
-    columns: u16,
-    term: AlacrittyTerm<EventProxy<W>>,
+/// An error returned when creating or resizing a [Term] with an invalid size: a size with zero
+/// lines or columns, or a size of more than [TermSizeError::MAX_CELLS] cells. See [Term::try_new].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TermSizeError {
+    /// The requested number of lines.
+    pub lines: u16,
+    /// The requested number of columns.
+    pub columns: u16,
+}
+
+impl TermSizeError {
+    /// The maximum number of cells of a terminal created or resized using [Term::try_new] or
+    /// [Term::try_resize], e.g., 2048 lines by 2048 columns. Each snapshot of the terminal
+    /// allocates its cells, so larger terminals quickly take up gigabytes of memory.
+    pub const MAX_CELLS: usize = 1 << 22;
+
+    /// Check whether `lines` by `columns` is a valid terminal size.
+    fn check(lines: u16, columns: u16) -> Result<(), TermSizeError> {
+        if lines == 0 || columns == 0 || usize::from(lines) * usize::from(columns) > Self::MAX_CELLS
+        {
+            Err(TermSizeError { lines, columns })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Display for TermSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let TermSizeError { lines, columns } = *self;
+        if lines == 0 || columns == 0 {
+            write!(
+                f,
+                "invalid terminal size of {lines} lines by {columns} columns: the terminal must have at least one line and column"
+            )
+        } else {
+            write!(
+                f,
+                "invalid terminal size of {lines} lines by {columns} columns: the terminal may have at most {} cells",
+                Self::MAX_CELLS
+            )
+        }
+    }
+}
+
+impl std::error::Error for TermSizeError {}
+
+/// A builder for [Term]s, created by [Term::builder].
+///
+/// ```rust
+/// use termsnap_lib::{Term, VoidPtyWriter};
+///
+/// let mut term = Term::builder()
+///     .lines(2)
+///     .columns(80)
+///     .scrollback(100)
+///     .build(VoidPtyWriter);
+/// term.process_bytes(b"one\r\ntwo\r\nthree");
+/// assert_eq!(term.scrollback_screen().lines(), 3);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TermBuilder {
+    lines: u16,
+    columns: u16,
+    scrollback: usize,
+}
+
+impl TermBuilder {
+    fn new() -> Self {
+        TermBuilder {
+            lines: 24,
+            columns: 80,
+            scrollback: 10_000,
+        }
+    }
+
+    /// Set the number of screen lines. Defaults to 24.
+    pub fn lines(mut self, lines: u16) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    /// Set the number of screen columns. Defaults to 80.
+    pub fn columns(mut self, columns: u16) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Set the maximum number of lines kept in the scrollback history, see
+    /// [Term::scrollback_screen]. Set to 0 to disable the history. Defaults to 10,000. The
+    /// snapshot is limited to `u16::MAX` lines and [TermSizeError::MAX_CELLS] cells, so larger
+    /// histories are not fully included in it.
+    pub fn scrollback(mut self, lines: usize) -> Self {
+        self.scrollback = lines;
+        self
+    }
+
+    /// Create the terminal. See [Term::new] for `pty_writer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of lines or columns is 0. See [TermBuilder::try_build] to validate
+    /// untrusted sizes.
+    pub fn build<W: PtyWriter>(self, pty_writer: W) -> Term<W> {
+        let config = Config {
+            scrolling_history: self.scrollback,
+            ..Config::default()
+        };
+        Term::with_config(self.lines, self.columns, config, pty_writer)
+    }
+
+    /// Create the terminal, or return an error if the size is invalid. See [Term::try_new].
+    pub fn try_build<W: PtyWriter>(self, pty_writer: W) -> Result<Term<W>, TermSizeError> {
+        TermSizeError::check(self.lines, self.columns)?;
+        Ok(self.build(pty_writer))
+    }
+}
+
+/// An in-memory terminal emulator.
+pub struct Term<W: PtyWriter> {
+    lines: u16,
+    columns: u16,
+    term: AlacrittyTerm<EventProxy<W>>,
     processor: Option<vte::ansi::Processor<vte::ansi::StdSyncHandler>>,
+    title: Arc<Mutex<Option<String>>>,
+    /// A second parser observing escape sequences alacritty does not handle, such as Sixel
+    /// graphics.
+    parser: vte::Parser,
+    observer: sequences::Observer,
+    /// The placed images, ordered by line.
+    images: VecDeque<images::PlacedImage>,
+    /// The size of the scrollback history before the most recently observed byte.
+    history_size: usize,
+    /// A snapshot of the inactive screen buffer, taken when the terminal last switched away from
+    /// it.
+    inactive_screen: Option<Screen>,
+    colors: Colors,
+    /// Whether screen-wide reverse video (DECSCNM) is set.
+    reverse_video: bool,
+    /// Whether all cells changed since the damage was last taken, for changes alacritty does not
+    /// track.
+    full_damage: bool,
+    /// The sizes of lines other than [LineSize::Single], by absolute line in the scrollback
+    /// history offset by `dropped_lines`.
+    line_sizes: BTreeMap<usize, LineSize>,
+    /// The number of lines dropped from the top of the scrollback history. Line sizes and images
+    /// are placed by line numbers including the dropped lines, such that they need not be
+    /// renumbered as lines move through a full history.
+    dropped_lines: usize,
+    /// The scrolling region set by DECSTBM as a range of screen lines, mirroring alacritty's to
+    /// move line sizes and images along with scrolled lines.
+    scroll_region: Range<usize>,
+    diagnostics_sink: Option<Box<dyn FnMut(Diagnostic) + Send>>,
+}
+
+impl Term<VoidPtyWriter> {
+    /// Get a [TermBuilder] to configure a new terminal. The PTY writer is set when building the
+    /// terminal; this is implemented on `Term<VoidPtyWriter>` only so that calling it does not
+    /// require type annotations.
+    pub fn builder() -> TermBuilder {
+        TermBuilder::new()
+    }
 }
 
 impl<W: PtyWriter> Term<W> {
@@ -601,15 +1242,43 @@ impl<W: PtyWriter> Term<W> {
     ///
     /// [`pty_writer`](PtyWriter) is used to send output from the emulated terminal in reponse to ANSI requests.
     /// Use [`VoidPtyWriter`] if you do not need to send responses to status requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lines` or `columns` is 0. See [Term::try_new] to validate untrusted sizes.
     pub fn new(lines: u16, columns: u16, pty_writer: W) -> Self {
+        TermBuilder::new()
+            .lines(lines)
+            .columns(columns)
+            .build(pty_writer)
+    }
+
+    /// Create a new emulated terminal with a cell matrix of `lines` by `columns`, or return an
+    /// error if `lines` or `columns` is 0 or the terminal would have more than
+    /// [TermSizeError::MAX_CELLS] cells. Use this rather than [Term::new] for sizes from untrusted
+    /// sources.
+    pub fn try_new(lines: u16, columns: u16, pty_writer: W) -> Result<Self, TermSizeError> {
+        TermBuilder::new()
+            .lines(lines)
+            .columns(columns)
+            .try_build(pty_writer)
+    }
+
+    fn with_config(lines: u16, columns: u16, config: Config, pty_writer: W) -> Self {
+        assert!(
+            lines > 0 && columns > 0,
+            "the terminal must have at least one line and column"
+        );
+        let title = Arc::new(Mutex::new(None));
         let term = AlacrittyTerm::new(
-            Config::default(),
+            config,
             &TermSize {
                 columns: columns.into(),
                 screen_lines: lines.into(),
             },
             EventProxy {
                 handler: pty_writer.into(),
+                title: title.clone(),
             },
         );
 
@@ -618,37 +1287,347 @@ impl<W: PtyWriter> Term<W> {
             columns,
             term,
             processor: Some(Processor::new()),
+            title,
+            parser: vte::Parser::new(),
+            observer: sequences::Observer::default(),
+            images: VecDeque::new(),
+            history_size: 0,
+            inactive_screen: None,
+            colors: Colors::default(),
+            reverse_video: false,
+            full_damage: true,
+            line_sizes: BTreeMap::new(),
+            dropped_lines: 0,
+            scroll_region: 0..usize::from(lines),
+            diagnostics_sink: None,
         }
     }
 
     /// Process one byte of ANSI-escaped terminal data.
     pub fn process(&mut self, byte: u8) {
-        self.processor
-            .as_mut()
-            .expect("unreachable")
-            .advance(&mut self.term, byte);
+        self.process_with_callback(byte, |_, _| {});
+    }
+
+    /// Process ANSI-escaped terminal data read from `read` until it reaches end-of-file.
+    /// Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub async fn process_async_read(
+        &mut self,
+        mut read: impl tokio::io::AsyncRead + Unpin,
+    ) -> std::io::Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let mut buf = [0; 4096];
+        loop {
+            match read.read(&mut buf).await? {
+                0 => return Ok(()),
+                n => self.process_bytes(&buf[..n]),
+            }
+        }
     }
 
     /// Process one byte of ANSI-escaped terminal data. Some ANSI signals will trigger callback
     /// `cb` to be called with a reference to the terminal and the signal that triggered the call,
-    /// right before applying the result of the ANSI signal to the terminal. This allows grabbing a
-    /// snapshot of the the terminal screen contents before application of the signal.
+    /// generally right before applying the result of the ANSI signal to the terminal. This allows
+    /// grabbing a snapshot of the the terminal screen contents before application of the signal.
+    /// Signals that are only known once applied, such as scrolling, are sent after.
     ///
     /// See also [AnsiSignal].
-    pub fn process_with_callback(&mut self, byte: u8, mut cb: impl FnMut(&Self, AnsiSignal)) {
-        let mut processor = self.processor.take().expect("unreachable");
+    pub fn process_with_callback(&mut self, byte: u8, cb: impl FnMut(&Self, AnsiSignal)) {
+        self.process_bytes_with_callback(&[byte], cb);
+    }
+
+    /// Process a slice of ANSI-escaped terminal data. This is equivalent to calling
+    /// [`Term::process`] for each byte, but faster.
+    pub fn process_bytes(&mut self, bytes: &[u8]) {
+        self.process_bytes_with_callback(bytes, |_, _| {});
+    }
+
+    /// Process a slice of ANSI-escaped terminal data. This is equivalent to calling
+    /// [`Term::process_with_callback`] for each byte, but faster.
+    pub fn process_bytes_with_callback(
+        &mut self,
+        bytes: &[u8],
+        mut cb: impl FnMut(&Self, AnsiSignal),
+    ) {
+        let mut processor = self.processor.take().unwrap_or_default();
 
         let mut handler = ansi::HandlerWrapper {
             term: self,
             cb: &mut cb,
         };
 
-        processor.advance(&mut handler, byte);
+        /// The escape byte starting every sequence the observer handles.
+        const ESC: u8 = 0x1b;
+
+        let mut bytes = bytes;
+        while let Some((&byte, rest)) = bytes.split_first() {
+            if !handler.term.observer.in_sequence && byte != ESC {
+                // the observer ignores everything up to the next escape
+                let plain = bytes.iter().position(|&b| b == ESC).unwrap_or(bytes.len());
+                for &byte in &bytes[..plain] {
+                    processor.advance(&mut handler, byte);
+                }
+                bytes = &bytes[plain..];
+                continue;
+            }
+
+            handler.term.observer.in_sequence = true;
+            handler.term.history_size = handler.term.term.grid().history_size();
+            processor.advance(&mut handler, byte);
+            handler.term.observe(byte, handler.cb);
+            bytes = rest;
+        }
         self.processor = Some(processor);
     }
 
+    /// Feed a byte to the parser observing escape sequences alacritty does not handle, and apply
+    /// the observed sequences. Signals resulting from the sequences are sent to `cb`.
+    fn observe(&mut self, byte: u8, cb: &mut dyn FnMut(&Self, AnsiSignal)) {
+        self.parser.advance(&mut self.observer, byte);
+
+        if self.observer.observed.is_empty() {
+            return;
+        }
+
+        for observed in std::mem::take(&mut self.observer.observed) {
+            match observed {
+                sequences::Observed::Sixel(image) => self.place_sixel(image),
+                sequences::Observed::InlineImage(image) => self.place_inline_image(image),
+                sequences::Observed::ClearHistory => {
+                    self.dropped_lines += self.history_size;
+                    self.drop_images();
+                    self.line_sizes = self.line_sizes.split_off(&self.dropped_lines);
+                }
+                sequences::Observed::Reset => {
+                    self.images.clear();
+                    self.reverse_video = false;
+                    self.line_sizes.clear();
+                }
+                sequences::Observed::Snapshot { name } => cb(self, AnsiSignal::Snapshot { name }),
+                sequences::Observed::Unhandled(diagnostic) => {
+                    if let Some(sink) = self.diagnostics_sink.as_mut() {
+                        sink(diagnostic);
+                    }
+                }
+                sequences::Observed::ResizeRequest { lines, columns } => {
+                    let signal = AnsiSignal::ResizeRequest {
+                        lines: if lines == 0 { self.lines } else { lines },
+                        columns: if columns == 0 { self.columns } else { columns },
+                    };
+                    cb(self, signal);
+                }
+                sequences::Observed::ReverseVideo(reverse_video) => {
+                    self.full_damage |= self.reverse_video != reverse_video;
+                    self.reverse_video = reverse_video;
+                }
+                sequences::Observed::LineSize(size) => {
+                    let line = self.dropped_lines
+                        + self.term.grid().history_size()
+                        + self.term.grid().cursor.point.line.0.max(0) as usize;
+                    if size == LineSize::Single {
+                        self.line_sizes.remove(&line);
+                    } else {
+                        self.line_sizes.insert(line, size);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Place an image of `columns` by `lines` cells with its top-left at the cursor.
+    fn place_image(&mut self, mime: &'static str, data: Vec<u8>, columns: f32, lines: f32) {
+        let cursor = self.term.grid().cursor.point;
+        let line =
+            self.dropped_lines + self.term.grid().history_size() + cursor.line.0.max(0) as usize;
+
+        let index = self
+            .images
+            .partition_point(|placed| placed.absolute_line <= line);
+        self.images.insert(
+            index,
+            images::PlacedImage {
+                absolute_line: line,
+                image: Image {
+                    line: 0,
+                    column: cursor.column.0 as i32,
+                    columns,
+                    lines,
+                    mime,
+                    data: data.into(),
+                },
+            },
+        );
+    }
+
+    /// Move line sizes and images along with the screen lines in `region`, which alacritty just
+    /// scrolled up by `lines` lines, or down if `lines` is negative. Lines scrolled up from the
+    /// top of the screen move into the scrollback history, which had `history_size` lines before
+    /// scrolling. Line sizes and images of lines scrolled out of the region, or dropped from a
+    /// full history, are forgotten.
+    pub(crate) fn lines_scrolled(
+        &mut self,
+        region: Range<usize>,
+        lines: isize,
+        history_size: usize,
+    ) {
+        // the alternate screen has no history, and line sizes and images are tracked on the main
+        // screen only
+        if self.term.mode().contains(TermMode::ALT_SCREEN)
+            || (self.line_sizes.is_empty() && self.images.is_empty())
+        {
+            return;
+        }
+
+        let up = lines > 0;
+        let lines = lines.unsigned_abs().min(region.len());
+        // lines keep their number as they move through the history, except for lines below the
+        // scrolling region, which stay on the screen as the history grows
+        let top = self.dropped_lines + history_size;
+        let (start, end) = (top + region.start, top + region.end);
+        if up && region.start == 0 {
+            // lines scrolled up from the top of the screen move into the history, dropping its
+            // oldest lines once it is full
+            self.dropped_lines +=
+                (history_size + lines).saturating_sub(self.term.grid().history_size());
+            self.drop_images();
+            let below = self
+                .images
+                .partition_point(|placed| placed.absolute_line < end);
+            for placed in self.images.range_mut(below..) {
+                placed.absolute_line += lines;
+            }
+
+            self.line_sizes = self.line_sizes.split_off(&self.dropped_lines);
+            let below = self.line_sizes.split_off(&end);
+            self.line_sizes
+                .extend(below.into_iter().map(|(line, size)| (line + lines, size)));
+        } else {
+            let scroll = |line: usize| {
+                if up {
+                    line.checked_sub(lines).filter(|&line| line >= start)
+                } else {
+                    Some(line + lines).filter(|&line| line < end)
+                }
+            };
+
+            let first = self
+                .images
+                .partition_point(|placed| placed.absolute_line < start);
+            let mut scrolled = self.images.split_off(first);
+            let below = scrolled.partition_point(|placed| placed.absolute_line < end);
+            let mut below = scrolled.split_off(below);
+            self.images
+                .extend(scrolled.into_iter().filter_map(|mut placed| {
+                    placed.absolute_line = scroll(placed.absolute_line)?;
+                    Some(placed)
+                }));
+            self.images.append(&mut below);
+
+            let mut scrolled = self.line_sizes.split_off(&start);
+            let below = scrolled.split_off(&end);
+            self.line_sizes.extend(
+                scrolled
+                    .into_iter()
+                    .filter_map(|(line, size)| Some((scroll(line)?, size))),
+            );
+            self.line_sizes.extend(below);
+        }
+    }
+
+    /// Forget the images above the top of the scrollback history, on lines that were dropped.
+    fn drop_images(&mut self) {
+        let dropped = self
+            .images
+            .partition_point(|placed| placed.absolute_line < self.dropped_lines);
+        self.images.drain(..dropped);
+    }
+
+    /// Reset the sizes of the screen lines in `lines`, which were erased.
+    pub(crate) fn lines_erased(&mut self, lines: Range<usize>) {
+        if self.line_sizes.is_empty() || self.term.mode().contains(TermMode::ALT_SCREEN) {
+            return;
+        }
+        let top = self.dropped_lines + self.term.grid().history_size();
+        let erased: Vec<usize> = self
+            .line_sizes
+            .range(top + lines.start..top + lines.end)
+            .map(|(&line, _)| line)
+            .collect();
+        for line in erased {
+            self.line_sizes.remove(&line);
+        }
+    }
+
+    /// Place a Sixel image at the cursor, and move the cursor below the image.
+    fn place_sixel(&mut self, image: sixel::SixelImage) {
+        use vte::ansi::Handler;
+
+        let (width, height) = (image.width as u32, image.height as u32);
+        let data = images::encode_png(width, height, &image.rgba);
+        let lines = height as f32 / images::CELL_HEIGHT_PX as f32;
+        self.place_image(
+            "image/png",
+            data,
+            width as f32 / images::CELL_WIDTH_PX as f32,
+            lines,
+        );
+
+        for _ in 0..lines.ceil() as usize {
+            self.term.linefeed();
+        }
+        self.term.carriage_return();
+    }
+
+    /// Place an iTerm2 inline image at the cursor, and move the cursor to the right of the
+    /// image's bottom line.
+    fn place_inline_image(&mut self, image: iterm::InlineImage) {
+        use vte::ansi::Handler;
+
+        let Some((columns, lines)) = image.size(self.columns, self.lines) else {
+            return;
+        };
+        let column = self.term.grid().cursor.point.column;
+        self.place_image(image.mime, image.data, columns, lines);
+
+        for _ in 1..lines.ceil() as usize {
+            self.term.linefeed();
+        }
+        self.term.goto_col(column.0 + columns.ceil() as usize);
+    }
+
+    /// Set the color table used to resolve the colors of cells in snapshots of the terminal
+    /// screen. Colors changed by the program running in the terminal, using the OSC 4, 10 and 11
+    /// escape sequences, take precedence.
+    pub fn set_colors(&mut self, colors: Colors) {
+        self.colors = colors;
+        self.full_damage = true;
+    }
+
+    /// Set a sink receiving the escape sequences the terminal emulator does not handle, such as
+    /// unknown OSC commands or DCS sequences other than Sixel graphics. This helps to find out why
+    /// a screen looks different from how it looks in other terminals.
+    pub fn set_diagnostics_sink(&mut self, sink: impl FnMut(Diagnostic) + Send + 'static) {
+        self.observer.diagnostics = true;
+        self.diagnostics_sink = Some(Box::new(sink));
+    }
+
+    /// The color table used to resolve the colors of cells in snapshots of the terminal screen,
+    /// including the colors changed by the program running in the terminal.
+    pub fn colors(&self) -> Colors {
+        self.colors.with_overrides(self.term.colors())
+    }
+
     /// Resize the terminal screen to the specified dimension.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lines` or `columns` is 0. See [Term::try_resize] to validate untrusted sizes.
     pub fn resize(&mut self, lines: u16, columns: u16) {
+        assert!(
+            lines > 0 && columns > 0,
+            "the terminal must have at least one line and column"
+        );
         let new_size = TermSize {
             columns: columns.into(),
             screen_lines: lines.into(),
@@ -656,22 +1635,290 @@ impl<W: PtyWriter> Term<W> {
         self.lines = lines;
         self.columns = columns;
         self.term.resize(new_size);
+        self.scroll_region = 0..usize::from(lines);
+    }
+
+    /// Resize the terminal screen to the specified dimension, or return an error and leave the
+    /// terminal unchanged if the size is invalid. See [Term::try_new].
+    pub fn try_resize(&mut self, lines: u16, columns: u16) -> Result<(), TermSizeError> {
+        TermSizeError::check(lines, columns)?;
+        self.resize(lines, columns);
+        Ok(())
+    }
+
+    /// Reset the terminal to its initial state, as if it was newly created with the same size,
+    /// scrollback length and color table. This clears the screen buffers, the scrollback history,
+    /// the title and colors changed by programs. The terminal's allocations are reused, making
+    /// this cheaper than creating a new terminal for each capture.
+    pub fn reset(&mut self) {
+        use vte::ansi::Handler;
+
+        self.term.reset_state();
+        for index in 0..alacritty_terminal::term::color::COUNT {
+            self.term.reset_color(index);
+        }
+        *self.title.lock().unwrap_or_else(PoisonError::into_inner) = None;
+
+        self.processor = Some(Processor::new());
+        self.parser = vte::Parser::new();
+        self.observer = sequences::Observer::default();
+        self.images.clear();
+        self.history_size = 0;
+        self.inactive_screen = None;
+        self.reverse_video = false;
+        self.full_damage = true;
+        self.line_sizes.clear();
+        self.dropped_lines = 0;
+        self.scroll_region = 0..usize::from(self.lines);
+    }
+
+    /// The terminal title, as set by the program running in the terminal using the OSC 0 or OSC 2
+    /// escape sequences.
+    pub fn title(&self) -> Option<String> {
+        self.title
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Get the images that are at least partially visible in the `lines` lines starting at
+    /// absolute line `top`, positioned relative to that line.
+    fn images_in(&self, top: i64, lines: u16) -> Vec<Image> {
+        let top = self.dropped_lines as i64 + top;
+        self.images
+            .iter()
+            .filter_map(|placed| {
+                let line = placed.absolute_line as i64 - top;
+                let visible = line < i64::from(lines) && (line as f32 + placed.image.lines) > 0.;
+                visible.then(|| Image {
+                    line: line as i32,
+                    ..placed.image.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Get the sizes of the `lines` lines starting at absolute line `top`, or an empty vector if
+    /// all are [LineSize::Single].
+    fn line_sizes_in(&self, top: i64, lines: u16) -> Vec<LineSize> {
+        let top = self.dropped_lines + top.max(0) as usize;
+        if self
+            .line_sizes
+            .range(top..top + usize::from(lines))
+            .next()
+            .is_none()
+        {
+            return Vec::new();
+        }
+        (top..top + usize::from(lines))
+            .map(|line| self.line_sizes.get(&line).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Returns `true` if the alternate screen buffer is active. Full-screen programs usually
+    /// switch to the alternate screen buffer, restoring the main screen buffer when they exit.
+    pub fn is_alternate_screen(&self) -> bool {
+        self.term.mode().contains(TermMode::ALT_SCREEN)
+    }
+
+    /// Get a snapshot of the main screen buffer. If the alternate screen buffer is active, this
+    /// is the main screen as it was when the terminal switched to the alternate screen buffer.
+    pub fn main_screen(&self) -> Screen {
+        match &self.inactive_screen {
+            Some(screen) if self.is_alternate_screen() => screen.clone(),
+            _ => self.current_screen(),
+        }
+    }
+
+    /// Get a snapshot of the alternate screen buffer. If the main screen buffer is active, this is
+    /// the alternate screen as it was when the terminal last switched back to the main screen
+    /// buffer. Returns `None` if the alternate screen buffer was never used.
+    pub fn alternate_screen(&self) -> Option<Screen> {
+        if self.is_alternate_screen() {
+            Some(self.current_screen())
+        } else {
+            self.inactive_screen.clone()
+        }
+    }
+
+    /// Get a snapshot of the screen buffer `buffer`, whether or not it is active. See
+    /// [Term::main_screen] and [Term::alternate_screen]. Returns `None` if the alternate screen
+    /// buffer was never used.
+    pub fn screen(&self, buffer: Buffer) -> Option<Screen> {
+        match buffer {
+            Buffer::Main => Some(self.main_screen()),
+            Buffer::Alternate => self.alternate_screen(),
+        }
+    }
+
+    /// Get the position and appearance of the terminal cursor. The position is relative to the
+    /// top-left of the terminal screen.
+    pub fn cursor(&self) -> Cursor {
+        let point = self.term.grid().cursor.point;
+        let style = self.term.cursor_style();
+        let shape = match style.shape {
+            vte::ansi::CursorShape::Block | vte::ansi::CursorShape::Hidden => CursorShape::Block,
+            vte::ansi::CursorShape::Underline => CursorShape::Underline,
+            vte::ansi::CursorShape::Beam => CursorShape::Beam,
+            vte::ansi::CursorShape::HollowBlock => CursorShape::HollowBlock,
+        };
+
+        Cursor {
+            line: point.line.0.max(0) as u16,
+            column: point.column.0 as u16,
+            shape,
+            visible: self.term.mode().contains(TermMode::SHOW_CURSOR)
+                && style.shape != vte::ansi::CursorShape::Hidden,
+        }
     }
 
     /// Get a snapshot of the current terminal screen.
     pub fn current_screen(&self) -> Screen {
-        // ideally users can define their own colors
-        let colors = Colors::default();
+        let mut screen = Screen {
+            lines: 0,
+            columns: 0,
+            title: None,
+            cells: Vec::with_capacity(usize::from(self.lines) * usize::from(self.columns)),
+            bg_indices: Vec::with_capacity(usize::from(self.lines) * usize::from(self.columns)),
+            images: Vec::new(),
+            cursor: None,
+            line_sizes: Vec::new(),
+        };
+        self.snapshot_into(&mut screen);
+        screen
+    }
 
+    /// Get a snapshot of the current terminal screen into `screen`, reusing its allocations. This
+    /// is equivalent to [Term::current_screen], but avoids allocating the screen's cells for each
+    /// snapshot when inspecting the screen often, e.g., to wait for some output to appear. To keep
+    /// a copy of the screen as it changes, see [Term::take_damage] and [Screen::apply_damage].
+    ///
+    /// ```rust
+    /// use termsnap_lib::{emulate, Term, VoidPtyWriter};
+    ///
+    /// let mut term = Term::new(24, 80, VoidPtyWriter);
+    /// let mut screen = emulate(24, 80, b"");
+    /// for frame in ["one", "two", "three"] {
+    ///     term.process_bytes(frame.as_bytes());
+    ///     term.snapshot_into(&mut screen);
+    ///     assert_eq!(screen, term.current_screen());
+    /// }
+    /// ```
+    pub fn snapshot_into(&self, screen: &mut Screen) {
+        let colors = &self.colors();
+        let top = self.top();
+
+        screen.lines = self.lines;
+        screen.columns = self.columns;
+        screen.title = self.title();
+        screen.cells.clear();
+        screen.bg_indices.clear();
+        for point_cell in self.term.grid().display_iter() {
+            let (cell, bg_index) =
+                Cell::from_alacritty_cell(colors, self.reverse_video, point_cell.cell);
+            screen.cells.push(cell);
+            screen.bg_indices.push(bg_index);
+        }
+        screen.images = self.images_in(top, self.lines);
+        screen.cursor = Some(self.cursor());
+        screen.line_sizes = self.line_sizes_in(top, self.lines);
+    }
+
+    /// The absolute line in the scrollback history of the top of the current screen.
+    fn top(&self) -> i64 {
+        self.term.grid().history_size() as i64 - self.term.grid().display_offset() as i64
+    }
+
+    /// Take the damage of the current screen: the cells that changed since the damage was last
+    /// taken, or since the terminal was created. Use [Screen::apply_damage] to update a snapshot
+    /// of the screen with only the changed cells, e.g., to record many frames of an animation.
+    ///
+    /// The damage may include cells that did not change, such as the cells the cursor was on.
+    pub fn take_damage(&mut self) -> Damage {
+        use alacritty_terminal::term::TermDamage;
+
+        let full = std::mem::take(&mut self.full_damage) || self.term.grid().display_offset() != 0;
+        let damage = match self.term.damage() {
+            TermDamage::Partial(lines) if !full => Damage::Lines(
+                lines
+                    .map(|bounds| LineDamage {
+                        line: bounds.line as u16,
+                        start_column: bounds.left as u16,
+                        end_column: (bounds.right + 1).min(usize::from(self.columns)) as u16,
+                    })
+                    .collect(),
+            ),
+            _ => Damage::Full,
+        };
+        self.term.reset_damage();
+        damage
+    }
+
+    /// Get a snapshot of all lines written to the terminal, including the lines scrolled into the
+    /// scrollback history. Empty lines at the bottom of the terminal are excluded. The scrollback
+    /// history is limited to 10,000 lines by default, see [TermBuilder::scrollback]. The oldest
+    /// history lines are left out as needed to keep the snapshot within `u16::MAX` lines and
+    /// [TermSizeError::MAX_CELLS] cells.
+    pub fn scrollback_screen(&self) -> Screen {
+        let colors = &self.colors();
+        let grid = self.term.grid();
+        let history_size = grid.history_size() as i32;
+
+        let used_lines = (0..i32::from(self.lines))
+            .rev()
+            .find(|&line| {
+                grid[Line(line)][..]
+                    .iter()
+                    .any(|cell| !GridCell::is_empty(cell))
+            })
+            .map_or(0, |line| line + 1)
+            .max(
+                // include images extending below the last line with content
+                self.images
+                    .iter()
+                    .map(|placed| {
+                        let bottom =
+                            (placed.absolute_line - self.dropped_lines) as f32 + placed.image.lines;
+                        bottom.ceil() as i32 - history_size
+                    })
+                    .max()
+                    .unwrap_or(0),
+            )
+            .min(i32::from(self.lines));
+
+        // keep the screen within `u16::MAX` lines and `TermSizeError::MAX_CELLS` cells by leaving
+        // out the oldest history lines
+        let max_lines = (TermSizeError::MAX_CELLS / usize::from(self.columns))
+            .min(usize::from(u16::MAX)) as i32;
+        let history = history_size.min(max_lines - used_lines).max(0);
+
+        let len = (history + used_lines) as usize * usize::from(self.columns);
+        let mut cells = Vec::with_capacity(len);
+        let mut bg_indices = Vec::with_capacity(len);
+        for line in -history..used_lines {
+            for cell in &grid[Line(line)][..] {
+                let (cell, bg_index) = Cell::from_alacritty_cell(colors, self.reverse_video, cell);
+                cells.push(cell);
+                bg_indices.push(bg_index);
+            }
+        }
+
+        let lines = (history + used_lines) as u16;
+        let top = i64::from(history_size - history);
+        let cursor = self.cursor();
+        let cursor_line = history + i32::from(cursor.line);
         Screen {
-            lines: self.lines,
+            lines,
             columns: self.columns,
-            cells: self
-                .term
-                .grid()
-                .display_iter()
-                .map(|point_cell| Cell::from_alacritty_cell(&colors, point_cell.cell))
-                .collect(),
+            title: self.title(),
+            cells,
+            bg_indices,
+            images: self.images_in(top, lines),
+            cursor: (cursor_line < i32::from(lines)).then_some(Cursor {
+                line: cursor_line as u16,
+                ..cursor
+            }),
+            line_sizes: self.line_sizes_in(top, lines),
         }
     }
 }
@@ -679,9 +1926,7 @@ impl<W: PtyWriter> Term<W> {
 /// Feed an ANSI sequence through a terminal emulator, returning the resulting terminal screen contents.
 pub fn emulate(lines: u16, columns: u16, ansi_sequence: &[u8]) -> Screen {
     let mut term = Term::new(lines, columns, VoidPtyWriter);
-    for &byte in ansi_sequence {
-        term.process(byte);
-    }
+    term.process_bytes(ansi_sequence);
     term.current_screen()
 }
 
@@ -703,25 +1948,1659 @@ drwxr-xr-x  2 thomas users  4096 Jun 19 12:20 src
 drwxr-xr-x  3 thomas users  4096 Jun 18 14:36 target
 drwxr-xr-x  3 thomas users  4096 Jun 18 11:22 termsnap-lib";
 
-        let mut line = 0;
-        let mut column = 0;
+        assert_eq!(screen.text(), expected);
+    }
 
-        for c in expected.chars() {
-            match c {
-                '\n' => {
-                    for column in column..80 {
-                        let idx = screen.idx(line, column);
-                        assert_eq!(screen.cells[idx].c, ' ', "failed at {line}x{column}");
+    #[test]
+    fn parse_rgb() {
+        use super::Rgb;
+
+        assert_eq!(
+            "#1e1e2e".parse::<Rgb>(),
+            Ok(Rgb {
+                r: 0x1e,
+                g: 0x1e,
+                b: 0x2e
+            })
+        );
+        assert_eq!(
+            "81A1C1".parse::<Rgb>(),
+            Ok(Rgb {
+                r: 0x81,
+                g: 0xa1,
+                b: 0xc1
+            })
+        );
+        assert!("#fff".parse::<Rgb>().is_err());
+        assert!("#gggggg".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn title() {
+        let screen = super::emulate(2, 10, b"\x1b]2;a title\x07text");
+        assert_eq!(screen.title(), Some("a title"));
+
+        let screen = super::emulate(2, 10, b"text");
+        assert_eq!(screen.title(), None);
+    }
+
+    #[test]
+    fn inverse() {
+        let screen = super::emulate(2, 10, b"a\x1b[7mb");
+        let a = screen.get(0, 0).unwrap();
+        let b = screen.get(0, 1).unwrap();
+        assert_eq!(a.fg, b.bg);
+        assert_eq!(a.bg, b.fg);
+    }
+
+    #[test]
+    fn alternate_screen() {
+        use super::{Term, VoidPtyWriter};
+
+        let mut term = Term::new(2, 10, VoidPtyWriter);
+        for &byte in b"main" {
+            term.process(byte);
+        }
+        assert!(term.alternate_screen().is_none());
+
+        for &byte in b"\x1b[?1049h\x1b[Halt" {
+            term.process(byte);
+        }
+        assert!(term.is_alternate_screen());
+        assert_eq!(term.main_screen().text(), "main");
+        assert_eq!(term.alternate_screen().unwrap().text(), "alt");
+
+        for &byte in b"\x1b[?1049l" {
+            term.process(byte);
+        }
+        assert!(!term.is_alternate_screen());
+        assert_eq!(term.current_screen().text(), "main");
+        assert_eq!(term.alternate_screen().unwrap().text(), "alt");
+    }
+
+    #[test]
+    fn trimmed() {
+        let screen = super::emulate(24, 80, b"\n  a\r\n   bc").trimmed();
+        assert_eq!((screen.lines(), screen.columns()), (2, 3));
+        assert_eq!(screen.get(0, 0).unwrap().c, 'a');
+        assert_eq!(screen.get(1, 2).unwrap().c, 'c');
+
+        let screen = super::emulate(24, 80, b"").trimmed();
+        assert_eq!((screen.lines(), screen.columns()), (0, 0));
+    }
+
+    #[test]
+    fn sixel() {
+        // a red image 16 pixels wide and 12 pixels high, followed by text
+        let screen = super::emulate(4, 10, b"ab\x1bPq#0;2;100;0;0#0!16~-!16~\x1b\\text");
+        let images = screen.images();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].line, images[0].column), (0, 2));
+        assert_eq!((images[0].columns, images[0].lines), (2., 0.75));
+        assert_eq!(images[0].mime, "image/png");
+        assert_eq!(screen.get(1, 0).unwrap().c, 't');
+
+        let screen = super::emulate(4, 10, b"\x1bPq#0;2;100;0;0#0!16~\x1b\\\x1b[2J");
+        assert!(screen.images().is_empty());
+    }
+
+    #[test]
+    fn images_scroll() {
+        use super::{Term, VoidPtyWriter};
+
+        let mut base64 = String::new();
+        super::images::base64_encode(&super::images::encode_png(1, 1, &[0; 4]), &mut base64);
+        let sixel = b"\x1bPq#0;2;100;0;0#0!16~\x1b\\".to_vec();
+        let inline = format!("\x1b]1337;File=inline=1;width=1;height=1:{base64}\x07").into_bytes();
+
+        for image in [sixel, inline] {
+            // images move with their line, into the history and out of a full history
+            for scrollback in [0, 1, 10] {
+                let mut term = Term::builder()
+                    .lines(3)
+                    .columns(10)
+                    .scrollback(scrollback)
+                    .build(VoidPtyWriter);
+                term.process_bytes(&image);
+                term.process_bytes(b"\x1b[3H\n");
+                assert!(term.current_screen().images().is_empty());
+                let images = term.scrollback_screen().images().to_vec();
+                assert_eq!(images.len(), usize::from(scrollback > 0));
+                if scrollback == 10 {
+                    assert_eq!(images[0].line, 0);
+                }
+
+                term.process_bytes(b"\n");
+                let images = term.scrollback_screen().images().to_vec();
+                assert_eq!(images.len(), usize::from(scrollback > 1));
+            }
+
+            // images move with their line within the scrolling region
+            let mut bytes = b"\x1b[2;3r\x1b[2H".to_vec();
+            bytes.extend_from_slice(&image);
+            bytes.extend_from_slice(b"\x1b[2H\x1b[L");
+            let screen = super::emulate(4, 10, &bytes);
+            assert_eq!(screen.images()[0].line, 2);
+
+            // clearing the history drops the images in it, and keeps those on the screen
+            let mut term = Term::builder()
+                .lines(3)
+                .columns(10)
+                .scrollback(10)
+                .build(VoidPtyWriter);
+            term.process_bytes(&image);
+            term.process_bytes(b"\x1b[3H\n\n\n\x1b[H");
+            term.process_bytes(&image);
+            term.process_bytes(b"\x1b[3J");
+            let images = term.scrollback_screen().images().to_vec();
+            assert_eq!(images.len(), 1);
+            assert_eq!(images[0].line, 0);
+        }
+    }
+
+    #[test]
+    fn process_bytes_batched() {
+        use super::{AnsiSignal, LineSize, Term, VoidPtyWriter};
+
+        // sequences observed alongside alacritty, interleaved with text, multi-byte characters,
+        // and aborted and ignored sequences
+        let bytes = [
+            &b"one\r\n\x1b#6wide \x1b[1;31mred\x1b[0m \xe2\x94\x82\r\n"[..],
+            b"\x1bPq#0;2;100;0;0#0!16~\x1b\\sixel\r\n",
+            b"\x1b]7979;snapshot;st\x1b\\text\x1b]7979;snapshot;bel\x07\r\n",
+            b"\x1b[1\x18#3 \x1b[1<m#4\r\n",
+            b"\x1b]2;title\x1b\\\x1b#3top\x1b[8;4;20t",
+        ]
+        .concat();
+
+        // sequences are observed regardless of how the output is split
+        for chunk in [bytes.len(), 3, 1] {
+            let mut term = Term::new(8, 20, VoidPtyWriter);
+            let mut signals = Vec::new();
+            for bytes in bytes.chunks(chunk) {
+                term.process_bytes_with_callback(bytes, |_, signal| match signal {
+                    AnsiSignal::Snapshot { name } => signals.push(format!("snapshot {name:?}")),
+                    AnsiSignal::ResizeRequest { lines, columns } => {
+                        signals.push(format!("resize {lines}x{columns}"))
                     }
-                    column = 0;
-                    line += 1;
+                    _ => {}
+                });
+            }
+
+            assert_eq!(
+                signals,
+                [
+                    r#"snapshot Some("st")"#,
+                    r#"snapshot Some("bel")"#,
+                    "resize 4x20"
+                ]
+            );
+            let screen = term.current_screen();
+            assert_eq!(
+                screen.text(),
+                "one\nwide red \u{2502}\n\nsixel\ntext\n#3 #4\ntop"
+            );
+            assert_eq!(screen.images().len(), 1);
+            assert_eq!(screen.images()[0].line, 2);
+            let sizes: Vec<_> = (0..7).map(|line| screen.line_size(line)).collect();
+            assert_eq!(
+                sizes,
+                [
+                    LineSize::Single,
+                    LineSize::DoubleWidth,
+                    LineSize::Single,
+                    LineSize::Single,
+                    LineSize::Single,
+                    LineSize::Single,
+                    LineSize::DoubleHeightTop
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn inline_image() {
+        // a 16 by 32 pixel image
+        let mut base64 = String::new();
+        super::images::base64_encode(
+            &super::images::encode_png(16, 32, &[0; 16 * 32 * 4]),
+            &mut base64,
+        );
+
+        let sequence = format!("ab\x1b]1337;File=name=a.png;inline=1:{base64}\x07c");
+        let screen = super::emulate(4, 10, sequence.as_bytes());
+        let images = screen.images();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].line, images[0].column), (0, 2));
+        assert_eq!((images[0].columns, images[0].lines), (2., 2.));
+        assert_eq!(screen.get(1, 4).unwrap().c, 'c');
+
+        let sequence = format!("\x1b]1337;File=width=4;inline=1:{base64}\x07");
+        let screen = super::emulate(10, 10, sequence.as_bytes());
+        assert_eq!(
+            (screen.images()[0].columns, screen.images()[0].lines),
+            (4., 4.)
+        );
+
+        let sequence = format!("\x1b]1337;File=name=a.png:{base64}\x07");
+        assert!(super::emulate(4, 10, sequence.as_bytes())
+            .images()
+            .is_empty());
+    }
+
+    #[test]
+    fn cursor() {
+        use super::{Cursor, CursorShape};
+
+        let screen = super::emulate(4, 10, b"ab\r\ncd\x1b[6 q");
+        assert_eq!(
+            screen.cursor(),
+            Some(Cursor {
+                line: 1,
+                column: 2,
+                shape: CursorShape::Beam,
+                visible: true,
+            })
+        );
+        assert_eq!(
+            screen.crop(1.., 1..).cursor().map(|c| (c.line, c.column)),
+            Some((0, 1))
+        );
+        assert_eq!(screen.crop(..1, ..).cursor(), None);
+
+        let screen = super::emulate(4, 10, b"\x1b[?25l");
+        assert!(!screen.cursor().unwrap().visible);
+    }
+
+    #[test]
+    fn parse_region() {
+        use super::Region;
+
+        assert_eq!(
+            "3:0-4:20".parse::<Region>(),
+            Ok(Region {
+                start_line: 3,
+                start_column: 0,
+                end_line: 4,
+                end_column: 20,
+            })
+        );
+        assert!("3:0".parse::<Region>().is_err());
+        assert!("3-4:20".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn parse_annotation() {
+        use super::AnnotationKind;
+
+        assert_eq!(
+            "arrow 1:2 3:4".parse::<AnnotationKind>(),
+            Ok(AnnotationKind::Arrow {
+                from_line: 1,
+                from_column: 2,
+                to_line: 3,
+                to_column: 4,
+            })
+        );
+        assert_eq!(
+            "label 0:5 a  label".parse::<AnnotationKind>(),
+            Ok(AnnotationKind::Label {
+                line: 0,
+                column: 5,
+                text: "a  label".to_owned(),
+            })
+        );
+        assert!("callout 1:2 x".parse::<AnnotationKind>().is_err());
+        assert!("circle 1:2 3".parse::<AnnotationKind>().is_err());
+    }
+
+    #[test]
+    fn alt_text() {
+        use super::{AltText, SvgOptions};
+
+        let screen = super::emulate(4, 20, b"a \"b\"\r\nc  ");
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                alt_text: AltText::ScreenText,
+                ..SvgOptions::default()
+            })
+            .to_string();
+        assert!(svg.contains(r#"role="img" aria-label="a &quot;b&quot;&#10;c">"#));
+        assert!(svg.contains("<desc>a \"b\"\nc</desc>"));
+
+        // the SVG is not made accessible by default
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(!svg.contains("role=") && !svg.contains("<title>") && !svg.contains("<desc>"));
+    }
+
+    #[test]
+    fn fallback_fonts() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(2, 20, "a 漢字 b ✔".as_bytes());
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                cjk_fonts: &["CJK Font"],
+                ..SvgOptions::default()
+            })
+            .to_string();
+        assert!(svg.contains(".screen .cjk {\n    font-family: \"CJK Font\", monospace;"));
+        assert!(svg.contains(r#"class="cjk" style="fill: #839496;">漢 字</text>"#));
+        // characters without a font stack are not split into separate runs
+        assert!(!svg.contains(".symbol"));
+        assert!(svg.contains(">b ✔</text>"));
+    }
+
+    #[cfg(feature = "redact")]
+    #[test]
+    fn redact() {
+        let screen = super::emulate(2, 10, "\x1b]0;a secret\x07a 漢字\r\nb\x1b[1mc".as_bytes());
+        let redacted = screen.redact(&regex::Regex::new("字|secret|b.").unwrap());
+        assert_eq!(redacted.text(), "a 漢 █\n██");
+        assert_eq!(redacted.title(), Some("a ██████"));
+        // styles are kept
+        assert!(redacted.get(1, 1).unwrap().bold);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn find() {
+        use super::Region;
+
+        let region = |line, start_column, end_column| Region {
+            start_line: line,
+            start_column,
+            end_line: line,
+            end_column,
+        };
+        let screen = super::emulate(2, 10, "a 漢字 é\r\naa\u{301}aa".as_bytes());
+        assert_eq!(screen.find("漢字"), [region(0, 2, 6)]);
+        assert_eq!(screen.find("aa"), [region(1, 0, 2), region(1, 2, 4)]);
+        assert_eq!(screen.find(""), []);
+        assert_eq!(
+            screen.find_regex(&regex::Regex::new("é|x*").unwrap()),
+            [region(0, 7, 8)]
+        );
+    }
+
+    #[test]
+    fn blur() {
+        use super::{Blur, BlurStyle, Region, SvgOptions};
+
+        let screen = super::emulate(2, 20, b"user secret ok");
+        let region: Region = "0:5-0:11".parse().unwrap();
+        for style in [BlurStyle::Blur, BlurStyle::Pixelate] {
+            let svg = screen
+                .to_svg_with_options(SvgOptions {
+                    blurs: &[Blur { region, style }],
+                    ..SvgOptions::default()
+                })
+                .to_string();
+            assert!(!svg.contains("secret"));
+            assert_eq!(
+                svg.contains(r#"filter="url(#termsnap-blur)""#),
+                style == BlurStyle::Blur
+            );
+        }
+    }
+
+    #[test]
+    fn window_frame() {
+        use super::{SvgOptions, WindowFrame};
+
+        let screen = super::emulate(2, 10, b"a");
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().window_frame(WindowFrame::MacOs))
+            .to_string();
+        // the screen is padded and placed below the title bar
+        assert!(svg.starts_with(r#"<svg viewBox="0 0 88 72.8""#));
+        assert!(svg.contains(r#"<g class="screen" transform="translate(8 36)">"#));
+        assert!(svg.contains(r#"<clipPath id="termsnap-window">"#));
+        assert_eq!(svg.matches("<circle ").count(), 3);
+        assert!(!svg.contains("termsnap-shadow"));
+
+        let svg = screen
+            .to_svg_with_options(
+                SvgOptions::default()
+                    .window_frame(WindowFrame::Generic)
+                    .window_shadow(true),
+            )
+            .to_string();
+        // the shadow gets a margin to be drawn in
+        assert!(svg.starts_with(r#"<svg viewBox="0 0 136 120.8""#));
+        assert!(svg.contains(r#"filter="url(#termsnap-shadow)""#));
+        assert!(svg.contains(r#"<g class="screen" transform="translate(32 60)">"#));
+        assert_eq!(svg.matches("<circle ").count(), 0);
+
+        // without a frame, the shadow has no effect
+        let plain = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().window_shadow(true))
+            .to_string();
+        assert_eq!(svg, plain);
+        assert!(!svg.contains("<clipPath"));
+    }
+
+    #[test]
+    fn padding_and_margin() {
+        use super::SvgOptions;
+
+        // the screen of 72 by 28.8 is offset by the padding and the margin
+        let screen = super::emulate(2, 10, b"a");
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().padding(4.).margin(6.))
+            .to_string();
+        assert!(svg.starts_with(r#"<svg viewBox="0 0 92 48.800003""#));
+        assert!(svg.contains(r#"<g class="screen" transform="translate(10 10)">"#));
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().padding(4.))
+            .to_string();
+        assert!(svg.starts_with(r#"<svg viewBox="0 0 80 36.800003""#));
+        assert!(svg.contains(r#"<g class="screen" transform="translate(4 4)">"#));
+
+        // negative and non-finite lengths are treated as 0
+        let plain = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        for length in [-100., f32::NAN, f32::INFINITY] {
+            let options = SvgOptions::default().padding(length).margin(length);
+            assert_eq!(screen.to_svg_with_options(options).to_string(), plain);
+            let options = SvgOptions {
+                padding: length,
+                margin: length,
+                ..SvgOptions::default()
+            };
+            assert_eq!(screen.to_svg_with_options(options).to_string(), plain);
+        }
+    }
+
+    #[test]
+    fn dim() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(1, 10, "a\x1b[2mb\u{2588}\x1b[22mc".as_bytes());
+        assert!(!screen[(0, 0)].dim);
+        assert!(screen[(0, 1)].dim);
+        assert!(screen[(0, 2)].dim);
+        assert!(!screen[(0, 3)].dim);
+
+        // dim text and glyphs are drawn with reduced opacity
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().glyph_geometry(true))
+            .to_string();
+        assert!(svg.contains(r#"style="fill: #839496; opacity: 0.6;">b</text>"#));
+        assert!(svg.contains(r#"style="fill: #839496; fill-opacity: 0.6;" />"#));
+        assert!(svg.contains(r#"style="fill: #839496;">c</text>"#));
+
+        // bold text is not brightened when it is also dim
+        let mut colors = super::Colors::default();
+        colors.set_bold_is_bright(true);
+        let mut term = super::Term::new(1, 10, super::VoidPtyWriter);
+        term.set_colors(colors);
+        term.process_bytes(b"\x1b[31ma\x1b[1mb\x1b[2mc");
+        let screen = term.current_screen();
+        assert_eq!(screen[(0, 0)].fg, screen[(0, 2)].fg);
+        assert_ne!(screen[(0, 0)].fg, screen[(0, 1)].fg);
+    }
+
+    #[test]
+    fn hidden() {
+        use super::{AltText, SvgOptions};
+
+        let screen = super::emulate(1, 10, b"a\x1b[4;8mpass\x1b[28mb");
+        assert!(!screen[(0, 0)].hidden);
+        assert!(screen[(0, 1)].hidden);
+        assert!(!screen[(0, 5)].hidden);
+        // the text of the screen still includes the concealed text
+        assert_eq!(screen.text(), "apassb");
+
+        // concealed text and its underline are not drawn, or drawn in the background color
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().alt_text(AltText::ScreenText))
+            .to_string();
+        assert!(!svg.contains("pass"));
+        assert!(svg.contains(r#"aria-label="a    b""#));
+        assert!(svg.contains(r#"<text x="36" y="10.8" textLength="7.2000003" style="fill: #839496; text-decoration: underline;">b</text>"#));
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().hidden_as_background(true))
+            .to_string();
+        assert!(svg.contains(r#"style="fill: #002b36; text-decoration: underline;">pass</text>"#));
+    }
+
+    #[test]
+    fn underline() {
+        use super::{FontMetrics, Underline};
+
+        let screen = super::emulate(
+            1,
+            10,
+            b"\x1b[4:2ma\x1b[4:3mb\x1b[4:4mc\x1b[4:5md\x1b[4:1;58;2;255;0;0me\x1b[59mf\x1b[0mg",
+        );
+        let underlines: Vec<_> = (0..7).map(|column| screen[(0, column)].underline).collect();
+        assert_eq!(
+            underlines,
+            [
+                Underline::Double,
+                Underline::Curly,
+                Underline::Dotted,
+                Underline::Dashed,
+                Underline::Single,
+                Underline::Single,
+                Underline::None,
+            ]
+        );
+        assert_eq!(
+            screen[(0, 4)]
+                .underline_color
+                .map(|color| color.to_string()),
+            Some("#ff0000".to_owned())
+        );
+        assert_eq!(screen[(0, 5)].underline_color, None);
+
+        // styled and colored underlines are drawn as lines, plain underlines as text decoration
+        let svg = screen.to_svg(&[], FontMetrics::DEFAULT).to_string();
+        let lines: Vec<_> = svg
+            .lines()
+            .filter(|line| line.starts_with("<line") || line.starts_with("<path"))
+            .collect();
+        assert_eq!(lines.len(), 6);
+        // double
+        assert!(lines[0].contains(r#"x1="0" y1="11.1""#));
+        assert!(lines[1].contains(r#"x1="0" y1="12.9""#));
+        // curly, with two half waves for the cell
+        assert!(lines[2]
+            .starts_with(r#"<path d="M7.2000003 12 q1.8000001 -0.72 3.6000001 0 t3.6000001 0""#));
+        // dotted and dashed
+        assert!(lines[3].contains("stroke-dasharray: 0.90000004;"));
+        assert!(lines[4].contains("stroke-dasharray: 2.4;"));
+        // colored
+        assert!(lines[5].contains(r#"x1="28.800001""#));
+        assert!(lines[5].contains("stroke: #ff0000;"));
+        assert!(svg.contains("text-decoration: underline;\">f</text>"));
+    }
+
+    #[test]
+    fn glyph_geometry() {
+        use super::SvgOptions;
+
+        // the glyphs are drawn as text by default
+        let screen = super::emulate(1, 3, "\u{1fb00}\u{1fb3b}\u{28ff}".as_bytes());
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(svg.contains("\u{1fb00}\u{1fb3b}\u{28ff}</text>"));
+        assert!(!svg.contains("<path"));
+
+        // the paths of the glyphs, with their subpaths' numbers scaled to fractions of a cell
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().glyph_geometry(true))
+            .to_string();
+        let paths: Vec<Vec<Vec<f32>>> = svg
+            .match_indices("<path d=\"")
+            .zip(0u8..)
+            .map(|((idx, _), column)| {
+                let d = &svg[idx + 9..];
+                let d = &d[..d.find('"').unwrap()];
+                d.split('M')
+                    .skip(1)
+                    .map(|subpath| {
+                        subpath
+                            .split(|c: char| c.is_ascii_alphabetic() || c == ' ')
+                            .filter(|n| !n.is_empty())
+                            .enumerate()
+                            // the horizontal numbers alternate with the vertical ones, and only
+                            // the first is absolute
+                            .map(|(idx, n)| {
+                                let n: f32 = n.parse().unwrap();
+                                match idx {
+                                    0 => n / 7.2 - f32::from(column),
+                                    _ if idx % 2 == 0 => n / 7.2,
+                                    _ => n / 14.4,
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let close = |a: &[f32], b: &[f32]| {
+            a.len() >= b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-4)
+        };
+
+        // the first sextant is the upper-left sixth
+        assert_eq!(paths[0].len(), 1);
+        assert!(close(&paths[0][0], &[0., 0., 0.5, 1. / 3.]));
+
+        // the last sextant is all but the upper-left sixth
+        let expected = [
+            [0.5, 0.],
+            [0., 1. / 3.],
+            [0.5, 1. / 3.],
+            [0., 2. / 3.],
+            [0.5, 2. / 3.],
+        ];
+        assert_eq!(paths[1].len(), expected.len());
+        for (rect, (x, y)) in paths[1].iter().zip(expected.map(|[x, y]| (x, y))) {
+            assert!(close(rect, &[x, y, 0.5, 1. / 3.]), "{rect:?}");
+        }
+
+        // the last Braille pattern has all eight dots, in two columns of four
+        assert_eq!(paths[2].len(), 8);
+        let radius = 1.08 / 7.2;
+        for (dot, (column, row)) in paths[2].iter().zip([
+            (0., 0.),
+            (0., 1.),
+            (0., 2.),
+            (1., 0.),
+            (1., 1.),
+            (1., 2.),
+            (0., 3.),
+            (1., 3.),
+        ]) {
+            let center = [(column + 0.5) / 2. - radius, (row + 0.5) / 4.];
+            assert!(close(dot, &center), "{dot:?}");
+        }
+    }
+
+    #[cfg(feature = "text-as-paths")]
+    #[test]
+    fn text_as_paths() {
+        use super::{OutlineFont, SvgOptions};
+
+        let path = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+        let Ok(data) = std::fs::read(path) else {
+            eprintln!("skipping test: {path} not found");
+            return;
+        };
+        let font = OutlineFont::new(data).unwrap();
+
+        let screen = super::emulate(1, 10, b"ab \x1b[1;4mc");
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(svg.contains("<text"));
+        assert!(!svg.contains("<path"));
+
+        let svg = screen
+            .to_svg_with_options(
+                SvgOptions::default()
+                    .font_metrics(font.metrics())
+                    .text_as_paths(Some(&font)),
+            )
+            .to_string();
+        assert!(!svg.contains("<text"));
+        // one path for each run, the bold run stroked
+        assert_eq!(svg.matches("<path d=\"M").count(), 2);
+        assert_eq!(svg.matches("stroke-width: 0.5;").count(), 1);
+        // the underline is still drawn
+        assert!(svg.contains("<line"));
+    }
+
+    #[test]
+    fn svg_options() {
+        use super::{FontMetrics, Layout, SvgOptions};
+
+        let screen = super::emulate(2, 10, b"a");
+        let metrics = FontMetrics {
+            units_per_em: 1000,
+            advance: 600.,
+            line_height: 1200.,
+            descent: 300.,
+        };
+
+        // the positional arguments are a shorthand for the options
+        let options = SvgOptions::default().fonts(&["Mono"]).font_metrics(metrics);
+        let svg = screen.to_svg_with_options(options).to_string();
+        assert_eq!(screen.to_svg(&["Mono"], metrics).to_string(), svg);
+        assert!(svg.contains("Mono"));
+        assert_ne!(svg, screen.to_svg(&[], FontMetrics::DEFAULT).to_string());
+
+        let mut written = Vec::new();
+        screen.write_svg(&mut written, options).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), svg);
+
+        let layout = Layout::horizontal([screen.clone(), screen.clone()]);
+        let mut written = Vec::new();
+        layout.write_svg(&mut written, options).unwrap();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            layout.to_svg_with_options(options).to_string()
+        );
+    }
+
+    #[test]
+    fn extra_css() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(2, 10, b"a");
+        let css = "a > b { content: \"]]>\"; }\n";
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().extra_css(Some(css)))
+            .to_string();
+        assert!(svg.contains("<![CDATA[\na > b { content: \"]]]]><![CDATA[>\"; }\n]]>\n</style>"));
+        assert_eq!(svg.matches("]]>").count(), 2);
+    }
+
+    #[test]
+    fn caption_and_badge() {
+        use super::{Badge, Corner, SvgOptions};
+
+        let screen = super::emulate(2, 10, b"a");
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                caption: Some("termsnap v0.5"),
+                badge: Some(Badge::Image {
+                    mime: "image/svg+xml",
+                    data: b"<svg/>",
+                }),
+                badge_corner: Corner::TopLeft,
+                ..SvgOptions::default()
+            })
+            .to_string();
+        let plain = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(svg.contains(">termsnap v0.5</text>"));
+        assert!(svg.contains(r#"href="data:image/svg+xml;base64,PHN2Zy8+""#));
+        assert!(svg.contains(r#"preserveAspectRatio="xMinYMin meet""#));
+        // the caption is drawn below the screen
+        let view_box = |svg: &str| svg.split('"').nth(1).unwrap().to_owned();
+        assert_ne!(view_box(&svg), view_box(&plain));
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                badge: Some(Badge::Text("v0.5 & up")),
+                ..SvgOptions::default()
+            })
+            .to_string();
+        assert!(svg.contains(r#"text-anchor="end""#));
+        assert!(svg.contains(">v0.5 &amp; up</text>"));
+    }
+
+    #[test]
+    fn exit_status() {
+        use super::{ExitStatus, SvgOptions};
+
+        let screen = super::emulate(2, 10, b"a");
+        for (status, text) in [
+            (ExitStatus::Code(0), ">✓ 0</text>"),
+            (ExitStatus::Code(3), ">✗ 3</text>"),
+            (ExitStatus::Signaled, ">✗ signal</text>"),
+        ] {
+            let svg = screen
+                .to_svg_with_options(SvgOptions {
+                    exit_status: Some(status),
+                    ..SvgOptions::default()
+                })
+                .to_string();
+            assert!(svg.contains(text));
+        }
+    }
+
+    #[test]
+    fn diagnostics() {
+        use std::sync::{Arc, Mutex};
+
+        use super::{Term, VoidPtyWriter};
+
+        let diagnostics = Arc::new(Mutex::new(Vec::new()));
+        let mut term = Term::new(2, 10, VoidPtyWriter);
+        term.set_diagnostics_sink({
+            let diagnostics = diagnostics.clone();
+            move |diagnostic| diagnostics.lock().unwrap().push(diagnostic.to_string())
+        });
+        term.process_bytes(
+            b"\x1b]777;notify;a;b\x07\x1b[?1049h\x1b[?9999h\x1b[31;1mx\x1bP+q544e\x1b\\\x1b[1 z\x1b]0;title\x07",
+        );
+        assert_eq!(
+            *diagnostics.lock().unwrap(),
+            ["OSC 777", "CSI ?9999h", "DCS +q", "CSI 1 z"]
+        );
+    }
+
+    #[test]
+    fn show_whitespace() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(2, 20, "a b\tc 漢字  ".as_bytes());
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                show_whitespace: true,
+                ..SvgOptions::default()
+            })
+            .to_string();
+        // the cells skipped by the tab, the spacers of wide characters and trailing spaces are
+        // not marked
+        assert_eq!(svg.matches('·').count(), 2);
+        assert_eq!(svg.matches('→').count(), 1);
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(!svg.contains('·'));
+    }
+
+    #[test]
+    fn exact_grid() {
+        use super::{FontMetrics, SvgOptions};
+
+        let screen = super::emulate(2, 20, "ab  c".as_bytes());
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                font_metrics: FontMetrics {
+                    units_per_em: 1000,
+                    advance: 500.,
+                    line_height: 1000.,
+                    descent: 0.,
+                },
+                exact_grid: true,
+                ..SvgOptions::default()
+            })
+            .to_string();
+        assert!(!svg.contains("textLength"));
+        assert!(svg.contains(
+            r#"<tspan x="0">a</tspan><tspan x="6">b &#160;</tspan><tspan x="24">c</tspan>"#
+        ));
+    }
+
+    #[test]
+    fn minimum_contrast() {
+        use crate::colors::contrast_ratio;
+
+        let screen = super::emulate(
+            1,
+            10,
+            b"\x1b[38;2;90;90;90;48;2;60;60;60ma\x1b[38;2;200;200;200;48;2;230;230;230mb\x1b[0mc",
+        );
+        let adjusted = screen.with_minimum_contrast(4.5);
+        for column in 0..2 {
+            let cell = &adjusted[(0, column)];
+            let ratio = contrast_ratio(cell.fg, cell.bg);
+            assert!((4.5..4.7).contains(&ratio), "{ratio}");
+        }
+        assert!(adjusted[(0, 1)].fg.r < 200);
+        // cells with sufficient contrast are left as they are
+        assert_eq!(adjusted[(0, 2)], screen[(0, 2)]);
+    }
+
+    #[test]
+    fn dynamic_colors() {
+        let screen = super::emulate(
+            2,
+            10,
+            b"\x1b]11;#102030\x07\x1b]4;1;rgb:ff/80/00\x07\x1b[31ma",
+        );
+        let cell = screen.get(0, 0).unwrap();
+        assert_eq!(cell.fg.to_string(), "#ff8000");
+        assert_eq!(cell.bg.to_string(), "#102030");
+
+        let screen = super::emulate(2, 10, b"\x1b]4;1;rgb:ff/80/00\x07\x1b]104;1\x07\x1b[31ma");
+        assert_eq!(screen.get(0, 0).unwrap().fg.to_string(), "#dc322f");
+    }
+
+    #[test]
+    fn background() {
+        // backgrounds from the color table and direct backgrounds of the same color are counted
+        // together
+        let screen = super::emulate(1, 10, b"\x1b[41m   \x1b[48;2;220;50;47m   \x1b[44m   ");
+        assert_eq!(screen.background().to_string(), "#dc322f");
+        assert_eq!(screen[(0, 0)], screen[(0, 3)]);
+
+        let screen = super::emulate(1, 10, b"\x1b[41m  \x1b[44m   \x1b[41m  ");
+        assert_eq!(screen.background().to_string(), "#dc322f");
+    }
+
+    #[test]
+    fn reverse_video() {
+        let normal = super::emulate(1, 10, b"a\x1b[7mb");
+        let reversed = super::emulate(1, 10, b"\x1b[?5ha\x1b[7mb");
+        for column in 0..2 {
+            assert_eq!(reversed[(0, column)].fg, normal[(0, column)].bg);
+            assert_eq!(reversed[(0, column)].bg, normal[(0, column)].fg);
+        }
+
+        let screen = super::emulate(1, 10, b"\x1b[?5ha\x1b[?5l");
+        assert_eq!(screen[(0, 0)], normal[(0, 0)]);
+    }
+
+    #[test]
+    fn line_sizes() {
+        use super::LineSize;
+
+        // line sizes move with their lines as the screen scrolls, and are cleared with the screen
+        let screen = super::emulate(2, 20, b"\x1b#3big\r\n\x1b#4big\r\n\x1b#6wide");
+        assert_eq!(screen.line_size(0), LineSize::DoubleHeightBottom);
+        assert_eq!(screen.line_size(1), LineSize::DoubleWidth);
+        let screen = super::emulate(2, 20, b"\x1b#6wide\x1b[2J");
+        assert_eq!(screen.line_size(0), LineSize::Single);
+
+        let screen = super::emulate(2, 20, b"\x1b#6wide\r\nnarrow");
+        let svg = screen.to_svg(&[], super::FontMetrics::DEFAULT).to_string();
+        assert_eq!(svg.matches(">wide<").count(), 1);
+        assert!(svg.contains("scale(2 1)"));
+        assert_eq!(screen.crop(1.., ..).line_size(0), LineSize::Single);
+
+        // erasing a line resets its size
+        let screen = super::emulate(2, 20, b"\x1b#6wide\x1b[2K");
+        assert_eq!(screen.line_size(0), LineSize::Single);
+        let screen = super::emulate(2, 20, b"\x1b#6wide\r\n\x1b#6wide\x1b[H\x1b[J");
+        assert_eq!(screen.line_size(0), LineSize::DoubleWidth);
+        assert_eq!(screen.line_size(1), LineSize::Single);
+    }
+
+    #[test]
+    fn line_sizes_scroll() {
+        use super::{LineSize, Term, VoidPtyWriter};
+
+        // lines dropped from a full scrollback history take their sizes along
+        for scrollback in [0, 1] {
+            let mut term = Term::builder()
+                .lines(3)
+                .columns(10)
+                .scrollback(scrollback)
+                .build(VoidPtyWriter);
+            term.process_bytes(b"\x1b#6wide\r\nb\r\nc\r\nd\r\ne");
+            let screen = term.current_screen();
+            assert_eq!(screen.text(), "c\nd\ne");
+            assert!((0..3).all(|line| screen.line_size(line) == LineSize::Single));
+            assert_eq!(term.scrollback_screen().line_size(0), LineSize::Single);
+        }
+
+        // within a scrolling region, sizes move with their lines and the lines below stay
+        let screen = super::emulate(
+            4,
+            10,
+            b"\x1b[1;3r\x1b[2H\x1b#6b\x1b[4H\x1b#3d\x1b[3H\r\n\r\n",
+        );
+        assert_eq!(screen.line_size(0), LineSize::Single);
+        assert_eq!(screen.line_size(1), LineSize::Single);
+        assert_eq!(screen.line_size(3), LineSize::DoubleHeightTop);
+        let screen = super::emulate(4, 10, b"\x1b[2;4r\x1b[2H\x1b#6b\x1b[3H\x1b[M");
+        assert_eq!(screen.line_size(1), LineSize::DoubleWidth);
+        let screen = super::emulate(4, 10, b"\x1b[2;4r\x1b[2H\x1b#6b\x1b[2H\x1b[L");
+        assert_eq!(screen.line_size(1), LineSize::Single);
+        assert_eq!(screen.line_size(2), LineSize::DoubleWidth);
+    }
+
+    #[test]
+    fn hyperlinks() {
+        use super::SvgOptions;
+
+        // a link wrapped over two lines, a link without id, and a link with the same URI but
+        // another id
+        let screen = super::emulate(
+            3,
+            10,
+            b"\x1b]8;id=a;https://a.example\x1b\\one two three\x1b]8;;\x1b\\\r\n\
+              \x1b]8;;https://b.example\x1b\\b\x1b]8;;\x1b\\ \
+              \x1b]8;id=c;https://b.example\x1b\\c\x1b]8;;\x1b\\",
+        );
+        let link = screen[(0, 0)].hyperlink.as_ref().unwrap();
+        assert_eq!((link.id(), link.uri()), ("a", "https://a.example"));
+        assert_eq!(screen[(1, 2)].hyperlink.as_ref(), Some(link));
+        assert_eq!(screen[(1, 3)].hyperlink, None);
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions {
+                hyperlinks: true,
+                ..SvgOptions::default()
+            })
+            .to_string();
+        // the text of each line of a link is wrapped in a link
+        assert_eq!(svg.matches(r#"<a href="https://a.example">"#).count(), 2);
+        assert!(svg.contains(r#"<a href="https://a.example"><text x="0" y="#));
+        assert_eq!(svg.matches(r#"<a href="https://b.example">"#).count(), 2);
+        assert!(!svg.contains("fill: transparent"));
+
+        // links split runs of backgrounds, which are wrapped in links; links with unsafe schemes
+        // are not clickable
+        let screen = super::emulate(
+            1,
+            20,
+            b"\x1b[41m\x1b]8;;https://a.example\x1b\\ab\x1b]8;;\x1b\\cd\
+              \x1b]8;;javascript:alert(1)\x1b\\ef\x1b]8;;\x1b\\",
+        );
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().hyperlinks(true))
+            .to_string();
+        assert!(svg.contains(r#"<a href="https://a.example"><rect x="0" y="0" width="#));
+        assert_eq!(svg.matches(r#"<a href="https://a.example">"#).count(), 2);
+        assert_eq!(svg.matches("<a ").count(), 2);
+        assert!(!svg.contains("javascript"));
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(!svg.contains("<a "));
+    }
+
+    #[test]
+    fn debug_cells() {
+        let screen = super::emulate(1, 8, "a\x1b[1;4:3;31mé\x1b[0m b".as_bytes());
+        let svg = screen
+            .to_svg_with_options(super::SvgOptions::default().debug_cells(true))
+            .to_string();
+        assert!(svg.contains(
+            "<g><title>0:1-0:2\n0:1 U+00E9 fg #dc322f bg #002b36 bold underline Curly</title><text"
+        ));
+        assert!(svg.contains("<g><title>0:3-0:4\n0:3 U+0062 fg #839496 bg #002b36</title><text"));
+    }
+
+    #[test]
+    fn grid() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(2, 12, b"grid");
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().grid(true))
+            .to_string();
+        assert!(svg.contains("<path d=\"M0 0v28.800001M7.2000003 0v28.800001"));
+        assert_eq!(svg.matches("h86.4").count(), 3);
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().rulers(true).padding(4.))
+            .to_string();
+        assert!(svg.contains(r#"<g class="screen" transform="translate(24 24)">"#));
+        assert!(svg.contains(r#"<text x="73" y="-8">10</text>"#));
+        assert!(svg.contains(r#"<text x="-3" y="25.2" text-anchor="end">1</text>"#));
+        assert!(!svg.contains(">2</text>"));
+    }
+
+    #[test]
+    fn scale() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(2, 10, b"scale");
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(!svg.lines().next().unwrap().contains("width="));
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().scale(Some(2.)))
+            .to_string();
+        assert!(svg.starts_with(
+            r#"<svg viewBox="0 0 72 28.800001" xmlns="http://www.w3.org/2000/svg" width="144" height="57.600002""#
+        ));
+    }
+
+    #[test]
+    fn decoration_lines() {
+        use super::SvgOptions;
+
+        let screen = super::emulate(1, 12, b"\x1b[4mab\x1b[9mcd\x1b[24mef");
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default())
+            .to_string();
+        assert!(svg.contains("text-decoration: underline line-through;"));
+        assert!(!svg.contains("<line"));
+
+        let svg = screen
+            .to_svg_with_options(SvgOptions::default().decoration_lines(true))
+            .to_string();
+        assert!(!svg.contains("text-decoration"));
+        assert!(svg.contains(r#"<line x1="0" y1="12" x2="28.800001" y2="12""#));
+        assert!(svg.contains(r#"<line x1="14.400001" y1="7.2" x2="43.2" y2="7.2""#));
+    }
+
+    #[test]
+    fn runs() {
+        use super::{BackgroundRegion, Rgb};
+
+        let screen = super::emulate(
+            3,
+            8,
+            "\x1b[45m字x\x1b[0m \x1b[9m█\r\n\x1b[45m\x1b[8mhid\x1b[0m".as_bytes(),
+        );
+        let runs: Vec<_> = screen
+            .text_runs()
+            .into_iter()
+            .map(|run| {
+                (
+                    run.line,
+                    run.column,
+                    run.columns,
+                    run.text,
+                    run.strikethrough,
+                )
+            })
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                (0, 0, 3, "字 x".to_owned(), false),
+                (0, 4, 1, "█".to_owned(), true)
+            ]
+        );
+
+        let magenta = Rgb {
+            r: 0xd3,
+            g: 0x36,
+            b: 0x82,
+        };
+        assert_eq!(
+            screen.background_regions(),
+            [BackgroundRegion {
+                line: 0,
+                column: 0,
+                lines: 2,
+                columns: 3,
+                color: magenta,
+            },]
+        );
+    }
+
+    #[test]
+    fn term_size() {
+        use super::{Term, TermSizeError, VoidPtyWriter};
+
+        assert_eq!(
+            Term::try_new(0, 80, VoidPtyWriter).err(),
+            Some(TermSizeError {
+                lines: 0,
+                columns: 80
+            })
+        );
+        assert!(Term::try_new(u16::MAX, u16::MAX, VoidPtyWriter).is_err());
+
+        let mut term = Term::try_new(2, 10, VoidPtyWriter).unwrap();
+        assert!(term.try_resize(24, 0).is_err());
+        assert!(term.try_resize(4096, 4096).is_err());
+        assert_eq!(term.current_screen().columns(), 10);
+        assert!(term.try_resize(4, 20).is_ok());
+        assert_eq!(term.current_screen().columns(), 20);
+    }
+
+    #[test]
+    fn scrollback_limit() {
+        use super::{Term, TermSizeError, VoidPtyWriter};
+
+        let output: Vec<String> = (1..=70_000).map(|line| line.to_string()).collect();
+        let output = output.join("\r\n");
+
+        // the oldest lines are left out to stay within `u16::MAX` lines
+        let mut term = Term::builder()
+            .lines(2)
+            .columns(10)
+            .scrollback(100_000)
+            .build(VoidPtyWriter);
+        term.process_bytes(output.as_bytes());
+        let screen = term.scrollback_screen();
+        assert_eq!(screen.lines(), u16::MAX);
+        let text = screen.text();
+        assert_eq!(text.lines().next(), Some("4466"));
+        assert_eq!(text.lines().last(), Some("70000"));
+        assert_eq!(
+            screen.cursor().map(|cursor| cursor.line),
+            Some(u16::MAX - 1)
+        );
+
+        // and within `TermSizeError::MAX_CELLS` cells
+        let mut term = Term::builder()
+            .lines(2)
+            .columns(1000)
+            .scrollback(100_000)
+            .build(VoidPtyWriter);
+        term.process_bytes(output.as_bytes());
+        let screen = term.scrollback_screen();
+        assert_eq!(usize::from(screen.lines()), TermSizeError::MAX_CELLS / 1000);
+        assert_eq!(screen.text().lines().last(), Some("70000"));
+    }
+
+    #[test]
+    fn damage() {
+        use super::{Damage, Term, VoidPtyWriter};
+
+        let mut term = Term::new(3, 10, VoidPtyWriter);
+        assert_eq!(term.take_damage(), Damage::Full);
+        let mut screen = term.current_screen();
+
+        // only the changed line and the cursor are damaged
+        term.process_bytes(b"\r\nhi");
+        let damage = term.take_damage();
+        let Damage::Lines(lines) = &damage else {
+            panic!("expected partial damage");
+        };
+        assert!(lines.iter().all(|damage| damage.line <= 1));
+        screen.apply_damage(&term, &damage);
+        assert_eq!(screen, term.current_screen());
+
+        let steps: [&[u8]; 8] = [
+            b"hello",
+            b"\x1b[31m\rworld",
+            b"\r\none\r\ntwo\r\nthree",
+            b"\x1b[2;3H\x1b[K",
+            b"\x1b]11;#102030\x07",
+            b"\x1b[?5h",
+            b"\x1b[?1049hfull screen",
+            b"\x1b[?1049l",
+        ];
+        for bytes in steps {
+            term.process_bytes(bytes);
+            let damage = term.take_damage();
+            screen.apply_damage(&term, &damage);
+            assert_eq!(screen, term.current_screen());
+        }
+
+        term.resize(4, 12);
+        let damage = term.take_damage();
+        screen.apply_damage(&term, &damage);
+        assert_eq!(screen, term.current_screen());
+    }
+
+    #[test]
+    fn snapshot_into() {
+        use super::{Term, VoidPtyWriter};
+
+        let mut term = Term::new(3, 10, VoidPtyWriter);
+        let mut screen = term.current_screen();
+
+        let steps: [&[u8]; 5] = [
+            b"\x1b]0;title\x07\x1b[41mhello\r\nworld",
+            b"\x1b[?1049h\x1b[32mfull screen",
+            b"\x1b]11;#102030\x07\x1b[?5h",
+            b"\x1b[?1049l\x1b[?5l",
+            b"\x1b]8;;https://example.com\x07link\x1b]8;;\x07",
+        ];
+        let sizes = [(3, 10), (5, 4), (1, 1), (8, 20), (2, 30)];
+        for (bytes, (lines, columns)) in steps.into_iter().zip(sizes) {
+            term.process_bytes(bytes);
+            term.snapshot_into(&mut screen);
+            assert_eq!(screen, term.current_screen());
+
+            // shrinking and growing the screen
+            term.resize(lines, columns);
+            term.snapshot_into(&mut screen);
+            assert_eq!(screen, term.current_screen());
+        }
+    }
+
+    #[test]
+    fn csv() {
+        use super::csv::quote;
+
+        assert_eq!(quote("a", ','), "a");
+        assert_eq!(quote("a,b", ','), "\"a,b\"");
+        assert_eq!(quote("a,b", '\t'), "a,b");
+        assert_eq!(quote("a\tb", '\t'), "\"a\tb\"");
+        assert_eq!(quote("say \"hi\"", '\t'), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote("a\nb", ','), "\"a\nb\"");
+        assert_eq!(quote("a\rb", '\t'), "\"a\rb\"");
+
+        // the header is followed by one row per cell, with its attributes separated by spaces; the
+        // cell of a tab holds the tab character
+        let screen = super::emulate(2, 2, b"\"\x1b[1;3;4m,\r\n\x1b[0m\t");
+        assert_eq!(
+            screen.to_csv(),
+            "line,column,char,fg,bg,flags
+0,0,\"\"\"\",#839496,#002b36,
+0,1,\",\",#839496,#002b36,bold italic underline
+1,0,\t,#839496,#002b36,
+1,1, ,#839496,#002b36,
+"
+        );
+        assert_eq!(
+            screen.to_tsv(),
+            "line\tcolumn\tchar\tfg\tbg\tflags
+0\t0\t\"\"\"\"\t#839496\t#002b36\t
+0\t1\t,\t#839496\t#002b36\tbold italic underline
+1\t0\t\"\t\"\t#839496\t#002b36\t
+1\t1\t \t#839496\t#002b36\t
+"
+        );
+    }
+
+    #[test]
+    fn stats() {
+        use super::ScreenStats;
+
+        let screen = super::emulate(
+            2,
+            10,
+            b"a\x1b[1;31mb\x1b[2;3;4:3;58;2;0;255;0mc\x1b[0;9;44md\x1b[0m\r\n\x1b[8me f\x1b[0m ",
+        );
+        assert_eq!(
+            screen.stats(),
+            ScreenStats {
+                // the default foreground and red, the default background and blue, and the green
+                // underline
+                colors: 5,
+                foreground_colors: 2,
+                background_colors: 2,
+                non_blank_cells: 6,
+                widest_line: 4,
+                bold_cells: 2,
+                dim_cells: 1,
+                italic_cells: 1,
+                underlined_cells: 1,
+                strikethrough_cells: 1,
+                hidden_cells: 3,
+            }
+        );
+
+        // the colors of hidden text are not drawn
+        let screen = super::emulate(1, 10, b"\x1b[8;32mhidden");
+        assert_eq!(screen.stats().foreground_colors, 0);
+        assert_eq!(screen.stats().colors, 1);
+    }
+
+    #[test]
+    fn backgrounds() {
+        use super::FontMetrics;
+
+        let rects = |svg: &str| -> Vec<String> {
+            svg.lines()
+                .filter(|line| line.starts_with("<rect"))
+                .map(ToOwned::to_owned)
+                .collect()
+        };
+
+        // runs spanning the same columns are coalesced over lines
+        let screen = super::emulate(
+            4,
+            10,
+            b"\x1b[44m1 \x1b[0mab\r\n\x1b[44m2 \x1b[0m\r\n\x1b[44m3 \x1b[0mabc",
+        );
+        let svg = screen.to_svg(&[], FontMetrics::DEFAULT).to_string();
+        let gutter = rects(&svg);
+        assert_eq!(gutter.len(), 2);
+        assert!(gutter[1].contains(r#"y="0" width="14.400001" height="43.2""#));
+
+        // cells of the last column are not drawn in the color of the line above
+        let screen = super::emulate(4, 2, b"\x1b[44ma\x1b[45mb\r\n\x1b[45mc\x1b[44md");
+        let svg = screen.to_svg(&[], FontMetrics::DEFAULT).to_string();
+        assert_eq!(rects(&svg).len(), 5);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel() {
+        use super::FontMetrics;
+
+        let mut bytes = Vec::new();
+        for line in 0..1000 {
+            bytes.extend(format!("\x1b[4{}mline-{line}\x1b[0m\r\n", line % 8).into_bytes());
+        }
+        let screen = super::emulate(1001, 20, &bytes);
+        let svg = screen.to_svg(&[], FontMetrics::DEFAULT).to_string();
+
+        // the sections are written in order, with all backgrounds before the text
+        let positions: Vec<_> = (0..1000)
+            .map(|line| svg.find(&format!(">line-{line}<")).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert!(svg.rfind("<rect").unwrap() < positions[0]);
+    }
+
+    #[test]
+    fn snapshot_signal() {
+        use super::{AnsiSignal, Term, VoidPtyWriter};
+
+        let mut term = Term::new(2, 10, VoidPtyWriter);
+        let mut snapshots = Vec::new();
+        term.process_bytes_with_callback(
+            b"a\x1b]7979;snapshot\x1b\\b\x1b]7979;snapshot;menu\x07",
+            |term, signal| {
+                if let AnsiSignal::Snapshot { name } = signal {
+                    snapshots.push((term.current_screen().text(), name));
                 }
-                _ => {
-                    let idx = screen.idx(line, column);
-                    assert_eq!(screen.cells[idx].c, c, "failed at {line}x{column}");
-                    column += 1;
+            },
+        );
+        assert_eq!(
+            snapshots,
+            [
+                ("a".to_owned(), None),
+                ("ab".to_owned(), Some("menu".to_owned()))
+            ]
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        let screen = super::emulate(2, 8, b"ab\x1b[1;31mcd\x1b[0m\r\nef");
+        let default = "fg=#839496 bg=#002b36";
+        assert_eq!(
+            screen.to_snapshot(),
+            format!(
+                "size: 2x8\ntext:\n  |abcd\n  |ef\nstyle: {default}\n  0:2-0:4 fg=#dc322f bg=#002b36 bold\n"
+            )
+        );
+    }
+
+    #[test]
+    fn to_ansi() {
+        let screen = super::emulate(2, 8, b"ab\x1b[1;4mc\r\n\x1b[0;44m d \x1b[0mx");
+        let ansi = screen.to_ansi().to_string();
+        let fg = "38;2;131;148;150";
+        let bg = "48;2;0;43;54";
+        assert_eq!(
+            ansi,
+            format!(
+                "\x1b[0;{fg};{bg}mab\x1b[0;1;4;{fg};{bg}mc\x1b[0;{fg};{bg}m\x1b[K\x1b[0m\n\
+                 \x1b[0;{fg};48;2;38;139;210m d \x1b[0;{fg};{bg}mx\x1b[0;{fg};{bg}m\x1b[K\x1b[0m\n"
+            )
+        );
+
+        // replaying the output reproduces the screen, with a terminal translating newlines
+        let replayed = super::emulate(3, 8, ansi.replace('\n', "\r\n").as_bytes());
+        assert!(screen.cells().eq(replayed.crop(..2, ..).cells()));
+    }
+
+    #[test]
+    fn index() {
+        let screen = super::emulate(2, 4, b"ab\r\n\x1b[31mcd");
+        assert_eq!(screen[(1, 1)].c, 'd');
+        assert_eq!(screen.get(0, 4), None);
+
+        let red: Vec<_> = screen
+            .cells_indexed()
+            .filter(|(_, _, cell)| cell.fg != screen[(0, 0)].fg)
+            .map(|(line, column, cell)| (line, column, cell.c))
+            .collect();
+        assert_eq!(red, [(1, 0, 'c'), (1, 1, 'd')]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds() {
+        let screen = super::emulate(2, 4, b"");
+        let _ = screen[(0, 4)];
+    }
+
+    #[test]
+    fn zerowidth() {
+        use super::FontMetrics;
+
+        let screen = super::emulate(2, 10, "e\u{301}x".as_bytes());
+        assert_eq!(screen[(0, 0)].zerowidth[..], ['\u{301}']);
+        assert_eq!(screen[(0, 1)].c, 'x');
+        assert_eq!(screen.text(), "e\u{301}x");
+
+        // the combining character is part of the text without taking up a cell
+        let svg = screen.to_svg(&[], FontMetrics::DEFAULT).to_string();
+        assert!(svg.contains(">e\u{301}x</text>"));
+        let plain = super::emulate(2, 10, b"ex");
+        assert_eq!(
+            svg.replace('\u{301}', ""),
+            plain.to_svg(&[], FontMetrics::DEFAULT).to_string()
+        );
+    }
+
+    #[test]
+    fn reset() {
+        use super::{Buffer, ColorName, Term, VoidPtyWriter};
+
+        let mut term = Term::builder().lines(2).columns(10).build(VoidPtyWriter);
+        term.process_bytes(b"\x1b]2;title\x07\x1b]11;#102030\x07a\r\nb\r\nc\x1b[?1049h\x1b[Halt");
+        assert_eq!(
+            term.colors().get(ColorName::Background).to_string(),
+            "#102030"
+        );
+        assert_eq!(term.screen(Buffer::Main).unwrap().text(), "b\nc");
+        assert_eq!(term.screen(Buffer::Alternate).unwrap().text(), "alt");
+
+        term.reset();
+        assert!(!term.is_alternate_screen());
+        assert!(term.screen(Buffer::Alternate).is_none());
+        assert_eq!(term.title(), None);
+        assert_eq!(term.scrollback_screen().lines(), 0);
+        assert_eq!(
+            term.colors().get(ColorName::Background).to_string(),
+            "#002b36"
+        );
+
+        term.process_bytes(b"new");
+        assert_eq!(term.current_screen().text(), "new");
+    }
+
+    #[test]
+    fn signals() {
+        use super::{AnsiSignal, Term, VoidPtyWriter};
+
+        let mut term = Term::new(2, 10, VoidPtyWriter);
+        let mut signals = Vec::new();
+        term.process_bytes_with_callback(
+            b"\x07\x1b]2;title\x07\x1b[?25la\nb\nc\x1b[2S\x1b[8;5;0t",
+            |_, signal| {
+                signals.push(match signal {
+                    AnsiSignal::Bell => "bell".to_owned(),
+                    AnsiSignal::TitleChanged { title } => format!("title {title:?}"),
+                    AnsiSignal::CursorVisibilityChanged { visible } => format!("cursor {visible}"),
+                    AnsiSignal::ScrollUp { lines } => format!("up {lines}"),
+                    AnsiSignal::ScrollDown { lines } => format!("down {lines}"),
+                    AnsiSignal::ResizeRequest { lines, columns } => {
+                        format!("resize {lines}x{columns}")
+                    }
+                    _ => "other".to_owned(),
+                })
+            },
+        );
+        assert_eq!(
+            signals,
+            [
+                "bell",
+                "title Some(\"title\")",
+                "cursor false",
+                "up 1",
+                "up 2",
+                "resize 5x10"
+            ]
+        );
+    }
+
+    #[cfg(feature = "session")]
+    #[test]
+    fn session() {
+        use super::session::{Command, Session};
+
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", "read line; echo \"$line $GREETING\"; exit 3"])
+            .env("GREETING", "world")
+            .size(4, 20);
+        let mut session = Session::spawn(&command).unwrap();
+        session.write_input(b"hello\r");
+        let exited = session
+            .wait_for(std::time::Duration::from_secs(10))
+            .unwrap();
+        assert!(exited);
+        assert_eq!(session.exit_code(), Some(3));
+        assert_eq!(session.screen().text(), "hello\nhello world");
+    }
+
+    #[cfg(feature = "session")]
+    #[test]
+    fn session_hooks() {
+        use super::{
+            session::{Command, Hooks, Session},
+            PtyWriter, Term,
+        };
+        use std::ops::ControlFlow;
+
+        /// Types a line once, and stops when the program echoes it.
+        struct Typist {
+            typed: bool,
+            outputs: usize,
+        }
+
+        impl Hooks for Typist {
+            type Error = std::io::Error;
+
+            fn tick(
+                &mut self,
+                session: &mut Session,
+            ) -> std::io::Result<ControlFlow<(), Option<std::time::Instant>>> {
+                if !self.typed {
+                    session.write_input(b"typed\r");
+                    self.typed = true;
                 }
+                Ok(ControlFlow::Continue(None))
+            }
+
+            fn output(
+                &mut self,
+                term: &mut Term<impl PtyWriter>,
+                _output: &[u8],
+            ) -> std::io::Result<ControlFlow<()>> {
+                self.outputs += 1;
+                Ok(if term.current_screen().text().contains("got typed") {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                })
             }
         }
+
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", "read line; echo \"got $line\"; sleep 10"])
+            .size(4, 20);
+        let mut session = Session::spawn(&command).unwrap();
+        let mut hooks = Typist {
+            typed: false,
+            outputs: 0,
+        };
+        let exited = session.run(&mut hooks).unwrap();
+        assert!(!exited);
+        assert!(hooks.outputs > 0);
+        assert_eq!(session.screen().text(), "typed\ngot typed");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn capture_async() {
+        use super::{
+            session::{capture, CaptureOptions, Command},
+            Term, VoidPtyWriter,
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut command = Command::new("sh");
+            command
+                .args(["-c", "read line; echo \"got $line\""])
+                .size(4, 20);
+            let options = CaptureOptions {
+                input: b"input\r".to_vec(),
+                timeout: Some(std::time::Duration::from_secs(10)),
+                ..CaptureOptions::default()
+            };
+            let screen = capture(&command, &options).await.unwrap();
+            assert_eq!(screen.text(), "input\ngot input");
+
+            let mut term = Term::new(2, 10, VoidPtyWriter);
+            term.process_async_read(&b"async\x1b[1m read"[..])
+                .await
+                .unwrap();
+            assert_eq!(term.current_screen().text(), "async read");
+        });
     }
 }
@@ -0,0 +1,220 @@
+//! Rendering of text as path outlines of the glyphs of a font, rather than as SVG text. This makes
+//! the SVG render identically everywhere, regardless of the fonts installed, at the cost of larger
+//! output and text no longer being selectable.
+
+use std::fmt::{Display, Write};
+
+use skrifa::{
+    instance::{LocationRef, Size},
+    outline::{DrawSettings, OutlinePen},
+    string::StringId,
+    FontRef, MetadataProvider,
+};
+
+use crate::{
+    svg::{fmt_strikethrough, fmt_underline, CalculatedFontMetrics, FONT_SIZE_PX},
+    FontMetrics, Rgb, Underline,
+};
+
+/// The horizontal shear applied to glyphs of italic text without an italic font.
+const ITALIC_SHEAR: f32 = 0.2;
+
+/// A font used to draw text as path outlines. See
+/// [SvgOptions::text_as_paths](crate::SvgOptions::text_as_paths).
+#[derive(Clone)]
+pub struct OutlineFont {
+    data: Vec<u8>,
+    index: u32,
+}
+
+impl std::fmt::Debug for OutlineFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutlineFont")
+            .field("data", &format_args!("[{} bytes]", self.data.len()))
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// An error returned when the data passed to [OutlineFont::new] is not a valid font.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidFontError;
+
+impl Display for InvalidFontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("expected an OpenType or TrueType font")
+    }
+}
+
+impl std::error::Error for InvalidFontError {}
+
+impl OutlineFont {
+    /// Load a font from the contents of a font file. For font collections, the first font is
+    /// used.
+    pub fn new(data: Vec<u8>) -> Result<Self, InvalidFontError> {
+        Self::from_index(data, 0)
+    }
+
+    /// Load the font at `index` from the contents of a font collection file.
+    pub fn from_index(data: Vec<u8>, index: u32) -> Result<Self, InvalidFontError> {
+        FontRef::from_index(&data, index).map_err(|_| InvalidFontError)?;
+        Ok(OutlineFont { data, index })
+    }
+
+    fn font_ref(&self) -> FontRef<'_> {
+        FontRef::from_index(&self.data, self.index).expect("font was validated on construction")
+    }
+
+    /// Get the metrics of the font, for laying out the terminal cells to match the font. The
+    /// advance is taken from the glyph for `0`.
+    pub fn metrics(&self) -> FontMetrics {
+        let font = self.font_ref();
+        let metrics = font.metrics(Size::unscaled(), LocationRef::default());
+        let advance = font
+            .charmap()
+            .map('0')
+            .and_then(|glyph| {
+                font.glyph_metrics(Size::unscaled(), LocationRef::default())
+                    .advance_width(glyph)
+            })
+            .or(metrics.average_width)
+            .unwrap_or(f32::from(metrics.units_per_em) * 0.6);
+
+        FontMetrics {
+            units_per_em: metrics.units_per_em,
+            advance,
+            line_height: metrics.ascent - metrics.descent + metrics.leading,
+            descent: -metrics.descent,
+        }
+    }
+
+    /// Get the font's family name, e.g., "JetBrains Mono", for referring to the font in CSS. The
+    /// typographic family name is preferred over the legacy family name, which may include the
+    /// style for fonts with more than four styles.
+    pub fn family_name(&self) -> Option<String> {
+        let font = self.font_ref();
+        [StringId::TYPOGRAPHIC_FAMILY_NAME, StringId::FAMILY_NAME]
+            .into_iter()
+            .find_map(|id| font.localized_strings(id).english_or_first())
+            .map(|name| name.to_string())
+    }
+}
+
+/// Writes the commands of a path to an SVG path's data, translating glyph coordinates (with the
+/// y-axis pointing up) to SVG coordinates.
+struct SvgPen<'s> {
+    data: &'s mut String,
+    x: f32,
+    baseline: f32,
+    shear: f32,
+}
+
+impl SvgPen<'_> {
+    fn point(&mut self, x: f32, y: f32) {
+        let _ = write!(
+            self.data,
+            "{} {}",
+            self.x + x + y * self.shear,
+            self.baseline - y
+        );
+    }
+}
+
+impl OutlinePen for SvgPen<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.data.push('M');
+        self.point(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.data.push('L');
+        self.point(x, y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.data.push('Q');
+        self.point(cx0, cy0);
+        self.data.push(' ');
+        self.point(x, y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.data.push('C');
+        self.point(cx0, cy0);
+        self.data.push(' ');
+        self.point(cx1, cy1);
+        self.data.push(' ');
+        self.point(x, y);
+    }
+
+    fn close(&mut self) {
+        self.data.push('Z');
+    }
+}
+
+/// The style of a run of text drawn as outlines.
+pub(crate) struct OutlineStyle {
+    pub fg: Rgb,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+/// Write the characters `chars`, starting at cell (`x`, `y`), as a path of glyph outlines. Each
+/// character is placed at the start of its cell. Characters not in the font are skipped.
+pub(crate) fn fmt_text(
+    f: &mut std::fmt::Formatter<'_>,
+    font: &OutlineFont,
+    x: u16,
+    y: u16,
+    chars: &[char],
+    style: &OutlineStyle,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let font = font.font_ref();
+    let charmap = font.charmap();
+    let outlines = font.outline_glyphs();
+    let baseline = f32::from(y + 1) * font_metrics.line_height - font_metrics.descent;
+
+    let mut data = String::new();
+    for (idx, &c) in chars.iter().enumerate() {
+        let Some(glyph) = charmap.map(c).and_then(|glyph| outlines.get(glyph)) else {
+            continue;
+        };
+        let mut pen = SvgPen {
+            data: &mut data,
+            x: (f32::from(x) + idx as f32) * font_metrics.advance,
+            baseline,
+            shear: if style.italic { ITALIC_SHEAR } else { 0. },
+        };
+        let _ = glyph.draw(
+            DrawSettings::unhinted(Size::new(FONT_SIZE_PX), LocationRef::default()),
+            &mut pen,
+        );
+    }
+
+    let color = style.fg;
+    if !data.is_empty() {
+        write!(f, r#"<path d="{data}" style="fill: {color};"#)?;
+        if style.bold {
+            // emulate a bold font by stroking the outline
+            write!(f, " stroke: {color}; stroke-width: {};", FONT_SIZE_PX / 24.)?;
+        }
+        if style.dim {
+            f.write_str(" opacity: 0.6;")?;
+        }
+        f.write_str("\" />\n")?;
+    }
+
+    let end_x = x + chars.len() as u16 - 1;
+    if style.underline {
+        fmt_underline(f, x, end_x, y, Underline::Single, color, font_metrics)?;
+    }
+    if style.strikethrough {
+        fmt_strikethrough(f, x, end_x, y, color, font_metrics)?;
+    }
+
+    Ok(())
+}
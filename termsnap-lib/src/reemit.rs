@@ -0,0 +1,98 @@
+//! Re-emitting a screen as text with ANSI escape sequences, such that printing the text to a
+//! terminal reproduces the screen.
+
+use std::fmt::{Display, Write};
+
+use crate::{Cell, Screen, Underline};
+
+pub(crate) struct Ansi<'s> {
+    pub screen: &'s Screen,
+}
+
+/// Whether the cell draws nothing but its background.
+fn is_blank(cell: &Cell) -> bool {
+    cell.c == ' '
+        && cell.zerowidth.is_empty()
+        && cell.underline == Underline::None
+        && !cell.strikethrough
+}
+
+/// Write the select graphic rendition sequence setting the style of `cell`. The sequence resets
+/// all attributes first, such that it does not depend on the style previously set.
+fn write_sgr(f: &mut std::fmt::Formatter<'_>, cell: &Cell) -> std::fmt::Result {
+    f.write_str("\x1b[0")?;
+    for (set, code) in [
+        (cell.bold, "1"),
+        (cell.dim, "2"),
+        (cell.italic, "3"),
+        (cell.hidden, "8"),
+        (cell.strikethrough, "9"),
+    ] {
+        if set {
+            write!(f, ";{code}")?;
+        }
+    }
+    match cell.underline {
+        Underline::None => {}
+        Underline::Single => f.write_str(";4")?,
+        Underline::Double => f.write_str(";4:2")?,
+        Underline::Curly => f.write_str(";4:3")?,
+        Underline::Dotted => f.write_str(";4:4")?,
+        Underline::Dashed => f.write_str(";4:5")?,
+    }
+    write!(
+        f,
+        ";38;2;{};{};{};48;2;{};{};{}",
+        cell.fg.r, cell.fg.g, cell.fg.b, cell.bg.r, cell.bg.g, cell.bg.b
+    )?;
+    if let Some(color) = cell.underline_color {
+        write!(f, ";58;2;{};{};{}", color.r, color.g, color.b)?;
+    }
+    f.write_char('m')
+}
+
+impl Display for Ansi<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let screen = self.screen;
+
+        for line in 0..screen.lines() {
+            let row: Vec<&Cell> = (0..screen.columns())
+                .filter_map(|column| screen.get(line, column))
+                .collect();
+
+            // trailing blank cells sharing a background are cleared by erasing the rest of the
+            // line, rather than by writing spaces
+            let mut end = row.len();
+            if let Some(last) = row.last() {
+                while end > 0 && is_blank(row[end - 1]) && row[end - 1].bg == last.bg {
+                    end -= 1;
+                }
+            }
+
+            // the style is compared ignoring the cell's characters
+            let mut style = None;
+            for cell in &row[..end] {
+                let cell_style = Cell {
+                    c: ' ',
+                    zerowidth: Box::default(),
+                    hyperlink: None,
+                    ..(*cell).clone()
+                };
+                if style.as_ref() != Some(&cell_style) {
+                    write_sgr(f, cell)?;
+                    style = Some(cell_style);
+                }
+                for c in cell.chars() {
+                    f.write_char(c)?;
+                }
+            }
+            if let Some(blank) = row.get(end) {
+                write_sgr(f, blank)?;
+                f.write_str("\x1b[K")?;
+            }
+            f.write_str("\x1b[0m\n")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! The decomposition of terminal screens into background rectangles and runs of text, as drawn in
+//! SVGs, for rendering screens in other formats.
+
+use crate::{
+    colors,
+    svg::{self, SvgOptions, TextStyle},
+    Rgb, Screen,
+};
+
+/// A rectangle of cells with the same background color. See [Screen::background_regions].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackgroundRegion {
+    /// The first line of the rectangle.
+    pub line: u16,
+    /// The first column of the rectangle.
+    pub column: u16,
+    /// The number of lines of the rectangle.
+    pub lines: u16,
+    /// The number of columns of the rectangle.
+    pub columns: u16,
+    /// The background color of the cells.
+    pub color: Rgb,
+}
+
+/// A run of consecutive cells of a line with the same text style. See [Screen::text_runs].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextRun {
+    /// The line of the run.
+    pub line: u16,
+    /// The column of the first cell of the run.
+    pub column: u16,
+    /// The number of cells of the run. Cells of wide characters count double.
+    pub columns: u16,
+    /// The text of the cells, including zero-width characters. Wide characters are followed by a
+    /// space for their spacer cell.
+    pub text: String,
+    pub fg: Rgb,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    /// Whether the text has a plain underline in the foreground color. Other underlines do not
+    /// split runs; see [Cell::underline](crate::Cell::underline).
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+pub(crate) fn background_regions(screen: &Screen) -> Vec<BackgroundRegion> {
+    let mut regions = Vec::new();
+    let _ = svg::background_rects(
+        screen,
+        0..screen.lines(),
+        colors::most_common_color(screen),
+        false,
+        |x0, y0, x1, y1, color, _| {
+            regions.push(BackgroundRegion {
+                line: y0,
+                column: x0,
+                lines: y1 - y0 + 1,
+                columns: x1 - x0 + 1,
+                color,
+            });
+            Ok(())
+        },
+    );
+    regions
+}
+
+pub(crate) fn text_runs(screen: &Screen) -> Vec<TextRun> {
+    // characters drawn as geometry in SVGs are text to other renderers
+    let options = SvgOptions {
+        glyph_geometry: false,
+        ..SvgOptions::default()
+    };
+
+    let mut runs = Vec::new();
+    let _ = svg::text_runs(screen, 0..screen.lines(), &options, |x, y, text, style| {
+        let columns = text.trimmed_len() as u16;
+        if columns == 0 {
+            return Ok(());
+        }
+        let TextStyle {
+            fg,
+            bold,
+            dim,
+            italic,
+            underline,
+            strikethrough,
+            ..
+        } = *style;
+        runs.push(TextRun {
+            line: y,
+            column: x,
+            columns,
+            text: text.chars().iter().collect(),
+            fg,
+            bold,
+            dim,
+            italic,
+            underline,
+            strikethrough,
+        });
+        Ok(())
+    });
+    runs
+}
@@ -0,0 +1,52 @@
+//! Searching the text of terminal screens.
+
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::{Region, Screen};
+
+/// Find the regions of the cells of the matches in the text of each line of `screen`.
+/// `find_in_line` gets the text of a line, and returns the byte ranges of the matches. Empty
+/// matches are ignored. The spacer cells following wide characters are left out of the text, such
+/// that text containing wide characters can be found, but are included in the regions.
+pub(crate) fn find_in_lines(
+    screen: &Screen,
+    mut find_in_line: impl FnMut(&str) -> Vec<Range<usize>>,
+) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut text = String::new();
+    let mut starts = Vec::with_capacity(usize::from(screen.columns));
+    for (line, row) in (0..).zip(screen.rows()) {
+        text.clear();
+        starts.clear();
+        let mut prev_wide = false;
+        for cell in row {
+            starts.push(text.len());
+            if !prev_wide {
+                text.extend(cell.chars());
+            }
+            prev_wide = cell.c.width() == Some(2);
+        }
+
+        for m in find_in_line(&text) {
+            if m.is_empty() {
+                continue;
+            }
+            // the cells containing the first and last bytes of the match, and the spacer of a
+            // wide last character
+            let start_column = starts.partition_point(|&start| start <= m.start) - 1;
+            let mut end_column = starts.partition_point(|&start| start < m.end);
+            if end_column < row.len() && row[end_column - 1].c.width() == Some(2) {
+                end_column += 1;
+            }
+            regions.push(Region {
+                start_line: line,
+                start_column: start_column as u16,
+                end_line: line,
+                end_column: end_column as u16,
+            });
+        }
+    }
+    regions
+}
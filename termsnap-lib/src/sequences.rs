@@ -0,0 +1,288 @@
+//! Observation of escape sequences that alacritty's terminal emulator does not handle. The
+//! terminal data is fed through a second parser alongside alacritty's.
+
+use std::fmt::{Display, Write};
+
+use alacritty_terminal::vte::{Params, Perform};
+
+use crate::{iterm, sixel, LineSize};
+
+/// An escape sequence of interest.
+pub(crate) enum Observed {
+    /// A Sixel image was completed.
+    Sixel(sixel::SixelImage),
+    /// An image was sent using the iTerm2 inline image protocol.
+    InlineImage(iterm::InlineImage),
+    /// The scrollback history was cleared.
+    ClearHistory,
+    /// The terminal was reset to its initial state.
+    Reset,
+    /// A snapshot was requested through `OSC 7979 ; snapshot [; name] ST`.
+    Snapshot { name: Option<String> },
+    /// Resizing the terminal was requested through `CSI 8 ; lines ; columns t`. A size of 0
+    /// keeps the current size.
+    ResizeRequest { lines: u16, columns: u16 },
+    /// Screen-wide reverse video (DECSCNM) was set or reset through `CSI ? 5 h` or `CSI ? 5 l`.
+    ReverseVideo(bool),
+    /// The size of the characters of the cursor's line was set through `ESC # 3` to `ESC # 6`.
+    LineSize(LineSize),
+    /// An escape sequence neither alacritty nor Termsnap handles.
+    Unhandled(Diagnostic),
+}
+
+/// The kind of an escape sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SequenceKind {
+    /// An escape sequence introduced by ESC alone.
+    Esc,
+    /// A control sequence, introduced by `ESC [`.
+    Csi,
+    /// An operating system command, introduced by `ESC ]`.
+    Osc,
+    /// A device control string, introduced by `ESC P`.
+    Dcs,
+}
+
+/// An escape sequence the terminal emulator does not handle, which may explain why a screen looks
+/// different from how it looks in other terminals. See
+/// [Term::set_diagnostics_sink](crate::Term::set_diagnostics_sink).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: SequenceKind,
+    /// The sequence without its introducer, e.g., `CSI ?1234h`. The data of OSC and DCS sequences
+    /// is left out, as it may be large or sensitive: only the OSC number or the DCS intermediates
+    /// and final character are kept, e.g., `OSC 777` or `DCS +q`.
+    pub sequence: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.sequence)
+    }
+}
+
+/// The OSC numbers handled by alacritty or Termsnap.
+const HANDLED_OSCS: &[&[u8]] = &[
+    b"0",
+    b"1",
+    b"2",
+    b"4",
+    b"8",
+    b"10",
+    b"11",
+    b"12",
+    b"22",
+    b"50",
+    b"52",
+    b"104",
+    b"110",
+    b"111",
+    b"112",
+    b"1337",
+    SNAPSHOT_OSC,
+];
+/// The ANSI modes handled by alacritty.
+const HANDLED_MODES: &[u16] = &[4, 20];
+/// The DEC private modes handled by alacritty or Termsnap.
+const HANDLED_PRIVATE_MODES: &[u16] = &[
+    1, 3, 5, 6, 7, 12, 25, 1000, 1002, 1003, 1004, 1005, 1006, 1007, 1042, 1049, 2004, 2026,
+];
+
+/// Whether alacritty handles the control sequence.
+fn csi_handled(params: &Params, intermediates: &[u8], action: char) -> bool {
+    let modes_handled = |handled: &[u16]| {
+        params
+            .iter()
+            .all(|param| handled.contains(&param.first().copied().unwrap_or(0)))
+    };
+    match (action, intermediates) {
+        (
+            '@' | 'A' | 'B' | 'e' | 'b' | 'C' | 'a' | 'D' | 'd' | 'E' | 'F' | 'G' | '`' | 'g' | 'H'
+            | 'f' | 'I' | 'J' | 'K' | 'L' | 'M' | 'm' | 'n' | 'P' | 'r' | 'S' | 's' | 'T' | 't'
+            | 'u' | 'X' | 'Z',
+            [],
+        ) => true,
+        ('c', _) => true,
+        ('h' | 'l', []) => modes_handled(HANDLED_MODES),
+        ('h' | 'l', [b'?']) => modes_handled(HANDLED_PRIVATE_MODES),
+        ('m', [b'>' | b'?'])
+        | ('p', [b'$'] | [b'?', b'$'])
+        | ('q', [b' '])
+        | ('u', [b'?' | b'=' | b'>' | b'<']) => true,
+        _ => false,
+    }
+}
+
+/// Whether alacritty or Termsnap handles the escape sequence.
+fn esc_handled(intermediates: &[u8], byte: u8) -> bool {
+    matches!(
+        (byte, intermediates),
+        (b'B' | b'0', [b'(' | b')' | b'*' | b'+'])
+            | (
+                b'D' | b'E' | b'H' | b'M' | b'Z' | b'c' | b'7' | b'8' | b'=' | b'>' | b'\\',
+                []
+            )
+            | (b'3' | b'4' | b'5' | b'6' | b'8', [b'#'])
+    )
+}
+
+/// Describe a control sequence. Private markers are written before the parameters, other
+/// intermediates after.
+fn describe_csi(params: &Params, intermediates: &[u8], action: char) -> String {
+    let is_private = |byte: &&u8| matches!(byte, b'<' | b'=' | b'>' | b'?');
+    let mut sequence = String::from("CSI ");
+    sequence.extend(
+        intermediates
+            .iter()
+            .filter(is_private)
+            .map(|&b| char::from(b)),
+    );
+    for (idx, param) in params.iter().enumerate() {
+        if idx > 0 {
+            sequence.push(';');
+        }
+        for (idx, subparam) in param.iter().enumerate() {
+            if idx > 0 {
+                sequence.push(':');
+            }
+            let _ = write!(sequence, "{subparam}");
+        }
+    }
+    sequence.extend(
+        intermediates
+            .iter()
+            .filter(|byte| !is_private(byte))
+            .map(|&b| char::from(b)),
+    );
+    sequence.push(action);
+    sequence
+}
+
+/// The number of the private OSC requesting a snapshot.
+const SNAPSHOT_OSC: &[u8] = b"7979";
+
+#[derive(Default)]
+pub(crate) struct Observer {
+    sixel: Option<sixel::Decoder>,
+    /// The escape sequences observed since this was last emptied.
+    pub observed: Vec<Observed>,
+    /// Whether to observe unhandled escape sequences.
+    pub diagnostics: bool,
+    /// Whether an escape sequence may be in progress. This is set when an escape byte is fed,
+    /// and cleared once a sequence is dispatched and the parser is back in its ground state, in
+    /// which bytes other than escape never form observed sequences.
+    pub in_sequence: bool,
+}
+
+impl Observer {
+    fn unhandled(&mut self, kind: SequenceKind, sequence: String) {
+        self.observed
+            .push(Observed::Unhandled(Diagnostic { kind, sequence }));
+    }
+}
+
+impl Perform for Observer {
+    fn hook(&mut self, _params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if action == 'q' && intermediates.is_empty() && !ignore {
+            self.sixel = Some(sixel::Decoder::new());
+        } else if self.diagnostics {
+            let mut sequence = String::from("DCS ");
+            sequence.extend(intermediates.iter().map(|&b| char::from(b)));
+            sequence.push(action);
+            self.unhandled(SequenceKind::Dcs, sequence);
+        }
+    }
+
+    fn put(&mut self, byte: u8) {
+        if let Some(decoder) = self.sixel.as_mut() {
+            decoder.put(byte);
+        }
+    }
+
+    fn unhook(&mut self) {
+        if let Some(image) = self.sixel.take().and_then(sixel::Decoder::finish) {
+            self.observed.push(Observed::Sixel(image));
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
+        // a string terminated by ST leaves the parser in the escape state of the terminator
+        self.in_sequence = !bell_terminated;
+        if let Some(image) = iterm::InlineImage::parse(params) {
+            self.observed.push(Observed::InlineImage(image));
+        } else if let [SNAPSHOT_OSC, b"snapshot", name @ ..] = params {
+            let name = name
+                .first()
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned());
+            self.observed.push(Observed::Snapshot { name });
+        }
+
+        if self.diagnostics {
+            let number = params.first().copied().unwrap_or_default();
+            if !HANDLED_OSCS.contains(&number) {
+                let sequence = format!("OSC {}", String::from_utf8_lossy(number));
+                self.unhandled(SequenceKind::Osc, sequence.trim_end().to_owned());
+            }
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        self.in_sequence = false;
+        if self.diagnostics && (ignore || !csi_handled(params, intermediates, action)) {
+            let sequence = describe_csi(params, intermediates, action);
+            self.unhandled(SequenceKind::Csi, sequence);
+        }
+
+        if action == 'J' && intermediates.is_empty() && !ignore {
+            let mode = params
+                .iter()
+                .next()
+                .and_then(|param| param.first().copied());
+            if mode == Some(3) {
+                self.observed.push(Observed::ClearHistory);
+            }
+        } else if action == 't' && intermediates.is_empty() && !ignore {
+            let mut params = params
+                .iter()
+                .map(|param| param.first().copied().unwrap_or(0));
+            if let Some(8) = params.next() {
+                self.observed.push(Observed::ResizeRequest {
+                    lines: params.next().unwrap_or(0),
+                    columns: params.next().unwrap_or(0),
+                });
+            }
+        } else if matches!(action, 'h' | 'l')
+            && intermediates == b"?"
+            && !ignore
+            && params.iter().any(|param| param.first() == Some(&5))
+        {
+            self.observed.push(Observed::ReverseVideo(action == 'h'));
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], ignore: bool, byte: u8) {
+        self.in_sequence = false;
+        if byte == b'c' && intermediates.is_empty() && !ignore {
+            self.observed.push(Observed::Reset);
+        } else if intermediates == b"#" && !ignore {
+            let size = match byte {
+                b'3' => Some(LineSize::DoubleHeightTop),
+                b'4' => Some(LineSize::DoubleHeightBottom),
+                b'5' => Some(LineSize::Single),
+                b'6' => Some(LineSize::DoubleWidth),
+                _ => None,
+            };
+            if let Some(size) = size {
+                self.observed.push(Observed::LineSize(size));
+            }
+        }
+
+        if self.diagnostics && (ignore || !esc_handled(intermediates, byte)) {
+            let mut sequence = String::from("ESC ");
+            sequence.extend(intermediates.iter().map(|&b| char::from(b)));
+            sequence.push(char::from(byte));
+            self.unhandled(SequenceKind::Esc, sequence);
+        }
+    }
+}
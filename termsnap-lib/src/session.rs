@@ -0,0 +1,596 @@
+//! Running commands in a pseudoterminal and emulating their output, available with the `session`
+//! feature.
+//!
+//! ```rust,no_run
+//! use termsnap_lib::session::{Command, Session};
+//!
+//! let mut command = Command::new("ls");
+//! command.arg("--color=always").size(24, 80);
+//! let screen = Session::spawn(&command)?.wait_for_exit()?;
+//! println!("{}", screen.text());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{ErrorKind, Read, Write},
+    ops::ControlFlow,
+    os::fd::{AsFd, BorrowedFd},
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use alacritty_terminal::{
+    event::{OnResize, WindowSize},
+    tty::{ChildEvent, EventedPty, EventedReadWrite, Pty},
+};
+use rustix::event::{poll, PollFd, PollFlags};
+
+use crate::{AnsiSignal, Colors, PtyWriter, Screen, Term, TermSizeError};
+
+/// How long to block waiting for output before checking whether the child exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A command to run in a pseudoterminal, configured similarly to [std::process::Command].
+///
+/// The command's environment is inherited from the current process, with `LINES` and `COLUMNS`
+/// set to the terminal size and `TERM` set to `linux`. Variables set using [Command::env] take
+/// precedence.
+#[derive(Clone, Debug)]
+pub struct Command {
+    program: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    inherit_env: bool,
+    cwd: Option<PathBuf>,
+    lines: u16,
+    columns: u16,
+    colors: Colors,
+}
+
+impl Command {
+    /// Create a command running `program`, in a terminal of 24 lines by 80 columns.
+    pub fn new(program: impl Into<String>) -> Self {
+        Command {
+            program: program.into(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            inherit_env: true,
+            cwd: None,
+            lines: 24,
+            columns: 80,
+            colors: Colors::default(),
+        }
+    }
+
+    /// Add an argument to pass to the program.
+    pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Add multiple arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set an environment variable of the program.
+    pub fn env(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Do not inherit the environment of the current process. The program's environment then
+    /// consists of `LINES`, `COLUMNS`, `TERM` and the variables set using [Command::env].
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.inherit_env = false;
+        self
+    }
+
+    /// Set the working directory of the program. Defaults to the current working directory.
+    pub fn current_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.cwd = Some(dir.into());
+        self
+    }
+
+    /// Set the size of the terminal in lines and columns.
+    pub fn size(&mut self, lines: u16, columns: u16) -> &mut Self {
+        self.lines = lines;
+        self.columns = columns;
+        self
+    }
+
+    /// Set the color table of the emulated terminal.
+    pub fn colors(&mut self, colors: Colors) -> &mut Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Spawn the program in a new pseudoterminal without emulating its output, for driving the
+    /// pseudoterminal directly. The program is the leader of its own process group. See
+    /// [Session::spawn] for emulating the output.
+    pub fn spawn_pty(&self) -> std::io::Result<Pty> {
+        let mut env = HashMap::new();
+        env.insert("LINES".to_owned(), self.lines.to_string());
+        env.insert("COLUMNS".to_owned(), self.columns.to_string());
+        env.insert("TERM".to_owned(), "linux".to_owned());
+        env.extend(self.env.clone());
+
+        let (program, args, env) = if self.inherit_env {
+            (self.program.clone(), self.args.clone(), env)
+        } else {
+            // the pseudoterminal always inherits our environment, so have env(1) clear it
+            let mut env_args = vec!["-i".to_owned()];
+            env_args.extend(
+                env.into_iter()
+                    .map(|(name, value)| format!("{name}={value}")),
+            );
+            env_args.push(self.program.clone());
+            env_args.extend(self.args.iter().cloned());
+            ("env".to_owned(), env_args, HashMap::new())
+        };
+
+        alacritty_terminal::tty::new(
+            &alacritty_terminal::tty::Options {
+                shell: Some(alacritty_terminal::tty::Shell::new(program, args)),
+                working_directory: self.cwd.clone(),
+                hold: false,
+                env,
+            },
+            WindowSize {
+                num_lines: self.lines,
+                num_cols: self.columns,
+                cell_width: 1,
+                cell_height: 1,
+            },
+            0,
+        )
+    }
+}
+
+/// Queues the terminal's responses to status requests, to be written to the pseudoterminal.
+struct Responses(Arc<Mutex<VecDeque<String>>>);
+
+impl PtyWriter for Responses {
+    fn write(&mut self, text: String) {
+        let mut responses = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        // a program flooding the terminal with requests without reading the responses should not
+        // exhaust memory
+        if responses.len() < 128 {
+            responses.push_back(text);
+        }
+    }
+}
+
+/// Hooks into a [Session] run using [Session::run], for example to forward input to the program,
+/// take snapshots or record the output. All methods default to doing nothing.
+pub trait Hooks {
+    /// The error returned by the hooks.
+    type Error: From<std::io::Error>;
+
+    /// Called before each wait for the program, for example to queue input using
+    /// [Session::write_input]. Returns the time to wake up at the latest, or
+    /// [ControlFlow::Break] to stop running the session.
+    fn tick(
+        &mut self,
+        session: &mut Session,
+    ) -> Result<ControlFlow<(), Option<Instant>>, Self::Error> {
+        let _ = session;
+        Ok(ControlFlow::Continue(None))
+    }
+
+    /// A file descriptor to wait on for reading besides the pseudoterminal, for example the
+    /// standard input to forward to the program. [Hooks::readable] is called once it is readable.
+    fn input<'h>(&'h self, session: &Session) -> Option<BorrowedFd<'h>> {
+        let _ = session;
+        None
+    }
+
+    /// Called once the file descriptor given by [Hooks::input] is readable. Returns
+    /// [ControlFlow::Break] to stop running the session.
+    fn readable(&mut self, session: &mut Session) -> Result<ControlFlow<()>, Self::Error> {
+        let _ = session;
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Called with the signals of the program's output while it is processed. See
+    /// [Term::process_bytes_with_callback].
+    fn signal(&mut self, term: &Term<impl PtyWriter>, signal: AnsiSignal) {
+        let _ = (term, signal);
+    }
+
+    /// Called with the output of the program after it was processed by `term`. Returns
+    /// [ControlFlow::Break] to stop running the session.
+    fn output(
+        &mut self,
+        term: &mut Term<impl PtyWriter>,
+        output: &[u8],
+    ) -> Result<ControlFlow<()>, Self::Error> {
+        let _ = (term, output);
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// No hooks.
+impl Hooks for () {
+    type Error = std::io::Error;
+}
+
+/// A command running in a pseudoterminal, with its output emulated by a [Term].
+pub struct Session {
+    pty: Pty,
+    term: Term<Responses>,
+    responses: Arc<Mutex<VecDeque<String>>>,
+    /// Whether the terminal's responses to status requests are written to the program.
+    respond: bool,
+    /// Bytes waiting to be written to the pseudoterminal.
+    input: VecDeque<u8>,
+    /// The exit code of the program once it exited. The inner value is `None` if the program
+    /// was terminated by a signal.
+    exit: Option<Option<i32>>,
+    /// The time the program last wrote output.
+    last_output: Instant,
+}
+
+impl Session {
+    /// Spawn `command` in a new pseudoterminal. Returns an error of kind
+    /// [InvalidInput](ErrorKind::InvalidInput) wrapping a [TermSizeError](crate::TermSizeError) if the size of
+    /// the command's terminal is invalid.
+    pub fn spawn(command: &Command) -> std::io::Result<Self> {
+        let responses = Arc::new(Mutex::new(VecDeque::new()));
+        let mut term = Term::try_new(command.lines, command.columns, Responses(responses.clone()))
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?;
+        let pty = command.spawn_pty()?;
+        term.set_colors(command.colors.clone());
+
+        Ok(Session {
+            pty,
+            term,
+            responses,
+            respond: true,
+            input: VecDeque::new(),
+            exit: None,
+            last_output: Instant::now(),
+        })
+    }
+
+    /// Queue `input` to be written to the program, as if typed. The input is written while
+    /// waiting, see [Session::wait_for_exit] and [Session::wait_for].
+    pub fn write_input(&mut self, input: &[u8]) {
+        self.input.extend(input);
+    }
+
+    /// Set whether the terminal answers the program's status requests, such as cursor position
+    /// reports. Defaults to `true`. Disable this when another terminal answers them, for example
+    /// when forwarding the program's output to a real terminal.
+    pub fn set_respond(&mut self, respond: bool) {
+        self.respond = respond;
+    }
+
+    /// The number of bytes of input queued to be written to the program, including the terminal's
+    /// responses.
+    pub fn queued_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// The terminal emulating the program's output.
+    pub fn term(&self) -> &Term<impl PtyWriter> {
+        &self.term
+    }
+
+    /// The terminal emulating the program's output, for configuring it.
+    pub fn term_mut(&mut self) -> &mut Term<impl PtyWriter> {
+        &mut self.term
+    }
+
+    /// The pseudoterminal the program runs in.
+    pub fn pty(&self) -> &Pty {
+        &self.pty
+    }
+
+    /// Resize the terminal and the pseudoterminal, or return an error and leave both unchanged if
+    /// the size is invalid. The program is notified of the new size.
+    pub fn resize(&mut self, lines: u16, columns: u16) -> Result<(), TermSizeError> {
+        self.term.try_resize(lines, columns)?;
+        self.pty.on_resize(WindowSize {
+            num_lines: lines,
+            num_cols: columns,
+            cell_width: 1,
+            cell_height: 1,
+        });
+        Ok(())
+    }
+
+    /// The time the program last wrote output, or the time it was spawned if it has not written
+    /// any.
+    pub fn last_output(&self) -> Instant {
+        self.last_output
+    }
+
+    /// Get a snapshot of the current terminal screen.
+    pub fn screen(&self) -> Screen {
+        self.term.current_screen()
+    }
+
+    /// The exit code of the program. Returns `None` if the program has not exited yet or was
+    /// terminated by a signal.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit.flatten()
+    }
+
+    /// Returns `true` if the program has exited.
+    pub fn has_exited(&self) -> bool {
+        self.exit.is_some()
+    }
+
+    /// Process the program's output and write queued input until the program exits, and get a
+    /// snapshot of the terminal screen.
+    pub fn wait_for_exit(mut self) -> std::io::Result<Screen> {
+        while !self.pump(None, &mut ())? {}
+        Ok(self.screen())
+    }
+
+    /// Process the program's output and write queued input until the program exits or `timeout`
+    /// elapses. Returns `true` if the program exited.
+    pub fn wait_for(&mut self, timeout: Duration) -> std::io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self.pump(Some(deadline), &mut ())? {
+                return Ok(true);
+            }
+        }
+        Ok(self.has_exited())
+    }
+
+    /// Process the program's output and write queued input, calling `hooks`, until the program
+    /// exits or a hook stops the session. Returns `true` if the program exited.
+    pub fn run<H: Hooks>(&mut self, hooks: &mut H) -> Result<bool, H::Error> {
+        while !self.pump(None, hooks)? {}
+        Ok(self.has_exited())
+    }
+
+    /// Check whether the program exited, and queue the terminal's responses to be written.
+    /// Returns `true` if the program has exited.
+    fn check_exit<H: Hooks>(&mut self, hooks: &mut H) -> Result<bool, H::Error> {
+        if self.has_exited() {
+            return Ok(true);
+        }
+        if let Some(ChildEvent::Exited(code)) = self.pty.next_child_event() {
+            // read the output the program wrote right before exiting
+            let _ = self.read_output(hooks)?;
+            self.exit = Some(code);
+            return Ok(true);
+        }
+
+        let mut responses = self
+            .responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if self.respond {
+            self.input
+                .extend(responses.drain(..).flat_map(String::into_bytes));
+        } else {
+            responses.clear();
+        }
+        Ok(false)
+    }
+
+    /// Wait until there is output to read, input to write or input for `hooks` to read, or until
+    /// `deadline`, and handle it. Returns `true` if the program has exited or a hook stopped the
+    /// session.
+    fn pump<H: Hooks>(
+        &mut self,
+        deadline: Option<Instant>,
+        hooks: &mut H,
+    ) -> Result<bool, H::Error> {
+        if self.check_exit(hooks)? {
+            return Ok(true);
+        }
+        let wake = match hooks.tick(self)? {
+            ControlFlow::Continue(wake) => wake,
+            ControlFlow::Break(()) => return Ok(true),
+        };
+
+        let timeout = match (deadline, wake) {
+            (Some(deadline), Some(wake)) => Some(deadline.min(wake)),
+            (deadline, wake) => deadline.or(wake),
+        }
+        .map_or(POLL_INTERVAL, |deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .min(POLL_INTERVAL)
+        });
+        let (readable, writable, input) = {
+            let fd = self.pty.file().as_fd();
+            let input = hooks.input(self);
+            let mut fds = vec![PollFd::new(&fd, PollFlags::IN)];
+            if !self.input.is_empty() {
+                fds[0] = PollFd::new(&fd, PollFlags::IN | PollFlags::OUT);
+            }
+            if let Some(input) = &input {
+                fds.push(PollFd::new(input, PollFlags::IN));
+            }
+            match poll(&mut fds, timeout.as_millis() as i32) {
+                Ok(_) => {}
+                Err(rustix::io::Errno::INTR) => return Ok(false),
+                Err(err) => return Err(std::io::Error::from(err).into()),
+            }
+            let revents = fds[0].revents();
+            (
+                revents.intersects(PollFlags::IN | PollFlags::HUP),
+                revents.contains(PollFlags::OUT),
+                fds.get(1).is_some_and(|fd| !fd.revents().is_empty()),
+            )
+        };
+
+        if readable && self.read_output(hooks)?.is_break() {
+            return Ok(true);
+        }
+        if input && hooks.readable(self)?.is_break() {
+            return Ok(true);
+        }
+        if writable {
+            self.write_queued()?;
+        }
+
+        Ok(false)
+    }
+
+    /// Write queued input that can be written without blocking. Returns `true` if writing would
+    /// block.
+    fn write_queued(&mut self) -> std::io::Result<bool> {
+        let (input, _) = self.input.as_slices();
+        match self.pty.writer().write(input) {
+            Ok(written) => {
+                self.input.drain(..written);
+                Ok(false)
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(true),
+            Err(err) if err.kind() == ErrorKind::Interrupted => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Read and emulate the output that is available without blocking, passing it to `hooks`.
+    fn read_output<H: Hooks>(&mut self, hooks: &mut H) -> Result<ControlFlow<()>, H::Error> {
+        let mut buf = [0; 4096];
+        loop {
+            match self.pty.reader().read(&mut buf) {
+                Ok(0) => return Ok(ControlFlow::Continue(())),
+                Ok(read) => {
+                    self.last_output = Instant::now();
+                    self.term
+                        .process_bytes_with_callback(&buf[..read], |term, signal| {
+                            hooks.signal(term, signal)
+                        });
+                    if hooks.output(&mut self.term, &buf[..read])?.is_break() {
+                        return Ok(ControlFlow::Break(()));
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                // reading fails once the program closed the pseudoterminal
+                Err(_) => return Ok(ControlFlow::Continue(())),
+            }
+        }
+    }
+}
+
+/// Options for [capture].
+#[cfg(feature = "tokio")]
+#[derive(Clone, Debug, Default)]
+pub struct CaptureOptions {
+    /// Input to write to the program, as if typed.
+    pub input: Vec<u8>,
+    /// Stop and capture the screen once the program has produced no output for this long.
+    pub settle: Option<Duration>,
+    /// Stop and capture the screen once the program has run for this long.
+    pub timeout: Option<Duration>,
+}
+
+/// Run `command` in a new pseudoterminal until it exits, or until the settle time or timeout in
+/// `options` elapses, and get a snapshot of the terminal screen. If the program is still running,
+/// it is sent SIGHUP. Requires the `tokio` feature and a Tokio runtime with I/O and time
+/// enabled.
+#[cfg(feature = "tokio")]
+pub async fn capture(command: &Command, options: &CaptureOptions) -> std::io::Result<Screen> {
+    let mut session = Session::spawn(command)?;
+    session.write_input(&options.input);
+
+    let fd = session.register()?;
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let settled = options.settle.map(|settle| session.last_output + settle);
+        let until = match (deadline, settled) {
+            (Some(deadline), Some(settled)) => Some(deadline.min(settled)),
+            (deadline, settled) => deadline.or(settled),
+        };
+        if until.is_some_and(|until| Instant::now() >= until)
+            || session.pump_async(&fd, until).await?
+        {
+            break;
+        }
+    }
+    drop(fd);
+
+    Ok(session.screen())
+}
+
+#[cfg(feature = "tokio")]
+impl Session {
+    /// Process the program's output and write queued input until the program exits, and get a
+    /// snapshot of the terminal screen. See [Session::wait_for_exit].
+    pub async fn wait_for_exit_async(mut self) -> std::io::Result<Screen> {
+        let fd = self.register()?;
+        while !self.pump_async(&fd, None).await? {}
+        drop(fd);
+        Ok(self.screen())
+    }
+
+    /// Process the program's output and write queued input until the program exits or `timeout`
+    /// elapses. Returns `true` if the program exited. See [Session::wait_for].
+    pub async fn wait_for_async(&mut self, timeout: Duration) -> std::io::Result<bool> {
+        let fd = self.register()?;
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self.pump_async(&fd, Some(deadline)).await? {
+                return Ok(true);
+            }
+        }
+        Ok(self.has_exited())
+    }
+
+    /// Register the pseudoterminal with the Tokio runtime. The registration must be dropped
+    /// before the session.
+    fn register(&self) -> std::io::Result<tokio::io::unix::AsyncFd<std::os::fd::RawFd>> {
+        use std::os::fd::AsRawFd;
+        use tokio::io::Interest;
+
+        tokio::io::unix::AsyncFd::with_interest(
+            self.pty.file().as_raw_fd(),
+            Interest::READABLE | Interest::WRITABLE,
+        )
+    }
+
+    /// Wait until there is output to read or input to write, or until `deadline`, and handle
+    /// it. Returns `true` if the program has exited.
+    async fn pump_async(
+        &mut self,
+        fd: &tokio::io::unix::AsyncFd<std::os::fd::RawFd>,
+        deadline: Option<Instant>,
+    ) -> std::io::Result<bool> {
+        if self.check_exit(&mut ())? {
+            return Ok(true);
+        }
+
+        // the exit of the program is not observable through the pseudoterminal, so wake up
+        // regularly to check for it
+        let timeout = deadline.map_or(POLL_INTERVAL, |deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .min(POLL_INTERVAL)
+        });
+        tokio::select! {
+            guard = fd.readable() => {
+                let mut guard = guard?;
+                // the output is read until reading would block or fails
+                let _ = self.read_output(&mut ())?;
+                guard.clear_ready();
+            }
+            guard = fd.writable(), if !self.input.is_empty() => {
+                let mut guard = guard?;
+                if self.write_queued()? {
+                    guard.clear_ready();
+                }
+            }
+            _ = tokio::time::sleep(timeout) => {}
+        }
+
+        Ok(false)
+    }
+}
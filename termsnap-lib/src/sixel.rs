@@ -0,0 +1,218 @@
+//! Decoding of Sixel graphics.
+
+/// The maximum width and height in pixels of a decoded Sixel image. Data outside of this area is
+/// discarded.
+const MAX_DIMENSION: usize = 4096;
+
+/// The default color palette of the VT340, in RGB percentages.
+const DEFAULT_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (20, 20, 80),
+    (80, 13, 13),
+    (20, 80, 20),
+    (80, 20, 80),
+    (20, 80, 80),
+    (80, 80, 20),
+    (53, 53, 53),
+    (26, 26, 26),
+    (33, 33, 60),
+    (60, 26, 26),
+    (33, 60, 33),
+    (60, 33, 60),
+    (33, 60, 60),
+    (60, 60, 33),
+    (80, 80, 80),
+];
+
+/// A decoded Sixel image.
+pub(crate) struct SixelImage {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels in RGBA format, row by row.
+    pub rgba: Vec<u8>,
+}
+
+/// An incremental Sixel decoder. Feed it the data bytes of a Sixel device control string.
+pub(crate) struct Decoder {
+    palette: Vec<[u8; 3]>,
+    color: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    /// Pixels as palette-resolved RGBA, `None` for pixels that have not been drawn.
+    rows: Vec<Vec<Option<[u8; 3]>>>,
+    /// A control sequence being parsed, such as a color introducer or repeat introducer.
+    control: Option<u8>,
+    params: Vec<u32>,
+}
+
+fn percent_to_u8(percent: u32) -> u8 {
+    (percent.min(100) * 255 / 100) as u8
+}
+
+/// Convert a color in the hue, lightness, saturation color space to RGB. Sixel hue angles are
+/// rotated compared to the common HSL definition: blue is at 0 degrees and red at 120 degrees.
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> [u8; 3] {
+    let hue = ((hue + 240) % 360) as f32;
+    let lightness = lightness.min(100) as f32 / 100.;
+    let saturation = saturation.min(100) as f32 / 100.;
+
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let x = chroma * (1. - ((hue / 60.) % 2. - 1.).abs());
+    let m = lightness - chroma / 2.;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let to_u8 = |v: f32| ((v + m) * 255.).round().clamp(0., 255.) as u8;
+    [to_u8(r), to_u8(g), to_u8(b)]
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        let mut palette = vec![[0, 0, 0]; 256];
+        for (color, (r, g, b)) in palette.iter_mut().zip(DEFAULT_PALETTE) {
+            *color = [r, g, b].map(|c| percent_to_u8(u32::from(c)));
+        }
+
+        Decoder {
+            palette,
+            color: 0,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            rows: Vec::new(),
+            control: None,
+            params: Vec::new(),
+        }
+    }
+
+    /// Draw a sixel (a column of six pixels) at the current position `count` times.
+    fn draw(&mut self, sixel: u8, count: usize) {
+        let color = self.palette[self.color];
+        let end_x = (self.x + count).min(MAX_DIMENSION);
+
+        for bit in 0..6 {
+            let y = self.y + bit;
+            if sixel & (1 << bit) == 0 || y >= MAX_DIMENSION {
+                continue;
+            }
+            if self.rows.len() <= y {
+                self.rows.resize_with(y + 1, Vec::new);
+            }
+            let row = &mut self.rows[y];
+            if row.len() < end_x {
+                row.resize(end_x, None);
+            }
+            for pixel in &mut row[self.x.min(end_x)..end_x] {
+                *pixel = Some(color);
+            }
+            self.height = self.height.max(y + 1);
+        }
+
+        self.x += count;
+        self.width = self.width.max(end_x);
+    }
+
+    /// Apply the control sequence that was being parsed.
+    fn finish_control(&mut self) {
+        let Some(control) = self.control.take() else {
+            return;
+        };
+        let params = std::mem::take(&mut self.params);
+
+        match (control, params.as_slice()) {
+            // raster attributes: pixel aspect ratio and image dimensions
+            (b'"', [_, _, width, height, ..]) => {
+                self.width = self.width.max((*width as usize).min(MAX_DIMENSION));
+                self.height = self.height.max((*height as usize).min(MAX_DIMENSION));
+            }
+            // color selection
+            (b'#', [color]) => {
+                self.color = (*color as usize).min(self.palette.len() - 1);
+            }
+            // color definition
+            (b'#', [color, space, x, y, z, ..]) => {
+                let color = (*color as usize).min(self.palette.len() - 1);
+                self.palette[color] = match space {
+                    1 => hls_to_rgb(*x, *y, *z),
+                    _ => [percent_to_u8(*x), percent_to_u8(*y), percent_to_u8(*z)],
+                };
+                self.color = color;
+            }
+            _ => {}
+        }
+    }
+
+    /// Feed a byte of Sixel data.
+    pub fn put(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' if self.control.is_some() => {
+                let digit = u32::from(byte - b'0');
+                match self.params.last_mut() {
+                    Some(param) => *param = param.saturating_mul(10).saturating_add(digit),
+                    None => self.params.push(digit),
+                }
+            }
+            b';' if self.control.is_some() => self.params.push(0),
+            b'?'..=b'~' => {
+                let count = if self.control == Some(b'!') {
+                    self.params.first().copied().unwrap_or(1).max(1) as usize
+                } else {
+                    1
+                };
+                if self.control != Some(b'!') {
+                    self.finish_control();
+                }
+                self.control = None;
+                self.params.clear();
+                self.draw(byte - b'?', count.min(MAX_DIMENSION));
+            }
+            b'"' | b'#' | b'!' => {
+                self.finish_control();
+                self.control = Some(byte);
+            }
+            b'$' => {
+                self.finish_control();
+                self.x = 0;
+            }
+            b'-' => {
+                self.finish_control();
+                self.x = 0;
+                self.y += 6;
+            }
+            _ => {}
+        }
+    }
+
+    /// Finish decoding, returning the image. Returns `None` if the image is empty.
+    pub fn finish(mut self) -> Option<SixelImage> {
+        self.finish_control();
+
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+
+        let mut rgba = vec![0; self.width * self.height * 4];
+        for (y, row) in self.rows.iter().enumerate().take(self.height) {
+            for (x, pixel) in row.iter().enumerate().take(self.width) {
+                if let Some([r, g, b]) = *pixel {
+                    let idx = (y * self.width + x) * 4;
+                    rgba[idx..idx + 4].copy_from_slice(&[r, g, b, 255]);
+                }
+            }
+        }
+
+        Some(SixelImage {
+            width: self.width,
+            height: self.height,
+            rgba,
+        })
+    }
+}
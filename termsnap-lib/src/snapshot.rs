@@ -0,0 +1,183 @@
+//! Snapshot testing of terminal screens. Screens are serialized to a stable, human-readable text
+//! format, which can be stored alongside tests and compared against in later test runs, or passed
+//! to a snapshot testing library such as insta.
+
+use std::{fmt::Write, path::Path};
+
+use crate::{Cell, Screen, Underline};
+
+/// The environment variable that, when set to `1`, makes [assert_snapshot] overwrite stored
+/// snapshots rather than comparing against them.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "TERMSNAP_UPDATE_SNAPSHOTS";
+
+/// The style of a cell, i.e., everything but its characters.
+#[derive(Clone, PartialEq, Eq)]
+struct Style(Cell);
+
+impl Style {
+    fn of(cell: &Cell) -> Self {
+        Style(Cell {
+            c: ' ',
+            zerowidth: Box::default(),
+            hyperlink: None,
+            ..cell.clone()
+        })
+    }
+}
+
+impl std::fmt::Display for Style {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cell = &self.0;
+        write!(f, "fg={} bg={}", cell.fg, cell.bg)?;
+        for (set, name) in [
+            (cell.bold, "bold"),
+            (cell.dim, "dim"),
+            (cell.italic, "italic"),
+            (cell.strikethrough, "strikethrough"),
+            (cell.hidden, "hidden"),
+        ] {
+            if set {
+                write!(f, " {name}")?;
+            }
+        }
+        if cell.underline != Underline::None {
+            write!(f, " underline={:?}", cell.underline)?;
+        }
+        if let Some(color) = cell.underline_color {
+            write!(f, " underline-color={color}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialize `screen` to text. The text lists the lines of the screen, with trailing whitespace
+/// removed, followed by the most common style of the screen's cells and the runs of cells with a
+/// different style.
+pub(crate) fn serialize(screen: &Screen) -> String {
+    // the styles in order of first appearance, with the number of cells having them
+    let mut counts: Vec<(Style, usize)> = Vec::new();
+    for cell in screen.cells() {
+        let style = Style::of(cell);
+        match counts.iter_mut().find(|(s, _)| *s == style) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((style, 1)),
+        }
+    }
+    // ties are broken by the first appearance, such that the output is stable
+    let mut default = None;
+    for (style, count) in counts {
+        if default.as_ref().is_none_or(|(_, max)| count > *max) {
+            default = Some((style, count));
+        }
+    }
+    let default = default.map(|(style, _)| style);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "size: {}x{}", screen.lines(), screen.columns());
+    if let Some(title) = screen.title() {
+        let _ = writeln!(out, "title: {title}");
+    }
+
+    out.push_str("text:\n");
+    for line in 0..screen.lines() {
+        let text = screen.line_text(line).unwrap_or_default();
+        let _ = writeln!(out, "  |{text}");
+    }
+
+    let Some(default) = default else {
+        return out;
+    };
+    let _ = writeln!(out, "style: {default}");
+    for line in 0..screen.lines() {
+        let mut column = 0;
+        while column < screen.columns() {
+            let style = Style::of(&screen[(line, column)]);
+            let start = column;
+            while column < screen.columns() && Style::of(&screen[(line, column)]) == style {
+                column += 1;
+            }
+            if style != default {
+                let _ = writeln!(out, "  {line}:{start}-{line}:{column} {style}");
+            }
+        }
+    }
+
+    out
+}
+
+/// Compare `screen` against the snapshot stored at `path`, panicking with a description of the
+/// differences if they do not match. If the snapshot does not exist yet, or if the
+/// [`TERMSNAP_UPDATE_SNAPSHOTS`](UPDATE_SNAPSHOTS_VAR) environment variable is set to `1`, the
+/// snapshot is written instead.
+///
+/// See also [assert_screen_snapshot](crate::assert_screen_snapshot), which stores snapshots in the
+/// calling crate's `tests/snapshots` directory, and [Screen::to_snapshot] for the format of
+/// snapshots.
+#[track_caller]
+pub fn assert_snapshot(screen: &Screen, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = serialize(screen);
+
+    let update = std::env::var(UPDATE_SNAPSHOTS_VAR).is_ok_and(|value| value == "1");
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) if !update => expected,
+        Ok(_) | Err(_) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).expect("failed to create snapshot directory");
+            }
+            std::fs::write(path, actual).expect("failed to write snapshot");
+            return;
+        }
+    };
+
+    if expected == actual {
+        return;
+    }
+
+    let mut diff = String::new();
+    let expected_lines: Vec<_> = expected.lines().collect();
+    let actual_lines: Vec<_> = actual.lines().collect();
+    for idx in 0..expected_lines.len().max(actual_lines.len()) {
+        match (expected_lines.get(idx), actual_lines.get(idx)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                let _ = writeln!(diff, " {expected}");
+            }
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    let _ = writeln!(diff, "-{expected}");
+                }
+                if let Some(actual) = actual {
+                    let _ = writeln!(diff, "+{actual}");
+                }
+            }
+        }
+    }
+
+    panic!(
+        "screen does not match snapshot {} (- expected, + actual):\n{diff}\nSet {UPDATE_SNAPSHOTS_VAR}=1 to update the snapshot.",
+        path.display(),
+    );
+}
+
+/// Compare a [Screen] against a snapshot named `name`, stored in the `tests/snapshots` directory
+/// of the crate calling this macro. See [assert_snapshot](crate::assert_snapshot).
+///
+/// ```rust,no_run
+/// use termsnap_lib::{assert_screen_snapshot, Term, VoidPtyWriter};
+///
+/// let mut term = Term::new(2, 20, VoidPtyWriter);
+/// term.process_bytes(b"\x1b[1mhello\x1b[0m, world");
+/// assert_screen_snapshot!(term.current_screen(), "hello");
+/// ```
+#[macro_export]
+macro_rules! assert_screen_snapshot {
+    ($screen:expr, $name:expr $(,)?) => {
+        $crate::assert_snapshot(
+            &$screen,
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("snapshots")
+                .join(format!("{}.snap", $name)),
+        )
+    };
+}
@@ -0,0 +1,69 @@
+//! Statistics of the content of terminal screens, e.g., to budget the size of rendered SVGs.
+
+use std::collections::HashSet;
+
+use crate::{Rgb, Screen, Underline};
+
+/// Statistics of the content of a [Screen]. See [Screen::stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScreenStats {
+    /// The number of distinct colors drawn: the foreground colors of visible text, the background
+    /// colors and the underline colors.
+    pub colors: usize,
+    /// The number of distinct foreground colors of visible text.
+    pub foreground_colors: usize,
+    /// The number of distinct background colors.
+    pub background_colors: usize,
+    /// The number of cells with a non-whitespace character.
+    pub non_blank_cells: usize,
+    /// The width in cells of the widest line, discarding trailing whitespace.
+    pub widest_line: u16,
+    /// The number of cells with bold text.
+    pub bold_cells: usize,
+    /// The number of cells with dim text.
+    pub dim_cells: usize,
+    /// The number of cells with italic text.
+    pub italic_cells: usize,
+    /// The number of underlined cells, of any underline style.
+    pub underlined_cells: usize,
+    /// The number of struck through cells.
+    pub strikethrough_cells: usize,
+    /// The number of cells with hidden (concealed) text.
+    pub hidden_cells: usize,
+}
+
+pub(crate) fn compute(screen: &Screen) -> ScreenStats {
+    let mut colors = HashSet::<Rgb>::new();
+    let mut foreground_colors = HashSet::new();
+    let mut background_colors = HashSet::new();
+    let mut stats = ScreenStats::default();
+
+    for (_, column, cell) in screen.cells_indexed() {
+        let blank = cell.c.is_whitespace();
+        if !blank {
+            stats.non_blank_cells += 1;
+            stats.widest_line = stats.widest_line.max(column + 1);
+            if !cell.hidden {
+                foreground_colors.insert(cell.fg);
+            }
+        }
+        background_colors.insert(cell.bg);
+        if cell.underline != Underline::None {
+            colors.insert(cell.underline_color.unwrap_or(cell.fg));
+        }
+
+        stats.bold_cells += usize::from(cell.bold);
+        stats.dim_cells += usize::from(cell.dim);
+        stats.italic_cells += usize::from(cell.italic);
+        stats.underlined_cells += usize::from(cell.underline != Underline::None);
+        stats.strikethrough_cells += usize::from(cell.strikethrough);
+        stats.hidden_cells += usize::from(cell.hidden);
+    }
+
+    colors.extend(&foreground_colors);
+    colors.extend(&background_colors);
+    stats.colors = colors.len();
+    stats.foreground_colors = foreground_colors.len();
+    stats.background_colors = background_colors.len();
+    stats
+}
@@ -0,0 +1,1573 @@
+//! Rendering of [Screen]s to SVG.
+
+use std::{
+    fmt::{Display, Write},
+    ops::Range,
+};
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    annotation,
+    blur::{self, Blur, BlurStyle},
+    colors,
+    decoration::{self, Badge, Corner, ExitStatus},
+    fallback::{self, FontClass},
+    glyphs, grid, highlight, hyperlink, Annotation, Cell, FontMetrics, Highlight, Hyperlink,
+    Layout, LineSize, Rgb, Screen, Underline, WindowFrame,
+};
+
+#[cfg(feature = "text-as-paths")]
+use crate::OutlineFont;
+
+/// A sensible default font size, in case some renderers don't automatically scale up the SVG.
+pub(crate) const FONT_SIZE_PX: f32 = 12.;
+
+/// A text alternative for the SVG, used by screen readers and search engines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AltText<'a> {
+    /// Do not make the SVG accessible.
+    #[default]
+    None,
+    /// Use the plain-text content of the terminal screen. Hidden text is left out.
+    ScreenText,
+    /// Use the given text.
+    Custom(&'a str),
+}
+
+/// Options for rendering a [Screen] to SVG. See [Screen::to_svg_with_options].
+///
+/// Options are set starting from the defaults, either using the builder methods or by assigning
+/// fields. More options may be added in minor releases.
+///
+/// ```rust
+/// use termsnap_lib::{emulate, SvgOptions, WindowFrame};
+///
+/// let screen = emulate(4, 20, b"hello, world");
+/// let options = SvgOptions::default()
+///     .fonts(&["JetBrains Mono"])
+///     .window_frame(WindowFrame::MacOs)
+///     .padding(8.);
+/// println!("{}", screen.to_svg_with_options(options));
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct SvgOptions<'a> {
+    /// Fonts to be included in the SVG's `font-family` style. `font-family` always includes
+    /// `monospace`.
+    pub fonts: &'a [&'a str],
+    /// Fonts for CJK characters, which are often missing from monospace fonts. If set, runs of
+    /// CJK characters get their own `font-family` style, listing these fonts before `fonts`.
+    pub cjk_fonts: &'a [&'a str],
+    /// Fonts for emoji, used as `cjk_fonts` is used for CJK characters.
+    pub emoji_fonts: &'a [&'a str],
+    /// Fonts for symbols, such as arrows, mathematical operators, dingbats and icons in the
+    /// private use area, used as `cjk_fonts` is used for CJK characters.
+    pub symbol_fonts: &'a [&'a str],
+    /// The metrics of the font used to lay out the terminal cells.
+    pub font_metrics: FontMetrics,
+    /// The window decoration drawn around the terminal screen.
+    pub window_frame: WindowFrame,
+    /// Draw a drop shadow below the window. This has no effect if `window_frame` is
+    /// [WindowFrame::None].
+    pub window_shadow: bool,
+    /// Space in pixels around the terminal screen, filled with the terminal's background color.
+    /// This prevents text from touching the edge of the image. Negative and non-finite values are
+    /// treated as 0.
+    pub padding: f32,
+    /// Space in pixels around the outside of the terminal screen (and its window decoration, if
+    /// any). Negative and non-finite values are treated as 0.
+    pub margin: f32,
+    /// The color to fill the margin with. If unset, the margin is filled with the terminal's
+    /// background color, or left transparent if a window decoration is drawn.
+    pub margin_color: Option<Rgb>,
+    /// Render the terminal title (see [Screen::title]). If a window decoration is drawn, the
+    /// title is drawn in its title bar. Otherwise the title is drawn as a caption above the
+    /// terminal screen.
+    pub show_title: bool,
+    /// A caption drawn below the terminal screen (and its window decoration, if any), in the
+    /// title's font.
+    pub caption: Option<&'a str>,
+    /// A small watermark drawn in a corner of the terminal screen, such as a logo or version.
+    pub badge: Option<Badge<'a>>,
+    /// The corner the badge is drawn in.
+    pub badge_corner: Corner,
+    /// The exit status of the captured command, drawn in a corner of the terminal screen as a
+    /// check mark or a cross with the exit code, e.g., to show whether a run in CI succeeded.
+    pub exit_status: Option<ExitStatus>,
+    /// The corner the exit status is drawn in.
+    pub exit_status_corner: Corner,
+    /// Render hidden (concealed) text in the cell's background color, rather than not rendering
+    /// it at all. This keeps the text invisible, but allows it to be selected.
+    pub hidden_as_background: bool,
+    /// Draw block element, sextant and Braille characters as shapes rather than as text. This
+    /// makes charts and graphs drawn using these characters render crisply and consistently,
+    /// regardless of the font used.
+    pub glyph_geometry: bool,
+    /// Draw spaces as middle dots and tabs as arrows, in a dimmed color, e.g., to document the
+    /// exact whitespace output of a formatter. Trailing spaces cannot be told apart from blank
+    /// cells, so only whitespace before the last non-whitespace character of a line is drawn.
+    pub show_whitespace: bool,
+    /// Position the characters of each cell at the cell's exact x coordinate, rather than
+    /// stretching runs of text to their width using `textLength`. Some renderers subtly mis-space
+    /// glyphs stretched by `textLength`, breaking the alignment of columns of text. This produces
+    /// larger SVGs.
+    pub exact_grid: bool,
+    /// Make the hyperlinks set using the OSC 8 escape sequence clickable, by wrapping the text and
+    /// backgrounds of their cells in `<a>` elements. Only links with the schemes `http`, `https`,
+    /// `file` and `mailto` are made clickable. Note links may point to local files, e.g., those
+    /// written by `ls --hyperlink`, revealing the host name and paths.
+    pub hyperlinks: bool,
+    /// Attach a `<title>` to each run of text listing, for each of its cells, the position in the
+    /// format of [Region](crate::Region), the codepoints, the colors and the text attributes.
+    /// Viewers show these as tooltips, which helps diagnosing why a rendered screen differs from
+    /// the screen in a terminal.
+    pub debug_cells: bool,
+    /// Draw faint borders around each cell, e.g., to check the alignment of the layout of a
+    /// terminal user interface.
+    pub grid: bool,
+    /// Draw rulers numbering the lines and columns above and to the left of the screen. Lines and
+    /// columns are counted from 0, as in [Region](crate::Region). The padding is increased as
+    /// needed to make room for the rulers. For layouts, cells are counted from the top-left of the
+    /// layout.
+    pub rulers: bool,
+    /// Draw underlines and strikethroughs as lines positioned from the font metrics, rather than
+    /// through CSS `text-decoration`. The thickness and position of text decorations vary between
+    /// renderers, and some stretch them oddly along with text stretched by `textLength`. Styled
+    /// and colored underlines are always drawn as lines.
+    pub decoration_lines: bool,
+    /// Give the SVG `width` and `height` attributes of its size in pixels multiplied by this
+    /// factor, rather than sizing it by its `viewBox` only, which some renderers do unpredictably.
+    /// The SVG is laid out for a font size of 12 pixels, so, e.g., a factor of 1.5 sizes the SVG
+    /// for text at 18 pixels.
+    pub scale: Option<f32>,
+    /// Regions of the terminal screen to draw highlighted, e.g., to call attention to part of a
+    /// screenshot. Highlights are drawn over the text of each rendered screen.
+    pub highlights: &'a [Highlight],
+    /// Annotations, such as numbered callouts, arrows and labels, drawn on top of each rendered
+    /// screen.
+    pub annotations: &'a [Annotation],
+    /// Regions of the terminal screen to draw blurred or pixelated, e.g., to obscure sensitive
+    /// content that cannot be matched by `Screen::redact`. The text of the regions is not
+    /// included in the SVG. Images are not obscured.
+    pub blurs: &'a [Blur],
+    /// CSS appended to the SVG's `<style>` element, e.g., to tweak font weights or to add a
+    /// `prefers-color-scheme` media query. The terminal screen is in an element with class
+    /// `screen`, and the title in an element with class `title`.
+    pub extra_css: Option<&'a str>,
+    /// The text alternative of the SVG. Unless this is [AltText::None], the SVG gets the `img`
+    /// role, an `aria-label` with the text alternative, a `<title>` with the terminal title, and a
+    /// `<desc>` with the plain-text content of the terminal screen. Defaults to [AltText::None].
+    pub alt_text: AltText<'a>,
+    /// Draw text as path outlines of the glyphs of this font, rather than as SVG text. This makes
+    /// the SVG render identically everywhere, regardless of the fonts installed, at the cost of
+    /// larger output and text no longer being selectable. Use [OutlineFont::metrics] as
+    /// `font_metrics` to lay out the cells to match the font.
+    #[cfg(feature = "text-as-paths")]
+    pub text_as_paths: Option<&'a OutlineFont>,
+}
+
+impl Default for SvgOptions<'_> {
+    fn default() -> Self {
+        SvgOptions {
+            fonts: &[],
+            cjk_fonts: &[],
+            emoji_fonts: &[],
+            symbol_fonts: &[],
+            font_metrics: FontMetrics::DEFAULT,
+            window_frame: WindowFrame::None,
+            window_shadow: false,
+            padding: 0.,
+            margin: 0.,
+            margin_color: None,
+            show_title: false,
+            caption: None,
+            badge: None,
+            badge_corner: Corner::BottomRight,
+            exit_status: None,
+            exit_status_corner: Corner::TopRight,
+            hidden_as_background: false,
+            glyph_geometry: false,
+            show_whitespace: false,
+            exact_grid: false,
+            hyperlinks: false,
+            debug_cells: false,
+            grid: false,
+            rulers: false,
+            decoration_lines: false,
+            scale: None,
+            highlights: &[],
+            annotations: &[],
+            blurs: &[],
+            extra_css: None,
+            alt_text: AltText::None,
+            #[cfg(feature = "text-as-paths")]
+            text_as_paths: None,
+        }
+    }
+}
+
+/// Get `length`, or 0 if it is negative or not finite.
+pub(crate) fn non_negative(length: f32) -> f32 {
+    if length.is_finite() {
+        length.max(0.)
+    } else {
+        0.
+    }
+}
+
+impl<'a> SvgOptions<'a> {
+    /// Set [SvgOptions::fonts].
+    pub fn fonts(mut self, fonts: &'a [&'a str]) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    /// Set [SvgOptions::cjk_fonts].
+    pub fn cjk_fonts(mut self, cjk_fonts: &'a [&'a str]) -> Self {
+        self.cjk_fonts = cjk_fonts;
+        self
+    }
+
+    /// Set [SvgOptions::emoji_fonts].
+    pub fn emoji_fonts(mut self, emoji_fonts: &'a [&'a str]) -> Self {
+        self.emoji_fonts = emoji_fonts;
+        self
+    }
+
+    /// Set [SvgOptions::symbol_fonts].
+    pub fn symbol_fonts(mut self, symbol_fonts: &'a [&'a str]) -> Self {
+        self.symbol_fonts = symbol_fonts;
+        self
+    }
+
+    /// Set [SvgOptions::font_metrics].
+    pub fn font_metrics(mut self, font_metrics: FontMetrics) -> Self {
+        self.font_metrics = font_metrics;
+        self
+    }
+
+    /// Set [SvgOptions::window_frame].
+    pub fn window_frame(mut self, window_frame: WindowFrame) -> Self {
+        self.window_frame = window_frame;
+        self
+    }
+
+    /// Set [SvgOptions::window_shadow].
+    pub fn window_shadow(mut self, window_shadow: bool) -> Self {
+        self.window_shadow = window_shadow;
+        self
+    }
+
+    /// Set [SvgOptions::padding].
+    pub fn padding(mut self, padding: f32) -> Self {
+        self.padding = non_negative(padding);
+        self
+    }
+
+    /// Set [SvgOptions::margin].
+    pub fn margin(mut self, margin: f32) -> Self {
+        self.margin = non_negative(margin);
+        self
+    }
+
+    /// Set [SvgOptions::margin_color].
+    pub fn margin_color(mut self, margin_color: Option<Rgb>) -> Self {
+        self.margin_color = margin_color;
+        self
+    }
+
+    /// Set [SvgOptions::show_title].
+    pub fn show_title(mut self, show_title: bool) -> Self {
+        self.show_title = show_title;
+        self
+    }
+
+    /// Set [SvgOptions::caption].
+    pub fn caption(mut self, caption: Option<&'a str>) -> Self {
+        self.caption = caption;
+        self
+    }
+
+    /// Set [SvgOptions::badge].
+    pub fn badge(mut self, badge: Option<Badge<'a>>) -> Self {
+        self.badge = badge;
+        self
+    }
+
+    /// Set [SvgOptions::badge_corner].
+    pub fn badge_corner(mut self, badge_corner: Corner) -> Self {
+        self.badge_corner = badge_corner;
+        self
+    }
+
+    /// Set [SvgOptions::exit_status].
+    pub fn exit_status(mut self, exit_status: Option<ExitStatus>) -> Self {
+        self.exit_status = exit_status;
+        self
+    }
+
+    /// Set [SvgOptions::exit_status_corner].
+    pub fn exit_status_corner(mut self, exit_status_corner: Corner) -> Self {
+        self.exit_status_corner = exit_status_corner;
+        self
+    }
+
+    /// Set [SvgOptions::hidden_as_background].
+    pub fn hidden_as_background(mut self, hidden_as_background: bool) -> Self {
+        self.hidden_as_background = hidden_as_background;
+        self
+    }
+
+    /// Set [SvgOptions::glyph_geometry].
+    pub fn glyph_geometry(mut self, glyph_geometry: bool) -> Self {
+        self.glyph_geometry = glyph_geometry;
+        self
+    }
+
+    /// Set [SvgOptions::show_whitespace].
+    pub fn show_whitespace(mut self, show_whitespace: bool) -> Self {
+        self.show_whitespace = show_whitespace;
+        self
+    }
+
+    /// Set [SvgOptions::exact_grid].
+    pub fn exact_grid(mut self, exact_grid: bool) -> Self {
+        self.exact_grid = exact_grid;
+        self
+    }
+
+    /// Set [SvgOptions::hyperlinks].
+    pub fn hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Set [SvgOptions::debug_cells].
+    pub fn debug_cells(mut self, debug_cells: bool) -> Self {
+        self.debug_cells = debug_cells;
+        self
+    }
+
+    /// Set [SvgOptions::grid].
+    pub fn grid(mut self, grid: bool) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Set [SvgOptions::rulers].
+    pub fn rulers(mut self, rulers: bool) -> Self {
+        self.rulers = rulers;
+        self
+    }
+
+    /// Set [SvgOptions::decoration_lines].
+    pub fn decoration_lines(mut self, decoration_lines: bool) -> Self {
+        self.decoration_lines = decoration_lines;
+        self
+    }
+
+    /// Set [SvgOptions::scale].
+    pub fn scale(mut self, scale: Option<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set [SvgOptions::highlights].
+    pub fn highlights(mut self, highlights: &'a [Highlight]) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    /// Set [SvgOptions::annotations].
+    pub fn annotations(mut self, annotations: &'a [Annotation]) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// Set [SvgOptions::blurs].
+    pub fn blurs(mut self, blurs: &'a [Blur]) -> Self {
+        self.blurs = blurs;
+        self
+    }
+
+    /// Set [SvgOptions::extra_css].
+    pub fn extra_css(mut self, extra_css: Option<&'a str>) -> Self {
+        self.extra_css = extra_css;
+        self
+    }
+
+    /// Set [SvgOptions::alt_text].
+    pub fn alt_text(mut self, alt_text: AltText<'a>) -> Self {
+        self.alt_text = alt_text;
+        self
+    }
+
+    /// Set [SvgOptions::text_as_paths].
+    #[cfg(feature = "text-as-paths")]
+    pub fn text_as_paths(mut self, text_as_paths: Option<&'a OutlineFont>) -> Self {
+        self.text_as_paths = text_as_paths;
+        self
+    }
+}
+
+/// Metrics for a font at a specific font size. Calculated from [FontMetrics].
+#[derive(Clone, Copy)]
+pub(crate) struct CalculatedFontMetrics {
+    /// The amount of horizontal advance between characters.
+    pub advance: f32,
+    /// Height of a line of text. Lines of text directly touch each other, i.e., it is assumed
+    /// the text "leading" is 0.
+    pub line_height: f32,
+    /// Distance below the text baseline. This is the distance between the text baseline of a line
+    /// and the top of the next line.It is assumed there is no
+    pub descent: f32,
+}
+
+impl FontMetrics {
+    /// Get the font metrics at a specific font size.
+    pub(crate) fn at_font_size(self, font_size: f32) -> CalculatedFontMetrics {
+        let scale_factor = font_size / f32::from(self.units_per_em);
+        CalculatedFontMetrics {
+            advance: self.advance * scale_factor,
+            line_height: self.line_height * scale_factor,
+            descent: self.descent * scale_factor,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct TextStyle {
+    pub fg: Rgb,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// The fallback font stack of the text, if any.
+    font: Option<FontClass>,
+}
+
+/// Whether the cell's underline is drawn as geometry rather than through the text's CSS
+/// `text-decoration`. Plain underlines are left to the SVG renderer unless
+/// [SvgOptions::decoration_lines] is set, styled and colored underlines are drawn explicitly.
+fn draws_underline(cell: &Cell, options: &SvgOptions) -> bool {
+    match cell.underline {
+        Underline::None => false,
+        Underline::Single => options.decoration_lines || cell.underline_color.is_some(),
+        Underline::Double | Underline::Curly | Underline::Dotted | Underline::Dashed => true,
+    }
+}
+
+impl TextStyle {
+    /// private conversion from alacritty Cell to Style
+    fn from_cell(cell: &Cell, options: &SvgOptions) -> Self {
+        let Cell {
+            fg,
+            bg,
+            bold,
+            dim,
+            italic,
+            underline,
+            strikethrough,
+            hidden,
+            ..
+        } = *cell;
+
+        TextStyle {
+            fg: if hidden { bg } else { fg },
+            bold,
+            dim,
+            italic,
+            underline: underline == Underline::Single && !draws_underline(cell, options),
+            strikethrough: strikethrough && !options.decoration_lines,
+            font: None,
+        }
+    }
+}
+
+pub(crate) struct TextLine {
+    /// The characters of the cells, including zero-width characters.
+    text: Vec<char>,
+    /// The index in `text` of the first character of each cell.
+    cell_starts: Vec<usize>,
+}
+
+impl TextLine {
+    fn with_capacity(capacity: usize) -> Self {
+        TextLine {
+            text: Vec::with_capacity(capacity),
+            cell_starts: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn push_cell(&mut self, char: char, zerowidth: &[char]) {
+        self.cell_starts.push(self.text.len());
+        self.text.push(char);
+        self.text.extend_from_slice(zerowidth);
+    }
+
+    fn clear(&mut self) {
+        self.text.clear();
+        self.cell_starts.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.cell_starts.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the number of cells of this text line, discarding trailing whitespace.
+    pub(crate) fn trimmed_len(&self) -> usize {
+        self.cell_starts
+            .iter()
+            .rposition(|&start| !self.text[start].is_whitespace())
+            .map_or(0, |idx| idx + 1)
+    }
+
+    /// Get the characters of this text line, discarding trailing whitespace.
+    pub(crate) fn chars(&self) -> &[char] {
+        let end = self
+            .cell_starts
+            .get(self.trimmed_len())
+            .copied()
+            .unwrap_or(self.text.len());
+        &self.text[..end]
+    }
+
+    /// Get the first character of each cell of this text line, discarding trailing whitespace.
+    #[cfg(feature = "text-as-paths")]
+    fn base_chars(&self) -> Vec<char> {
+        self.cell_starts[..self.trimmed_len()]
+            .iter()
+            .map(|&start| self.text[start])
+            .collect()
+    }
+}
+
+fn fmt_rect(
+    f: &mut std::fmt::Formatter<'_>,
+    x0: u16,
+    y0: u16,
+    x1: u16,
+    y1: u16,
+    color: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    writeln!(
+        f,
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" style="fill: {color};" />"#,
+        x = f32::from(x0) * font_metrics.advance,
+        y = f32::from(y0) * font_metrics.line_height,
+        width = f32::from(x1 - x0 + 1) * font_metrics.advance,
+        height = f32::from(y1 - y0 + 1) * font_metrics.line_height,
+        color = color,
+    )
+}
+
+fn fmt_text(
+    f: &mut std::fmt::Formatter<'_>,
+    x: u16,
+    y: u16,
+    text: &TextLine,
+    style: &TextStyle,
+    exact_grid: bool,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let y = f32::from(y + 1) * font_metrics.line_height - font_metrics.descent;
+    if exact_grid {
+        write!(f, r#"<text y="{y}""#)?;
+    } else {
+        let text_length = text.trimmed_len() as f32 * font_metrics.advance;
+        write!(
+            f,
+            r#"<text x="{x}" y="{y}" textLength="{text_length}""#,
+            x = f32::from(x) * font_metrics.advance,
+        )?;
+    }
+    if let Some(font) = style.font {
+        write!(f, r#" class="{}""#, font.css_class())?;
+    }
+    write!(f, r#" style="fill: {};"#, style.fg)?;
+
+    if style.bold {
+        f.write_str(" font-weight: 600;")?;
+    }
+    if style.dim {
+        f.write_str(" opacity: 0.6;")?;
+    }
+    if style.italic {
+        f.write_str(" font-style: italic;")?;
+    }
+    if style.underline || style.strikethrough {
+        f.write_str(" text-decoration:")?;
+        if style.underline {
+            f.write_str(" underline")?;
+        }
+        if style.strikethrough {
+            f.write_str(" line-through")?;
+        }
+        f.write_char(';')?;
+    }
+
+    f.write_str(r#"">"#)?;
+    if exact_grid {
+        fmt_cells_positioned(f, x, text, font_metrics)?;
+    } else {
+        fmt_escaped(f, text.chars())?;
+    }
+    f.write_str("</text>\n")?;
+
+    Ok(())
+}
+
+/// Write the cells of `text`, starting at cell `x`, each in a `<tspan>` positioned at its cell.
+/// Whitespace cells are written in the `<tspan>` of the cell before them.
+fn fmt_cells_positioned(
+    f: &mut std::fmt::Formatter<'_>,
+    x: u16,
+    text: &TextLine,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let len = text.trimmed_len();
+    let mut idx = 0;
+    while idx < len {
+        let group_len = 1 + text.cell_starts[idx + 1..len]
+            .iter()
+            .take_while(|&&start| text.text[start].is_whitespace())
+            .count();
+        let start = text.cell_starts[idx];
+        let end = text
+            .cell_starts
+            .get(idx + group_len)
+            .copied()
+            .unwrap_or(text.text.len());
+        write!(
+            f,
+            r#"<tspan x="{}">"#,
+            (f32::from(x) + idx as f32) * font_metrics.advance,
+        )?;
+        fmt_escaped(f, &text.text[start..end])?;
+        f.write_str("</tspan>")?;
+        idx += group_len;
+    }
+    Ok(())
+}
+
+/// Write a run of text in the same style, either as SVG text or, if set in `options`, as glyph
+/// outlines.
+fn fmt_text_run(
+    f: &mut std::fmt::Formatter<'_>,
+    options: &SvgOptions,
+    x: u16,
+    y: u16,
+    text: &TextLine,
+    style: &TextStyle,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    #[cfg(feature = "text-as-paths")]
+    if let Some(font) = options.text_as_paths {
+        // glyphs are drawn one per cell, so zero-width characters are not drawn
+        let chars = text.base_chars();
+        if chars.is_empty() {
+            return Ok(());
+        }
+        let TextStyle {
+            fg,
+            bold,
+            dim,
+            italic,
+            underline,
+            strikethrough,
+            font: _,
+        } = *style;
+        return crate::outline::fmt_text(
+            f,
+            font,
+            x,
+            y,
+            &chars,
+            &crate::outline::OutlineStyle {
+                fg,
+                bold,
+                dim,
+                italic,
+                underline,
+                strikethrough,
+            },
+            font_metrics,
+        );
+    }
+    #[cfg(not(feature = "text-as-paths"))]
+    let _ = options;
+
+    fmt_text(f, x, y, text, style, options.exact_grid, font_metrics)
+}
+
+/// Write an underline below cells `x0` up to and including `x1` on line `y`.
+pub(crate) fn fmt_underline(
+    f: &mut std::fmt::Formatter<'_>,
+    x0: u16,
+    x1: u16,
+    y: u16,
+    underline: Underline,
+    color: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let thickness = font_metrics.line_height / 16.;
+    let start_x = f32::from(x0) * font_metrics.advance;
+    let end_x = f32::from(x1 + 1) * font_metrics.advance;
+    let baseline = f32::from(y + 1) * font_metrics.line_height - font_metrics.descent;
+    let line_y = baseline + font_metrics.descent / 3.;
+
+    let fmt_line = |f: &mut std::fmt::Formatter<'_>, y: f32, dasharray: Option<f32>| {
+        write!(
+            f,
+            r#"<line x1="{start_x}" y1="{y}" x2="{end_x}" y2="{y}" style="stroke: {color}; stroke-width: {thickness};"#,
+        )?;
+        if let Some(dash) = dasharray {
+            write!(f, " stroke-dasharray: {dash};")?;
+        }
+        f.write_str("\" />\n")
+    };
+
+    match underline {
+        Underline::None => Ok(()),
+        Underline::Single => fmt_line(f, line_y, None),
+        Underline::Double => {
+            fmt_line(f, line_y - thickness, None)?;
+            fmt_line(f, line_y + thickness, None)
+        }
+        Underline::Dotted => fmt_line(f, line_y, Some(thickness)),
+        Underline::Dashed => fmt_line(f, line_y, Some(font_metrics.advance / 3.)),
+        Underline::Curly => {
+            // one wave per cell
+            let amplitude = font_metrics.descent / 5.;
+            let half_wave = font_metrics.advance / 2.;
+            write!(
+                f,
+                "<path d=\"M{start_x} {line_y} q{quarter} {neg_amplitude} {half_wave} 0",
+                quarter = half_wave / 2.,
+                neg_amplitude = -amplitude,
+            )?;
+            for _ in 1..(x1 - x0 + 1) * 2 {
+                write!(f, " t{half_wave} 0")?;
+            }
+            writeln!(
+                f,
+                r#"" style="fill: none; stroke: {color}; stroke-width: {thickness};" />"#
+            )
+        }
+    }
+}
+
+/// Write a strikethrough through cells `x0` up to and including `x1` on line `y`.
+pub(crate) fn fmt_strikethrough(
+    f: &mut std::fmt::Formatter<'_>,
+    x0: u16,
+    x1: u16,
+    y: u16,
+    color: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let baseline = f32::from(y + 1) * font_metrics.line_height - font_metrics.descent;
+    let line_y = baseline - font_metrics.line_height * 0.25;
+    writeln!(
+        f,
+        r#"<line x1="{}" y1="{line_y}" x2="{}" y2="{line_y}" style="stroke: {color}; stroke-width: {};" />"#,
+        f32::from(x0) * font_metrics.advance,
+        f32::from(x1 + 1) * font_metrics.advance,
+        font_metrics.line_height / 16.,
+    )
+}
+
+/// Write characters as SVG text content, escaping where necessary.
+pub(crate) fn fmt_escaped(f: &mut std::fmt::Formatter<'_>, chars: &[char]) -> std::fmt::Result {
+    let mut prev_char_was_space = false;
+    for char in chars {
+        match *char {
+            ' ' => {
+                if prev_char_was_space {
+                    // non-breaking space
+                    f.write_str("&#160;")?
+                } else {
+                    f.write_char(' ')?
+                }
+            }
+            // escape tag opening
+            '<' => f.write_str("&lt;")?,
+            '&' => f.write_str("&amp;")?,
+            c => f.write_char(c)?,
+        }
+
+        prev_char_was_space = *char == ' ';
+    }
+
+    Ok(())
+}
+
+/// Write text as an attribute value, escaping where necessary.
+pub(crate) fn fmt_attribute_escaped(
+    f: &mut std::fmt::Formatter<'_>,
+    text: &str,
+) -> std::fmt::Result {
+    for char in text.chars() {
+        match char {
+            '<' => f.write_str("&lt;")?,
+            '&' => f.write_str("&amp;")?,
+            '"' => f.write_str("&quot;")?,
+            '\n' => f.write_str("&#10;")?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// The distance between tab stops. Tab stops set by applications are not known after the fact.
+const TAB_WIDTH: u16 = 8;
+
+/// Get the characters drawn for the whitespace of each cell on line `y`: a middle dot for a space
+/// and an arrow for a tab. Cells skipped over by a tab and the spacers of wide characters are not
+/// marked, nor is whitespace after the line's last non-whitespace character.
+fn whitespace_markers(screen: &Screen, y: u16) -> Vec<Option<char>> {
+    let cells = (0..screen.columns).map(|x| &screen.cells[screen.idx(y, x)]);
+    let end = cells
+        .clone()
+        .rposition(|cell| cell.c != ' ')
+        .map_or(0, |idx| idx + 1);
+
+    let mut markers = Vec::with_capacity(usize::from(screen.columns));
+    let mut tab_end = 0;
+    let mut prev_wide = false;
+    for (x, cell) in (0..).zip(cells) {
+        let marker = if usize::from(x) >= end || cell.hidden {
+            None
+        } else if cell.c == '\t' {
+            tab_end = (x / TAB_WIDTH + 1) * TAB_WIDTH;
+            Some('→')
+        } else if cell.c == ' ' && x >= tab_end && !prev_wide {
+            Some('·')
+        } else {
+            None
+        };
+        markers.push(marker);
+        prev_wide = cell.c.width() == Some(2);
+    }
+    markers
+}
+
+/// Get a copy of `screen` with the text of lines of double-size characters cleared. The cells'
+/// backgrounds are kept.
+fn clear_resized_lines(screen: &Screen) -> Screen {
+    let mut screen = screen.clone();
+    for line in 0..screen.lines {
+        if screen.line_size(line) == LineSize::Single {
+            continue;
+        }
+        for column in 0..screen.columns {
+            let idx = screen.idx(line, column);
+            screen.cells[idx] = Cell {
+                c: ' ',
+                zerowidth: Box::default(),
+                underline: Underline::None,
+                strikethrough: false,
+                ..screen.cells[idx].clone()
+            };
+        }
+    }
+    screen
+}
+
+/// Write the lines of double-size characters of `screen`, scaling the first half of the cells of
+/// each line to the full width of the screen. Double-height lines show the top or bottom half of
+/// their characters.
+fn fmt_resized_lines(
+    f: &mut std::fmt::Formatter<'_>,
+    screen: &Screen,
+    options: &SvgOptions,
+    main_bg: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    for line in 0..screen.lines {
+        let (scale_y, offset_y) = match screen.line_size(line) {
+            LineSize::Single => continue,
+            LineSize::DoubleWidth => (1., 0.),
+            LineSize::DoubleHeightTop => (2., 0.),
+            LineSize::DoubleHeightBottom => (2., -font_metrics.line_height),
+        };
+        let mut half = screen.crop(line..=line, ..screen.columns.div_ceil(2));
+        half.line_sizes.clear();
+        half.images.clear();
+
+        // a nested viewport clips the half of double-height characters on the other line
+        writeln!(
+            f,
+            r#"<svg x="0" y="{}" width="{}" height="{}">"#,
+            f32::from(line) * font_metrics.line_height,
+            f32::from(screen.columns) * font_metrics.advance,
+            font_metrics.line_height,
+        )?;
+        writeln!(
+            f,
+            r#"<g transform="translate(0 {offset_y}) scale(2 {scale_y})">"#
+        )?;
+        fmt_screen(
+            f,
+            &half,
+            &SvgOptions {
+                highlights: &[],
+                annotations: &[],
+                blurs: &[],
+                ..*options
+            },
+            main_bg,
+            font_metrics,
+        )?;
+        f.write_str("</g>\n</svg>\n")?;
+    }
+    Ok(())
+}
+
+/// Write the backgrounds of the cells of `lines` that differ from `main_bg`.
+fn fmt_backgrounds(
+    f: &mut std::fmt::Formatter<'_>,
+    screen: &Screen,
+    lines: Range<u16>,
+    options: &SvgOptions,
+    main_bg: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    background_rects(
+        screen,
+        lines,
+        main_bg,
+        options.hyperlinks,
+        |x0, y0, x1, y1, color, link| {
+            hyperlink::fmt_linked(f, link, |f| {
+                fmt_rect(f, x0, y0, x1, y1, color, font_metrics)
+            })
+        },
+    )
+}
+
+/// Call `rect` with the rectangles covering the cells of `lines` with a background color other
+/// than `main_bg`, as the start column, start line, end column and end line, inclusive, the color
+/// and, if `links` is set, the hyperlink of the cells. Rectangles do not span cells of different
+/// hyperlinks.
+pub(crate) fn background_rects<'s>(
+    screen: &'s Screen,
+    lines: Range<u16>,
+    main_bg: Rgb,
+    links: bool,
+    mut rect: impl FnMut(u16, u16, u16, u16, Rgb, Option<&'s Hyperlink>) -> std::fmt::Result,
+) -> std::fmt::Result {
+    let Screen {
+        columns, ref cells, ..
+    } = *screen;
+
+    let link = |x: u16, y: u16| {
+        links
+            .then(|| cells[screen.idx(y, x)].hyperlink.as_ref())
+            .flatten()
+    };
+
+    // find background rectangles to draw by merging cells into horizontal runs of the same color
+    // and link, and coalescing runs spanning the same columns on consecutive lines. the open
+    // rectangles are tuples of `(start x, end x, color, link, start y)`, with inclusive ends,
+    // ordered by start x
+    let mut open: Vec<(u16, u16, Rgb, Option<&Hyperlink>, u16)> = Vec::new();
+    let mut next = Vec::new();
+    for y in lines.clone() {
+        let mut x = 0;
+        let mut i = 0;
+        while x < columns {
+            let (bg, run_link) = (cells[screen.idx(y, x)].bg, link(x, y));
+            let start_x = x;
+            while x < columns && cells[screen.idx(y, x)].bg == bg && link(x, y) == run_link {
+                x += 1;
+            }
+            if bg == main_bg {
+                continue;
+            }
+
+            // rectangles ending before this run are not continued
+            while let Some(&(x0, x1, color, link, y0)) = open.get(i) {
+                if x0 >= start_x {
+                    break;
+                }
+                rect(x0, y0, x1, y - 1, color, link)?;
+                i += 1;
+            }
+            match open.get(i) {
+                Some(&(x0, x1, color, link, y0))
+                    if (x0, x1, color, link) == (start_x, x - 1, bg, run_link) =>
+                {
+                    next.push((x0, x1, color, link, y0));
+                    i += 1;
+                }
+                _ => next.push((start_x, x - 1, bg, run_link, y)),
+            }
+        }
+        for &(x0, x1, color, link, y0) in &open[i..] {
+            rect(x0, y0, x1, y - 1, color, link)?;
+        }
+        open.clear();
+        std::mem::swap(&mut open, &mut next);
+    }
+    for (x0, x1, color, link, y0) in open {
+        rect(x0, y0, x1, lines.end - 1, color, link)?;
+    }
+
+    Ok(())
+}
+
+/// Call `run` with the runs of text of `lines` in the same style, as the column of the first cell,
+/// the line, the text and its style. Blank cells before runs are skipped.
+pub(crate) fn text_runs(
+    screen: &Screen,
+    lines: Range<u16>,
+    options: &SvgOptions,
+    mut run: impl FnMut(u16, u16, &TextLine, &TextStyle) -> std::fmt::Result,
+) -> std::fmt::Result {
+    let Screen {
+        columns, ref cells, ..
+    } = *screen;
+
+    let mut text_line = TextLine::with_capacity(usize::from(columns).next_power_of_two());
+    for y in lines.clone() {
+        let idx = screen.idx(y, 0);
+        let cell = &cells[idx];
+        let mut style = TextStyle::from_cell(cell, options);
+        let mut link = options.hyperlinks.then_some(&cell.hyperlink);
+        let mut start_x = 0;
+        let markers = options
+            .show_whitespace
+            .then(|| whitespace_markers(screen, y));
+
+        for x in 0..columns {
+            let idx = screen.idx(y, x);
+            let cell = &cells[idx];
+            let marker = markers.as_ref().and_then(|markers| markers[usize::from(x)]);
+            let mut style_ = TextStyle::from_cell(cell, options);
+            // whitespace does not break up runs of fallback fonts
+            style_.font = if cell.c == ' ' {
+                style.font
+            } else {
+                fallback::classify(options, cell.c, &cell.zerowidth)
+            };
+            if marker.is_some() {
+                style_.fg = decoration::mix(cell.bg, cell.fg, 0.4);
+                style_.font = None;
+            }
+
+            // links are written around their runs of text
+            let link_ = options.hyperlinks.then_some(&cell.hyperlink);
+            if style_ != style || link_ != link {
+                if !text_line.is_empty() {
+                    run(start_x, y, &text_line, &style)?;
+                }
+                text_line.clear();
+                style = style_;
+                link = link_;
+            }
+
+            let (c, zerowidth) = if let Some(marker) = marker {
+                (marker, &[][..])
+            } else if (cell.hidden && !options.hidden_as_background)
+                || (options.glyph_geometry && glyphs::is_geometric(cell.c))
+            {
+                (' ', &[][..])
+            } else {
+                (cell.c, &cell.zerowidth[..])
+            };
+
+            if text_line.is_empty() {
+                start_x = x;
+                if c == ' ' {
+                    continue;
+                }
+            }
+
+            text_line.push_cell(c, zerowidth);
+        }
+
+        if !text_line.is_empty() {
+            run(start_x, y, &text_line, &style)?;
+            text_line.clear();
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the text of `lines`, the characters drawn as geometry, and the underlines not drawn as
+/// part of the text.
+fn fmt_lines_text(
+    f: &mut std::fmt::Formatter<'_>,
+    screen: &Screen,
+    lines: Range<u16>,
+    options: &SvgOptions,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    let Screen {
+        columns, ref cells, ..
+    } = *screen;
+
+    // write text
+    text_runs(screen, lines.clone(), options, |x, y, text, style| {
+        let link = options
+            .hyperlinks
+            .then(|| cells[screen.idx(y, x)].hyperlink.as_ref())
+            .flatten();
+        hyperlink::fmt_linked(f, link, |f| {
+            if options.debug_cells {
+                f.write_str("<g>")?;
+                fmt_debug_title(f, screen, x, y, text.trimmed_len() as u16)?;
+                fmt_text_run(f, options, x, y, text, style, font_metrics)?;
+                f.write_str("</g>\n")
+            } else {
+                fmt_text_run(f, options, x, y, text, style, font_metrics)
+            }
+        })
+    })?;
+
+    // write characters that are drawn as geometry
+    if options.glyph_geometry {
+        for y in lines.clone() {
+            for x in 0..columns {
+                let cell = &cells[screen.idx(y, x)];
+                if cell.hidden || !glyphs::is_geometric(cell.c) {
+                    continue;
+                }
+                let opacity = if cell.dim { 0.6 } else { 1. };
+                glyphs::fmt_glyph(f, cell.c, x, y, cell.fg, opacity, font_metrics)?;
+            }
+        }
+    }
+
+    // write underlines that are not drawn as part of the text
+    for y in lines.clone() {
+        let mut run: Option<(u16, Underline, Rgb)> = None;
+        for x in 0..=columns {
+            let underline = screen
+                .get(y, x)
+                .filter(|cell| x < columns && draws_underline(cell, options) && !cell.hidden)
+                .map(|cell| (cell.underline, cell.underline_color.unwrap_or(cell.fg)));
+
+            if let Some((start_x, kind, color)) = run {
+                if underline != Some((kind, color)) {
+                    fmt_underline(f, start_x, x - 1, y, kind, color, font_metrics)?;
+                    run = None;
+                }
+            }
+            if run.is_none() {
+                run = underline.map(|(kind, color)| (x, kind, color));
+            }
+        }
+    }
+
+    // write strikethroughs that are not drawn as part of the text
+    if options.decoration_lines {
+        for y in lines.clone() {
+            let mut run: Option<(u16, Rgb)> = None;
+            for x in 0..=columns {
+                let strikethrough = screen
+                    .get(y, x)
+                    .filter(|cell| x < columns && cell.strikethrough && !cell.hidden)
+                    .map(|cell| cell.fg);
+
+                if let Some((start_x, color)) = run {
+                    if strikethrough != Some(color) {
+                        fmt_strikethrough(f, start_x, x - 1, y, color, font_metrics)?;
+                        run = None;
+                    }
+                }
+                if run.is_none() {
+                    run = strikethrough.map(|color| (x, color));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a `<title>` describing the `len` cells starting at cell `x` of line `y`, for
+/// [SvgOptions::debug_cells].
+fn fmt_debug_title(
+    f: &mut std::fmt::Formatter<'_>,
+    screen: &Screen,
+    x: u16,
+    y: u16,
+    len: u16,
+) -> std::fmt::Result {
+    let mut title = format!("{y}:{x}-{y}:{}", x + len);
+    for x in x..x + len {
+        let cell = &screen.cells[screen.idx(y, x)];
+        write!(title, "\n{y}:{x}")?;
+        for c in cell.chars() {
+            write!(title, " U+{:04X}", u32::from(c))?;
+        }
+        write!(title, " fg {} bg {}", cell.fg, cell.bg)?;
+        let flags = [
+            (cell.bold, "bold"),
+            (cell.dim, "dim"),
+            (cell.italic, "italic"),
+            (cell.strikethrough, "strikethrough"),
+            (cell.hidden, "hidden"),
+            (cell.hyperlink.is_some(), "hyperlink"),
+        ];
+        for (_, flag) in flags.iter().filter(|(set, _)| *set) {
+            write!(title, " {flag}")?;
+        }
+        if cell.underline != Underline::None {
+            write!(title, " underline {:?}", cell.underline)?;
+            if let Some(color) = cell.underline_color {
+                write!(title, " {color}")?;
+            }
+        }
+    }
+
+    f.write_str("<title>")?;
+    fmt_escaped(f, &title.chars().collect::<Vec<_>>())?;
+    f.write_str("</title>")
+}
+
+/// The number of lines in each section of a screen rendered in parallel.
+#[cfg(feature = "parallel")]
+const SECTION_LINES: u16 = 256;
+
+/// Write a screen of `lines` lines in sections of consecutive lines, using `fmt_section` to write
+/// each section. With the `parallel` feature, the sections of large screens are rendered in
+/// parallel and then written in order.
+fn fmt_sections(
+    f: &mut std::fmt::Formatter<'_>,
+    lines: u16,
+    fmt_section: impl Fn(&mut std::fmt::Formatter<'_>, Range<u16>) -> std::fmt::Result + Sync,
+) -> std::fmt::Result {
+    #[cfg(feature = "parallel")]
+    if lines > SECTION_LINES {
+        use rayon::prelude::*;
+
+        struct Section<'a, F>(&'a F, Range<u16>);
+
+        impl<F> Display for Section<'_, F>
+        where
+            F: Fn(&mut std::fmt::Formatter<'_>, Range<u16>) -> std::fmt::Result,
+        {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                (self.0)(f, self.1.clone())
+            }
+        }
+
+        let sections: Vec<String> = (0..lines.div_ceil(SECTION_LINES))
+            .into_par_iter()
+            .map(|section| {
+                let start = section * SECTION_LINES;
+                let end = lines.min(start + SECTION_LINES);
+                Section(&fmt_section, start..end).to_string()
+            })
+            .collect();
+        return sections.iter().try_for_each(|section| f.write_str(section));
+    }
+
+    fmt_section(f, 0..lines)
+}
+
+/// Write the screen's background and text, with the top-left of the screen at (0, 0).
+pub(crate) fn fmt_screen(
+    f: &mut std::fmt::Formatter<'_>,
+    screen: &Screen,
+    options: &SvgOptions,
+    main_bg: Rgb,
+    font_metrics: &CalculatedFontMetrics,
+) -> std::fmt::Result {
+    // the text of blurred cells is drawn separately, by the blurs
+    let original = screen;
+    let cleared;
+    let screen = if options.blurs.is_empty() {
+        screen
+    } else {
+        cleared = blur::clear(screen, options.blurs);
+        &cleared
+    };
+    // the text of double-size lines is drawn separately, scaled
+    let resized = screen;
+    let single;
+    let screen = if screen.line_sizes.is_empty() {
+        screen
+    } else {
+        single = clear_resized_lines(screen);
+        &single
+    };
+    let Screen { lines, columns, .. } = *screen;
+
+    fmt_rect(
+        f,
+        0,
+        0,
+        screen.columns().saturating_sub(1),
+        screen.lines().saturating_sub(1),
+        main_bg,
+        font_metrics,
+    )?;
+
+    fmt_sections(f, lines, |f, lines| {
+        fmt_backgrounds(f, screen, lines, options, main_bg, font_metrics)
+    })?;
+
+    // write images, in a nested viewport to clip images partially off screen
+    if !screen.images().is_empty() {
+        writeln!(
+            f,
+            r#"<svg x="0" y="0" width="{}" height="{}">"#,
+            f32::from(columns) * font_metrics.advance,
+            f32::from(lines) * font_metrics.line_height,
+        )?;
+        for image in screen.images() {
+            writeln!(
+                f,
+                r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="{}" />"#,
+                image.column as f32 * font_metrics.advance,
+                image.line as f32 * font_metrics.line_height,
+                image.columns * font_metrics.advance,
+                image.lines * font_metrics.line_height,
+                image.data_uri(),
+            )?;
+        }
+        f.write_str("</svg>\n")?;
+    }
+
+    fmt_sections(f, lines, |f, lines| {
+        fmt_lines_text(f, screen, lines, options, font_metrics)
+    })?;
+
+    fmt_resized_lines(f, resized, options, main_bg, font_metrics)?;
+    blur::fmt_blurs(f, original, options, main_bg, font_metrics)?;
+    if options.grid {
+        grid::fmt_grid(f, lines, columns, main_bg, font_metrics)?;
+    }
+    highlight::fmt_highlights(f, options.highlights, lines, columns, font_metrics)?;
+    annotation::fmt_annotations(f, options.annotations, font_metrics)?;
+
+    Ok(())
+}
+
+/// The content rendered to an SVG: either a single screen, or multiple screens composed in a
+/// layout.
+#[derive(Clone, Copy)]
+pub(crate) enum Content<'s> {
+    Screen(&'s Screen),
+    Layout(&'s Layout),
+}
+
+impl<'s> Content<'s> {
+    /// The title drawn in the window decoration, if any.
+    fn title(&self) -> Option<&'s str> {
+        match self {
+            Content::Screen(screen) => screen.title(),
+            Content::Layout(layout) => layout.title(),
+        }
+    }
+
+    /// The background color of the padding and window decoration.
+    fn background(&self) -> Rgb {
+        match self {
+            Content::Screen(screen) => colors::most_common_color(screen),
+            Content::Layout(layout) => layout.background(),
+        }
+    }
+
+    /// The plain-text content, leaving out the text of blurred regions.
+    fn text(&self, blurs: &[Blur]) -> String {
+        match self {
+            Content::Screen(screen) => blur::text(screen, blurs),
+            Content::Layout(layout) => layout.text(blurs),
+        }
+    }
+
+    /// The width and height of the content in pixels.
+    fn size(&self, font_metrics: &CalculatedFontMetrics) -> (f32, f32) {
+        match self {
+            Content::Screen(screen) => (
+                f32::from(screen.columns()) * font_metrics.advance,
+                f32::from(screen.lines()) * font_metrics.line_height,
+            ),
+            Content::Layout(layout) => layout.size(font_metrics),
+        }
+    }
+
+    /// Write the content with its top-left at (0, 0).
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: &SvgOptions,
+        background: Rgb,
+        font_metrics: &CalculatedFontMetrics,
+    ) -> std::fmt::Result {
+        match self {
+            Content::Screen(screen) => fmt_screen(f, screen, options, background, font_metrics),
+            Content::Layout(layout) => layout.fmt(f, options, font_metrics),
+        }
+    }
+}
+
+pub(crate) struct Svg<'s> {
+    pub content: Content<'s>,
+    pub options: SvgOptions<'s>,
+}
+
+impl<'s> Display for Svg<'s> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let font_metrics = self.options.font_metrics.at_font_size(FONT_SIZE_PX);
+        let main_bg = self.content.background();
+
+        let (screen_width, screen_height) = self.content.size(&font_metrics);
+        let title = self
+            .content
+            .title()
+            .filter(|title| self.options.show_title && !title.is_empty());
+        let layout =
+            decoration::FrameLayout::new(&self.options, title, screen_width, screen_height);
+
+        write!(
+            f,
+            r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg""#,
+            layout.width, layout.height,
+        )?;
+        if let Some(scale) = self.options.scale {
+            write!(
+                f,
+                r#" width="{}" height="{}""#,
+                layout.width * scale,
+                layout.height * scale
+            )?;
+        }
+
+        let text = match self.options.alt_text {
+            AltText::None => None,
+            AltText::ScreenText | AltText::Custom(_) => Some(self.content.text(self.options.blurs)),
+        };
+        if let Some(text) = &text {
+            f.write_str(r#" role="img" aria-label=""#)?;
+            match self.options.alt_text {
+                AltText::Custom(alt_text) => fmt_attribute_escaped(f, alt_text)?,
+                _ => fmt_attribute_escaped(f, text)?,
+            }
+            f.write_char('"')?;
+        }
+        f.write_char('>')?;
+
+        if let Some(text) = &text {
+            f.write_str("\n<title>")?;
+            let title = self.content.title().unwrap_or("Terminal");
+            fmt_escaped(f, &title.chars().collect::<Vec<_>>())?;
+            f.write_str("</title>\n<desc>")?;
+            fmt_escaped(f, &text.chars().collect::<Vec<_>>())?;
+            f.write_str("</desc>")?;
+        }
+
+        f.write_str(
+            "
+<style>
+  .screen {
+    font-family: ",
+        )?;
+
+        for font in self.options.fonts {
+            f.write_char('"')?;
+            f.write_str(font)?;
+            f.write_str("\", ")?;
+        }
+
+        write!(
+            f,
+            r#"monospace;
+    font-size: {FONT_SIZE_PX}px;
+  }}
+"#,
+        )?;
+
+        for class in FontClass::ALL {
+            let fonts = class.fonts(&self.options);
+            if fonts.is_empty() {
+                continue;
+            }
+            write!(
+                f,
+                "  .screen .{} {{
+    font-family: ",
+                class.css_class()
+            )?;
+            for font in fonts.iter().chain(self.options.fonts) {
+                f.write_char('"')?;
+                f.write_str(font)?;
+                f.write_str("\", ")?;
+            }
+            f.write_str("monospace;\n  }\n")?;
+        }
+
+        if title.is_some() || self.options.caption.is_some() {
+            write!(
+                f,
+                r#"  .title {{
+    font-family: sans-serif;
+    font-size: {FONT_SIZE_PX}px;
+  }}
+"#,
+            )?;
+        }
+
+        if let Some(css) = self.options.extra_css {
+            // CDATA allows the CSS to contain characters such as `<` and `&`; a `]]>` in the CSS
+            // would end the section, so it is split over two sections
+            f.write_str("<![CDATA[\n")?;
+            f.write_str(&css.trim_end().replace("]]>", "]]]]><![CDATA[>"))?;
+            f.write_str("\n]]>\n")?;
+        }
+
+        f.write_str("</style>\n")?;
+
+        if self
+            .options
+            .blurs
+            .iter()
+            .any(|blur| blur.style == BlurStyle::Blur)
+        {
+            blur::fmt_filter(f, &font_metrics)?;
+        }
+
+        decoration::fmt_frame_start(f, &self.options, &layout, title, main_bg)?;
+
+        if layout.screen_x == 0. && layout.screen_y == 0. {
+            f.write_str("<g class=\"screen\">\n")?;
+        } else {
+            writeln!(
+                f,
+                r#"<g class="screen" transform="translate({} {})">"#,
+                layout.screen_x, layout.screen_y,
+            )?;
+        }
+        self.content.fmt(f, &self.options, main_bg, &font_metrics)?;
+        if self.options.rulers {
+            grid::fmt_rulers(
+                f,
+                (screen_height / font_metrics.line_height).round() as u16,
+                (screen_width / font_metrics.advance).round() as u16,
+                main_bg,
+                &font_metrics,
+            )?;
+        }
+        f.write_str("</g>\n")?;
+
+        decoration::fmt_frame_end(f, &self.options, &layout, main_bg)?;
+
+        f.write_str("</svg>")?;
+
+        Ok(())
+    }
+}